@@ -0,0 +1,58 @@
+//! Compiles the GLSL shaders in the `shaders` directory to SPIR-V.
+
+use naga::{
+    back::spv,
+    front::glsl,
+    valid::{Capabilities, ValidationFlags, Validator},
+    ShaderStage,
+};
+use std::{env, fs, path::Path};
+
+const SHADER_DIRECTORY: &str = "shaders";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", SHADER_DIRECTORY);
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR not set.");
+    let entries = fs::read_dir(SHADER_DIRECTORY)
+        .unwrap_or_else(|err| panic!("Could not read shader directory: {}", err));
+
+    for entry in entries {
+        let path = entry
+            .unwrap_or_else(|err| panic!("Could not read shader directory entry: {}", err))
+            .path();
+        let stage = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("vert") => ShaderStage::Vertex,
+            Some("frag") => ShaderStage::Fragment,
+            _ => continue,
+        };
+        println!("cargo:rerun-if-changed={}", path.display());
+
+        let source = fs::read_to_string(&path)
+            .unwrap_or_else(|err| panic!("Could not read {}: {}", path.display(), err));
+        let spirv = compile_shader(&source, stage)
+            .unwrap_or_else(|err| panic!("Could not compile {}:\n{}", path.display(), err));
+
+        let file_name = path.file_name().unwrap().to_str().unwrap();
+        let output_path = Path::new(&out_dir).join(format!("{}.spv", file_name));
+        let bytes: Vec<u8> = spirv.iter().flat_map(|word| word.to_le_bytes()).collect();
+        fs::write(&output_path, bytes)
+            .unwrap_or_else(|err| panic!("Could not write {}: {}", output_path.display(), err));
+    }
+}
+
+fn compile_shader(source: &str, stage: ShaderStage) -> Result<Vec<u32>, String> {
+    let module = glsl::Frontend::default()
+        .parse(&glsl::Options::from(stage), source)
+        .map_err(|err| err.emit_to_string(source))?;
+    let module_info = Validator::new(ValidationFlags::all(), Capabilities::PUSH_CONSTANT)
+        .validate(&module)
+        .map_err(|err| err.emit_to_string(source))?;
+    // The projection matrices already flip the y-axis to the downward clip
+    // space y-axis of the backends, so the writer must not flip it again
+    let mut options = spv::Options::default();
+    options
+        .flags
+        .remove(spv::WriterFlags::ADJUST_COORDINATE_SPACE);
+    spv::write_vec(&module, &module_info, &options, None).map_err(|err| err.to_string())
+}
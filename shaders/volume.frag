@@ -0,0 +1,265 @@
+#version 450
+
+// Ray-marches through the volume from the back face of its bounding box towards
+// the eye, either compositing transfer function colors front-to-back,
+// projecting the extreme or average value along the ray or compositing shaded
+// isosurfaces.
+
+layout(push_constant) uniform PushConstants {
+    mat4 model_view_projection;
+    // Eye position in texture coordinates (w = 1), or the viewing direction
+    // for orthographic projections (w = 0).
+    vec4 eye;
+    // Size of a texel in texture coordinates (xyz) and distance between
+    // samples along the ray (w).
+    vec4 sampling;
+    // Sampled values mapping to the ends of the transfer function (xy) and
+    // exponent for correcting opacities to the sample distance (z).
+    vec4 value_mapping;
+    // How samples along the ray are combined (x), see the RENDER_MODE constants,
+    // and the number of isosurfaces (y).
+    ivec4 settings;
+} push_constants;
+
+const int MAX_ISOSURFACES = 8;
+
+struct Isosurface {
+    vec4 color;
+    // Normalized volume value on the surface
+    float value;
+};
+
+layout(set = 0, binding = 4) uniform Uniforms {
+    // Transforms texture coordinates to world space
+    mat4 model_matrix;
+    // Transforms gradients in texture coordinates to world space
+    mat4 normal_matrix;
+    Isosurface isosurfaces[MAX_ISOSURFACES];
+} uniforms;
+
+layout(set = 0, binding = 0) uniform texture3D volume_texture;
+layout(set = 0, binding = 1) uniform sampler volume_sampler;
+layout(set = 0, binding = 2) uniform texture1D transfer_function_texture;
+layout(set = 0, binding = 3) uniform sampler transfer_function_sampler;
+
+layout(location = 0) in vec3 texture_coords;
+
+layout(location = 0) out vec4 color;
+
+const int RENDER_MODE_COMPOSITE = 0;
+const int RENDER_MODE_MAXIMUM_INTENSITY = 1;
+const int RENDER_MODE_MINIMUM_INTENSITY = 2;
+const int RENDER_MODE_AVERAGE = 3;
+const int RENDER_MODE_ISOSURFACE = 4;
+
+const int MAX_STEPS = 4096;
+const float OPACITY_THRESHOLD = 0.99;
+const float MIN_DIRECTION_COMPONENT = 1e-6;
+
+const int BISECTION_ITERATIONS = 8;
+const float AMBIENT_REFLECTANCE = 0.2;
+const float DIFFUSE_REFLECTANCE = 0.7;
+const float SPECULAR_REFLECTANCE = 0.3;
+const float SHININESS = 32.0;
+
+// Returns the volume value at the given position, normalized so that the
+// smallest and largest value in the volume map to zero and one.
+float sample_volume(vec3 position) {
+    vec3 texel_size = push_constants.sampling.xyz;
+    // Map the unit cube to the centers of the outermost texels
+    vec3 coords = position * (1.0 - texel_size) + 0.5 * texel_size;
+    float value = texture(sampler3D(volume_texture, volume_sampler), coords).r;
+    return clamp(
+        (value - push_constants.value_mapping.x)
+            / (push_constants.value_mapping.y - push_constants.value_mapping.x),
+        0.0,
+        1.0
+    );
+}
+
+// Returns the color of the transfer function for the given normalized value.
+vec4 look_up_color(float normalized_value) {
+    float table_size = float(textureSize(sampler1D(transfer_function_texture, transfer_function_sampler), 0));
+    float table_coord = (normalized_value * (table_size - 1.0) + 0.5) / table_size;
+    return texture(sampler1D(transfer_function_texture, transfer_function_sampler), table_coord);
+}
+
+// Composites the transfer function colors of the samples along the ray
+// front-to-back, with opacities corrected using the given exponent.
+vec4 composite(vec3 start, vec3 step_vector, int step_count, float opacity_exponent) {
+    vec4 accumulated = vec4(0.0);
+    vec3 position = start;
+    for (int step = 0; step < step_count; step++) {
+        vec4 sample_color = look_up_color(sample_volume(position));
+        sample_color.a = 1.0 - pow(1.0 - sample_color.a, opacity_exponent);
+        accumulated.rgb += (1.0 - accumulated.a) * sample_color.a * sample_color.rgb;
+        accumulated.a += (1.0 - accumulated.a) * sample_color.a;
+        if (accumulated.a > OPACITY_THRESHOLD) {
+            break;
+        }
+        position += step_vector;
+    }
+    return accumulated;
+}
+
+// Combines the samples along the ray into a single value according to the
+// given projection mode, and returns its opaque transfer function color.
+vec4 project(vec3 start, vec3 step_vector, int step_count, int render_mode) {
+    if (step_count == 0) {
+        return vec4(0.0);
+    }
+    float projected = render_mode == RENDER_MODE_MINIMUM_INTENSITY ? 1.0 : 0.0;
+    vec3 position = start;
+    for (int step = 0; step < step_count; step++) {
+        float value = sample_volume(position);
+        if (render_mode == RENDER_MODE_MAXIMUM_INTENSITY) {
+            projected = max(projected, value);
+        } else if (render_mode == RENDER_MODE_MINIMUM_INTENSITY) {
+            projected = min(projected, value);
+        } else {
+            projected += value;
+        }
+        position += step_vector;
+    }
+    if (render_mode == RENDER_MODE_AVERAGE) {
+        projected /= float(step_count);
+    }
+    return vec4(look_up_color(projected).rgb, 1.0);
+}
+
+// Whether the isosurface with the given value lies between the given values.
+bool crosses(float value, float start_value, float end_value) {
+    return (start_value < value) != (end_value < value);
+}
+
+// Returns the fraction of the way from the start to the end position where the
+// volume crosses the given value, found by bisection.
+float find_crossing(vec3 start, vec3 end, float start_value, float value) {
+    float lower = 0.0;
+    float upper = 1.0;
+    for (int iteration = 0; iteration < BISECTION_ITERATIONS; iteration++) {
+        float middle = 0.5 * (lower + upper);
+        float middle_value = sample_volume(mix(start, end, middle));
+        if (crosses(value, start_value, middle_value)) {
+            upper = middle;
+        } else {
+            lower = middle;
+            start_value = middle_value;
+        }
+    }
+    return 0.5 * (lower + upper);
+}
+
+// Returns the gradient of the volume at the given position, estimated with
+// central differences.
+vec3 compute_gradient(vec3 position) {
+    vec3 offset = push_constants.sampling.xyz;
+    return vec3(
+        sample_volume(position + vec3(offset.x, 0.0, 0.0)) - sample_volume(position - vec3(offset.x, 0.0, 0.0)),
+        sample_volume(position + vec3(0.0, offset.y, 0.0)) - sample_volume(position - vec3(0.0, offset.y, 0.0)),
+        sample_volume(position + vec3(0.0, 0.0, offset.z)) - sample_volume(position - vec3(0.0, 0.0, offset.z))
+    ) / (2.0 * offset);
+}
+
+// Returns the color of the given isosurface at the given position, shaded with
+// the Blinn-Phong model for a light at the eye.
+vec4 shade(vec3 position, vec3 direction, Isosurface isosurface) {
+    vec3 to_eye = normalize(mat3(uniforms.model_matrix) * -direction);
+    vec3 gradient = mat3(uniforms.normal_matrix) * compute_gradient(position);
+    // Face the eye where the gradient vanishes
+    vec3 normal = dot(gradient, gradient) > 0.0 ? normalize(gradient) : to_eye;
+    // Light both sides of the surface
+    if (dot(normal, to_eye) < 0.0) {
+        normal = -normal;
+    }
+    // With the light at the eye, the halfway vector equals the eye direction
+    float diffuse = max(dot(normal, to_eye), 0.0);
+    float specular = diffuse > 0.0 ? pow(diffuse, SHININESS) : 0.0;
+    vec3 shaded = isosurface.color.rgb * (AMBIENT_REFLECTANCE + DIFFUSE_REFLECTANCE * diffuse)
+        + vec3(SPECULAR_REFLECTANCE * specular);
+    return vec4(min(shaded, vec3(1.0)), isosurface.color.a);
+}
+
+// Composites the shaded isosurfaces crossed by the ray front-to-back.
+vec4 render_isosurfaces(vec3 start, vec3 step_vector, int step_count, vec3 direction) {
+    int isosurface_count = min(push_constants.settings.y, MAX_ISOSURFACES);
+    vec4 accumulated = vec4(0.0);
+    vec3 position = start;
+    float value = sample_volume(position);
+    for (int step = 0; step < step_count; step++) {
+        vec3 next_position = position + step_vector;
+        float next_value = sample_volume(next_position);
+
+        // Composite the surfaces crossed within the step in order of distance
+        float previous_fraction = -1.0;
+        for (int crossing = 0; crossing < isosurface_count; crossing++) {
+            int nearest = -1;
+            float nearest_fraction = 2.0;
+            for (int index = 0; index < isosurface_count; index++) {
+                float isosurface_value = uniforms.isosurfaces[index].value;
+                if (crosses(isosurface_value, value, next_value)) {
+                    float fraction = find_crossing(position, next_position, value, isosurface_value);
+                    if (fraction > previous_fraction && fraction < nearest_fraction) {
+                        nearest = index;
+                        nearest_fraction = fraction;
+                    }
+                }
+            }
+            if (nearest < 0) {
+                break;
+            }
+            vec3 hit_position = mix(position, next_position, nearest_fraction);
+            vec4 surface_color = shade(hit_position, direction, uniforms.isosurfaces[nearest]);
+            accumulated.rgb += (1.0 - accumulated.a) * surface_color.a * surface_color.rgb;
+            accumulated.a += (1.0 - accumulated.a) * surface_color.a;
+            previous_fraction = nearest_fraction;
+        }
+        if (accumulated.a > OPACITY_THRESHOLD) {
+            break;
+        }
+
+        position = next_position;
+        value = next_value;
+    }
+    return accumulated;
+}
+
+void main() {
+    vec3 exit_point = texture_coords;
+    bool is_perspective = push_constants.eye.w != 0.0;
+    vec3 direction = normalize(is_perspective ? exit_point - push_constants.eye.xyz : push_constants.eye.xyz);
+
+    // Find where the ray enters the box by intersecting it backwards with the
+    // slabs bounded by the faces
+    vec3 safe_direction = mix(
+        direction,
+        vec3(MIN_DIRECTION_COMPONENT),
+        lessThan(abs(direction), vec3(MIN_DIRECTION_COMPONENT))
+    );
+    vec3 lower_distances = -exit_point / safe_direction;
+    vec3 upper_distances = (1.0 - exit_point) / safe_direction;
+    vec3 entry_distances = min(lower_distances, upper_distances);
+    float ray_length = -max(max(entry_distances.x, entry_distances.y), entry_distances.z);
+
+    // Start at the eye if it is inside the box
+    if (is_perspective) {
+        ray_length = min(ray_length, distance(exit_point, push_constants.eye.xyz));
+    }
+    vec3 entry_point = exit_point - ray_length * direction;
+
+    float step_length = push_constants.sampling.w;
+    int step_count = min(int(ceil(ray_length / step_length)), MAX_STEPS);
+    float actual_step_length = ray_length / float(max(step_count, 1));
+    vec3 step_vector = direction * actual_step_length;
+    float opacity_exponent = push_constants.value_mapping.z * actual_step_length / step_length;
+
+    // Output premultiplied color for blending over the background
+    int render_mode = push_constants.settings.x;
+    if (render_mode == RENDER_MODE_COMPOSITE) {
+        color = composite(entry_point, step_vector, step_count, opacity_exponent);
+    } else if (render_mode == RENDER_MODE_ISOSURFACE) {
+        color = render_isosurfaces(entry_point, step_vector, step_count, direction);
+    } else {
+        color = project(entry_point, step_vector, step_count, render_mode);
+    }
+}
@@ -0,0 +1,31 @@
+#version 450
+
+// Renders the faces of the volume's bounding box, represented in texture
+// coordinates as the unit cube.
+
+layout(push_constant) uniform PushConstants {
+    mat4 model_view_projection;
+    vec4 eye;
+    vec4 sampling;
+    vec4 value_mapping;
+    ivec4 settings;
+} push_constants;
+
+layout(location = 0) out vec3 texture_coords;
+
+// Corner indices of the triangles making up the cube faces, wound counter-clockwise
+// when seen from the outside. The bits of an index give the x, y and z coordinate.
+const int CUBE_INDICES[36] = int[36](
+    0, 4, 6, 0, 6, 2,
+    1, 3, 7, 1, 7, 5,
+    0, 1, 5, 0, 5, 4,
+    2, 6, 7, 2, 7, 3,
+    0, 2, 3, 0, 3, 1,
+    4, 5, 7, 4, 7, 6
+);
+
+void main() {
+    int corner = CUBE_INDICES[gl_VertexIndex];
+    texture_coords = vec3(float(corner & 1), float((corner >> 1) & 1), float((corner >> 2) & 1));
+    gl_Position = push_constants.model_view_projection * vec4(texture_coords, 1.0);
+}
@@ -1,10 +1,35 @@
 //! Application.
 
-use crate::{color::Color, input::UserInput};
+use crate::{
+    camera::{Camera, DragMode, Projection},
+    color::Color,
+    graphics::rendering::{Isosurface, RenderMode, MAX_ISOSURFACES},
+    input::{
+        bindings::{Action, ActionEvent, InputBindings},
+        UserInput,
+    },
+};
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+
+/// Number of key presses needed to move an isosurface across the value range.
+const ISOSURFACE_VALUE_STEPS: f32 = 100.0;
 
 pub struct ApplicationState {
     physical_window_size: (u32, u32),
     current_background_color: Color,
+    input_bindings: InputBindings,
+    render_mode: RenderMode,
+    isosurfaces: Vec<Isosurface>,
+    selected_isosurface: usize,
+    value_range: (f32, f32),
+    camera: Camera,
+    volume_bounding_box: Option<([f32; 3], [f32; 3])>,
+    timestep: usize,
+    timestep_count: usize,
+    screenshot_requested: bool,
+    screenshot_scale: u32,
+    screenshot_directory: PathBuf,
 }
 
 impl ApplicationState {
@@ -12,20 +37,268 @@ impl ApplicationState {
         Self {
             physical_window_size,
             current_background_color: default_background_color,
+            input_bindings: InputBindings::default(),
+            render_mode: RenderMode::default(),
+            isosurfaces: Vec::new(),
+            selected_isosurface: 0,
+            value_range: (0.0, 1.0),
+            camera: Camera::default(),
+            volume_bounding_box: None,
+            timestep: 0,
+            timestep_count: 1,
+            screenshot_requested: false,
+            screenshot_scale: 1,
+            screenshot_directory: PathBuf::from("."),
         }
     }
 
     pub fn update_from_input(&mut self, input: &UserInput) {
-        if let UserInput::CursorMoved((x, y)) = *input {
-            let r = x as f32 / (self.physical_window_size.0 as f32);
-            let g = y as f32 / (self.physical_window_size.1 as f32);
-            let b = (r + g) * 0.3;
-            let a = 1.0;
-            self.current_background_color = Color::from_components(r, g, b, a);
+        match self.input_bindings.translate(input) {
+            Some(ActionEvent::Started(action)) => self.start_action(action),
+            Some(ActionEvent::Ended(action)) => self.end_action(action),
+            None => {}
+        }
+        self.camera
+            .update_from_input(input, self.physical_window_size);
+        match *input {
+            UserInput::Resized(physical_window_size)
+            | UserInput::ScaleFactorChanged {
+                physical_size: physical_window_size,
+                ..
+            } => {
+                self.physical_window_size = physical_window_size;
+            }
+            _ => {}
         }
     }
 
+    /// Returns the bindings from user input to actions.
+    pub fn input_bindings(&self) -> &InputBindings {
+        &self.input_bindings
+    }
+
+    /// Sets the bindings from user input to actions.
+    pub fn set_input_bindings(&mut self, input_bindings: InputBindings) {
+        self.input_bindings = input_bindings;
+    }
+
     pub fn background_color(&self) -> &Color {
         &self.current_background_color
     }
+
+    /// Returns the camera viewing the volume.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Returns a mutable reference to the camera viewing the volume.
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Sets the world space box enclosing the rendered volume, and fits the
+    /// camera to it.
+    pub fn set_volume_bounding_box(&mut self, lower: [f32; 3], upper: [f32; 3]) {
+        self.volume_bounding_box = Some((lower, upper));
+        self.fit_camera_to_volume();
+    }
+
+    /// Points the camera at the volume from a distance where all of it is
+    /// visible, if the bounding box of the volume is known.
+    pub fn fit_camera_to_volume(&mut self) {
+        if let Some((lower, upper)) = self.volume_bounding_box {
+            self.camera.fit_to_bounding_box(lower, upper);
+        }
+    }
+
+    /// Returns how samples along each ray through the volume are combined.
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Sets how samples along each ray through the volume are combined.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    /// Sets the range of values in the rendered volume, which determines how
+    /// much isosurface values change when adjusted with the keyboard.
+    ///
+    /// If there are no isosurfaces, one is added in the middle of the range.
+    pub fn set_value_range(&mut self, value_range: (f32, f32)) {
+        self.value_range = value_range;
+        if self.isosurfaces.is_empty() {
+            self.add_isosurface(Isosurface {
+                value: 0.5 * (value_range.0 + value_range.1),
+                color: Color::from_components(0.9, 0.9, 0.9, 1.0),
+            });
+        }
+    }
+
+    /// Returns the isosurfaces to render in isosurface mode.
+    pub fn isosurfaces(&self) -> &[Isosurface] {
+        &self.isosurfaces
+    }
+
+    /// Adds the given isosurface, unless the maximum number of isosurfaces has
+    /// been reached.
+    pub fn add_isosurface(&mut self, isosurface: Isosurface) {
+        if self.isosurfaces.len() < MAX_ISOSURFACES {
+            self.isosurfaces.push(isosurface);
+        } else {
+            warn!(
+                "Ignoring isosurface since the maximum number ({}) is reached.",
+                MAX_ISOSURFACES
+            );
+        }
+    }
+
+    /// Removes the isosurface with the given index.
+    ///
+    /// # Panics
+    /// If the index is out of bounds.
+    pub fn remove_isosurface(&mut self, index: usize) {
+        self.isosurfaces.remove(index);
+        if self.selected_isosurface >= self.isosurfaces.len() {
+            self.selected_isosurface = 0;
+        }
+    }
+
+    /// Sets the value of the isosurface with the given index.
+    ///
+    /// # Panics
+    /// If the index is out of bounds.
+    pub fn set_isosurface_value(&mut self, index: usize, value: f32) {
+        self.isosurfaces[index].value = value;
+    }
+
+    /// Sets the color and opacity of the isosurface with the given index.
+    ///
+    /// # Panics
+    /// If the index is out of bounds.
+    pub fn set_isosurface_color(&mut self, index: usize, color: Color) {
+        self.isosurfaces[index].color = color;
+    }
+
+    /// Returns the index of the current timestep.
+    pub fn timestep(&self) -> usize {
+        self.timestep
+    }
+
+    /// Sets the number of timesteps that can be stepped through, keeping the
+    /// current timestep within range.
+    pub fn set_timestep_count(&mut self, timestep_count: usize) {
+        self.timestep_count = timestep_count.max(1);
+        self.timestep = self.timestep.min(self.timestep_count - 1);
+    }
+
+    /// Returns whether a screenshot has been requested since the last call,
+    /// and clears the request.
+    pub fn take_screenshot_request(&mut self) -> bool {
+        std::mem::take(&mut self.screenshot_requested)
+    }
+
+    /// Returns the physical size of the window.
+    pub fn physical_window_size(&self) -> (u32, u32) {
+        self.physical_window_size
+    }
+
+    /// Returns the factor by which the resolution of screenshots exceeds that
+    /// of the window.
+    pub fn screenshot_scale(&self) -> u32 {
+        self.screenshot_scale
+    }
+
+    /// Sets the factor by which the resolution of screenshots exceeds that of
+    /// the window.
+    ///
+    /// # Panics
+    /// If the factor is zero.
+    pub fn set_screenshot_scale(&mut self, screenshot_scale: u32) {
+        assert!(screenshot_scale > 0, "Screenshot scale must be positive.");
+        self.screenshot_scale = screenshot_scale;
+    }
+
+    /// Returns the directory that screenshots are saved in.
+    pub fn screenshot_directory(&self) -> &Path {
+        &self.screenshot_directory
+    }
+
+    /// Sets the directory that screenshots are saved in.
+    pub fn set_screenshot_directory<P: Into<PathBuf>>(&mut self, screenshot_directory: P) {
+        self.screenshot_directory = screenshot_directory.into();
+    }
+
+    /// Performs the given action, or starts it if it lasts while its binding
+    /// is held.
+    fn start_action(&mut self, action: Action) {
+        match action {
+            Action::RotateCamera => self.camera.start_dragging(DragMode::Orbit),
+            Action::PanCamera => self.camera.start_dragging(DragMode::Pan),
+            Action::ZoomIn => self.camera.zoom_steps(1.0),
+            Action::ZoomOut => self.camera.zoom_steps(-1.0),
+            Action::ResetCamera => {
+                self.camera.reset_orientation();
+                self.fit_camera_to_volume();
+            }
+            Action::ToggleProjection => {
+                let projection = match self.camera.projection() {
+                    Projection::Perspective => Projection::Orthographic,
+                    Projection::Orthographic => Projection::Perspective,
+                };
+                self.camera.set_projection(projection);
+                info!("Switched to {:?} projection.", projection);
+            }
+            Action::NextRenderMode => {
+                self.render_mode = self.render_mode.next();
+                info!("Switched to render mode {:?}.", self.render_mode);
+            }
+            Action::NextTimestep => {
+                self.timestep = (self.timestep + 1) % self.timestep_count;
+                info!("Switched to timestep {}.", self.timestep);
+            }
+            Action::PreviousTimestep => {
+                self.timestep = (self.timestep + self.timestep_count - 1) % self.timestep_count;
+                info!("Switched to timestep {}.", self.timestep);
+            }
+            Action::Screenshot => {
+                self.screenshot_requested = true;
+            }
+            Action::SelectNextIsosurface if !self.isosurfaces.is_empty() => {
+                self.selected_isosurface = (self.selected_isosurface + 1) % self.isosurfaces.len();
+                info!("Selected isosurface {}.", self.selected_isosurface);
+            }
+            Action::SelectNextIsosurface => {}
+            Action::IncreaseIsosurfaceValue => self.shift_selected_isosurface_value(1.0),
+            Action::DecreaseIsosurfaceValue => self.shift_selected_isosurface_value(-1.0),
+        }
+    }
+
+    /// Ends the given action if it lasts while its binding is held.
+    fn end_action(&mut self, action: Action) {
+        let drag_mode = match action {
+            Action::RotateCamera => DragMode::Orbit,
+            Action::PanCamera => DragMode::Pan,
+            _ => return,
+        };
+        if self.camera.drag_mode() == Some(drag_mode) {
+            self.camera.stop_dragging();
+        }
+    }
+
+    /// Moves the value of the selected isosurface the given number of steps
+    /// through the value range.
+    fn shift_selected_isosurface_value(&mut self, steps: f32) {
+        let (min_value, max_value) = self.value_range;
+        if let Some(isosurface) = self.isosurfaces.get_mut(self.selected_isosurface) {
+            isosurface.value = (isosurface.value
+                + steps * (max_value - min_value) / ISOSURFACE_VALUE_STEPS)
+                .max(min_value)
+                .min(max_value);
+            info!(
+                "Set value of isosurface {} to {}.",
+                self.selected_isosurface, isosurface.value
+            );
+        }
+    }
 }
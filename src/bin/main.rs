@@ -1,7 +1,19 @@
 //! Main executable.
 
-use vortek::running;
+use std::process;
+use vortek::{cli, running};
 
 fn main() {
-    running::run();
+    let options = cli::parse_args();
+
+    #[cfg(feature = "logging")]
+    simple_logger::init_with_level(options.log_level).unwrap_or_else(|err| {
+        eprintln!("Logger initialization failed: {}", err);
+        process::exit(1);
+    });
+
+    if let Err(err) = running::run(options) {
+        eprintln!("Error: {}", err);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,461 @@
+//! Camera for viewing the volume.
+
+use crate::{
+    input::UserInput,
+    transform::{self, Matrix4},
+};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+/// Default vertical field of view, in radians.
+pub const DEFAULT_VERTICAL_FIELD_OF_VIEW: f32 = FRAC_PI_4;
+
+/// World space direction that appears upwards in the view.
+const UP_DIRECTION: [f32; 3] = [0.0, 0.0, 1.0];
+/// Default yaw and pitch angles of the camera, in radians.
+const DEFAULT_ORIENTATION: (f32, f32) = (2.266, -0.473);
+/// Largest magnitude of the pitch angle, keeping the view direction away from
+/// the up direction.
+const MAX_PITCH: f32 = FRAC_PI_2 - 1e-3;
+/// Angle the camera orbits when the cursor is dragged across the full height
+/// of the window, in radians.
+const ORBIT_ANGLE_PER_WINDOW_HEIGHT: f32 = PI;
+/// Factor by which the distance to the target changes for each scrolled line.
+const ZOOM_FACTOR_PER_LINE: f32 = 0.9;
+/// Smallest distance to the target relative to the radius of the scene.
+const MIN_RELATIVE_DISTANCE: f32 = 1e-3;
+/// Largest distance to the target relative to the radius of the scene.
+const MAX_RELATIVE_DISTANCE: f32 = 1e3;
+
+/// How the camera projects the scene onto the view plane.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Projection {
+    /// Objects appear smaller with increasing distance.
+    #[default]
+    Perspective,
+    /// Objects appear with the same size regardless of distance. The height of
+    /// the view is that of a perspective view at the target.
+    Orthographic,
+}
+
+/// What dragging the cursor does to the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DragMode {
+    /// Rotate the camera around the target.
+    Orbit,
+    /// Move the camera and target along the view plane.
+    Pan,
+}
+
+/// Camera orbiting around a target point.
+///
+/// The camera looks at the target from a given distance, with its direction
+/// given by a yaw angle around the up direction (the world z-axis) and a pitch
+/// angle above or below the horizontal plane. While dragging, cursor motion
+/// orbits or pans the camera, and scrolling zooms.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Camera {
+    target: [f32; 3],
+    distance: f32,
+    yaw: f32,
+    pitch: f32,
+    projection: Projection,
+    vertical_field_of_view: f32,
+    scene_radius: f32,
+    cursor_position: Option<(i32, i32)>,
+    drag_mode: Option<DragMode>,
+}
+
+impl Camera {
+    /// Creates a new perspective camera looking at the given target from the
+    /// given distance, from the default direction.
+    ///
+    /// # Panics
+    /// If the distance is not positive.
+    pub fn new(target: [f32; 3], distance: f32) -> Self {
+        assert!(distance > 0.0, "Camera distance must be positive.");
+        let (yaw, pitch) = DEFAULT_ORIENTATION;
+        Self {
+            target,
+            distance,
+            yaw,
+            pitch,
+            projection: Projection::default(),
+            vertical_field_of_view: DEFAULT_VERTICAL_FIELD_OF_VIEW,
+            scene_radius: distance,
+            cursor_position: None,
+            drag_mode: None,
+        }
+    }
+
+    /// Returns the point the camera looks at.
+    pub fn target(&self) -> [f32; 3] {
+        self.target
+    }
+
+    /// Sets the point the camera looks at.
+    pub fn set_target(&mut self, target: [f32; 3]) {
+        self.target = target;
+    }
+
+    /// Returns the distance from the camera to the target.
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    /// Sets the distance from the camera to the target.
+    ///
+    /// # Panics
+    /// If the distance is not positive.
+    pub fn set_distance(&mut self, distance: f32) {
+        assert!(distance > 0.0, "Camera distance must be positive.");
+        self.distance = distance;
+    }
+
+    /// Returns the yaw and pitch angles of the camera, in radians.
+    pub fn orientation(&self) -> (f32, f32) {
+        (self.yaw, self.pitch)
+    }
+
+    /// Points the camera in the default direction.
+    pub fn reset_orientation(&mut self) {
+        let (yaw, pitch) = DEFAULT_ORIENTATION;
+        self.set_orientation(yaw, pitch);
+    }
+
+    /// Sets the yaw angle around the up direction and the pitch angle above the
+    /// horizontal plane of the view direction, in radians. The pitch is clamped
+    /// to stay away from straight up or down.
+    pub fn set_orientation(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = yaw.rem_euclid(2.0 * PI);
+        self.pitch = pitch.clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    /// Returns how the camera projects the scene onto the view plane.
+    pub fn projection(&self) -> Projection {
+        self.projection
+    }
+
+    /// Sets how the camera projects the scene onto the view plane.
+    pub fn set_projection(&mut self, projection: Projection) {
+        self.projection = projection;
+    }
+
+    /// Returns the vertical field of view, in radians.
+    pub fn vertical_field_of_view(&self) -> f32 {
+        self.vertical_field_of_view
+    }
+
+    /// Sets the vertical field of view, in radians.
+    ///
+    /// # Panics
+    /// If the field of view is not between zero and π.
+    pub fn set_vertical_field_of_view(&mut self, vertical_field_of_view: f32) {
+        assert!(
+            vertical_field_of_view > 0.0 && vertical_field_of_view < PI,
+            "Invalid field of view {}.",
+            vertical_field_of_view
+        );
+        self.vertical_field_of_view = vertical_field_of_view;
+    }
+
+    /// Sets the radius of a sphere around the target enclosing everything that
+    /// should be visible, which determines the clip distances.
+    pub fn set_scene_radius(&mut self, scene_radius: f32) {
+        self.scene_radius = scene_radius.max(f32::MIN_POSITIVE);
+    }
+
+    /// Points the camera at the center of the given axis-aligned box and moves
+    /// it to the distance where the bounding sphere of the box fills the view,
+    /// keeping the current direction.
+    pub fn fit_to_bounding_box(&mut self, lower: [f32; 3], upper: [f32; 3]) {
+        self.target = [
+            0.5 * (lower[0] + upper[0]),
+            0.5 * (lower[1] + upper[1]),
+            0.5 * (lower[2] + upper[2]),
+        ];
+        let radius = 0.5 * transform::length(transform::subtract(upper, lower));
+        self.set_scene_radius(radius);
+        self.distance = self.scene_radius / (0.5 * self.vertical_field_of_view).sin();
+    }
+
+    /// Returns the unit direction in which the camera looks.
+    pub fn view_direction(&self) -> [f32; 3] {
+        [
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+        ]
+    }
+
+    /// Returns the position of the camera.
+    pub fn eye(&self) -> [f32; 3] {
+        transform::add_scaled(self.target, self.view_direction(), -self.distance)
+    }
+
+    /// Computes the matrix transforming world space to view space.
+    pub fn view_matrix(&self) -> Matrix4 {
+        transform::look_at(self.eye(), self.target, UP_DIRECTION)
+    }
+
+    /// Computes the matrix transforming view space to clip space for a view with
+    /// the given aspect ratio (width over height).
+    ///
+    /// The clip distances enclose the sphere with the scene radius around the
+    /// target.
+    pub fn projection_matrix(&self, aspect_ratio: f32) -> Matrix4 {
+        let far = self.distance + self.scene_radius;
+        match self.projection {
+            Projection::Perspective => transform::perspective(
+                self.vertical_field_of_view,
+                aspect_ratio,
+                (self.distance - self.scene_radius).max(MIN_RELATIVE_DISTANCE * far),
+                far,
+            ),
+            Projection::Orthographic => {
+                let height = 2.0 * self.distance * (0.5 * self.vertical_field_of_view).tan();
+                transform::orthographic(
+                    aspect_ratio * height,
+                    height,
+                    self.distance - self.scene_radius,
+                    far,
+                )
+            }
+        }
+    }
+
+    /// Rotates the camera around the target by the given yaw and pitch angles,
+    /// in radians.
+    pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        self.set_orientation(self.yaw + delta_yaw, self.pitch + delta_pitch);
+    }
+
+    /// Moves the camera and target by the given distances along the horizontal
+    /// and vertical directions of the view.
+    pub fn pan(&mut self, right_distance: f32, up_distance: f32) {
+        let forward = self.view_direction();
+        let right = transform::normalize(transform::cross(forward, UP_DIRECTION));
+        let up = transform::cross(right, forward);
+        self.target = transform::add_scaled(self.target, right, right_distance);
+        self.target = transform::add_scaled(self.target, up, up_distance);
+    }
+
+    /// Divides the distance to the target by the given factor, keeping it
+    /// within a range relative to the scene radius.
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance = (self.distance / factor).clamp(
+            MIN_RELATIVE_DISTANCE * self.scene_radius,
+            MAX_RELATIVE_DISTANCE * self.scene_radius,
+        );
+    }
+
+    /// Zooms in by the given number of steps, or out for negative steps, where
+    /// each step corresponds to one line of scrolling.
+    pub fn zoom_steps(&mut self, steps: f32) {
+        self.zoom(ZOOM_FACTOR_PER_LINE.powf(-steps));
+    }
+
+    /// Makes subsequent cursor motion orbit or pan the camera.
+    pub fn start_dragging(&mut self, drag_mode: DragMode) {
+        self.drag_mode = Some(drag_mode);
+    }
+
+    /// Stops cursor motion from affecting the camera.
+    pub fn stop_dragging(&mut self) {
+        self.drag_mode = None;
+    }
+
+    /// Returns what cursor motion currently does to the camera, if anything.
+    pub fn drag_mode(&self) -> Option<DragMode> {
+        self.drag_mode
+    }
+
+    /// Orbits, pans or zooms the camera in response to cursor motion and
+    /// scrolling, for a window with the given physical size.
+    pub fn update_from_input(&mut self, input: &UserInput, physical_window_size: (u32, u32)) {
+        match *input {
+            // Button releases are missed while the window is unfocused
+            UserInput::FocusChanged(false) => {
+                self.drag_mode = None;
+            }
+            // Avoid a jump when the cursor reenters the window elsewhere
+            UserInput::CursorLeft => {
+                self.cursor_position = None;
+            }
+            UserInput::CursorMoved(position) => {
+                if let (Some(drag_mode), Some(previous_position)) =
+                    (self.drag_mode, self.cursor_position)
+                {
+                    let window_height = physical_window_size.1.max(1) as f32;
+                    let delta_x = (position.0 - previous_position.0) as f32 / window_height;
+                    let delta_y = (position.1 - previous_position.1) as f32 / window_height;
+                    match drag_mode {
+                        DragMode::Orbit => self.orbit(
+                            -delta_x * ORBIT_ANGLE_PER_WINDOW_HEIGHT,
+                            -delta_y * ORBIT_ANGLE_PER_WINDOW_HEIGHT,
+                        ),
+                        DragMode::Pan => {
+                            // Move the target along with the cursor
+                            let view_height =
+                                2.0 * self.distance * (0.5 * self.vertical_field_of_view).tan();
+                            self.pan(-delta_x * view_height, delta_y * view_height);
+                        }
+                    }
+                }
+                self.cursor_position = Some(position);
+            }
+            UserInput::Scrolled(delta) => {
+                self.zoom_steps(delta.vertical_lines());
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Default for Camera {
+    /// Creates a camera looking at the origin from unit distance.
+    fn default() -> Self {
+        Self::new([0.0; 3], 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::ScrollDelta;
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        for (&a_value, &b_value) in a.iter().zip(b) {
+            assert!(
+                (a_value - b_value).abs() < 1e-4,
+                "{:?} is not close to {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    /// Creates a camera looking along the x-axis at the origin from unit
+    /// distance.
+    fn horizontal_camera() -> Camera {
+        let mut camera = Camera::default();
+        camera.set_orientation(0.0, 0.0);
+        camera
+    }
+
+    /// Moves the cursor by the given amount while dragging in the given mode,
+    /// in a square window with the given side length.
+    fn drag(camera: &mut Camera, drag_mode: DragMode, delta: (i32, i32), window_size: u32) {
+        camera.update_from_input(
+            &UserInput::CursorMoved((10, 10)),
+            (window_size, window_size),
+        );
+        camera.start_dragging(drag_mode);
+        camera.update_from_input(
+            &UserInput::CursorMoved((10 + delta.0, 10 + delta.1)),
+            (window_size, window_size),
+        );
+        camera.stop_dragging();
+    }
+
+    #[test]
+    fn fit_to_bounding_box_makes_bounding_sphere_fill_view() {
+        let mut camera = Camera::default();
+        camera.fit_to_bounding_box([0.0, 0.0, 0.0], [2.0, 4.0, 4.0]);
+        assert_eq!(camera.target(), [1.0, 2.0, 2.0]);
+        let expected_distance = 3.0 / (0.5 * DEFAULT_VERTICAL_FIELD_OF_VIEW).sin();
+        assert!((camera.distance() - expected_distance).abs() < 1e-4);
+        assert_close(
+            &transform::subtract(camera.target(), camera.eye()),
+            &transform::add_scaled([0.0; 3], camera.view_direction(), expected_distance),
+        );
+
+        // The whole bounding sphere lies between the clip planes
+        let matrix = transform::multiply(&camera.projection_matrix(1.0), &camera.view_matrix());
+        let closest = transform::add_scaled(camera.target(), camera.view_direction(), -3.0);
+        let farthest = transform::add_scaled(camera.target(), camera.view_direction(), 3.0);
+        assert!(transform::transform_point(&matrix, closest)[2] >= 0.0);
+        assert!((transform::transform_point(&matrix, farthest)[2] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn zoom_is_clamped_relative_to_scene_radius() {
+        let mut camera = Camera::default();
+        camera.set_scene_radius(2.0);
+        camera.zoom(2.0);
+        assert_eq!(camera.distance(), 0.5);
+        camera.zoom(1e9);
+        assert_eq!(camera.distance(), MIN_RELATIVE_DISTANCE * 2.0);
+        camera.zoom(1e-12);
+        assert_eq!(camera.distance(), MAX_RELATIVE_DISTANCE * 2.0);
+    }
+
+    #[test]
+    fn scrolling_up_zooms_in() {
+        let mut camera = Camera::default();
+        camera.update_from_input(&UserInput::Scrolled(ScrollDelta::Lines(0.0, 1.0)), (1, 1));
+        assert!((camera.distance() - ZOOM_FACTOR_PER_LINE).abs() < 1e-6);
+        camera.update_from_input(&UserInput::Scrolled(ScrollDelta::Lines(0.0, -2.0)), (1, 1));
+        assert!((camera.distance() - 1.0 / ZOOM_FACTOR_PER_LINE).abs() < 1e-5);
+    }
+
+    #[test]
+    fn pan_moves_along_view_plane() {
+        let mut camera = horizontal_camera();
+        camera.pan(1.0, 0.0);
+        assert_close(&camera.target(), &[0.0, -1.0, 0.0]);
+        camera.pan(0.0, 2.0);
+        assert_close(&camera.target(), &[0.0, -1.0, 2.0]);
+        assert_eq!(camera.orientation(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn dragging_to_the_right_pans_scene_along_with_cursor() {
+        let mut camera = horizontal_camera();
+        drag(&mut camera, DragMode::Pan, (50, 0), 100);
+        let view_height = 2.0 * (0.5 * DEFAULT_VERTICAL_FIELD_OF_VIEW).tan();
+        // The camera moves left, so the scene follows the cursor to the right
+        assert_close(&camera.target(), &[0.0, 0.5 * view_height, 0.0]);
+
+        let mut camera = horizontal_camera();
+        drag(&mut camera, DragMode::Pan, (0, 50), 100);
+        assert_close(&camera.target(), &[0.0, 0.0, 0.5 * view_height]);
+    }
+
+    #[test]
+    fn dragging_orbits_around_target() {
+        let mut camera = Camera::default();
+        camera.set_orientation(PI, 0.0);
+        drag(&mut camera, DragMode::Orbit, (50, 0), 100);
+        let (yaw, pitch) = camera.orientation();
+        assert!((yaw - FRAC_PI_2).abs() < 1e-5);
+        assert_eq!(pitch, 0.0);
+
+        drag(&mut camera, DragMode::Orbit, (0, 25), 100);
+        let (_, pitch) = camera.orientation();
+        assert!((pitch + FRAC_PI_4).abs() < 1e-5);
+        assert_eq!(camera.target(), [0.0; 3]);
+        assert_eq!(camera.distance(), 1.0);
+
+        camera.orbit(0.0, 10.0);
+        assert_eq!(camera.orientation().1, MAX_PITCH);
+        camera.orbit(-2.0 * PI, 0.0);
+        assert!(
+            (camera.orientation().0 - FRAC_PI_2).abs() < 1e-5,
+            "{:?}",
+            camera.orientation()
+        );
+    }
+
+    #[test]
+    fn cursor_motion_without_dragging_leaves_camera_unchanged() {
+        let mut camera = Camera::default();
+        let original = camera.clone();
+        camera.start_dragging(DragMode::Orbit);
+        camera.update_from_input(&UserInput::FocusChanged(false), (100, 100));
+        assert_eq!(camera.drag_mode(), None);
+        camera.update_from_input(&UserInput::CursorMoved((0, 0)), (100, 100));
+        camera.update_from_input(&UserInput::CursorMoved((50, 50)), (100, 100));
+        assert_eq!(camera.orientation(), original.orientation());
+        assert_eq!(camera.target(), original.target());
+    }
+}
@@ -0,0 +1,484 @@
+//! Command line interface.
+
+use crate::{
+    camera::Projection,
+    color::colormap::{self, Colormap},
+    graphics::rendering::{backend::BackendKind, RenderMode},
+    io::{paraview, VolumeFormat},
+    running::{InputOptions, RunOptions},
+    transfer_function::OpacityPoint,
+};
+use clap::{App, Arg, ArgMatches, Error, ErrorKind};
+use log::Level;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Names of the log levels, from least to most verbose.
+const LOG_LEVEL_NAMES: [&str; 5] = ["error", "warn", "info", "debug", "trace"];
+
+/// Parses the command line arguments of the process into options for running
+/// the application.
+///
+/// Help or version information is printed and the process exits if requested,
+/// and an error is printed and the process exits if the arguments are invalid.
+pub fn parse_args() -> RunOptions {
+    parse_matches(&create_app().get_matches()).unwrap_or_else(|err| err.exit())
+}
+
+/// Creates the command line application with all supported arguments.
+pub fn create_app() -> App<'static, 'static> {
+    let volume_format_names: Vec<_> = VolumeFormat::names().collect();
+    let render_mode_names: Vec<_> = RenderMode::names().collect();
+    let backend_names: Vec<_> = BackendKind::available().map(BackendKind::name).collect();
+    App::new("vortek")
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("GPU-based volume renderer.")
+        .arg(
+            Arg::with_name("input")
+                .value_name("INPUT")
+                .help("Volume file to render")
+                .validator(|value| validate_file(Path::new(&value))),
+        )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Format of the input file [default: determined from the extension]")
+                .requires("input")
+                .possible_values(&volume_format_names),
+        )
+        .arg(
+            Arg::with_name("variable")
+                .short("v")
+                .long("variable")
+                .value_name("NAME")
+                .help(
+                    "Variable to render, for formats holding several volumes \
+                     (field, array, HDU index or snapshot variable)",
+                )
+                .requires("input"),
+        )
+        .arg(
+            Arg::with_name("size")
+                .short("s")
+                .long("size")
+                .value_name("WIDTHxHEIGHT")
+                .help("Logical size of the window, or pixel size of the image when headless")
+                .default_value("800x600")
+                .validator(|value| parse_size(&value).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("colormap")
+                .short("c")
+                .long("colormap")
+                .value_name("NAME")
+                .help("Built-in colormap to color the volume with (append _r to reverse it)")
+                .conflicts_with("preset-file")
+                .validator(|value| parse_colormap(&value).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("preset-file")
+                .long("preset-file")
+                .value_name("FILE")
+                .help("ParaView JSON file with transfer function presets")
+                .validator(|value| validate_file(Path::new(&value))),
+        )
+        .arg(
+            Arg::with_name("preset")
+                .short("p")
+                .long("preset")
+                .value_name("NAME")
+                .help("Preset in the preset file to use [default: the first one]")
+                .requires("preset-file"),
+        )
+        .arg(
+            Arg::with_name("render-mode")
+                .short("m")
+                .long("render-mode")
+                .value_name("MODE")
+                .help("How the samples along each ray are combined")
+                .default_value(RenderMode::default().name())
+                .possible_values(&render_mode_names),
+        )
+        .arg(
+            Arg::with_name("view")
+                .long("view")
+                .value_name("YAW,PITCH")
+                .help(
+                    "Initial camera direction as angles in degrees around and \
+                     above the horizontal plane",
+                )
+                .allow_hyphen_values(true)
+                .validator(|value| parse_finite_pair(&value).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("zoom")
+                .short("z")
+                .long("zoom")
+                .value_name("FACTOR")
+                .help("Initial zoom relative to the view of the whole volume")
+                .default_value("1")
+                .validator(|value| parse_positive_finite(&value).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("orthographic")
+                .long("orthographic")
+                .help("Use orthographic instead of perspective projection"),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .short("b")
+                .long("backend")
+                .value_name("NAME")
+                .help(
+                    "Graphics backend to try first [default: the one given by the \
+                     VORTEK_BACKEND environment variable, or the first one that works; \
+                     the empty backend is only used when given explicitly]",
+                )
+                .possible_values(&backend_names),
+        )
+        .arg(
+            Arg::with_name("log-level")
+                .short("l")
+                .long("log-level")
+                .value_name("LEVEL")
+                .help("Most verbose level of log messages to print")
+                .default_value("info")
+                .possible_values(&LOG_LEVEL_NAMES),
+        )
+        .arg(
+            Arg::with_name("headless")
+                .long("headless")
+                .help("Render a single image to the output file without opening a window")
+                .requires("output"),
+        )
+        .arg(
+            Arg::with_name("output")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .help("PNG file to write the image to when headless")
+                .requires("headless"),
+        )
+        .arg(
+            Arg::with_name("bindings")
+                .long("bindings")
+                .value_name("FILE")
+                .help(
+                    "TOML file binding user input to actions [default: the one given by the \
+                     VORTEK_BINDINGS environment variable, or bindings.toml in the working \
+                     directory if present]",
+                )
+                .validator(|value| validate_file(Path::new(&value))),
+        )
+        .arg(
+            Arg::with_name("screenshot-scale")
+                .long("screenshot-scale")
+                .value_name("FACTOR")
+                .help("Resolution of screenshots relative to the window")
+                .default_value("1")
+                .validator(|value| parse_positive::<u32>(&value).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("screenshot-dir")
+                .long("screenshot-dir")
+                .value_name("DIRECTORY")
+                .help("Directory to save screenshots in")
+                .default_value(".")
+                .validator(|value| {
+                    if Path::new(&value).is_dir() {
+                        Ok(())
+                    } else {
+                        Err(format!("{} is not a directory", value))
+                    }
+                }),
+        )
+}
+
+/// Converts the given matched arguments into options for running the
+/// application, or returns an error if they are inconsistent.
+pub fn parse_matches(matches: &ArgMatches) -> Result<RunOptions, Error> {
+    let mut options = RunOptions::default();
+
+    if let Some(path) = matches.value_of("input") {
+        let path = PathBuf::from(path);
+        let format = match matches.value_of("format") {
+            Some(name) => VolumeFormat::from_name(name),
+            None => Some(VolumeFormat::from_path(&path).ok_or_else(|| {
+                validation_error(format!(
+                    "Could not determine the format of {} from its extension, use --format",
+                    path.display()
+                ))
+            })?),
+        };
+        let variable = matches.value_of("variable").map(String::from);
+        if variable.is_some() && !format.is_some_and(VolumeFormat::has_variables) {
+            return Err(validation_error(format!(
+                "Variables cannot be selected for the {} format",
+                format.map_or("", VolumeFormat::name)
+            )));
+        }
+        options.input = Some(InputOptions {
+            path,
+            format,
+            variable,
+        });
+    }
+
+    options.viewer.window_size = parse_size(matches.value_of("size").unwrap()).unwrap();
+
+    if let Some(name) = matches.value_of("colormap") {
+        options.viewer.transfer_function =
+            parse_colormap(name).unwrap().to_transfer_function(vec![
+                OpacityPoint {
+                    position: 0.0,
+                    opacity: 0.0,
+                },
+                OpacityPoint {
+                    position: 1.0,
+                    opacity: 1.0,
+                },
+            ]);
+    } else if let Some(path) = matches.value_of("preset-file") {
+        let presets = paraview::read_paraview_presets(path)
+            .map_err(|err| validation_error(format!("Invalid preset file: {}", err)))?;
+        let preset = match matches.value_of("preset") {
+            Some(name) => presets.iter().find(|preset| preset.name == name),
+            None => presets.first(),
+        }
+        .ok_or_else(|| {
+            validation_error(format!(
+                "Preset {} not found in {} (available presets are {})",
+                matches.value_of("preset").unwrap_or(""),
+                path,
+                presets
+                    .iter()
+                    .map(|preset| preset.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))
+        })?;
+        options.viewer.transfer_function = preset.transfer_function.clone();
+    }
+
+    options.viewer.render_mode =
+        RenderMode::from_name(matches.value_of("render-mode").unwrap()).unwrap();
+
+    options.viewer.camera_orientation = matches.value_of("view").map(|value| {
+        let (yaw, pitch) = parse_finite_pair(value).unwrap();
+        (yaw.to_radians(), pitch.to_radians())
+    });
+    options.viewer.camera_zoom = parse_positive_finite(matches.value_of("zoom").unwrap()).unwrap();
+    if matches.is_present("orthographic") {
+        options.viewer.projection = Projection::Orthographic;
+    }
+
+    options.viewer.backend = matches.value_of("backend").and_then(BackendKind::from_name);
+
+    options.log_level = Level::from_str(matches.value_of("log-level").unwrap()).unwrap();
+
+    if matches.is_present("headless") {
+        options.headless_output = matches.value_of("output").map(PathBuf::from);
+    }
+
+    options.bindings_file = matches.value_of("bindings").map(PathBuf::from);
+
+    options.viewer.screenshot_scale =
+        parse_positive(matches.value_of("screenshot-scale").unwrap()).unwrap();
+    options.viewer.screenshot_directory =
+        PathBuf::from(matches.value_of("screenshot-dir").unwrap());
+
+    Ok(options)
+}
+
+fn validation_error<S: Display>(message: S) -> Error {
+    Error::with_description(&message.to_string(), ErrorKind::ValueValidation)
+}
+
+fn validate_file(path: &Path) -> Result<(), String> {
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(format!("{} is not an existing file", path.display()))
+    }
+}
+
+/// Parses a size of the form `WIDTHxHEIGHT` with positive integer dimensions.
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let invalid = || format!("invalid size {} (expected WIDTHxHEIGHT)", value);
+    let (width, height) = value.split_once('x').ok_or_else(invalid)?;
+    match (width.trim().parse(), height.trim().parse()) {
+        (Ok(width), Ok(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses two comma-separated finite numbers.
+fn parse_finite_pair(value: &str) -> Result<(f32, f32), String> {
+    let invalid = || {
+        format!(
+            "invalid value {} (expected two comma-separated finite numbers)",
+            value
+        )
+    };
+    let (first, second) = value.split_once(',').ok_or_else(invalid)?;
+    match (first.trim().parse::<f32>(), second.trim().parse::<f32>()) {
+        (Ok(first), Ok(second)) if first.is_finite() && second.is_finite() => Ok((first, second)),
+        _ => Err(invalid()),
+    }
+}
+
+/// Parses a finite number that must be larger than zero.
+fn parse_positive_finite(value: &str) -> Result<f32, String> {
+    match value.parse::<f32>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+        _ => Err(format!(
+            "invalid value {} (expected a positive finite number)",
+            value
+        )),
+    }
+}
+
+/// Parses a number that must be larger than zero.
+fn parse_positive<T: FromStr + PartialOrd + Default>(value: &str) -> Result<T, String> {
+    match value.parse() {
+        Ok(number) if number > T::default() => Ok(number),
+        _ => Err(format!(
+            "invalid value {} (expected a positive number)",
+            value
+        )),
+    }
+}
+
+fn parse_colormap(name: &str) -> Result<Colormap, String> {
+    Colormap::by_name(name).ok_or_else(|| {
+        format!(
+            "unknown colormap {} (built-in colormaps are {})",
+            name,
+            colormap::BUILTIN_NAMES.join(", ")
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::temporary_path;
+    use std::{fs, iter};
+
+    fn parse(args: &[&str]) -> Result<RunOptions, Error> {
+        create_app()
+            .get_matches_from_safe(iter::once("vortek").chain(args.iter().copied()))
+            .and_then(|matches| parse_matches(&matches))
+    }
+
+    fn parse_with_file(name: &str, contents: &str, args: &[&str]) -> Result<RunOptions, Error> {
+        let path = temporary_path(name);
+        fs::write(&path, contents).unwrap();
+        let path_str = path.to_str().unwrap();
+        let args: Vec<&str> = args
+            .iter()
+            .map(|&arg| if arg == "FILE" { path_str } else { arg })
+            .collect();
+        let result = parse(&args);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    #[test]
+    fn defaults_are_used_without_arguments() {
+        let options = parse(&[]).unwrap();
+        assert!(options.input.is_none());
+        assert!(options.headless_output.is_none());
+        assert_eq!(options.log_level, Level::Info);
+        assert_eq!(options.viewer.window_size, (800, 600));
+        assert_eq!(options.viewer.camera_orientation, None);
+        assert_eq!(options.viewer.camera_zoom, 1.0);
+    }
+
+    #[test]
+    fn headless_requires_output() {
+        let err = parse(&["--headless"]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::MissingRequiredArgument);
+
+        let options = parse(&["--headless", "--output", "image.png"]).unwrap();
+        assert_eq!(options.headless_output, Some(PathBuf::from("image.png")));
+    }
+
+    #[test]
+    fn variable_requires_format_with_variables() {
+        let err = parse_with_file("single.nrrd", "", &["FILE", "--variable", "a"]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ValueValidation);
+
+        let options = parse_with_file("several.npz", "", &["FILE", "--variable", "a"]).unwrap();
+        let input = options.input.unwrap();
+        assert_eq!(input.format, Some(VolumeFormat::Npz));
+        assert_eq!(input.variable.as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn parses_bindings_file() {
+        assert!(parse(&[]).unwrap().bindings_file.is_none());
+        let options = parse_with_file("bindings.toml", "", &["--bindings", "FILE"]).unwrap();
+        assert_eq!(options.bindings_file, Some(temporary_path("bindings.toml")));
+        let err = parse(&["--bindings", "missing.toml"]).unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ValueValidation);
+    }
+
+    #[test]
+    fn unknown_preset_is_an_error() {
+        let presets = r#"[{"Name": "Gray", "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1]}]"#;
+        let err = parse_with_file(
+            "unknown.json",
+            presets,
+            &["--preset-file", "FILE", "--preset", "Other"],
+        )
+        .unwrap_err();
+        assert_eq!(err.kind, ErrorKind::ValueValidation);
+
+        assert!(parse_with_file(
+            "known.json",
+            presets,
+            &["--preset-file", "FILE", "--preset", "Gray"]
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn parses_size_view_and_zoom() {
+        let options = parse(&["-s", "640x480", "--view", "-90,45", "-z", "2.5"]).unwrap();
+        assert_eq!(options.viewer.window_size, (640, 480));
+        assert_eq!(
+            options.viewer.camera_orientation,
+            Some((-90f32.to_radians(), 45f32.to_radians()))
+        );
+        assert_eq!(options.viewer.camera_zoom, 2.5);
+    }
+
+    #[test]
+    fn invalid_size_view_and_zoom_are_errors() {
+        for args in [
+            ["-s", "640"],
+            ["-s", "0x480"],
+            ["--view", "30"],
+            ["--view", "a,b"],
+            ["--view", "NaN,0"],
+            ["--view", "0,inf"],
+            ["-z", "0"],
+            ["-z", "large"],
+            ["-z", "inf"],
+            ["-z", "NaN"],
+        ] {
+            assert_eq!(
+                parse(&args).unwrap_err().kind,
+                ErrorKind::ValueValidation,
+                "{:?}",
+                args
+            );
+        }
+    }
+}
@@ -1,9 +1,54 @@
 //! Representation of colors.
+//!
+//! Colors are stored with sRGB-encoded red, green and blue components, as used
+//! in color pickers, hex codes and colormap tables. Conversions to linear RGB
+//! and to perceptual color spaces are provided for blending and interpolation.
+
+pub mod colormap;
+
+/// Lab component δ = 6/29, separating the linear and cubic parts of the transfer
+/// function between XYZ and Lab.
+const LAB_DELTA: f32 = 6.0 / 29.0;
+
+/// XYZ coordinates of the D65 reference white.
+const D65_WHITE: [f32; 3] = [0.950_47, 1.0, 1.088_83];
+
+/// Msh saturation below which a color counts as unsaturated in diverging
+/// interpolation.
+const MIN_DIVERGING_SATURATION: f32 = 0.05;
+/// Smallest Msh magnitude of the unsaturated midpoint inserted in diverging
+/// interpolation.
+const MIN_DIVERGING_MIDPOINT_MAGNITUDE: f32 = 88.0;
 
 /// Color represented with red, green, blue and alpha components.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Color([f32; 4]);
 
+/// Color space in which colors can be interpolated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum ColorSpace {
+    /// sRGB-encoded red, green and blue components.
+    #[default]
+    Srgb,
+    /// Linear red, green and blue components, giving physically correct
+    /// blending of light.
+    LinearRgb,
+    /// Hue, saturation and value.
+    Hsv,
+    /// Hue, saturation and lightness.
+    Hsl,
+    /// CIE L*a*b*, with the D65 reference white.
+    Lab,
+    /// Cylindrical representation of CIE L*a*b* with lightness, chroma and hue.
+    Lch,
+    /// Oklab perceptual color space.
+    Oklab,
+    /// Kenneth Moreland's polar Msh representation of CIE L*a*b*, where
+    /// interpolation between saturated colors of distinct hues passes through
+    /// an unsaturated midpoint as in diverging colormaps.
+    Diverging,
+}
+
 impl Color {
     pub fn from_components(red: f32, green: f32, blue: f32, alpha: f32) -> Self {
         Self::from_slice([red, green, blue, alpha])
@@ -18,6 +63,11 @@ impl Color {
         Self([0.0, 0.0, 0.0, 1.0])
     }
 
+    /// Creates a white color.
+    pub fn white() -> Self {
+        Self([1.0, 1.0, 1.0, 1.0])
+    }
+
     /// Returns a 4-element slice containing the red, green, blue and alpha values of
     /// the color.
     pub fn to_slice(&self) -> [f32; 4] {
@@ -30,4 +80,610 @@ impl Color {
     pub fn into_slice(self) -> [f32; 4] {
         self.0
     }
+
+    /// Returns the alpha component of the color.
+    pub fn alpha(&self) -> f32 {
+        self.0[3]
+    }
+
+    /// Parses a hex color code of the form `#rgb`, `#rrggbb` or `#rrggbbaa`,
+    /// where the leading `#` is optional. Returns `None` if the code is invalid.
+    pub fn from_hex(code: &str) -> Option<Self> {
+        let digits = code.strip_prefix('#').unwrap_or(code);
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        let parse = |digits: &str| u8::from_str_radix(digits, 16).ok();
+        let components: Vec<u8> = match digits.len() {
+            3 => digits
+                .chars()
+                .map(|digit| parse(&digit.to_string().repeat(2)))
+                .collect::<Option<_>>()?,
+            6 | 8 => (0..digits.len())
+                .step_by(2)
+                .map(|start| parse(&digits[start..start + 2]))
+                .collect::<Option<_>>()?,
+            _ => return None,
+        };
+        let mut color = Self::black();
+        for (component, &value) in color.0.iter_mut().zip(&components) {
+            *component = f32::from(value) / 255.0;
+        }
+        Some(color)
+    }
+
+    /// Formats the color as a hex color code of the form `#rrggbb`, or
+    /// `#rrggbbaa` if the color is not fully opaque.
+    pub fn to_hex(&self) -> String {
+        let to_byte = |component: f32| (component.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let [red, green, blue, alpha] = self.0;
+        let mut code = format!(
+            "#{:02x}{:02x}{:02x}",
+            to_byte(red),
+            to_byte(green),
+            to_byte(blue)
+        );
+        if to_byte(alpha) < u8::MAX {
+            code.push_str(&format!("{:02x}", to_byte(alpha)));
+        }
+        code
+    }
+
+    /// Returns the color with its red, green and blue components converted from
+    /// sRGB encoding to linear intensities.
+    pub fn srgb_to_linear(&self) -> Self {
+        let [red, green, blue, alpha] = self.0;
+        Self([
+            srgb_component_to_linear(red),
+            srgb_component_to_linear(green),
+            srgb_component_to_linear(blue),
+            alpha,
+        ])
+    }
+
+    /// Returns the color with its red, green and blue components converted from
+    /// linear intensities to sRGB encoding.
+    pub fn linear_to_srgb(&self) -> Self {
+        let [red, green, blue, alpha] = self.0;
+        Self([
+            linear_component_to_srgb(red),
+            linear_component_to_srgb(green),
+            linear_component_to_srgb(blue),
+            alpha,
+        ])
+    }
+
+    /// Returns the color with its red, green and blue components multiplied by
+    /// its alpha.
+    pub fn premultiplied(&self) -> Self {
+        let [red, green, blue, alpha] = self.0;
+        Self([red * alpha, green * alpha, blue * alpha, alpha])
+    }
+
+    /// Returns the color with its red, green and blue components divided by its
+    /// alpha, reversing premultiplication. Fully transparent colors become
+    /// transparent black.
+    pub fn unpremultiplied(&self) -> Self {
+        let [red, green, blue, alpha] = self.0;
+        if alpha > 0.0 {
+            Self([red / alpha, green / alpha, blue / alpha, alpha])
+        } else {
+            Self([0.0; 4])
+        }
+    }
+
+    /// Creates a color from the given hue in degrees, saturation and value
+    /// between zero and one, and alpha.
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, alpha: f32) -> Self {
+        let chroma = value * saturation;
+        Self::from_hue_and_chroma(hue, chroma, value - chroma, alpha)
+    }
+
+    /// Returns the hue in degrees, saturation and value of the color.
+    pub fn to_hsv(&self) -> [f32; 3] {
+        let (hue, chroma, max, _) = self.hue_and_chroma();
+        let saturation = if max > 0.0 { chroma / max } else { 0.0 };
+        [hue, saturation, max]
+    }
+
+    /// Creates a color from the given hue in degrees, saturation and lightness
+    /// between zero and one, and alpha.
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, alpha: f32) -> Self {
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+        Self::from_hue_and_chroma(hue, chroma, lightness - 0.5 * chroma, alpha)
+    }
+
+    /// Returns the hue in degrees, saturation and lightness of the color.
+    pub fn to_hsl(&self) -> [f32; 3] {
+        let (hue, chroma, max, min) = self.hue_and_chroma();
+        let lightness = 0.5 * (max + min);
+        let saturation = if lightness > 0.0 && lightness < 1.0 {
+            chroma / (1.0 - (2.0 * lightness - 1.0).abs())
+        } else {
+            0.0
+        };
+        [hue, saturation, lightness]
+    }
+
+    /// Creates a color from the given CIE L*a*b* coordinates and alpha, with
+    /// components clamped to the displayable range.
+    pub fn from_lab([lightness, a, b]: [f32; 3], alpha: f32) -> Self {
+        let inverse_f = |t: f32| {
+            if t > LAB_DELTA {
+                t * t * t
+            } else {
+                3.0 * LAB_DELTA * LAB_DELTA * (t - 4.0 / 29.0)
+            }
+        };
+        let f_y = (lightness + 16.0) / 116.0;
+        let x = D65_WHITE[0] * inverse_f(f_y + a / 500.0);
+        let y = D65_WHITE[1] * inverse_f(f_y);
+        let z = D65_WHITE[2] * inverse_f(f_y - b / 200.0);
+        Self::from_linear_rgb(
+            [
+                3.240_454_2 * x - 1.537_138_5 * y - 0.498_531_4 * z,
+                -0.969_266 * x + 1.876_010_8 * y + 0.041_556 * z,
+                0.055_643_4 * x - 0.204_025_9 * y + 1.057_225_2 * z,
+            ],
+            alpha,
+        )
+    }
+
+    /// Returns the CIE L*a*b* coordinates of the color.
+    pub fn to_lab(&self) -> [f32; 3] {
+        let [red, green, blue, _] = self.srgb_to_linear().0;
+        let x = 0.412_456_4 * red + 0.357_576_1 * green + 0.180_437_5 * blue;
+        let y = 0.212_672_9 * red + 0.715_152_2 * green + 0.072_175 * blue;
+        let z = 0.019_333_9 * red + 0.119_192 * green + 0.950_304_1 * blue;
+        let f = |t: f32| {
+            if t > LAB_DELTA * LAB_DELTA * LAB_DELTA {
+                t.cbrt()
+            } else {
+                t / (3.0 * LAB_DELTA * LAB_DELTA) + 4.0 / 29.0
+            }
+        };
+        let f_x = f(x / D65_WHITE[0]);
+        let f_y = f(y / D65_WHITE[1]);
+        let f_z = f(z / D65_WHITE[2]);
+        [116.0 * f_y - 16.0, 500.0 * (f_x - f_y), 200.0 * (f_y - f_z)]
+    }
+
+    /// Creates a color from the given CIE LCh lightness, chroma and hue in
+    /// degrees, and alpha, with components clamped to the displayable range.
+    pub fn from_lch([lightness, chroma, hue]: [f32; 3], alpha: f32) -> Self {
+        let hue = hue.to_radians();
+        Self::from_lab([lightness, chroma * hue.cos(), chroma * hue.sin()], alpha)
+    }
+
+    /// Returns the CIE LCh lightness, chroma and hue in degrees of the color.
+    pub fn to_lch(&self) -> [f32; 3] {
+        let [lightness, a, b] = self.to_lab();
+        [
+            lightness,
+            a.hypot(b),
+            b.atan2(a).to_degrees().rem_euclid(360.0),
+        ]
+    }
+
+    /// Creates a color from the given Oklab coordinates and alpha, with
+    /// components clamped to the displayable range.
+    pub fn from_oklab([lightness, a, b]: [f32; 3], alpha: f32) -> Self {
+        let l = (lightness + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+        let m = (lightness - 0.105_561_35 * a - 0.063_854_17 * b).powi(3);
+        let s = (lightness - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+        Self::from_linear_rgb(
+            [
+                4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+                -1.268_438 * l + 2.609_757_4 * m - 0.341_319_4 * s,
+                -0.004_196_086 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+            ],
+            alpha,
+        )
+    }
+
+    /// Returns the Oklab coordinates of the color.
+    pub fn to_oklab(&self) -> [f32; 3] {
+        let [red, green, blue, _] = self.srgb_to_linear().0;
+        let l = (0.412_221_46 * red + 0.536_332_55 * green + 0.051_445_995 * blue).cbrt();
+        let m = (0.211_903_5 * red + 0.680_699_5 * green + 0.107_396_96 * blue).cbrt();
+        let s = (0.088_302_46 * red + 0.281_718_85 * green + 0.629_978_7 * blue).cbrt();
+        [
+            0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+            1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+            0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+        ]
+    }
+
+    /// Interpolates between this and the given color in the given color space,
+    /// where a fraction of zero gives this color and one gives the other color.
+    ///
+    /// Hues are interpolated along the shortest way around the color wheel.
+    /// Alpha is always interpolated linearly.
+    pub fn interpolate(&self, other: &Self, fraction: f32, space: ColorSpace) -> Self {
+        let alpha = lerp(self.alpha(), other.alpha(), fraction);
+        let lerp_triple = |a: [f32; 3], b: [f32; 3]| {
+            [
+                lerp(a[0], b[0], fraction),
+                lerp(a[1], b[1], fraction),
+                lerp(a[2], b[2], fraction),
+            ]
+        };
+        match space {
+            ColorSpace::Srgb => {
+                let [red, green, blue] = lerp_triple(rgb(self), rgb(other));
+                Self([red, green, blue, alpha])
+            }
+            ColorSpace::LinearRgb => {
+                let interpolated =
+                    lerp_triple(rgb(&self.srgb_to_linear()), rgb(&other.srgb_to_linear()));
+                Self::from_linear_rgb(interpolated, alpha)
+            }
+            ColorSpace::Hsv => {
+                let [hue, saturation, value] =
+                    lerp_polar(self.to_hsv(), other.to_hsv(), 0, 1, fraction);
+                Self::from_hsv(hue, saturation, value, alpha)
+            }
+            ColorSpace::Hsl => {
+                let [hue, saturation, lightness] =
+                    lerp_polar(self.to_hsl(), other.to_hsl(), 0, 1, fraction);
+                Self::from_hsl(hue, saturation, lightness, alpha)
+            }
+            ColorSpace::Lab => Self::from_lab(lerp_triple(self.to_lab(), other.to_lab()), alpha),
+            ColorSpace::Lch => Self::from_lch(
+                lerp_polar(self.to_lch(), other.to_lch(), 2, 1, fraction),
+                alpha,
+            ),
+            ColorSpace::Oklab => {
+                Self::from_oklab(lerp_triple(self.to_oklab(), other.to_oklab()), alpha)
+            }
+            ColorSpace::Diverging => Self::from_lab(
+                msh_to_lab(interpolate_diverging(
+                    lab_to_msh(self.to_lab()),
+                    lab_to_msh(other.to_lab()),
+                    fraction,
+                )),
+                alpha,
+            ),
+        }
+    }
+
+    /// Creates a color from linear red, green and blue intensities, which are
+    /// clamped to the range [0, 1] before encoding.
+    fn from_linear_rgb([red, green, blue]: [f32; 3], alpha: f32) -> Self {
+        Self([
+            red.clamp(0.0, 1.0),
+            green.clamp(0.0, 1.0),
+            blue.clamp(0.0, 1.0),
+            alpha,
+        ])
+        .linear_to_srgb()
+    }
+
+    /// Creates a color from the given hue in degrees and chroma, with the given
+    /// amount added to all of the red, green and blue components.
+    fn from_hue_and_chroma(hue: f32, chroma: f32, offset: f32, alpha: f32) -> Self {
+        let sector = hue.rem_euclid(360.0) / 60.0;
+        let intermediate = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+        let [red, green, blue] = match sector as u32 {
+            0 => [chroma, intermediate, 0.0],
+            1 => [intermediate, chroma, 0.0],
+            2 => [0.0, chroma, intermediate],
+            3 => [0.0, intermediate, chroma],
+            4 => [intermediate, 0.0, chroma],
+            _ => [chroma, 0.0, intermediate],
+        };
+        Self([red + offset, green + offset, blue + offset, alpha])
+    }
+
+    /// Returns the hue in degrees, the chroma and the largest and smallest of
+    /// the red, green and blue components.
+    fn hue_and_chroma(&self) -> (f32, f32, f32, f32) {
+        let [red, green, blue, _] = self.0;
+        let max = red.max(green).max(blue);
+        let min = red.min(green).min(blue);
+        let chroma = max - min;
+        let hue = if chroma <= 0.0 {
+            0.0
+        } else if max == red {
+            60.0 * ((green - blue) / chroma).rem_euclid(6.0)
+        } else if max == green {
+            60.0 * ((blue - red) / chroma + 2.0)
+        } else {
+            60.0 * ((red - green) / chroma + 4.0)
+        };
+        (hue, chroma, max, min)
+    }
+}
+
+fn srgb_component_to_linear(component: f32) -> f32 {
+    if component <= 0.040_45 {
+        component / 12.92
+    } else {
+        ((component + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_component_to_srgb(component: f32) -> f32 {
+    if component <= 0.003_130_8 {
+        component * 12.92
+    } else {
+        1.055 * component.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn rgb(color: &Color) -> [f32; 3] {
+    let [red, green, blue, _] = color.0;
+    [red, green, blue]
+}
+
+fn lerp(a: f32, b: f32, fraction: f32) -> f32 {
+    a + fraction * (b - a)
+}
+
+/// Interpolates between coordinates containing a hue in degrees at the given
+/// hue index, going the shortest way around the color wheel. If the color
+/// intensity at the given intensity index vanishes for one of the colors, the
+/// hue of the other color is used.
+fn lerp_polar(
+    mut a: [f32; 3],
+    mut b: [f32; 3],
+    hue_index: usize,
+    intensity_index: usize,
+    fraction: f32,
+) -> [f32; 3] {
+    if a[intensity_index] <= f32::EPSILON {
+        a[hue_index] = b[hue_index];
+    } else if b[intensity_index] <= f32::EPSILON {
+        b[hue_index] = a[hue_index];
+    }
+    let hue_difference = (b[hue_index] - a[hue_index] + 180.0).rem_euclid(360.0) - 180.0;
+    let mut interpolated = [0.0; 3];
+    for (index, value) in interpolated.iter_mut().enumerate() {
+        *value = if index == hue_index {
+            (a[index] + fraction * hue_difference).rem_euclid(360.0)
+        } else {
+            lerp(a[index], b[index], fraction)
+        };
+    }
+    interpolated
+}
+
+fn lab_to_msh([lightness, a, b]: [f32; 3]) -> [f32; 3] {
+    let magnitude = (lightness * lightness + a * a + b * b).sqrt();
+    let saturation = if magnitude > 0.0 {
+        (lightness / magnitude).clamp(-1.0, 1.0).acos()
+    } else {
+        0.0
+    };
+    [magnitude, saturation, b.atan2(a)]
+}
+
+fn msh_to_lab([magnitude, saturation, hue]: [f32; 3]) -> [f32; 3] {
+    [
+        magnitude * saturation.cos(),
+        magnitude * saturation.sin() * hue.cos(),
+        magnitude * saturation.sin() * hue.sin(),
+    ]
+}
+
+/// Interpolates between the given Msh coordinates the way Moreland's diverging
+/// colormaps do, inserting an unsaturated midpoint between saturated colors of
+/// distinct hues and spinning the hue of unsaturated colors towards the
+/// saturated one.
+fn interpolate_diverging(mut a: [f32; 3], mut b: [f32; 3], mut fraction: f32) -> [f32; 3] {
+    if a[1] > MIN_DIVERGING_SATURATION
+        && b[1] > MIN_DIVERGING_SATURATION
+        && angle_difference(a[2], b[2]) > std::f32::consts::FRAC_PI_3
+    {
+        let midpoint_magnitude = a[0].max(b[0]).max(MIN_DIVERGING_MIDPOINT_MAGNITUDE);
+        if fraction < 0.5 {
+            b = [midpoint_magnitude, 0.0, 0.0];
+            fraction *= 2.0;
+        } else {
+            a = [midpoint_magnitude, 0.0, 0.0];
+            fraction = 2.0 * fraction - 1.0;
+        }
+    }
+    if a[1] < MIN_DIVERGING_SATURATION && b[1] > MIN_DIVERGING_SATURATION {
+        a[2] = adjust_diverging_hue(b, a[0]);
+    } else if b[1] < MIN_DIVERGING_SATURATION && a[1] > MIN_DIVERGING_SATURATION {
+        b[2] = adjust_diverging_hue(a, b[0]);
+    }
+    [
+        lerp(a[0], b[0], fraction),
+        lerp(a[1], b[1], fraction),
+        lerp(a[2], b[2], fraction),
+    ]
+}
+
+/// Returns the hue an unsaturated color with the given magnitude should have
+/// when interpolated towards the given saturated Msh color.
+fn adjust_diverging_hue([magnitude, saturation, hue]: [f32; 3], unsaturated_magnitude: f32) -> f32 {
+    if magnitude >= unsaturated_magnitude {
+        return hue;
+    }
+    let spin = saturation
+        * (unsaturated_magnitude * unsaturated_magnitude - magnitude * magnitude).sqrt()
+        / (magnitude * saturation.sin());
+    if hue > -std::f32::consts::FRAC_PI_3 {
+        hue + spin
+    } else {
+        hue - spin
+    }
+}
+
+/// Returns the absolute difference between the given angles in radians, in
+/// the range [0, π].
+fn angle_difference(a: f32, b: f32) -> f32 {
+    let difference = (a - b).abs() % (2.0 * std::f32::consts::PI);
+    difference.min(2.0 * std::f32::consts::PI - difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f32], b: &[f32], tolerance: f32) {
+        assert_eq!(a.len(), b.len());
+        for (&a_value, &b_value) in a.iter().zip(b) {
+            assert!(
+                (a_value - b_value).abs() <= tolerance,
+                "{:?} is not close to {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn srgb_and_linear_round_trip() {
+        let gray = Color::from_components(0.5, 0.5, 0.5, 0.25);
+        assert_close(
+            &gray.srgb_to_linear().to_slice(),
+            &[0.214_041, 0.214_041, 0.214_041, 0.25],
+            1e-5,
+        );
+        for &component in &[0.0, 0.002, 0.04, 0.1, 0.5, 0.9, 1.0] {
+            let color = Color::from_components(component, 1.0 - component, 0.5, 1.0);
+            assert_close(
+                &color.srgb_to_linear().linear_to_srgb().to_slice(),
+                &color.to_slice(),
+                1e-5,
+            );
+            assert_close(
+                &color.linear_to_srgb().srgb_to_linear().to_slice(),
+                &color.to_slice(),
+                1e-5,
+            );
+        }
+    }
+
+    #[test]
+    fn hex_codes_are_parsed_and_formatted() {
+        let color = Color::from_hex("#f80").unwrap();
+        assert_close(&color.to_slice(), &[1.0, 136.0 / 255.0, 0.0, 1.0], 1e-6);
+        assert_eq!(color.to_hex(), "#ff8800");
+
+        let color = Color::from_hex("3366CC").unwrap();
+        assert_eq!(color.to_hex(), "#3366cc");
+
+        let color = Color::from_hex("#ff000080").unwrap();
+        assert_close(&color.to_slice(), &[1.0, 0.0, 0.0, 128.0 / 255.0], 1e-6);
+        assert_eq!(color.to_hex(), "#ff000080");
+
+        for &code in &[
+            "",
+            "#",
+            "#12",
+            "#1234",
+            "#12345",
+            "#ggg",
+            "#12345g",
+            "#+1234",
+            "#ff00ff00ff",
+        ] {
+            assert_eq!(Color::from_hex(code), None, "{} should be invalid", code);
+        }
+    }
+
+    #[test]
+    fn lab_and_lch_match_reference_values() {
+        assert_close(&Color::white().to_lab(), &[100.0, 0.0, 0.0], 1e-2);
+        assert_close(&Color::black().to_lab(), &[0.0, 0.0, 0.0], 1e-4);
+
+        let red = Color::from_components(1.0, 0.0, 0.0, 1.0);
+        assert_close(&red.to_lab(), &[53.24, 80.09, 67.20], 2e-2);
+        assert_close(&red.to_lch(), &[53.24, 104.55, 40.0], 2e-2);
+        let blue = Color::from_components(0.0, 0.0, 1.0, 1.0);
+        assert_close(&blue.to_lab(), &[32.30, 79.19, -107.86], 2e-2);
+        assert_close(&blue.to_lch(), &[32.30, 133.81, 306.29], 2e-2);
+
+        let color = Color::from_components(0.2, 0.6, 0.4, 0.5);
+        assert_close(
+            &Color::from_lab(color.to_lab(), 0.5).to_slice(),
+            &color.to_slice(),
+            1e-4,
+        );
+        assert_close(
+            &Color::from_lch(color.to_lch(), 0.5).to_slice(),
+            &color.to_slice(),
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn oklab_matches_reference_values() {
+        assert_close(&Color::white().to_oklab(), &[1.0, 0.0, 0.0], 1e-4);
+        let red = Color::from_components(1.0, 0.0, 0.0, 1.0);
+        assert_close(&red.to_oklab(), &[0.627_955, 0.224_863, 0.125_846], 1e-4);
+        let blue = Color::from_components(0.0, 0.0, 1.0, 1.0);
+        assert_close(&blue.to_oklab(), &[0.452_014, -0.032_457, -0.311_528], 1e-4);
+
+        let color = Color::from_components(0.2, 0.6, 0.4, 1.0);
+        assert_close(
+            &Color::from_oklab(color.to_oklab(), 1.0).to_slice(),
+            &color.to_slice(),
+            1e-4,
+        );
+    }
+
+    #[test]
+    fn hue_interpolation_wraps_around() {
+        assert_close(
+            &lerp_polar([350.0, 1.0, 1.0], [10.0, 1.0, 1.0], 0, 1, 0.5),
+            &[0.0, 1.0, 1.0],
+            1e-4,
+        );
+        assert_close(
+            &lerp_polar([10.0, 1.0, 1.0], [350.0, 1.0, 1.0], 0, 1, 0.75),
+            &[355.0, 1.0, 1.0],
+            1e-4,
+        );
+        // The hue of a color without saturation is taken from the other color
+        assert_close(
+            &lerp_polar([0.0, 0.0, 1.0], [240.0, 1.0, 1.0], 0, 1, 0.5),
+            &[240.0, 0.5, 1.0],
+            1e-4,
+        );
+
+        let start = Color::from_hsv(350.0, 1.0, 1.0, 1.0);
+        let end = Color::from_hsv(10.0, 1.0, 1.0, 1.0);
+        let middle = start.interpolate(&end, 0.5, ColorSpace::Hsv);
+        assert_close(&middle.to_slice(), &[1.0, 0.0, 0.0, 1.0], 1e-4);
+
+        let start = Color::from_hsl(340.0, 1.0, 0.5, 1.0);
+        let end = Color::from_hsl(20.0, 1.0, 0.5, 1.0);
+        let middle = start.interpolate(&end, 0.5, ColorSpace::Hsl);
+        assert_close(&middle.to_slice(), &[1.0, 0.0, 0.0, 1.0], 1e-4);
+    }
+
+    #[test]
+    fn diverging_interpolation_passes_through_unsaturated_midpoint() {
+        // End points of Moreland's cool to warm colormap
+        let cool = Color::from_components(0.230, 0.299, 0.754, 1.0);
+        let warm = Color::from_components(0.706, 0.016, 0.150, 1.0);
+
+        let [lightness, chroma, _] = cool.interpolate(&warm, 0.5, ColorSpace::Diverging).to_lch();
+        assert!(chroma < 1.0, "midpoint chroma {} is too high", chroma);
+        assert!((lightness - MIN_DIVERGING_MIDPOINT_MAGNITUDE).abs() < 1.0);
+
+        // Straight Lab interpolation instead passes through a saturated purple
+        let [_, chroma, _] = cool.interpolate(&warm, 0.5, ColorSpace::Lab).to_lch();
+        assert!(chroma > 20.0);
+
+        assert_close(
+            &cool
+                .interpolate(&warm, 0.0, ColorSpace::Diverging)
+                .to_slice(),
+            &cool.to_slice(),
+            1e-3,
+        );
+        assert_close(
+            &cool
+                .interpolate(&warm, 1.0, ColorSpace::Diverging)
+                .to_slice(),
+            &warm.to_slice(),
+            1e-3,
+        );
+    }
 }
@@ -0,0 +1,441 @@
+//! Built-in scientific colormaps.
+
+mod data;
+
+use super::{Color, ColorSpace};
+use crate::transfer_function::{self, ColorPoint, OpacityPoint, TransferFunction};
+
+/// Names of the built-in colormaps, as accepted by `Colormap::by_name`.
+pub const BUILTIN_NAMES: &[&str] = &[
+    "viridis",
+    "inferno",
+    "magma",
+    "plasma",
+    "cividis",
+    "coolwarm",
+    "RdBu",
+    "sdoaia94",
+    "sdoaia131",
+    "sdoaia171",
+    "sdoaia193",
+    "sdoaia211",
+    "sdoaia304",
+    "sdoaia335",
+    "sdoaia1600",
+    "sdoaia1700",
+    "sdoaia4500",
+];
+
+/// Wavelengths in ångström of the SDO/AIA channels with built-in colormaps.
+pub const SDO_AIA_WAVELENGTHS: &[u32] = &[94, 131, 171, 193, 211, 304, 335, 1600, 1700, 4500];
+
+/// Suffix added to the name of a reversed colormap.
+const REVERSED_SUFFIX: &str = "_r";
+
+/// Number of colors in the tables of the SDO/AIA colormaps.
+const SDO_AIA_TABLE_SIZE: usize = 256;
+
+/// Colormap going through a sequence of colors over the normalized range
+/// [0, 1], with linear interpolation between the control points.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Colormap {
+    name: String,
+    color_points: Vec<ColorPoint>,
+}
+
+impl Colormap {
+    /// Creates a new colormap with the given name and control points, which need
+    /// not be sorted.
+    ///
+    /// # Panics
+    /// If there are no points, or if any position is outside the range [0, 1].
+    pub fn new<S: Into<String>>(name: S, mut color_points: Vec<ColorPoint>) -> Self {
+        assert!(!color_points.is_empty(), "No colormap control points.");
+        for point in color_points.iter_mut() {
+            assert!(
+                (0.0..=1.0).contains(&point.position),
+                "Control point position {} is outside the range [0, 1].",
+                point.position
+            );
+            point.color = opaque(&point.color);
+        }
+        color_points.sort_by(|a, b| a.position.total_cmp(&b.position));
+        Self {
+            name: name.into(),
+            color_points,
+        }
+    }
+
+    /// Creates the built-in colormap with the given name, ignoring case, or
+    /// returns `None` if there is no such colormap.
+    ///
+    /// Reversed versions of the colormaps are available by appending `_r` to
+    /// the name.
+    pub fn by_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        if let Some(unreversed_name) = name.strip_suffix(REVERSED_SUFFIX) {
+            return Self::by_name(unreversed_name).map(|colormap| colormap.reversed());
+        }
+        match name.as_str() {
+            "viridis" => Some(Self::viridis()),
+            "inferno" => Some(Self::inferno()),
+            "magma" => Some(Self::magma()),
+            "plasma" => Some(Self::plasma()),
+            "cividis" => Some(Self::cividis()),
+            "coolwarm" => Some(Self::coolwarm()),
+            "rdbu" => Some(Self::red_blue()),
+            _ => name
+                .strip_prefix("sdoaia")
+                .and_then(|wavelength| wavelength.parse().ok())
+                .and_then(Self::sdo_aia),
+        }
+    }
+
+    /// Creates the perceptually uniform viridis colormap, going from dark
+    /// purple through blue and green to yellow.
+    pub fn viridis() -> Self {
+        Self::from_table("viridis", &data::VIRIDIS)
+    }
+
+    /// Creates the perceptually uniform inferno colormap, going from black
+    /// through purple, red and orange to pale yellow.
+    pub fn inferno() -> Self {
+        Self::from_table("inferno", &data::INFERNO)
+    }
+
+    /// Creates the perceptually uniform magma colormap, going from black
+    /// through purple and pink to pale yellow.
+    pub fn magma() -> Self {
+        Self::from_table("magma", &data::MAGMA)
+    }
+
+    /// Creates the perceptually uniform plasma colormap, going from dark blue
+    /// through purple and orange to yellow.
+    pub fn plasma() -> Self {
+        Self::from_table("plasma", &data::PLASMA)
+    }
+
+    /// Creates the perceptually uniform cividis colormap, going from dark blue
+    /// through gray to yellow, designed to look the same to viewers with
+    /// color vision deficiency.
+    pub fn cividis() -> Self {
+        Self::from_table("cividis", &data::CIVIDIS)
+    }
+
+    /// Creates the diverging coolwarm colormap by Kenneth Moreland, going from
+    /// blue through light gray to red.
+    pub fn coolwarm() -> Self {
+        Self::from_table("coolwarm", &data::COOLWARM)
+    }
+
+    /// Creates the diverging RdBu colormap from ColorBrewer, going from dark red
+    /// through white to dark blue.
+    pub fn red_blue() -> Self {
+        Self::from_table("RdBu", &data::RDBU)
+    }
+
+    /// Creates the colormap conventionally used for images from the SDO/AIA
+    /// channel with the given wavelength in ångström, or returns `None` if the
+    /// wavelength is not in `SDO_AIA_WAVELENGTHS`.
+    ///
+    /// The colors are built from the same curves as the AIA color tables in
+    /// SolarSoft and SunPy, with IDL's red temperature table approximated by
+    /// piecewise-linear ramps.
+    pub fn sdo_aia(wavelength: u32) -> Option<Self> {
+        // Color component curves over the normalized position
+        let linear = |x: f32| x;
+        let square_root = |x: f32| x.sqrt();
+        let square = |x: f32| x * x;
+        let mixed = |x: f32| (x.sqrt() + 0.5 * x * x) / 1.5;
+        let half_linear = |x: f32| 0.5 * x;
+        let red_temperature_red = |x: f32| (x * 255.0 / 176.0).min(1.0);
+        let red_temperature_green = |x: f32| ((x * 255.0 - 120.0) / 135.0).max(0.0);
+        let red_temperature_blue = |x: f32| ((x * 255.0 - 190.0) / 65.0).max(0.0);
+
+        type Curve = dyn Fn(f32) -> f32;
+        let curves: [&Curve; 3] = match wavelength {
+            94 => [&square, &mixed, &linear],
+            131 => [&square, &linear, &mixed],
+            171 => [&red_temperature_red, &linear, &red_temperature_blue],
+            193 => [&square_root, &linear, &square],
+            211 => [&square_root, &linear, &mixed],
+            304 => [
+                &red_temperature_red,
+                &red_temperature_green,
+                &red_temperature_blue,
+            ],
+            335 => [&square, &linear, &square_root],
+            1600 => [&mixed, &mixed, &square],
+            1700 => [&square_root, &linear, &linear],
+            4500 => [&linear, &linear, &half_linear],
+            _ => return None,
+        };
+
+        let color_points = (0..SDO_AIA_TABLE_SIZE)
+            .map(|index| {
+                let position = index as f32 / (SDO_AIA_TABLE_SIZE - 1) as f32;
+                ColorPoint {
+                    position,
+                    color: Color::from_components(
+                        curves[0](position),
+                        curves[1](position),
+                        curves[2](position),
+                        1.0,
+                    ),
+                }
+            })
+            .collect();
+        Some(Self::new(format!("sdoaia{}", wavelength), color_points))
+    }
+
+    /// Returns the name of the colormap.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the control points of the colormap, sorted by position.
+    pub fn color_points(&self) -> &[ColorPoint] {
+        &self.color_points
+    }
+
+    /// Returns the opaque color at the given normalized position, which is
+    /// clamped to the range [0, 1].
+    pub fn sample(&self, position: f32) -> Color {
+        transfer_function::interpolate(
+            &self.color_points,
+            position.clamp(0.0, 1.0),
+            |point| point.position,
+            |lower, upper, fraction| {
+                lower
+                    .color
+                    .interpolate(&upper.color, fraction, ColorSpace::Srgb)
+            },
+            |point| point.color.clone(),
+        )
+    }
+
+    /// Returns a copy of the colormap going through the colors in reverse.
+    pub fn reversed(&self) -> Self {
+        let name = match self.name.strip_suffix(REVERSED_SUFFIX) {
+            Some(unreversed_name) => unreversed_name.to_string(),
+            None => format!("{}{}", self.name, REVERSED_SUFFIX),
+        };
+        let color_points = self
+            .color_points
+            .iter()
+            .rev()
+            .map(|point| ColorPoint {
+                position: 1.0 - point.position,
+                color: point.color.clone(),
+            })
+            .collect();
+        Self { name, color_points }
+    }
+
+    /// Returns a colormap stretching the part of this colormap between the
+    /// given normalized start and end positions over the full range.
+    ///
+    /// # Panics
+    /// If the start position is not smaller than the end position, or if any
+    /// of them is outside the range [0, 1].
+    pub fn sub_range(&self, start: f32, end: f32) -> Self {
+        assert!(
+            0.0 <= start && start < end && end <= 1.0,
+            "Invalid colormap sub-range [{}, {}].",
+            start,
+            end
+        );
+        let scale = 1.0 / (end - start);
+        let mut color_points = vec![ColorPoint {
+            position: 0.0,
+            color: self.sample(start),
+        }];
+        color_points.extend(
+            self.color_points
+                .iter()
+                .filter(|point| point.position > start && point.position < end)
+                .map(|point| ColorPoint {
+                    position: (point.position - start) * scale,
+                    color: point.color.clone(),
+                }),
+        );
+        color_points.push(ColorPoint {
+            position: 1.0,
+            color: self.sample(end),
+        });
+        Self {
+            name: self.name.clone(),
+            color_points,
+        }
+    }
+
+    /// Computes a lookup table with the given number of opaque colors evenly
+    /// covering the colormap.
+    ///
+    /// # Panics
+    /// If the size is smaller than two.
+    pub fn lookup_table(&self, size: usize) -> Vec<Color> {
+        assert!(size >= 2, "Lookup table must have at least two entries.");
+        (0..size)
+            .map(|index| self.sample(index as f32 / (size - 1) as f32))
+            .collect()
+    }
+
+    /// Creates a transfer function with the colors of the colormap and the
+    /// given opacity control points.
+    ///
+    /// # Panics
+    /// If there are no opacity points, or if any position is outside the
+    /// range [0, 1].
+    pub fn to_transfer_function(&self, opacity_points: Vec<OpacityPoint>) -> TransferFunction {
+        TransferFunction::new(self.color_points.clone(), opacity_points)
+    }
+
+    /// Creates a colormap with the given evenly spaced colors encoded as
+    /// `0xRRGGBB`.
+    fn from_table(name: &str, table: &[u32]) -> Self {
+        let color_points = table
+            .iter()
+            .enumerate()
+            .map(|(index, &rgb)| ColorPoint {
+                position: index as f32 / (table.len() - 1) as f32,
+                color: Color::from_components(
+                    ((rgb >> 16) & 0xff) as f32 / 255.0,
+                    ((rgb >> 8) & 0xff) as f32 / 255.0,
+                    (rgb & 0xff) as f32 / 255.0,
+                    1.0,
+                ),
+            })
+            .collect();
+        Self::new(name, color_points)
+    }
+}
+
+fn opaque(color: &Color) -> Color {
+    let [red, green, blue, _] = color.to_slice();
+    Color::from_components(red, green, blue, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_colors_close(a: &Color, b: &Color) {
+        for (&a_component, &b_component) in a.to_slice().iter().zip(&b.to_slice()) {
+            assert!(
+                (a_component - b_component).abs() < 1e-5,
+                "{:?} is not close to {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    #[test]
+    fn by_name_ignores_case() {
+        for &name in BUILTIN_NAMES {
+            let colormap = Colormap::by_name(name).unwrap();
+            assert_eq!(colormap.name(), name);
+            assert_eq!(Colormap::by_name(&name.to_uppercase()), Some(colormap));
+        }
+        assert_eq!(Colormap::by_name("Viridis"), Some(Colormap::viridis()));
+        assert_eq!(Colormap::by_name("rdbu"), Some(Colormap::red_blue()));
+        assert_eq!(Colormap::by_name("jet"), None);
+        assert_eq!(Colormap::by_name("sdoaia"), None);
+        assert_eq!(Colormap::by_name("sdoaia100"), None);
+        assert_eq!(Colormap::by_name("_r"), None);
+    }
+
+    #[test]
+    fn by_name_reverses_with_suffix() {
+        let reversed = Colormap::by_name("Magma_R").unwrap();
+        assert_eq!(reversed, Colormap::magma().reversed());
+        assert_eq!(reversed.name(), "magma_r");
+        assert_eq!(Colormap::by_name("magma_r_r").unwrap().name(), "magma");
+    }
+
+    #[test]
+    fn reversed_mirrors_colors() {
+        let colormap = Colormap::coolwarm();
+        let reversed = colormap.reversed();
+        for &position in &[0.0, 0.1, 0.37, 0.5, 0.9, 1.0] {
+            assert_colors_close(&reversed.sample(position), &colormap.sample(1.0 - position));
+        }
+        assert_eq!(reversed.reversed().name(), colormap.name());
+    }
+
+    #[test]
+    fn sub_range_stretches_part_of_colormap() {
+        let colormap = Colormap::viridis();
+        let sub_range = colormap.sub_range(0.25, 0.75);
+        assert_eq!(sub_range.name(), "viridis");
+        assert_colors_close(&sub_range.sample(0.0), &colormap.sample(0.25));
+        assert_colors_close(&sub_range.sample(0.3), &colormap.sample(0.4));
+        assert_colors_close(&sub_range.sample(1.0), &colormap.sample(0.75));
+        assert_colors_close(&sub_range.sample(1.5), &colormap.sample(0.75));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid colormap sub-range")]
+    fn empty_sub_range_panics() {
+        Colormap::viridis().sub_range(0.5, 0.5);
+    }
+
+    #[test]
+    fn lookup_table_samples_evenly() {
+        let colormap = Colormap::red_blue();
+        let table = colormap.lookup_table(5);
+        assert_eq!(table.len(), 5);
+        for (index, color) in table.iter().enumerate() {
+            assert_eq!(color, &colormap.sample(index as f32 / 4.0));
+            assert_eq!(color.alpha(), 1.0);
+        }
+        assert_eq!(table[0], colormap.color_points()[0].color);
+        assert_eq!(table[4], colormap.color_points().last().unwrap().color);
+    }
+
+    #[test]
+    fn sample_interpolates_between_control_points() {
+        let colormap = Colormap::new(
+            "test",
+            vec![
+                ColorPoint {
+                    position: 1.0,
+                    color: Color::white(),
+                },
+                ColorPoint {
+                    position: 0.5,
+                    color: Color::from_components(0.0, 0.0, 0.0, 0.5),
+                },
+            ],
+        );
+        assert_eq!(colormap.sample(0.0), Color::black());
+        assert_eq!(colormap.sample(0.5), Color::black());
+        assert_colors_close(
+            &colormap.sample(0.75),
+            &Color::from_components(0.5, 0.5, 0.5, 1.0),
+        );
+        assert_eq!(colormap.sample(2.0), Color::white());
+    }
+
+    #[test]
+    fn sdo_aia_colormaps_go_from_black_to_bright() {
+        for &wavelength in SDO_AIA_WAVELENGTHS {
+            let colormap = Colormap::sdo_aia(wavelength).unwrap();
+            assert_eq!(colormap.name(), format!("sdoaia{}", wavelength));
+            assert_eq!(colormap.color_points().len(), SDO_AIA_TABLE_SIZE);
+            assert_colors_close(&colormap.sample(0.0), &Color::black());
+            let [red, green, _, _] = colormap.sample(1.0).to_slice();
+            assert!(red > 0.99 && green > 0.99);
+        }
+        assert_colors_close(
+            &Colormap::sdo_aia(171).unwrap().sample(1.0),
+            &Color::white(),
+        );
+        assert_colors_close(
+            &Colormap::sdo_aia(4500).unwrap().sample(1.0),
+            &Color::from_components(1.0, 1.0, 0.5, 1.0),
+        );
+        assert_eq!(Colormap::sdo_aia(170), None);
+    }
+}
@@ -1,6 +1,10 @@
 //! Error handling.
 
-use crate::graphics::{rendering::RenderingError, window::WindowError};
+use crate::{
+    graphics::{rendering::RenderingError, window::WindowError},
+    input::bindings::BindingError,
+    io::DataIOError,
+};
 use std::{error::Error, fmt};
 
 /// Common error enum for the Vortek library.
@@ -8,6 +12,8 @@ use std::{error::Error, fmt};
 pub enum VortekError {
     RenderingError(RenderingError),
     WindowError(WindowError),
+    DataIOError(DataIOError),
+    BindingError(BindingError),
 }
 
 pub type VortekResult<T> = Result<T, VortekError>;
@@ -17,6 +23,8 @@ impl fmt::Display for VortekError {
         match *self {
             VortekError::RenderingError(ref error) => write!(f, "{}", error.message()),
             VortekError::WindowError(ref error) => write!(f, "{}", error.message()),
+            VortekError::DataIOError(ref error) => write!(f, "{}", error.message()),
+            VortekError::BindingError(ref error) => write!(f, "{}", error.message()),
         }
     }
 }
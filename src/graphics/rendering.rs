@@ -2,44 +2,197 @@
 
 pub mod adapter;
 pub mod backend;
+pub mod buffer;
+pub mod descriptor;
 pub mod device;
+pub mod dispatch;
+pub mod empty;
 pub mod framebuffer;
+pub mod offscreen;
+pub mod pipeline;
 pub mod render_pass;
 pub mod swapchain;
+pub mod texture;
 
 use super::window::WindowState;
 use crate::{
+    camera::{Camera, Projection},
     color::Color,
     error::{VortekError, VortekResult},
+    transfer_function::{self, TransferFunction},
+    transform::{self, Matrix4},
+    volume::ScalarVolume,
 };
-use backend::{BackendState, BackendType};
+use backend::BackendState;
+use buffer::BufferState;
+use descriptor::DescriptorSetState;
 use device::DeviceState;
 use framebuffer::FramebufferState;
 use log::{info, warn};
+use offscreen::{ImageData, OffscreenState};
+use pipeline::{shaders, PipelineDescription, PipelineState};
 use render_pass::RenderPassState;
 use std::{borrow::Cow, cell::RefCell, fmt, iter, ops::Drop, rc::Rc};
 use swapchain::SwapchainState;
+use texture::TextureState;
 
 use gfx_hal::{
+    adapter::PhysicalDevice,
+    buffer::Usage,
     command::{ClearColor, ClearValue, CommandBuffer, CommandBufferFlags, Level, SubpassContents},
     device::{Device, OomOrDeviceLost},
+    format::{ChannelType, Format},
     image::Extent,
+    memory::Properties,
     pool::CommandPool,
-    pso::{PipelineStage, Rect, Viewport},
+    pso::{
+        BlendState, DescriptorBinding, DescriptorSetLayoutBinding, DescriptorType, Face, FrontFace,
+        PipelineStage, Primitive, Rasterizer, Rect, ShaderStageFlags, Viewport,
+    },
     queue::{CommandQueue, Submission},
     window::Swapchain,
     Backend,
 };
 
-pub type RendererStateType = RendererState<BackendType>;
+/// Binding of the volume texture in the descriptor set of the volume pipeline.
+/// The sampler is bound to the next binding.
+const VOLUME_TEXTURE_BINDING: DescriptorBinding = 0;
+/// Binding of the transfer function texture in the descriptor set of the volume
+/// pipeline. The sampler is bound to the next binding.
+const TRANSFER_FUNCTION_TEXTURE_BINDING: DescriptorBinding = 2;
+/// Binding of the uniform buffer in the descriptor set of the volume pipeline.
+const VOLUME_UNIFORM_BUFFER_BINDING: DescriptorBinding = 4;
+/// Number of 32-bit words in the uniform buffer of the volume pipeline.
+const VOLUME_UNIFORM_WORDS: usize = 2 * 16 + MAX_ISOSURFACES * ISOSURFACE_UNIFORM_WORDS;
+/// Number of 32-bit words occupied by each isosurface in the uniform buffer.
+const ISOSURFACE_UNIFORM_WORDS: usize = 8;
+/// Number of 32-bit words in the push constants of the volume pipeline.
+const VOLUME_PUSH_CONSTANT_WORDS: usize = 32;
+/// Number of ray-marching samples per voxel spacing along the most finely
+/// sampled volume axis.
+const SAMPLES_PER_VOXEL: f32 = 2.0;
 
+/// Maximum number of isosurfaces that can be rendered at the same time.
+pub const MAX_ISOSURFACES: usize = 8;
+
+/// How the samples along each ray through the volume are combined into a
+/// pixel color.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// Samples are colored by the transfer function and composited front-to-back.
+    #[default]
+    Composite,
+    /// The largest value along the ray is colored by the transfer function.
+    MaximumIntensity,
+    /// The smallest value along the ray is colored by the transfer function.
+    MinimumIntensity,
+    /// The average value along the ray is colored by the transfer function,
+    /// similar to an X-ray image.
+    Average,
+    /// The surfaces where the volume takes on the values of the isosurfaces
+    /// are shaded and composited front-to-back.
+    Isosurface,
+}
+
+/// Names of the render modes, as used on the command line.
+const RENDER_MODE_NAMES: [(RenderMode, &str); 5] = [
+    (RenderMode::Composite, "composite"),
+    (RenderMode::MaximumIntensity, "maximum_intensity"),
+    (RenderMode::MinimumIntensity, "minimum_intensity"),
+    (RenderMode::Average, "average"),
+    (RenderMode::Isosurface, "isosurface"),
+];
+
+/// Surface where the volume takes on a given value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Isosurface {
+    /// Volume value on the surface.
+    pub value: f32,
+    /// Color of the surface, with the alpha component giving its opacity.
+    pub color: Color,
+}
+
+impl RenderMode {
+    /// Returns the names of all render modes.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        RENDER_MODE_NAMES.iter().map(|(_, name)| *name)
+    }
+
+    /// Returns the name of the render mode.
+    pub fn name(self) -> &'static str {
+        RENDER_MODE_NAMES
+            .iter()
+            .find(|(render_mode, _)| *render_mode == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    /// Returns the render mode with the given name, or `None` if there is no
+    /// such render mode.
+    pub fn from_name(name: &str) -> Option<Self> {
+        RENDER_MODE_NAMES
+            .iter()
+            .find(|(_, render_mode_name)| *render_mode_name == name)
+            .map(|(render_mode, _)| *render_mode)
+    }
+
+    /// Returns the mode following this one, wrapping around after the last.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Composite => Self::MaximumIntensity,
+            Self::MaximumIntensity => Self::MinimumIntensity,
+            Self::MinimumIntensity => Self::Average,
+            Self::Average => Self::Isosurface,
+            Self::Isosurface => Self::Composite,
+        }
+    }
+
+    /// Returns the integer identifying the mode in the volume shader.
+    fn shader_index(self) -> u32 {
+        match self {
+            Self::Composite => 0,
+            Self::MaximumIntensity => 1,
+            Self::MinimumIntensity => 2,
+            Self::Average => 3,
+            Self::Isosurface => 4,
+        }
+    }
+}
+
+/// Structure for managing the rendering of volumes.
+///
+/// A renderer created from a headless backend state has no swapchain, and can
+/// only render to offscreen images with `render_volume_image`.
 pub struct RendererState<B: Backend> {
     backend_state: BackendState<B>,
     device_state: Rc<RefCell<DeviceState<B>>>,
     swapchain_state: Option<SwapchainState<B>>,
-    render_pass_state: RenderPassState<B>,
-    framebuffer_state: FramebufferState<B>,
+    /// Render pass for drawing to the swapchain images, absent when headless.
+    render_pass_state: Option<RenderPassState<B>>,
+    /// Render pass for drawing to offscreen images with the output format. The
+    /// volume pipeline is built for this render pass, which is compatible with
+    /// the one for the swapchain images.
+    offscreen_render_pass_state: RenderPassState<B>,
+    framebuffer_state: Option<FramebufferState<B>>,
+    /// Format of the swapchain images, or of offscreen images when headless.
+    output_format: Format,
+    volume_pipeline_state: PipelineState<B>,
+    volume_descriptor_set_state: DescriptorSetState<B>,
+    volume_texture_state: Option<TextureState<B>>,
+    volume_model_matrix: Matrix4,
+    volume_value_range: (f32, f32),
+    volume_uniform_buffer_state: BufferState<B>,
+    transfer_function: TransferFunction,
+    transfer_function_table_size: usize,
+    transfer_function_texture_state: TextureState<B>,
+    render_mode: RenderMode,
+    isosurfaces: Vec<Isosurface>,
+    camera: Camera,
+    /// Viewport covering the swapchain images, empty when headless.
     viewport: Viewport,
+    /// Whether the output format is sRGB, so that the shaders must output
+    /// linear colors.
+    srgb_output: bool,
     recreate_swapchain: bool,
 }
 
@@ -64,65 +217,404 @@ impl RenderingError {
             message: Cow::from(message),
         }
     }
+
+    pub(crate) fn from_string(message: String) -> Self {
+        Self {
+            message: Cow::from(message),
+        }
+    }
 }
 
 impl<B: Backend> RendererState<B> {
-    /// Creates a new renderer state from the given backend state.
-    pub fn new(mut backend_state: BackendState<B>) -> VortekResult<Self> {
-        let device_state = Rc::new(RefCell::new(DeviceState::new(
+    /// Creates a new renderer state from the given backend state, which may be
+    /// headless.
+    pub fn new(backend_state: BackendState<B>) -> VortekResult<Self> {
+        Self::new_or_return_window(backend_state).map_err(|(err, _)| err)
+    }
+
+    /// Creates a new renderer state like `new`, but returns the window state of
+    /// the backend state along with the error if creation fails, so that the
+    /// window can be used with another backend.
+    pub fn new_or_return_window(
+        mut backend_state: BackendState<B>,
+    ) -> Result<Self, (VortekError, Option<Box<WindowState>>)> {
+        macro_rules! return_window_on_err {
+            ($result:expr) => {
+                match $result {
+                    Ok(value) => value,
+                    Err(err) => return Err((err, backend_state.take_window_state().map(Box::new))),
+                }
+            };
+        }
+
+        let device_state = Rc::new(RefCell::new(return_window_on_err!(DeviceState::new(
             backend_state.adapter_state_mut().take_adapter(),
             backend_state.surface(),
-        )?));
+        ))));
 
-        let mut swapchain_state =
-            SwapchainState::new(Rc::clone(&device_state), &mut backend_state)?;
-
-        let render_pass_state = RenderPassState::new(Rc::clone(&device_state), &swapchain_state)?;
+        let (swapchain_state, render_pass_state, framebuffer_state) = if backend_state.is_headless()
+        {
+            (None, None, None)
+        } else {
+            let (swapchain_state, render_pass_state, framebuffer_state) = return_window_on_err!(
+                Self::create_presentation_states(&device_state, &mut backend_state)
+            );
+            (
+                Some(swapchain_state),
+                Some(render_pass_state),
+                Some(framebuffer_state),
+            )
+        };
 
-        let framebuffer_state = unsafe {
-            FramebufferState::new(
-                Rc::clone(&device_state),
-                &mut swapchain_state,
-                &render_pass_state,
-            )?
+        let output_format = match &swapchain_state {
+            Some(swapchain_state) => swapchain_state.format(),
+            None => return_window_on_err!(OffscreenState::<B>::select_headless_format(
+                device_state.borrow().physical_device(),
+            )),
         };
 
-        let viewport = Self::create_viewport(swapchain_state.extent());
+        let offscreen_render_pass_state = return_window_on_err!(RenderPassState::new_offscreen(
+            Rc::clone(&device_state),
+            output_format
+        ));
+
+        let volume_pipeline_state = return_window_on_err!(PipelineState::new(
+            Rc::clone(&device_state),
+            &offscreen_render_pass_state,
+            Self::create_volume_pipeline_description(),
+        ));
 
-        Ok(Self {
+        let mut volume_descriptor_set_state = return_window_on_err!(DescriptorSetState::new(
+            Rc::clone(&device_state),
+            &volume_pipeline_state.descriptor_set_layouts()[0],
+            &volume_pipeline_state
+                .description()
+                .descriptor_set_layout_bindings[0],
+        ));
+
+        let srgb_output = output_format.base_format().1 == ChannelType::Srgb;
+
+        let transfer_function = TransferFunction::default();
+        let transfer_function_table_size = transfer_function::STANDARD_TABLE_SIZE;
+        let transfer_function_texture_state = return_window_on_err!(TextureState::new_1d(
+            Rc::clone(&device_state),
+            &Self::encode_output_colors(
+                transfer_function.bake(transfer_function_table_size, (0.0, 1.0)),
+                srgb_output,
+            ),
+        ));
+        volume_descriptor_set_state.write_texture(
+            TRANSFER_FUNCTION_TEXTURE_BINDING,
+            transfer_function_texture_state.image_view(),
+            transfer_function_texture_state.sampler(),
+        );
+
+        let volume_uniform_buffer_state = return_window_on_err!(BufferState::new(
+            Rc::clone(&device_state),
+            (VOLUME_UNIFORM_WORDS * 4) as u64,
+            Usage::UNIFORM,
+            Properties::CPU_VISIBLE | Properties::COHERENT,
+        ));
+        volume_descriptor_set_state.write_buffer(
+            VOLUME_UNIFORM_BUFFER_BINDING,
+            volume_uniform_buffer_state.buffer(),
+        );
+
+        let viewport = Self::create_viewport(
+            swapchain_state
+                .as_ref()
+                .map_or(&Extent::default(), SwapchainState::extent),
+        );
+
+        let mut renderer_state = Self {
             backend_state,
             device_state,
-            swapchain_state: Some(swapchain_state),
+            swapchain_state,
             render_pass_state,
+            offscreen_render_pass_state,
             framebuffer_state,
+            output_format,
+            volume_pipeline_state,
+            volume_descriptor_set_state,
+            volume_texture_state: None,
+            volume_model_matrix: transform::identity(),
+            volume_value_range: (0.0, 1.0),
+            volume_uniform_buffer_state,
+            transfer_function,
+            transfer_function_table_size,
+            transfer_function_texture_state,
+            render_mode: RenderMode::default(),
+            isosurfaces: Vec::new(),
+            camera: Camera::default(),
             viewport,
+            srgb_output,
             recreate_swapchain: false,
-        })
+        };
+        if let Err(err) = renderer_state.write_volume_uniforms() {
+            return Err((
+                err,
+                renderer_state
+                    .backend_state
+                    .take_window_state()
+                    .map(Box::new),
+            ));
+        }
+        Ok(renderer_state)
+    }
+
+    /// Whether the renderer has no window and can only render offscreen images.
+    pub fn is_headless(&self) -> bool {
+        self.backend_state.is_headless()
     }
 
     /// Returns a mutable reference to the window state held by the renderer state.
+    ///
+    /// # Panics
+    /// If the renderer is headless.
     pub fn window_state_mut(&mut self) -> &mut WindowState {
         self.backend_state.window_state_mut()
     }
 
+    /// Uploads the given volume to device memory as a 3D texture, replacing any
+    /// previously uploaded volume.
+    pub fn upload_volume(&mut self, volume: &ScalarVolume) -> VortekResult<()> {
+        // Make sure the old texture is no longer in use before it is destroyed
+        self.device_state
+            .borrow()
+            .device()
+            .wait_idle()
+            .map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not wait for device to become idle: ",
+                    err,
+                ))
+            })?;
+        self.volume_texture_state.take();
+        let volume_texture_state = TextureState::new_3d(Rc::clone(&self.device_state), volume)?;
+        self.volume_descriptor_set_state.write_texture(
+            VOLUME_TEXTURE_BINDING,
+            volume_texture_state.image_view(),
+            volume_texture_state.sampler(),
+        );
+        self.volume_texture_state = Some(volume_texture_state);
+        self.volume_model_matrix = Self::compute_volume_model_matrix(volume);
+        self.volume_value_range = volume.value_range().unwrap_or((0.0, 1.0));
+        // The lookup table spans the value range of the volume
+        self.upload_transfer_function_table()?;
+        self.write_volume_uniforms()
+    }
+
+    /// Sets the transfer function mapping volume values to colors, and uploads
+    /// it as a lookup table with the given number of entries.
+    pub fn set_transfer_function(
+        &mut self,
+        transfer_function: TransferFunction,
+        table_size: usize,
+    ) -> VortekResult<()> {
+        self.transfer_function = transfer_function;
+        self.transfer_function_table_size = table_size;
+        self.upload_transfer_function_table()
+    }
+
+    /// Returns the transfer function mapping volume values to colors.
+    pub fn transfer_function(&self) -> &TransferFunction {
+        &self.transfer_function
+    }
+
+    /// Bakes the transfer function into a lookup table spanning the value range
+    /// of the volume, and uploads it as a 1D texture replacing the current one.
+    fn upload_transfer_function_table(&mut self) -> VortekResult<()> {
+        let table = Self::encode_output_colors(
+            self.transfer_function
+                .bake(self.transfer_function_table_size, self.volume_value_range),
+            self.srgb_output,
+        );
+        // Make sure the old texture is no longer in use before it is destroyed
+        self.device_state
+            .borrow()
+            .device()
+            .wait_idle()
+            .map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not wait for device to become idle: ",
+                    err,
+                ))
+            })?;
+        self.transfer_function_texture_state =
+            TextureState::new_1d(Rc::clone(&self.device_state), &table)?;
+        self.volume_descriptor_set_state.write_texture(
+            TRANSFER_FUNCTION_TEXTURE_BINDING,
+            self.transfer_function_texture_state.image_view(),
+            self.transfer_function_texture_state.sampler(),
+        );
+        Ok(())
+    }
+
+    /// Sets the camera to view the volume with.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.camera = camera.clone();
+    }
+
+    /// Returns the camera the volume is viewed with.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Sets how samples along each ray are combined when drawing the volume.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    /// Returns how samples along each ray are combined when drawing the volume.
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Sets the isosurfaces to render in isosurface mode.
+    ///
+    /// The isosurfaces are only updated if they differ from the current ones.
+    /// At most `MAX_ISOSURFACES` isosurfaces can be rendered.
+    pub fn set_isosurfaces(&mut self, isosurfaces: &[Isosurface]) -> VortekResult<()> {
+        if isosurfaces == self.isosurfaces.as_slice() {
+            return Ok(());
+        }
+        check_isosurface_count(isosurfaces)?;
+        // Make sure the uniform buffer is no longer in use before it is modified
+        self.device_state
+            .borrow()
+            .device()
+            .wait_idle()
+            .map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not wait for device to become idle: ",
+                    err,
+                ))
+            })?;
+        self.isosurfaces = isosurfaces.to_vec();
+        self.write_volume_uniforms()
+    }
+
+    /// Returns the isosurfaces to render in isosurface mode.
+    pub fn isosurfaces(&self) -> &[Isosurface] {
+        &self.isosurfaces
+    }
+
+    /// Draws a frame where the uploaded volume is rendered by ray-marching
+    /// through it and combining the samples according to the current render
+    /// mode, over the given background color.
+    ///
+    /// Only the background is drawn if no volume has been uploaded.
+    pub fn draw_volume_frame(&mut self, background_color: &Color) -> VortekResult<()> {
+        self.draw_frame(background_color, true)
+    }
+
+    /// Draws a frame filled with the given color.
     pub fn draw_clear_frame(&mut self, color: &Color) -> VortekResult<()> {
+        self.draw_frame(color, false)
+    }
+
+    /// Renders the uploaded volume over the given background color into an
+    /// offscreen image with the given width and height, in the same way as
+    /// `draw_volume_frame`, and reads back its pixels.
+    ///
+    /// This is the only way of rendering for headless renderers. The pixels
+    /// have the output format of the renderer, which for headless renderers is
+    /// 8-bit RGBA with sRGB encoding.
+    ///
+    /// # Panics
+    /// If the width or height is zero.
+    pub fn render_volume_image(
+        &self,
+        background_color: &Color,
+        (width, height): (u32, u32),
+    ) -> VortekResult<ImageData> {
+        let max_extent = self
+            .device_state
+            .borrow()
+            .physical_device()
+            .limits()
+            .max_framebuffer_extent;
+        let max_size = max_extent.width.min(max_extent.height).min(i16::MAX as u32);
+        if width > max_size || height > max_size {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                format!(
+                    "Image size {}x{} exceeds the maximum framebuffer size ({}).",
+                    width, height, max_size
+                ),
+            )));
+        }
+
+        let offscreen_state = OffscreenState::new(
+            Rc::clone(&self.device_state),
+            &self.offscreen_render_pass_state,
+            self.output_format,
+            Extent {
+                width,
+                height,
+                depth: 1,
+            },
+        )?;
+        let viewport = Self::create_viewport(offscreen_state.extent());
+        let volume_push_constants = self.compute_volume_push_constants(&viewport);
+        let clear_color = self.encode_output_color(background_color);
+
+        unsafe {
+            self.device_state
+                .borrow_mut()
+                .execute_one_time_commands(|command_buffer| {
+                    Self::record_frame_commands(
+                        command_buffer,
+                        self.offscreen_render_pass_state.render_pass(),
+                        offscreen_state.framebuffer(),
+                        &viewport,
+                        &clear_color,
+                        volume_push_constants.as_ref(),
+                        &self.volume_pipeline_state,
+                        &self.volume_descriptor_set_state,
+                    );
+                })?;
+        }
+        offscreen_state.read_pixels()
+    }
+
+    fn draw_frame(&mut self, background_color: &Color, draw_volume: bool) -> VortekResult<()> {
+        if self.is_headless() {
+            return Err(VortekError::RenderingError(RenderingError::from_str(
+                "Could not draw frame because the renderer is headless.",
+            )));
+        }
+
         if self.recreate_swapchain {
             self.recreate_swapchain()?;
             self.recreate_swapchain = false;
         }
 
-        let semaphore_index = self.framebuffer_state.advance_semaphore_index();
+        let volume_push_constants = if draw_volume {
+            self.compute_volume_push_constants(&self.viewport)
+        } else {
+            None
+        };
+
+        let clear_color = self.encode_output_color(background_color);
+
+        let swapchain_state = self
+            .swapchain_state
+            .as_mut()
+            .expect("No swapchain state in renderer state.");
+        let framebuffer_state = self
+            .framebuffer_state
+            .as_mut()
+            .expect("No framebuffer state in renderer state.");
+
+        let semaphore_index = framebuffer_state.advance_semaphore_index();
 
         let swap_image_index = unsafe {
-            let acquire_semaphore = self.framebuffer_state.acquire_semaphore(semaphore_index);
-
-            match self
-                .swapchain_state
-                .as_mut()
-                .unwrap()
-                .swapchain_mut()
-                .acquire_image(std::u64::MAX, Some(acquire_semaphore), None)
-            {
+            let acquire_semaphore = framebuffer_state.acquire_semaphore(semaphore_index);
+            match swapchain_state.swapchain_mut().acquire_image(
+                u64::MAX,
+                Some(acquire_semaphore),
+                None,
+            ) {
                 Ok((swap_image_index, _)) => swap_image_index,
                 Err(_) => {
                     // Resizing the window will make the current swapchain obsolete,
@@ -137,15 +629,13 @@ impl<B: Backend> RendererState<B> {
         let (
             (framebuffer, (command_pool, command_buffer_list), in_flight_fence),
             (acquire_semaphore, present_semaphore),
-        ) = self
-            .framebuffer_state
-            .frame_data_mut(swap_image_index, semaphore_index);
+        ) = framebuffer_state.frame_data_mut(swap_image_index, semaphore_index);
 
         unsafe {
             self.device_state
                 .borrow()
                 .device()
-                .wait_for_fence(in_flight_fence, std::u64::MAX)
+                .wait_for_fence(in_flight_fence, u64::MAX)
                 .map_err(|oom_or_device_lost| match oom_or_device_lost {
                     OomOrDeviceLost::OutOfMemory(out_of_memory_err) => {
                         VortekError::RenderingError(RenderingError::from_error(
@@ -178,21 +668,20 @@ impl<B: Backend> RendererState<B> {
                 .pop()
                 .unwrap_or_else(|| command_pool.allocate_one(Level::Primary));
 
-            let clear_values = [ClearValue {
-                color: ClearColor {
-                    float32: color.to_slice(),
-                },
-            }];
-
             command_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
-            command_buffer.begin_render_pass(
-                self.render_pass_state.render_pass(),
+            Self::record_frame_commands(
+                &mut command_buffer,
+                self.render_pass_state
+                    .as_ref()
+                    .expect("No render pass state in renderer state.")
+                    .render_pass(),
                 framebuffer,
-                self.viewport.rect,
-                clear_values.iter(),
-                SubpassContents::Inline,
+                &self.viewport,
+                &clear_color,
+                volume_push_constants.as_ref(),
+                &self.volume_pipeline_state,
+                &self.volume_descriptor_set_state,
             );
-            command_buffer.end_render_pass();
             command_buffer.finish();
 
             let submission = Submission {
@@ -209,10 +698,7 @@ impl<B: Backend> RendererState<B> {
 
             command_buffer_list.push(command_buffer);
 
-            if self
-                .swapchain_state
-                .as_ref()
-                .unwrap()
+            if swapchain_state
                 .swapchain()
                 .present(
                     &mut self.device_state.borrow_mut().queue_group_mut().queues[0],
@@ -250,27 +736,299 @@ impl<B: Backend> RendererState<B> {
             .take()
             .expect("No swapchain state in renderer state.");
 
-        self.swapchain_state = Some(SwapchainState::new(
-            Rc::clone(&self.device_state),
-            &mut self.backend_state,
-        )?);
+        let (swapchain_state, render_pass_state, framebuffer_state) =
+            Self::create_presentation_states(&self.device_state, &mut self.backend_state)?;
+        self.viewport = Self::create_viewport(swapchain_state.extent());
+        let output_format = swapchain_state.format();
+        self.swapchain_state = Some(swapchain_state);
+        self.render_pass_state = Some(render_pass_state);
+        self.framebuffer_state = Some(framebuffer_state);
 
-        self.render_pass_state = RenderPassState::new(
-            Rc::clone(&self.device_state),
-            self.swapchain_state.as_ref().unwrap(),
-        )?;
+        // Only a format change makes the pipeline incompatible with the new render pass
+        if output_format != self.output_format {
+            self.offscreen_render_pass_state =
+                RenderPassState::new_offscreen(Rc::clone(&self.device_state), output_format)?;
+            self.volume_pipeline_state
+                .rebuild(&self.offscreen_render_pass_state)?;
+            self.output_format = output_format;
 
-        self.framebuffer_state = unsafe {
+            // Colors must be re-encoded if the new format differs in sRGB-ness
+            self.srgb_output = output_format.base_format().1 == ChannelType::Srgb;
+            self.upload_transfer_function_table()?;
+            self.write_volume_uniforms()?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates the swapchain for the surface of the given backend state, along
+    /// with a render pass and framebuffers for drawing to its images.
+    fn create_presentation_states(
+        device_state: &Rc<RefCell<DeviceState<B>>>,
+        backend_state: &mut BackendState<B>,
+    ) -> VortekResult<(SwapchainState<B>, RenderPassState<B>, FramebufferState<B>)> {
+        let mut swapchain_state = SwapchainState::new(Rc::clone(device_state), backend_state)?;
+
+        let render_pass_state = RenderPassState::new(Rc::clone(device_state), &swapchain_state)?;
+
+        let framebuffer_state = unsafe {
             FramebufferState::new(
-                Rc::clone(&self.device_state),
-                self.swapchain_state.as_mut().unwrap(),
-                &self.render_pass_state,
+                Rc::clone(device_state),
+                &mut swapchain_state,
+                &render_pass_state,
             )?
         };
 
-        self.viewport = Self::create_viewport(self.swapchain_state.as_ref().unwrap().extent());
+        Ok((swapchain_state, render_pass_state, framebuffer_state))
+    }
 
-        Ok(())
+    /// Records the commands for drawing a frame with the given render pass and
+    /// framebuffer into the given command buffer. The frame is cleared to the
+    /// given color, and the volume is drawn if there are push constants for it.
+    #[allow(clippy::too_many_arguments)]
+    unsafe fn record_frame_commands(
+        command_buffer: &mut B::CommandBuffer,
+        render_pass: &B::RenderPass,
+        framebuffer: &B::Framebuffer,
+        viewport: &Viewport,
+        clear_color: &Color,
+        volume_push_constants: Option<&[u32; VOLUME_PUSH_CONSTANT_WORDS]>,
+        pipeline_state: &PipelineState<B>,
+        descriptor_set_state: &DescriptorSetState<B>,
+    ) {
+        let clear_values = [ClearValue {
+            color: ClearColor {
+                float32: clear_color.to_slice(),
+            },
+        }];
+        command_buffer.begin_render_pass(
+            render_pass,
+            framebuffer,
+            viewport.rect,
+            clear_values.iter(),
+            SubpassContents::Inline,
+        );
+        if let Some(push_constants) = volume_push_constants {
+            Self::record_volume_draw_commands(
+                command_buffer,
+                pipeline_state,
+                descriptor_set_state,
+                viewport,
+                push_constants,
+            );
+        }
+        command_buffer.end_render_pass();
+    }
+
+    /// Records the commands for ray-marching through the volume into the given
+    /// command buffer, which must be inside the render pass.
+    unsafe fn record_volume_draw_commands(
+        command_buffer: &mut B::CommandBuffer,
+        pipeline_state: &PipelineState<B>,
+        descriptor_set_state: &DescriptorSetState<B>,
+        viewport: &Viewport,
+        push_constants: &[u32],
+    ) {
+        command_buffer.bind_graphics_pipeline(pipeline_state.pipeline());
+        command_buffer.set_viewports(0, iter::once(viewport));
+        command_buffer.set_scissors(0, iter::once(&viewport.rect));
+        command_buffer.bind_graphics_descriptor_sets(
+            pipeline_state.pipeline_layout(),
+            0,
+            iter::once(descriptor_set_state.descriptor_set()),
+            iter::empty::<u32>(),
+        );
+        command_buffer.push_graphics_constants(
+            pipeline_state.pipeline_layout(),
+            ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+            0,
+            push_constants,
+        );
+        command_buffer.draw(0..36, 0..1);
+    }
+
+    /// Computes the push constants for the volume pipeline when drawing to the
+    /// given viewport, or returns `None` if there is no volume to draw.
+    fn compute_volume_push_constants(
+        &self,
+        viewport: &Viewport,
+    ) -> Option<[u32; VOLUME_PUSH_CONSTANT_WORDS]> {
+        let volume_texture_state = self.volume_texture_state.as_ref()?;
+        let model_matrix = &self.volume_model_matrix;
+
+        let aspect_ratio = f32::from(viewport.rect.w) / f32::from(viewport.rect.h.max(1));
+        let model_view_projection_matrix = transform::multiply(
+            &self.camera.projection_matrix(aspect_ratio),
+            &transform::multiply(&self.camera.view_matrix(), model_matrix),
+        );
+
+        // The shader needs the eye position for perspective projections and the
+        // viewing direction for orthographic projections
+        let inverse_model_matrix = transform::invert(model_matrix)?;
+        let (eye_in_texture_coords, eye_w) = match self.camera.projection() {
+            Projection::Perspective => (
+                transform::transform_point(&inverse_model_matrix, self.camera.eye()),
+                1.0,
+            ),
+            Projection::Orthographic => (
+                transform::transform_direction(&inverse_model_matrix, self.camera.view_direction()),
+                0.0,
+            ),
+        };
+
+        let extent = volume_texture_state.extent();
+        let dimensions = [extent.width, extent.height, extent.depth];
+        let texel_size = dimensions.map(|dimension| 1.0 / dimension as f32);
+        let voxel_spacing = dimensions
+            .iter()
+            .map(|&dimension| 1.0 / dimension.saturating_sub(1).max(1) as f32)
+            .fold(1.0, f32::min);
+
+        let (min_value, max_value) = volume_texture_state.sampled_value_range();
+        let max_value = if max_value > min_value {
+            max_value
+        } else {
+            min_value + 1.0
+        };
+
+        let values = model_view_projection_matrix
+            .iter()
+            .flatten()
+            .copied()
+            .chain(eye_in_texture_coords)
+            .chain([eye_w])
+            .chain(texel_size)
+            .chain([voxel_spacing / SAMPLES_PER_VOXEL])
+            .chain([min_value, max_value, 1.0 / SAMPLES_PER_VOXEL, 0.0]);
+        Some(pack_volume_push_constants(
+            values,
+            self.render_mode,
+            self.isosurfaces.len(),
+        ))
+    }
+
+    /// Writes the transformation matrices of the volume and the isosurfaces to
+    /// the uniform buffer of the volume pipeline.
+    ///
+    /// The uniform buffer must not be in use by any pending command buffers.
+    fn write_volume_uniforms(&self) -> VortekResult<()> {
+        let model_matrix = &self.volume_model_matrix;
+        let normal_matrix = transform::invert(model_matrix)
+            .map(|inverse| transform::transpose(&inverse))
+            .unwrap_or_else(transform::identity);
+
+        let mut uniforms = [0.0f32; VOLUME_UNIFORM_WORDS];
+        let (matrix_uniforms, isosurface_uniforms) = uniforms.split_at_mut(32);
+        for (uniform, &value) in matrix_uniforms
+            .iter_mut()
+            .zip(model_matrix.iter().chain(&normal_matrix).flatten())
+        {
+            *uniform = value;
+        }
+        pack_isosurface_uniforms(
+            isosurface_uniforms,
+            &self.isosurfaces,
+            self.volume_value_range,
+            |color| self.encode_output_color(color),
+        );
+
+        let bytes: Vec<u8> = uniforms
+            .iter()
+            .copied()
+            .flat_map(f32::to_ne_bytes)
+            .collect();
+        self.volume_uniform_buffer_state.write_data(&bytes)
+    }
+
+    /// Converts the given sRGB-encoded color to the space in which the shaders
+    /// output colors, which is linear if the swapchain images have an sRGB
+    /// format since the hardware then encodes written colors.
+    fn encode_output_color(&self, color: &Color) -> Color {
+        if self.srgb_output {
+            color.srgb_to_linear()
+        } else {
+            color.clone()
+        }
+    }
+
+    /// Converts the given sRGB-encoded colors to the output space of the
+    /// shaders, see `encode_output_color`.
+    fn encode_output_colors(colors: Vec<Color>, srgb_output: bool) -> Vec<Color> {
+        if srgb_output {
+            colors.iter().map(Color::srgb_to_linear).collect()
+        } else {
+            colors
+        }
+    }
+
+    /// Computes the matrix transforming the unit cube to the world space box
+    /// spanned by the voxel centers of the given volume.
+    fn compute_volume_model_matrix(volume: &ScalarVolume) -> Matrix4 {
+        let mut model_matrix = transform::identity();
+        for (((column, direction), &extent), &spacing) in model_matrix
+            .iter_mut()
+            .zip(&volume.orientation())
+            .zip(&volume.extent())
+            .zip(&volume.spacing())
+        {
+            // Give flat axes a thickness of one voxel so that the box does not
+            // collapse
+            let length = if extent > 0.0 { extent } else { spacing };
+            for (element, &component) in column.iter_mut().zip(direction) {
+                *element = component * length;
+            }
+        }
+        let origin = volume.origin();
+        model_matrix[3][..3].copy_from_slice(&origin);
+        model_matrix
+    }
+
+    /// Creates the description of the pipeline rendering the volume's bounding box.
+    ///
+    /// Only the back faces of the box are rasterized, so that the volume stays
+    /// visible when the camera is inside it. The fragment shader marches rays
+    /// through the volume texture towards the eye and looks up sample colors in
+    /// the transfer function texture. The matrices and sampling parameters are
+    /// supplied as push constants, while the isosurfaces and the matrices for
+    /// shading them are supplied in a uniform buffer.
+    fn create_volume_pipeline_description() -> PipelineDescription {
+        let fragment_binding = |binding, ty| DescriptorSetLayoutBinding {
+            binding,
+            ty,
+            count: 1,
+            stage_flags: ShaderStageFlags::FRAGMENT,
+            immutable_samplers: false,
+        };
+        PipelineDescription {
+            vertex_shader: shaders::VOLUME_VERTEX,
+            fragment_shader: shaders::VOLUME_FRAGMENT,
+            descriptor_set_layout_bindings: vec![vec![
+                fragment_binding(VOLUME_TEXTURE_BINDING, DescriptorType::SampledImage),
+                fragment_binding(VOLUME_TEXTURE_BINDING + 1, DescriptorType::Sampler),
+                fragment_binding(
+                    TRANSFER_FUNCTION_TEXTURE_BINDING,
+                    DescriptorType::SampledImage,
+                ),
+                fragment_binding(
+                    TRANSFER_FUNCTION_TEXTURE_BINDING + 1,
+                    DescriptorType::Sampler,
+                ),
+                fragment_binding(VOLUME_UNIFORM_BUFFER_BINDING, DescriptorType::UniformBuffer),
+            ]],
+            push_constant_ranges: vec![(
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0..(VOLUME_PUSH_CONSTANT_WORDS * 4) as u32,
+            )],
+            primitive: Primitive::TriangleList,
+            rasterizer: Rasterizer {
+                cull_face: Face::FRONT,
+                // The projection flips the y-axis, which reverses the winding
+                // of the triangles in the framebuffer
+                front_face: FrontFace::Clockwise,
+                ..Rasterizer::FILL
+            },
+            blend_state: Some(BlendState::PREMULTIPLIED_ALPHA),
+        }
     }
 
     fn create_viewport(extent: &Extent) -> Viewport {
@@ -292,8 +1050,141 @@ impl<B: Backend> Drop for RendererState<B> {
     }
 }
 
+/// Returns an error if there are more isosurfaces than can be rendered.
+fn check_isosurface_count(isosurfaces: &[Isosurface]) -> VortekResult<()> {
+    if isosurfaces.len() > MAX_ISOSURFACES {
+        Err(VortekError::RenderingError(RenderingError::from_string(
+            format!(
+                "Number of isosurfaces ({}) exceeds the maximum ({}).",
+                isosurfaces.len(),
+                MAX_ISOSURFACES
+            ),
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Packs the given floating-point push constants of the volume pipeline,
+/// followed by the settings for the render mode and number of isosurfaces, into
+/// 32-bit words.
+fn pack_volume_push_constants<I: IntoIterator<Item = f32>>(
+    values: I,
+    render_mode: RenderMode,
+    isosurface_count: usize,
+) -> [u32; VOLUME_PUSH_CONSTANT_WORDS] {
+    let mut push_constants = [0; VOLUME_PUSH_CONSTANT_WORDS];
+    let float_word_count = push_constants
+        .iter_mut()
+        .zip(values)
+        .map(|(word, value)| *word = value.to_bits())
+        .count();
+    push_constants[float_word_count] = render_mode.shader_index();
+    push_constants[float_word_count + 1] = isosurface_count as u32;
+    push_constants
+}
+
+/// Writes the color, encoded with the given function, and value of each of the
+/// given isosurfaces to consecutive groups of uniform words.
+///
+/// The values are normalized to the given value range in the same way as the
+/// sampled volume values.
+fn pack_isosurface_uniforms<F: Fn(&Color) -> Color>(
+    uniforms: &mut [f32],
+    isosurfaces: &[Isosurface],
+    (min_value, max_value): (f32, f32),
+    encode_color: F,
+) {
+    let normalize_value = |value: f32| {
+        if max_value > min_value {
+            (value - min_value) / (max_value - min_value)
+        } else {
+            0.5
+        }
+    };
+    for (words, isosurface) in uniforms
+        .chunks_exact_mut(ISOSURFACE_UNIFORM_WORDS)
+        .zip(isosurfaces)
+    {
+        words[..4].copy_from_slice(&encode_color(&isosurface.color).into_slice());
+        words[4] = normalize_value(isosurface.value);
+    }
+}
+
 impl fmt::Display for RenderingError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.message)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn isosurface(value: f32, red: f32) -> Isosurface {
+        Isosurface {
+            value,
+            color: Color::from_components(red, 0.5, 0.25, 0.75),
+        }
+    }
+
+    #[test]
+    fn push_constants_end_with_render_mode_and_isosurface_count() {
+        let values: Vec<f32> = (0..28).map(|value| value as f32).collect();
+        let push_constants =
+            pack_volume_push_constants(values.iter().copied(), RenderMode::Isosurface, 3);
+        for (&word, &value) in push_constants.iter().zip(&values) {
+            assert_eq!(f32::from_bits(word), value);
+        }
+        assert_eq!(push_constants[28], 4);
+        assert_eq!(push_constants[29], 3);
+        assert_eq!(push_constants[30..], [0, 0]);
+    }
+
+    #[test]
+    fn render_modes_have_distinct_shader_indices() {
+        let mut indices: Vec<u32> = RenderMode::names()
+            .map(|name| RenderMode::from_name(name).unwrap().shader_index())
+            .collect();
+        indices.sort_unstable();
+        assert_eq!(indices, vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn isosurface_uniforms_hold_colors_and_normalized_values() {
+        let isosurfaces = [isosurface(2.0, 1.0), isosurface(4.0, 0.0)];
+        let mut uniforms = [-1.0; 3 * ISOSURFACE_UNIFORM_WORDS];
+        pack_isosurface_uniforms(&mut uniforms, &isosurfaces, (1.0, 5.0), Color::clone);
+        assert_eq!(uniforms[..5], [1.0, 0.5, 0.25, 0.75, 0.25]);
+        assert_eq!(
+            uniforms[ISOSURFACE_UNIFORM_WORDS..ISOSURFACE_UNIFORM_WORDS + 5],
+            [0.0, 0.5, 0.25, 0.75, 0.75]
+        );
+        // Words of unused isosurfaces are left unchanged
+        assert!(uniforms[2 * ISOSURFACE_UNIFORM_WORDS..]
+            .iter()
+            .all(|&word| word == -1.0));
+    }
+
+    #[test]
+    fn isosurface_values_are_centered_for_constant_volumes() {
+        let mut uniforms = [0.0; ISOSURFACE_UNIFORM_WORDS];
+        pack_isosurface_uniforms(
+            &mut uniforms,
+            &[isosurface(3.0, 1.0)],
+            (3.0, 3.0),
+            |color| color.srgb_to_linear(),
+        );
+        assert_eq!(uniforms[0], 1.0);
+        assert!(uniforms[1] < 0.5);
+        assert_eq!(uniforms[4], 0.5);
+    }
+
+    #[test]
+    fn isosurface_count_is_limited() {
+        let isosurfaces = vec![isosurface(0.0, 1.0); MAX_ISOSURFACES + 1];
+        assert!(check_isosurface_count(&isosurfaces[..MAX_ISOSURFACES]).is_ok());
+        assert!(check_isosurface_count(&isosurfaces).is_err());
+        assert!(check_isosurface_count(&[]).is_ok());
+    }
+}
@@ -11,8 +11,9 @@ pub struct AdapterState<B: Backend> {
 
 impl<B: Backend> AdapterState<B> {
     /// Creates a new adapter state representing the first adaptor supported by the
-    /// given surface.
-    pub fn new(adapters: Vec<Adapter<B>>, surface: &B::Surface) -> VortekResult<Self> {
+    /// given surface, or the first adapter with graphics support if there is no
+    /// surface.
+    pub fn new(adapters: Vec<Adapter<B>>, surface: Option<&B::Surface>) -> VortekResult<Self> {
         let adapter = Self::select_adapter(adapters, surface)?;
         Ok(Self {
             adapter: Some(adapter),
@@ -25,14 +26,17 @@ impl<B: Backend> AdapterState<B> {
     }
 
     /// Selects the first available adapter with a queue family that supports graphics
-    /// and is supported by the surface.
-    fn select_adapter(adapters: Vec<Adapter<B>>, surface: &B::Surface) -> VortekResult<Adapter<B>> {
+    /// and is supported by the surface, if any.
+    fn select_adapter(
+        adapters: Vec<Adapter<B>>,
+        surface: Option<&B::Surface>,
+    ) -> VortekResult<Adapter<B>> {
         adapters
             .into_iter()
             .find(|adapter| {
                 adapter.queue_families.iter().any(|queue_family| {
                     queue_family.queue_type().supports_graphics()
-                        && surface.supports_queue_family(queue_family)
+                        && surface.is_none_or(|surface| surface.supports_queue_family(queue_family))
                 })
             })
             .ok_or_else(|| {
@@ -1,44 +1,85 @@
 //! Backend management.
+//!
+//! Several backends can be compiled in at once through the `vulkan`, `metal`
+//! and `dx12` features, in addition to the always available empty backend,
+//! which renders nothing and is only used when explicitly requested. The
+//! backend is selected when the renderer is created, see
+//! `BackendKind::candidates`.
 
 use super::{super::window::WindowState, adapter::AdapterState, RenderingError};
 use crate::error::{VortekError, VortekResult};
 use gfx_hal::{Backend, Instance};
+use std::env;
 
-#[cfg(feature = "dx12")]
-use gfx_backend_dx12 as backend;
-#[cfg(feature = "metal")]
-use gfx_backend_metal as backend;
-#[cfg(feature = "vulkan")]
-use gfx_backend_vulkan as backend;
+/// Environment variable holding the name of the backend to prefer.
+pub const BACKEND_ENV_VAR: &str = "VORTEK_BACKEND";
 
-pub type BackendType = backend::Backend;
+/// Graphics backends that a renderer can use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BackendKind {
+    Vulkan,
+    Metal,
+    Dx12,
+    /// Backend without any device, which accepts all rendering commands but
+    /// renders nothing.
+    Empty,
+}
+
+/// Names of the backends, in order of priority for automatic selection.
+const BACKEND_KIND_NAMES: [(BackendKind, &str); 4] = [
+    (BackendKind::Vulkan, "vulkan"),
+    (BackendKind::Metal, "metal"),
+    (BackendKind::Dx12, "dx12"),
+    (BackendKind::Empty, "empty"),
+];
 
 /// Structure for managing backend state.
+///
+/// A headless backend state has no window or surface, and can only be used
+/// for rendering to offscreen images.
 pub struct BackendState<B: Backend> {
-    window_state: WindowState,
-    surface: B::Surface,
+    window_state: Option<WindowState>,
+    surface: Option<B::Surface>,
     adapter_state: AdapterState<B>,
 }
 
 impl<B: Backend> BackendState<B> {
+    /// Whether the backend state has no window or surface.
+    pub fn is_headless(&self) -> bool {
+        self.surface.is_none()
+    }
+
     /// Returns a reference to the window state held by the backend state.
     pub fn window_state(&self) -> &WindowState {
-        &self.window_state
+        self.window_state
+            .as_ref()
+            .expect("No window state in headless backend state.")
     }
 
     /// Returns a mutable reference to the window state held by the backend state.
     pub fn window_state_mut(&mut self) -> &mut WindowState {
-        &mut self.window_state
+        self.window_state
+            .as_mut()
+            .expect("No window state in headless backend state.")
     }
 
-    /// Returns a reference to the surface held by the backend state.
-    pub fn surface(&self) -> &B::Surface {
-        &self.surface
+    /// Removes the window state from the backend state and returns it, or
+    /// `None` if the backend state is headless.
+    pub fn take_window_state(&mut self) -> Option<WindowState> {
+        self.window_state.take()
+    }
+
+    /// Returns a reference to the surface held by the backend state, or `None`
+    /// if the backend state is headless.
+    pub fn surface(&self) -> Option<&B::Surface> {
+        self.surface.as_ref()
     }
 
     /// Returns a mutable reference to the surface held by the backend state.
     pub fn surface_mut(&mut self) -> &mut B::Surface {
-        &mut self.surface
+        self.surface
+            .as_mut()
+            .expect("No surface in headless backend state.")
     }
 
     /// Returns a reference to the adapter state held by the backend state.
@@ -52,32 +93,205 @@ impl<B: Backend> BackendState<B> {
     }
 }
 
-/// Creates a new backend state from the given window state.
-pub fn create_backend_state(
-    window_state: WindowState,
-) -> VortekResult<(BackendState<backend::Backend>, backend::Instance)> {
-    let instance = backend::Instance::create(window_state.window_title(), 1).map_err(|_| {
+impl BackendKind {
+    /// Returns the names of all backends, including unavailable ones.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        BACKEND_KIND_NAMES.iter().map(|(_, name)| *name)
+    }
+
+    /// Returns the name of the backend.
+    pub fn name(self) -> &'static str {
+        BACKEND_KIND_NAMES
+            .iter()
+            .find(|(kind, _)| *kind == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    /// Returns the backend with the given name, or `None` if there is no such
+    /// backend.
+    pub fn from_name(name: &str) -> Option<Self> {
+        BACKEND_KIND_NAMES
+            .iter()
+            .find(|(_, kind_name)| *kind_name == name)
+            .map(|(kind, _)| *kind)
+    }
+
+    /// Whether support for the backend is compiled into the library.
+    pub fn is_available(self) -> bool {
+        match self {
+            BackendKind::Vulkan => cfg!(feature = "vulkan"),
+            BackendKind::Metal => cfg!(all(feature = "metal", target_os = "macos")),
+            BackendKind::Dx12 => cfg!(all(feature = "dx12", windows)),
+            BackendKind::Empty => true,
+        }
+    }
+
+    /// Returns the available backends in order of priority.
+    pub fn available() -> impl Iterator<Item = Self> {
+        BACKEND_KIND_NAMES
+            .iter()
+            .map(|(kind, _)| *kind)
+            .filter(|kind| kind.is_available())
+    }
+
+    /// Returns the backend named by the `VORTEK_BACKEND` environment variable,
+    /// or `None` if it is not set.
+    ///
+    /// Returns an error if the name is invalid or the backend is unavailable.
+    pub fn from_env() -> VortekResult<Option<Self>> {
+        let name = match env::var(BACKEND_ENV_VAR) {
+            Ok(name) => name,
+            Err(env::VarError::NotPresent) => return Ok(None),
+            Err(err) => {
+                return Err(VortekError::RenderingError(RenderingError::from_error(
+                    "Invalid backend environment variable: ",
+                    err,
+                )))
+            }
+        };
+        let kind = Self::from_name(name.trim())
+            .filter(|kind| kind.is_available())
+            .ok_or_else(|| {
+                VortekError::RenderingError(RenderingError::from_string(format!(
+                    "Backend {} given by {} is not available (available backends are {}).",
+                    name,
+                    BACKEND_ENV_VAR,
+                    Self::available_names()
+                )))
+            })?;
+        Ok(Some(kind))
+    }
+
+    /// Returns the backends to attempt creating a renderer with, in order.
+    ///
+    /// The given preferred backend, or else the one given by the
+    /// `VORTEK_BACKEND` environment variable, comes first, followed by the
+    /// remaining available backends in order of priority. The empty backend
+    /// is only included when it is preferred, in which case it is the only
+    /// candidate.
+    ///
+    /// Returns an error if the preferred backend is unavailable or the
+    /// environment variable is invalid.
+    pub fn candidates(preferred: Option<Self>) -> VortekResult<Vec<Self>> {
+        let preferred = match preferred {
+            Some(kind) if !kind.is_available() => {
+                return Err(VortekError::RenderingError(RenderingError::from_string(
+                    format!(
+                        "Backend {} is not available (available backends are {}).",
+                        kind.name(),
+                        Self::available_names()
+                    ),
+                )))
+            }
+            Some(kind) => Some(kind),
+            None => Self::from_env()?,
+        };
+        if preferred == Some(BackendKind::Empty) {
+            return Ok(vec![BackendKind::Empty]);
+        }
+        Ok(preferred
+            .into_iter()
+            .chain(
+                Self::available()
+                    .filter(|&kind| Some(kind) != preferred && kind != BackendKind::Empty),
+            )
+            .collect())
+    }
+
+    /// Returns a comma-separated list of the names of the available backends.
+    fn available_names() -> String {
+        Self::available()
+            .map(Self::name)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Creates a new instance of the given backend for the application with the
+/// given name.
+pub fn create_instance<B: Backend>(application_name: &str) -> VortekResult<B::Instance> {
+    B::Instance::create(application_name, 1).map_err(|_| {
         VortekError::RenderingError(RenderingError::from_str(
             "Could not instantiate backend because it is not supported.",
         ))
-    })?;
-    let surface = unsafe {
-        instance
-            .create_surface(window_state.window())
-            .map_err(|err| {
+    })
+}
+
+/// Creates a new backend state from the given instance and window state.
+///
+/// The window state is returned along with the error if creation fails, so
+/// that the window can be used with another backend.
+pub fn create_backend_state<B: Backend>(
+    instance: &B::Instance,
+    window_state: WindowState,
+) -> Result<BackendState<B>, (VortekError, Box<WindowState>)> {
+    let surface = match unsafe { instance.create_surface(window_state.window()) } {
+        Ok(surface) => surface,
+        Err(err) => {
+            return Err((
                 VortekError::RenderingError(RenderingError::from_error(
                     "Could not create surface: ",
                     err,
-                ))
-            })?
+                )),
+                Box::new(window_state),
+            ))
+        }
     };
-    let adapter_state = AdapterState::new(instance.enumerate_adapters(), &surface)?;
-    Ok((
-        BackendState {
-            window_state,
-            surface,
+    match AdapterState::new(instance.enumerate_adapters(), Some(&surface)) {
+        Ok(adapter_state) => Ok(BackendState {
+            window_state: Some(window_state),
+            surface: Some(surface),
             adapter_state,
-        },
-        instance,
-    ))
+        }),
+        Err(err) => {
+            unsafe { instance.destroy_surface(surface) };
+            Err((err, Box::new(window_state)))
+        }
+    }
+}
+
+/// Creates a new headless backend state from the given instance, without a
+/// window or surface.
+///
+/// The first adapter with graphics support is selected, so no display is
+/// required.
+pub fn create_headless_backend_state<B: Backend>(
+    instance: &B::Instance,
+) -> VortekResult<BackendState<B>> {
+    let adapter_state = AdapterState::new(instance.enumerate_adapters(), None)?;
+    Ok(BackendState {
+        window_state: None,
+        surface: None,
+        adapter_state,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidates_put_preferred_backend_first_and_exclude_unrequested_empty_backend() {
+        let candidates = BackendKind::candidates(Some(BackendKind::Empty)).unwrap();
+        assert_eq!(candidates, vec![BackendKind::Empty]);
+
+        if let Some(kind) = BackendKind::available().find(|&kind| kind != BackendKind::Empty) {
+            let candidates = BackendKind::candidates(Some(kind)).unwrap();
+            assert_eq!(candidates.first(), Some(&kind));
+            assert!(!candidates.contains(&BackendKind::Empty));
+        }
+    }
+
+    #[test]
+    fn unavailable_preferred_backend_is_an_error() {
+        let unavailable = BACKEND_KIND_NAMES
+            .iter()
+            .map(|(kind, _)| *kind)
+            .filter(|kind| !kind.is_available());
+        for kind in unavailable {
+            let err = BackendKind::candidates(Some(kind)).unwrap_err();
+            assert!(err.to_string().contains("empty"));
+        }
+    }
 }
@@ -0,0 +1,153 @@
+//! Buffer management.
+
+use super::{device::DeviceState, RenderingError};
+use crate::error::{VortekError, VortekResult};
+use gfx_hal::{buffer::Usage, device::Device, memory::Properties, Backend};
+use std::{cell::RefCell, ops::Drop, ptr, rc::Rc};
+
+/// Structure for managing a buffer and its bound memory.
+pub struct BufferState<B: Backend> {
+    buffer: Option<B::Buffer>,
+    memory: Option<B::Memory>,
+    size: u64,
+    device_state: Rc<RefCell<DeviceState<B>>>,
+}
+
+impl<B: Backend> BufferState<B> {
+    /// Creates a new buffer of the given size and usage, bound to newly allocated
+    /// memory with the given properties.
+    pub fn new(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        size: u64,
+        usage: Usage,
+        memory_properties: Properties,
+    ) -> VortekResult<Self> {
+        let (buffer, memory) = {
+            let borrowed_device_state = device_state.borrow();
+            let device = borrowed_device_state.device();
+            unsafe {
+                let mut buffer = device.create_buffer(size, usage).map_err(|err| {
+                    VortekError::RenderingError(RenderingError::from_error(
+                        "Could not create buffer: ",
+                        err,
+                    ))
+                })?;
+                let requirements = device.get_buffer_requirements(&buffer);
+                let memory_type = borrowed_device_state
+                    .find_memory_type(requirements.type_mask, memory_properties)
+                    .and_then(|memory_type| {
+                        device
+                            .allocate_memory(memory_type, requirements.size)
+                            .map_err(|err| {
+                                VortekError::RenderingError(RenderingError::from_error(
+                                    "Could not allocate buffer memory: ",
+                                    err,
+                                ))
+                            })
+                    });
+                let memory = match memory_type {
+                    Ok(memory) => memory,
+                    Err(err) => {
+                        device.destroy_buffer(buffer);
+                        return Err(err);
+                    }
+                };
+                if let Err(err) = device.bind_buffer_memory(&memory, 0, &mut buffer) {
+                    device.destroy_buffer(buffer);
+                    device.free_memory(memory);
+                    return Err(VortekError::RenderingError(RenderingError::from_error(
+                        "Could not bind buffer memory: ",
+                        err,
+                    )));
+                }
+                (buffer, memory)
+            }
+        };
+        Ok(Self {
+            buffer: Some(buffer),
+            memory: Some(memory),
+            size,
+            device_state,
+        })
+    }
+
+    /// Creates a new host visible buffer that can be used as the source of a
+    /// transfer, and fills it with the given data.
+    pub fn new_staging(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        data: &[u8],
+    ) -> VortekResult<Self> {
+        let buffer_state = Self::new(
+            device_state,
+            data.len() as u64,
+            Usage::TRANSFER_SRC,
+            Properties::CPU_VISIBLE | Properties::COHERENT,
+        )?;
+        buffer_state.write_data(data)?;
+        Ok(buffer_state)
+    }
+
+    /// Returns a reference to the buffer held by the buffer state.
+    pub fn buffer(&self) -> &B::Buffer {
+        self.buffer.as_ref().expect("No buffer in buffer state.")
+    }
+
+    /// Returns the size of the buffer in bytes.
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Copies the given data to the beginning of the buffer memory, which must
+    /// be host visible and coherent.
+    pub fn write_data(&self, data: &[u8]) -> VortekResult<()> {
+        assert!(
+            data.len() as u64 <= self.size,
+            "Data does not fit in buffer."
+        );
+        let memory = self.memory.as_ref().expect("No memory in buffer state.");
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        unsafe {
+            let mapped = device.map_memory(memory, 0..self.size).map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not map buffer memory: ",
+                    err,
+                ))
+            })?;
+            ptr::copy_nonoverlapping(data.as_ptr(), mapped, data.len());
+            device.unmap_memory(memory);
+        }
+        Ok(())
+    }
+
+    /// Copies the whole content of the buffer memory, which must be host visible
+    /// and coherent, to a new vector.
+    pub fn read_data(&self) -> VortekResult<Vec<u8>> {
+        let memory = self.memory.as_ref().expect("No memory in buffer state.");
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        let mut data = vec![0; self.size as usize];
+        unsafe {
+            let mapped = device.map_memory(memory, 0..self.size).map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not map buffer memory: ",
+                    err,
+                ))
+            })?;
+            ptr::copy_nonoverlapping(mapped, data.as_mut_ptr(), data.len());
+            device.unmap_memory(memory);
+        }
+        Ok(data)
+    }
+}
+
+impl<B: Backend> Drop for BufferState<B> {
+    fn drop(&mut self) {
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        unsafe {
+            device.destroy_buffer(self.buffer.take().expect("No buffer in buffer state."));
+            device.free_memory(self.memory.take().expect("No memory in buffer state."));
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Descriptor set management.
+
+use super::{device::DeviceState, RenderingError};
+use crate::error::{VortekError, VortekResult};
+use gfx_hal::{
+    device::Device,
+    image::Layout,
+    pso::{
+        Descriptor, DescriptorBinding, DescriptorPool, DescriptorPoolCreateFlags,
+        DescriptorRangeDesc, DescriptorSetLayoutBinding, DescriptorSetWrite,
+    },
+    Backend,
+};
+use std::{cell::RefCell, iter, ops::Drop, rc::Rc};
+
+/// Structure for managing a descriptor set along with the pool it is allocated from.
+pub struct DescriptorSetState<B: Backend> {
+    descriptor_pool: Option<B::DescriptorPool>,
+    descriptor_set: Option<B::DescriptorSet>,
+    device_state: Rc<RefCell<DeviceState<B>>>,
+}
+
+impl<B: Backend> DescriptorSetState<B> {
+    /// Creates a new descriptor set with the given layout, allocated from a pool
+    /// with room for exactly the given bindings.
+    pub fn new(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        layout: &B::DescriptorSetLayout,
+        bindings: &[DescriptorSetLayoutBinding],
+    ) -> VortekResult<Self> {
+        let ranges: Vec<_> = bindings
+            .iter()
+            .map(|binding| DescriptorRangeDesc {
+                ty: binding.ty,
+                count: binding.count,
+            })
+            .collect();
+
+        let mut descriptor_pool = unsafe {
+            device_state
+                .borrow()
+                .device()
+                .create_descriptor_pool(1, ranges, DescriptorPoolCreateFlags::empty())
+                .map_err(|err| {
+                    VortekError::RenderingError(RenderingError::from_error(
+                        "Could not create descriptor pool: ",
+                        err,
+                    ))
+                })?
+        };
+
+        let descriptor_set = match unsafe { descriptor_pool.allocate_set(layout) } {
+            Ok(descriptor_set) => descriptor_set,
+            Err(err) => {
+                unsafe {
+                    device_state
+                        .borrow()
+                        .device()
+                        .destroy_descriptor_pool(descriptor_pool)
+                };
+                return Err(VortekError::RenderingError(RenderingError::from_error(
+                    "Could not allocate descriptor set: ",
+                    format!("{:?}", err),
+                )));
+            }
+        };
+
+        Ok(Self {
+            descriptor_pool: Some(descriptor_pool),
+            descriptor_set: Some(descriptor_set),
+            device_state,
+        })
+    }
+
+    /// Returns a reference to the descriptor set held by the descriptor set state.
+    pub fn descriptor_set(&self) -> &B::DescriptorSet {
+        self.descriptor_set
+            .as_ref()
+            .expect("No descriptor set in descriptor set state.")
+    }
+
+    /// Writes the given sampled image view and sampler to the given pair of
+    /// consecutive bindings.
+    ///
+    /// The descriptor set must not be in use by any pending command buffers.
+    pub fn write_texture(
+        &mut self,
+        image_binding: DescriptorBinding,
+        image_view: &B::ImageView,
+        sampler: &B::Sampler,
+    ) {
+        let descriptor_set = self.descriptor_set();
+        unsafe {
+            self.device_state
+                .borrow()
+                .device()
+                .write_descriptor_sets(vec![
+                    DescriptorSetWrite {
+                        set: descriptor_set,
+                        binding: image_binding,
+                        array_offset: 0,
+                        descriptors: iter::once(Descriptor::Image(
+                            image_view,
+                            Layout::ShaderReadOnlyOptimal,
+                        )),
+                    },
+                    DescriptorSetWrite {
+                        set: descriptor_set,
+                        binding: image_binding + 1,
+                        array_offset: 0,
+                        descriptors: iter::once(Descriptor::Sampler(sampler)),
+                    },
+                ]);
+        }
+    }
+
+    /// Writes the whole of the given buffer to the given binding.
+    ///
+    /// The descriptor set must not be in use by any pending command buffers.
+    pub fn write_buffer(&mut self, binding: DescriptorBinding, buffer: &B::Buffer) {
+        let descriptor_set = self.descriptor_set();
+        unsafe {
+            self.device_state
+                .borrow()
+                .device()
+                .write_descriptor_sets(iter::once(DescriptorSetWrite {
+                    set: descriptor_set,
+                    binding,
+                    array_offset: 0,
+                    descriptors: iter::once(Descriptor::Buffer(buffer, None..None)),
+                }));
+        }
+    }
+}
+
+impl<B: Backend> Drop for DescriptorSetState<B> {
+    fn drop(&mut self) {
+        // Destroying the pool implicitly frees the descriptor set
+        self.descriptor_set.take();
+        unsafe {
+            self.device_state.borrow().device().destroy_descriptor_pool(
+                self.descriptor_pool
+                    .take()
+                    .expect("No descriptor pool in descriptor set state."),
+            );
+        }
+    }
+}
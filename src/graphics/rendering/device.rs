@@ -4,11 +4,16 @@ use super::RenderingError;
 use crate::error::{VortekError, VortekResult};
 use gfx_hal::{
     adapter::{Adapter, Gpu, PhysicalDevice},
-    queue::{QueueFamily, QueueGroup},
+    command::{CommandBuffer, CommandBufferFlags, Level},
+    device::{Device, OomOrDeviceLost},
+    memory::Properties,
+    pool::{CommandPool, CommandPoolCreateFlags},
+    queue::{CommandQueue, QueueFamily, QueueGroup},
     window::Surface,
-    Backend, Features,
+    Backend, Features, MemoryTypeId,
 };
 use log::debug;
+use std::iter;
 
 /// Structure for managing device state.
 pub struct DeviceState<B: Backend> {
@@ -19,8 +24,9 @@ pub struct DeviceState<B: Backend> {
 }
 
 impl<B: Backend> DeviceState<B> {
-    /// Creates a new device state from the given adapter.
-    pub fn new(adapter: Adapter<B>, surface: &B::Surface) -> VortekResult<Self> {
+    /// Creates a new device state from the given adapter, with a queue supporting
+    /// the given surface if there is one.
+    pub fn new(adapter: Adapter<B>, surface: Option<&B::Surface>) -> VortekResult<Self> {
         let Adapter {
             info,
             physical_device,
@@ -70,16 +76,102 @@ impl<B: Backend> DeviceState<B> {
         &mut self.queue_group
     }
 
+    /// Finds the first memory type that is allowed by the given type mask and has
+    /// all the given properties.
+    pub fn find_memory_type(
+        &self,
+        type_mask: u64,
+        properties: Properties,
+    ) -> VortekResult<MemoryTypeId> {
+        self.physical_device
+            .memory_properties()
+            .memory_types
+            .iter()
+            .enumerate()
+            .position(|(id, memory_type)| {
+                type_mask & (1 << id) != 0 && memory_type.properties.contains(properties)
+            })
+            .map(MemoryTypeId)
+            .ok_or_else(|| {
+                VortekError::RenderingError(RenderingError::from_str(
+                    "Could not find a suitable memory type.",
+                ))
+            })
+    }
+
+    /// Records commands into a one-shot command buffer using the given closure,
+    /// submits the buffer to the first queue of the queue group and waits for
+    /// the commands to complete.
+    ///
+    /// # Safety
+    /// The recorded commands must be valid for the resources they refer to.
+    pub unsafe fn execute_one_time_commands<F>(&mut self, record_commands: F) -> VortekResult<()>
+    where
+        F: FnOnce(&mut B::CommandBuffer),
+    {
+        let mut command_pool = self
+            .device
+            .create_command_pool(self.queue_family.id(), CommandPoolCreateFlags::TRANSIENT)
+            .map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not create command pool: ",
+                    err,
+                ))
+            })?;
+
+        let mut command_buffer = command_pool.allocate_one(Level::Primary);
+        command_buffer.begin_primary(CommandBufferFlags::ONE_TIME_SUBMIT);
+        record_commands(&mut command_buffer);
+        command_buffer.finish();
+
+        let result =
+            self.device
+                .create_fence(false)
+                .map_err(|err| {
+                    VortekError::RenderingError(RenderingError::from_error(
+                        "Could not create fence: ",
+                        err,
+                    ))
+                })
+                .and_then(|fence| {
+                    self.queue_group.queues[0]
+                        .submit_without_semaphores(iter::once(&command_buffer), Some(&fence));
+                    let wait_result = self.device.wait_for_fence(&fence, u64::MAX).map_err(
+                        |oom_or_device_lost| match oom_or_device_lost {
+                            OomOrDeviceLost::OutOfMemory(out_of_memory_err) => {
+                                VortekError::RenderingError(RenderingError::from_error(
+                                    "Could not wait for one-time command fence (out of memory): ",
+                                    out_of_memory_err,
+                                ))
+                            }
+                            OomOrDeviceLost::DeviceLost(device_lost_err) => {
+                                VortekError::RenderingError(RenderingError::from_error(
+                                    "Could not wait for one-time command fence (device lost): ",
+                                    device_lost_err,
+                                ))
+                            }
+                        },
+                    );
+                    self.device.destroy_fence(fence);
+                    wait_result.map(|_| ())
+                });
+
+        command_pool.free(iter::once(command_buffer));
+        self.device.destroy_command_pool(command_pool);
+        result
+    }
+
     /// Takes and returns the first available queue family that supports graphics
-    /// and is supported by the surface.
+    /// and is supported by the surface, if any.
     fn take_queue_family(
         queue_families: Vec<<B as Backend>::QueueFamily>,
-        surface: &B::Surface,
+        surface: Option<&B::Surface>,
     ) -> VortekResult<<B as Backend>::QueueFamily> {
         queue_families
             .into_iter()
             .find(|family| {
-                family.queue_type().supports_graphics() && surface.supports_queue_family(family)
+                family.queue_type().supports_graphics()
+                    && surface.is_none_or(|surface| surface.supports_queue_family(family))
             })
             .ok_or_else(|| {
                 VortekError::RenderingError(RenderingError::from_str(
@@ -0,0 +1,314 @@
+//! Renderer using a backend selected at runtime.
+
+use super::{
+    super::window::WindowState,
+    backend::{self, BackendKind},
+    empty::EmptyRendererState,
+    offscreen::ImageData,
+    Isosurface, RenderMode, RendererState, RenderingError,
+};
+use crate::{
+    camera::Camera,
+    color::Color,
+    error::{VortekError, VortekResult},
+    transfer_function::TransferFunction,
+    volume::ScalarVolume,
+};
+use gfx_hal::Backend;
+use log::{info, warn};
+
+/// Renderer state for one of the backends compiled into the library, along
+/// with the backend instance it was created from.
+///
+/// The renderer state is dropped before the instance.
+pub enum AnyRendererState {
+    #[cfg(feature = "vulkan")]
+    Vulkan(
+        Box<RendererState<gfx_backend_vulkan::Backend>>,
+        gfx_backend_vulkan::Instance,
+    ),
+    #[cfg(all(feature = "metal", target_os = "macos"))]
+    Metal(
+        Box<RendererState<gfx_backend_metal::Backend>>,
+        gfx_backend_metal::Instance,
+    ),
+    #[cfg(all(feature = "dx12", windows))]
+    Dx12(
+        Box<RendererState<gfx_backend_dx12::Backend>>,
+        gfx_backend_dx12::Instance,
+    ),
+    Empty(Box<EmptyRendererState>),
+}
+
+/// Evaluates the given expression with the identifier bound to the renderer
+/// state of whichever backend is in use.
+macro_rules! dispatch {
+    ($any_renderer_state:expr, $renderer_state:ident => $body:expr) => {
+        match $any_renderer_state {
+            #[cfg(feature = "vulkan")]
+            AnyRendererState::Vulkan($renderer_state, _) => $body,
+            #[cfg(all(feature = "metal", target_os = "macos"))]
+            AnyRendererState::Metal($renderer_state, _) => $body,
+            #[cfg(all(feature = "dx12", windows))]
+            AnyRendererState::Dx12($renderer_state, _) => $body,
+            AnyRendererState::Empty($renderer_state) => $body,
+        }
+    };
+}
+
+impl AnyRendererState {
+    /// Creates a new renderer state for the given window, or a headless one if
+    /// there is no window, for the application with the given name.
+    ///
+    /// The backends returned by `BackendKind::candidates` for the given
+    /// preferred backend are tried in order, falling back to the next one when
+    /// an instance, surface, adapter or device of a backend cannot be created.
+    /// The empty backend is only used when it is the preferred backend.
+    ///
+    /// Returns an error if the preferred backend is unavailable or none of the
+    /// candidate backends could be used.
+    #[cfg_attr(
+        not(any(
+            feature = "vulkan",
+            all(feature = "metal", target_os = "macos"),
+            all(feature = "dx12", windows)
+        )),
+        allow(unused_variables, unused_mut, unreachable_code, clippy::never_loop)
+    )]
+    pub fn new(
+        mut window_state: Option<WindowState>,
+        application_name: &str,
+        preferred_backend: Option<BackendKind>,
+    ) -> VortekResult<Self> {
+        let mut failed_backend_names = Vec::new();
+        for kind in BackendKind::candidates(preferred_backend)? {
+            match kind {
+                #[cfg(feature = "vulkan")]
+                BackendKind::Vulkan => {
+                    if let Some(instance) =
+                        try_create_instance::<gfx_backend_vulkan::Backend>(kind, application_name)
+                    {
+                        match create_renderer_state(kind, &instance, window_state) {
+                            Ok(renderer_state) => {
+                                return Ok(AnyRendererState::Vulkan(renderer_state, instance))
+                            }
+                            Err(returned_window_state) => {
+                                window_state =
+                                    returned_window_state.map(|window_state| *window_state)
+                            }
+                        }
+                    }
+                }
+                #[cfg(all(feature = "metal", target_os = "macos"))]
+                BackendKind::Metal => {
+                    if let Some(instance) =
+                        try_create_instance::<gfx_backend_metal::Backend>(kind, application_name)
+                    {
+                        match create_renderer_state(kind, &instance, window_state) {
+                            Ok(renderer_state) => {
+                                return Ok(AnyRendererState::Metal(renderer_state, instance))
+                            }
+                            Err(returned_window_state) => {
+                                window_state =
+                                    returned_window_state.map(|window_state| *window_state)
+                            }
+                        }
+                    }
+                }
+                #[cfg(all(feature = "dx12", windows))]
+                BackendKind::Dx12 => {
+                    if let Some(instance) =
+                        try_create_instance::<gfx_backend_dx12::Backend>(kind, application_name)
+                    {
+                        match create_renderer_state(kind, &instance, window_state) {
+                            Ok(renderer_state) => {
+                                return Ok(AnyRendererState::Dx12(renderer_state, instance))
+                            }
+                            Err(returned_window_state) => {
+                                window_state =
+                                    returned_window_state.map(|window_state| *window_state)
+                            }
+                        }
+                    }
+                }
+                BackendKind::Empty => {
+                    warn!("Using the empty backend, so nothing will be rendered.");
+                    return Ok(AnyRendererState::Empty(Box::new(EmptyRendererState::new(
+                        window_state,
+                    ))));
+                }
+                #[allow(unreachable_patterns)]
+                _ => unreachable!("Unavailable backend among candidates."),
+            }
+            failed_backend_names.push(kind.name());
+        }
+        Err(VortekError::RenderingError(
+            if failed_backend_names.is_empty() {
+                RenderingError::from_str(
+                    "No graphics backend is compiled in (enable the vulkan, metal or dx12 \
+                 feature, or request the empty backend explicitly).",
+                )
+            } else {
+                RenderingError::from_string(format!(
+                    "Could not create a renderer with any of the backends {} \
+                 (request the empty backend explicitly to render nothing).",
+                    failed_backend_names.join(", ")
+                ))
+            },
+        ))
+    }
+
+    /// Returns the backend used by the renderer.
+    pub fn backend_kind(&self) -> BackendKind {
+        match self {
+            #[cfg(feature = "vulkan")]
+            AnyRendererState::Vulkan(..) => BackendKind::Vulkan,
+            #[cfg(all(feature = "metal", target_os = "macos"))]
+            AnyRendererState::Metal(..) => BackendKind::Metal,
+            #[cfg(all(feature = "dx12", windows))]
+            AnyRendererState::Dx12(..) => BackendKind::Dx12,
+            AnyRendererState::Empty(_) => BackendKind::Empty,
+        }
+    }
+
+    /// Whether the renderer has no window and can only render offscreen images.
+    pub fn is_headless(&self) -> bool {
+        dispatch!(self, renderer_state => renderer_state.is_headless())
+    }
+
+    /// Returns a mutable reference to the window state held by the renderer state.
+    ///
+    /// # Panics
+    /// If the renderer is headless.
+    pub fn window_state_mut(&mut self) -> &mut WindowState {
+        dispatch!(self, renderer_state => renderer_state.window_state_mut())
+    }
+
+    /// Uploads the given volume, replacing any previously uploaded volume.
+    pub fn upload_volume(&mut self, volume: &ScalarVolume) -> VortekResult<()> {
+        dispatch!(self, renderer_state => renderer_state.upload_volume(volume))
+    }
+
+    /// Sets the transfer function mapping volume values to colors, to be used
+    /// as a lookup table with the given number of entries.
+    pub fn set_transfer_function(
+        &mut self,
+        transfer_function: TransferFunction,
+        table_size: usize,
+    ) -> VortekResult<()> {
+        dispatch!(self, renderer_state => {
+            renderer_state.set_transfer_function(transfer_function, table_size)
+        })
+    }
+
+    /// Returns the transfer function mapping volume values to colors.
+    pub fn transfer_function(&self) -> &TransferFunction {
+        dispatch!(self, renderer_state => renderer_state.transfer_function())
+    }
+
+    /// Sets the camera to view the volume with.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        dispatch!(self, renderer_state => renderer_state.set_camera(camera))
+    }
+
+    /// Returns the camera the volume is viewed with.
+    pub fn camera(&self) -> &Camera {
+        dispatch!(self, renderer_state => renderer_state.camera())
+    }
+
+    /// Sets how samples along each ray are combined when drawing the volume.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        dispatch!(self, renderer_state => renderer_state.set_render_mode(render_mode))
+    }
+
+    /// Returns how samples along each ray are combined when drawing the volume.
+    pub fn render_mode(&self) -> RenderMode {
+        dispatch!(self, renderer_state => renderer_state.render_mode())
+    }
+
+    /// Sets the isosurfaces to render in isosurface mode.
+    pub fn set_isosurfaces(&mut self, isosurfaces: &[Isosurface]) -> VortekResult<()> {
+        dispatch!(self, renderer_state => renderer_state.set_isosurfaces(isosurfaces))
+    }
+
+    /// Returns the isosurfaces to render in isosurface mode.
+    pub fn isosurfaces(&self) -> &[Isosurface] {
+        dispatch!(self, renderer_state => renderer_state.isosurfaces())
+    }
+
+    /// Draws a frame with the uploaded volume over the given background color.
+    pub fn draw_volume_frame(&mut self, background_color: &Color) -> VortekResult<()> {
+        dispatch!(self, renderer_state => renderer_state.draw_volume_frame(background_color))
+    }
+
+    /// Draws a frame filled with the given color.
+    pub fn draw_clear_frame(&mut self, color: &Color) -> VortekResult<()> {
+        dispatch!(self, renderer_state => renderer_state.draw_clear_frame(color))
+    }
+
+    /// Renders the uploaded volume over the given background color into an
+    /// image with the given width and height, and reads back its pixels.
+    ///
+    /// # Panics
+    /// If the width or height is zero.
+    pub fn render_volume_image(
+        &self,
+        background_color: &Color,
+        size: (u32, u32),
+    ) -> VortekResult<ImageData> {
+        dispatch!(self, renderer_state => {
+            renderer_state.render_volume_image(background_color, size)
+        })
+    }
+}
+
+/// Creates an instance of the given backend, or logs a warning and returns
+/// `None` if that fails.
+#[allow(dead_code)]
+fn try_create_instance<B: Backend>(
+    kind: BackendKind,
+    application_name: &str,
+) -> Option<B::Instance> {
+    match backend::create_instance::<B>(application_name) {
+        Ok(instance) => Some(instance),
+        Err(_) => {
+            warn!(
+                "Could not instantiate the {} backend, trying the next one.",
+                kind.name()
+            );
+            None
+        }
+    }
+}
+
+/// Creates a renderer state for the given window, or a headless one if there
+/// is no window, from the given instance of the given backend.
+///
+/// If that fails, a warning is logged and the window state is returned so
+/// that it can be used with the next backend.
+#[allow(dead_code)]
+fn create_renderer_state<B: Backend>(
+    kind: BackendKind,
+    instance: &B::Instance,
+    window_state: Option<WindowState>,
+) -> Result<Box<RendererState<B>>, Option<Box<WindowState>>> {
+    let backend_state = match window_state {
+        Some(window_state) => backend::create_backend_state(instance, window_state)
+            .map_err(|(err, window_state)| (err, Some(window_state))),
+        None => backend::create_headless_backend_state(instance).map_err(|err| (err, None)),
+    };
+    match backend_state.and_then(RendererState::new_or_return_window) {
+        Ok(renderer_state) => {
+            info!("Using the {} backend.", kind.name());
+            Ok(Box::new(renderer_state))
+        }
+        Err((err, window_state)) => {
+            warn!(
+                "Could not create renderer with the {} backend, trying the next one: {}",
+                kind.name(),
+                err
+            );
+            Err(window_state)
+        }
+    }
+}
@@ -0,0 +1,184 @@
+//! Renderer for the empty backend.
+
+use super::{
+    super::window::WindowState, check_isosurface_count, offscreen::ImageData, Isosurface,
+    RenderMode,
+};
+use crate::{
+    camera::Camera, color::Color, error::VortekResult, transfer_function::TransferFunction,
+    volume::ScalarVolume,
+};
+use gfx_hal::format::Format;
+
+/// Structure for managing the state of a renderer without any device.
+///
+/// The rendering settings are kept so that they can be queried like for other
+/// renderers, but frames are not drawn anywhere and rendered images only hold
+/// the background color.
+pub struct EmptyRendererState {
+    window_state: Option<WindowState>,
+    transfer_function: TransferFunction,
+    render_mode: RenderMode,
+    isosurfaces: Vec<Isosurface>,
+    camera: Camera,
+}
+
+impl EmptyRendererState {
+    /// Creates a new empty renderer state for the given window, or a headless
+    /// one if there is no window.
+    pub fn new(window_state: Option<WindowState>) -> Self {
+        Self {
+            window_state,
+            transfer_function: TransferFunction::default(),
+            render_mode: RenderMode::default(),
+            isosurfaces: Vec::new(),
+            camera: Camera::default(),
+        }
+    }
+
+    /// Whether the renderer has no window.
+    pub fn is_headless(&self) -> bool {
+        self.window_state.is_none()
+    }
+
+    /// Returns a mutable reference to the window state held by the renderer state.
+    ///
+    /// # Panics
+    /// If the renderer is headless.
+    pub fn window_state_mut(&mut self) -> &mut WindowState {
+        self.window_state
+            .as_mut()
+            .expect("No window state in headless renderer state.")
+    }
+
+    /// Does nothing, since there is no device to upload the volume to.
+    pub fn upload_volume(&mut self, _volume: &ScalarVolume) -> VortekResult<()> {
+        Ok(())
+    }
+
+    /// Sets the transfer function mapping volume values to colors. The table
+    /// size is ignored.
+    pub fn set_transfer_function(
+        &mut self,
+        transfer_function: TransferFunction,
+        _table_size: usize,
+    ) -> VortekResult<()> {
+        self.transfer_function = transfer_function;
+        Ok(())
+    }
+
+    /// Returns the transfer function mapping volume values to colors.
+    pub fn transfer_function(&self) -> &TransferFunction {
+        &self.transfer_function
+    }
+
+    /// Sets the camera to view the volume with.
+    pub fn set_camera(&mut self, camera: &Camera) {
+        self.camera = camera.clone();
+    }
+
+    /// Returns the camera the volume is viewed with.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    /// Sets how samples along each ray are combined when drawing the volume.
+    pub fn set_render_mode(&mut self, render_mode: RenderMode) {
+        self.render_mode = render_mode;
+    }
+
+    /// Returns how samples along each ray are combined when drawing the volume.
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+
+    /// Sets the isosurfaces to render in isosurface mode.
+    ///
+    /// At most `MAX_ISOSURFACES` isosurfaces can be set.
+    pub fn set_isosurfaces(&mut self, isosurfaces: &[Isosurface]) -> VortekResult<()> {
+        check_isosurface_count(isosurfaces)?;
+        self.isosurfaces = isosurfaces.to_vec();
+        Ok(())
+    }
+
+    /// Returns the isosurfaces to render in isosurface mode.
+    pub fn isosurfaces(&self) -> &[Isosurface] {
+        &self.isosurfaces
+    }
+
+    /// Does nothing, since there is nothing to draw to.
+    pub fn draw_volume_frame(&mut self, _background_color: &Color) -> VortekResult<()> {
+        Ok(())
+    }
+
+    /// Does nothing, since there is nothing to draw to.
+    pub fn draw_clear_frame(&mut self, _color: &Color) -> VortekResult<()> {
+        Ok(())
+    }
+
+    /// Returns an image with the given width and height filled with the given
+    /// background color, with 8-bit RGBA pixels with sRGB encoding.
+    ///
+    /// # Panics
+    /// If the width or height is zero.
+    pub fn render_volume_image(
+        &self,
+        background_color: &Color,
+        (width, height): (u32, u32),
+    ) -> VortekResult<ImageData> {
+        assert!(width > 0 && height > 0, "Image extent is zero.");
+        let texel: Vec<u8> = background_color
+            .to_slice()
+            .iter()
+            .map(|component| (component.clamp(0.0, 1.0) * 255.0).round() as u8)
+            .collect();
+        Ok(ImageData {
+            width,
+            height,
+            format: Format::Rgba8Srgb,
+            bytes: texel.repeat(width as usize * height as usize),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{dispatch::AnyRendererState, MAX_ISOSURFACES},
+        *,
+    };
+    use crate::graphics::rendering::backend::BackendKind;
+
+    #[test]
+    fn renders_background_color() {
+        let mut renderer_state =
+            AnyRendererState::new(None, "vortek test", Some(BackendKind::Empty)).unwrap();
+        assert_eq!(renderer_state.backend_kind(), BackendKind::Empty);
+        assert!(renderer_state.is_headless());
+
+        let volume = ScalarVolume::with_unit_spacing([2, 2, 2], vec![0.0_f32; 8]);
+        renderer_state.upload_volume(&volume).unwrap();
+        renderer_state.draw_volume_frame(&Color::black()).unwrap();
+
+        let background_color = Color::from_components(1.0, 0.5, 0.0, 1.0);
+        let image = renderer_state
+            .render_volume_image(&background_color, (3, 2))
+            .unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.to_rgba8().unwrap(), [255, 128, 0, 255].repeat(6));
+    }
+
+    #[test]
+    fn too_many_isosurfaces_is_an_error() {
+        let mut renderer_state = EmptyRendererState::new(None);
+        let isosurface = Isosurface {
+            value: 0.5,
+            color: Color::white(),
+        };
+        let isosurfaces = vec![isosurface; MAX_ISOSURFACES + 1];
+        assert!(renderer_state.set_isosurfaces(&isosurfaces).is_err());
+        assert!(renderer_state
+            .set_isosurfaces(&isosurfaces[..MAX_ISOSURFACES])
+            .is_ok());
+    }
+}
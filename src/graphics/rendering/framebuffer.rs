@@ -72,7 +72,7 @@ impl<B: Backend> FramebufferState<B> {
 
         Ok(FramebufferState {
             framebuffers: Some(framebuffers),
-            frame_images: Some(images.into_iter().zip(image_views.into_iter()).collect()),
+            frame_images: Some(images.into_iter().zip(image_views).collect()),
             command_pools: Some(command_pools),
             command_buffer_lists,
             in_flight_fences: Some(in_flight_fences),
@@ -367,7 +367,7 @@ impl<B: Backend> Drop for FramebufferState<B> {
                 .expect("No in-flight fences in framebuffer state.")
             {
                 device
-                    .wait_for_fence(&fence, std::u64::MAX)
+                    .wait_for_fence(&fence, u64::MAX)
                     .unwrap_or_else(|oom_or_device_lost| match oom_or_device_lost {
                         OomOrDeviceLost::OutOfMemory(out_of_memory_err) => panic!(
                             "Could not wait for in-flight fence (out of memory): {}",
@@ -0,0 +1,537 @@
+//! Offscreen render target management.
+
+use super::{
+    buffer::BufferState, device::DeviceState, render_pass::RenderPassState, RenderingError,
+};
+use crate::error::{VortekError, VortekResult};
+use gfx_hal::{
+    adapter::PhysicalDevice,
+    buffer,
+    command::{BufferImageCopy, CommandBuffer},
+    device::Device,
+    format::{Aspects, Format, ImageFeature, Swizzle},
+    image::{
+        Access, Extent, Kind, Layout, Offset, SubresourceLayers, SubresourceRange, Tiling, Usage,
+        ViewCapabilities, ViewKind,
+    },
+    memory::{Barrier, Dependencies, Properties},
+    pso::PipelineStage,
+    Backend,
+};
+use std::{cell::RefCell, iter, ops::Drop, rc::Rc};
+
+/// Formats that offscreen images can have when there is no swapchain to
+/// match, in order of preference.
+const HEADLESS_FORMATS: [Format; 2] = [Format::Rgba8Srgb, Format::Rgba8Unorm];
+
+/// Structure for managing an image in device local memory that can be rendered
+/// into and read back to host memory.
+pub struct OffscreenState<B: Backend> {
+    image: Option<B::Image>,
+    memory: Option<B::Memory>,
+    image_view: Option<B::ImageView>,
+    framebuffer: Option<B::Framebuffer>,
+    format: Format,
+    extent: Extent,
+    device_state: Rc<RefCell<DeviceState<B>>>,
+}
+
+/// Pixels read back from an image, stored row by row from the top with tightly
+/// packed texels of the given format.
+#[derive(Clone, Debug)]
+pub struct ImageData {
+    pub width: u32,
+    pub height: u32,
+    pub format: Format,
+    pub bytes: Vec<u8>,
+}
+
+impl<B: Backend> OffscreenState<B> {
+    /// Creates a new offscreen image with the given format and extent, along with
+    /// a framebuffer for rendering into it with the given render pass.
+    ///
+    /// # Panics
+    /// If the extent is zero.
+    pub fn new(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        render_pass_state: &RenderPassState<B>,
+        format: Format,
+        extent: Extent,
+    ) -> VortekResult<Self> {
+        assert!(
+            extent.width > 0 && extent.height > 0,
+            "Image extent is zero."
+        );
+        let extent = Extent { depth: 1, ..extent };
+
+        // Resources are stored as they are created, so that the ones already
+        // created are destroyed if a later step fails
+        let mut offscreen_state = Self {
+            image: None,
+            memory: None,
+            image_view: None,
+            framebuffer: None,
+            format,
+            extent,
+            device_state: Rc::clone(&device_state),
+        };
+
+        unsafe {
+            let borrowed_device_state = device_state.borrow();
+            let device = borrowed_device_state.device();
+
+            offscreen_state.image = Some(
+                device
+                    .create_image(
+                        Kind::D2(extent.width, extent.height, 1, 1),
+                        1,
+                        format,
+                        Tiling::Optimal,
+                        Usage::COLOR_ATTACHMENT | Usage::TRANSFER_SRC,
+                        ViewCapabilities::empty(),
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create offscreen image: ",
+                            err,
+                        ))
+                    })?,
+            );
+            let image = offscreen_state.image.as_mut().unwrap();
+
+            let requirements = device.get_image_requirements(image);
+            let memory_type = borrowed_device_state
+                .find_memory_type(requirements.type_mask, Properties::DEVICE_LOCAL)?;
+            let memory = device
+                .allocate_memory(memory_type, requirements.size)
+                .map_err(|err| {
+                    VortekError::RenderingError(RenderingError::from_error(
+                        "Could not allocate offscreen image memory: ",
+                        err,
+                    ))
+                })?;
+            let bind_result = device.bind_image_memory(&memory, 0, image);
+            offscreen_state.memory = Some(memory);
+            bind_result.map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not bind offscreen image memory: ",
+                    err,
+                ))
+            })?;
+
+            offscreen_state.image_view = Some(
+                device
+                    .create_image_view(
+                        offscreen_state.image.as_ref().unwrap(),
+                        ViewKind::D2,
+                        format,
+                        Swizzle::NO,
+                        Self::color_range(),
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create image view: ",
+                            err,
+                        ))
+                    })?,
+            );
+
+            offscreen_state.framebuffer = Some(
+                device
+                    .create_framebuffer(
+                        render_pass_state.render_pass(),
+                        offscreen_state.image_view.as_ref(),
+                        extent,
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create framebuffer: ",
+                            err,
+                        ))
+                    })?,
+            );
+        }
+
+        Ok(offscreen_state)
+    }
+
+    /// Selects the first format of offscreen images without a swapchain that
+    /// the device can render to with blending.
+    pub fn select_headless_format(physical_device: &B::PhysicalDevice) -> VortekResult<Format> {
+        HEADLESS_FORMATS
+            .iter()
+            .copied()
+            .find(|&format| {
+                physical_device
+                    .format_properties(Some(format))
+                    .optimal_tiling
+                    .contains(ImageFeature::COLOR_ATTACHMENT | ImageFeature::COLOR_ATTACHMENT_BLEND)
+            })
+            .ok_or_else(|| {
+                VortekError::RenderingError(RenderingError::from_str(
+                    "Could not find a supported offscreen image format.",
+                ))
+            })
+    }
+
+    /// Returns a reference to the framebuffer held by the offscreen state.
+    pub fn framebuffer(&self) -> &B::Framebuffer {
+        self.framebuffer
+            .as_ref()
+            .expect("No framebuffer in offscreen state.")
+    }
+
+    /// Returns the format of the offscreen image.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the extent of the offscreen image.
+    pub fn extent(&self) -> &Extent {
+        &self.extent
+    }
+
+    /// Copies the pixels of the offscreen image to host memory.
+    ///
+    /// The image must have been rendered into with a render pass created by
+    /// `RenderPassState::new_offscreen`, which leaves it in the layout for
+    /// copying.
+    pub fn read_pixels(&self) -> VortekResult<ImageData> {
+        let image = self.image.as_ref().expect("No image in offscreen state.");
+        let extent = self.extent;
+        let bytes_per_texel = u64::from(self.format.surface_desc().bits / 8);
+        let size = u64::from(extent.width) * u64::from(extent.height) * bytes_per_texel;
+
+        let readback_buffer_state = BufferState::new(
+            Rc::clone(&self.device_state),
+            size,
+            buffer::Usage::TRANSFER_DST,
+            Properties::CPU_VISIBLE | Properties::COHERENT,
+        )?;
+
+        unsafe {
+            self.device_state
+                .borrow_mut()
+                .execute_one_time_commands(|command_buffer| {
+                    command_buffer.pipeline_barrier(
+                        PipelineStage::COLOR_ATTACHMENT_OUTPUT..PipelineStage::TRANSFER,
+                        Dependencies::empty(),
+                        iter::once(&Barrier::Image {
+                            states: (Access::COLOR_ATTACHMENT_WRITE, Layout::TransferSrcOptimal)
+                                ..(Access::TRANSFER_READ, Layout::TransferSrcOptimal),
+                            target: image,
+                            families: None,
+                            range: Self::color_range(),
+                        }),
+                    );
+                    command_buffer.copy_image_to_buffer(
+                        image,
+                        Layout::TransferSrcOptimal,
+                        readback_buffer_state.buffer(),
+                        iter::once(&BufferImageCopy {
+                            buffer_offset: 0,
+                            buffer_width: extent.width,
+                            buffer_height: extent.height,
+                            image_layers: SubresourceLayers {
+                                aspects: Aspects::COLOR,
+                                level: 0,
+                                layers: 0..1,
+                            },
+                            image_offset: Offset::ZERO,
+                            image_extent: extent,
+                        }),
+                    );
+                    command_buffer.pipeline_barrier(
+                        PipelineStage::TRANSFER..PipelineStage::HOST,
+                        Dependencies::empty(),
+                        iter::once(&Barrier::whole_buffer(
+                            readback_buffer_state.buffer(),
+                            buffer::Access::TRANSFER_WRITE..buffer::Access::HOST_READ,
+                        )),
+                    );
+                })?;
+        }
+
+        Ok(ImageData {
+            width: extent.width,
+            height: extent.height,
+            format: self.format,
+            bytes: readback_buffer_state.read_data()?,
+        })
+    }
+
+    fn color_range() -> SubresourceRange {
+        SubresourceRange {
+            aspects: Aspects::COLOR,
+            levels: 0..1,
+            layers: 0..1,
+        }
+    }
+}
+
+impl ImageData {
+    /// Converts the pixels to 8-bit RGBA values, as stored in for example PNG
+    /// images.
+    ///
+    /// The renderer writes sRGB-encoded colors to images without an sRGB
+    /// format, and the hardware encodes the colors written to images with an
+    /// sRGB format, so the values are only reordered and requantized.
+    pub fn to_rgba8(&self) -> VortekResult<Vec<u8>> {
+        let unorm8 = |value: u32, max: u32| ((value * 255 + max / 2) / max) as u8;
+        let float8 = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let words = |bytes: &[u8]| {
+            bytes
+                .chunks_exact(4)
+                .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect::<Vec<_>>()
+        };
+        let half_words = |bytes: &[u8]| {
+            bytes
+                .chunks_exact(2)
+                .map(|chunk| u16::from_ne_bytes([chunk[0], chunk[1]]))
+                .collect::<Vec<_>>()
+        };
+
+        Ok(match self.format {
+            Format::Rgba8Unorm | Format::Rgba8Srgb => self.bytes.clone(),
+            Format::Bgra8Unorm | Format::Bgra8Srgb => self
+                .bytes
+                .chunks_exact(4)
+                .flat_map(|texel| [texel[2], texel[1], texel[0], texel[3]])
+                .collect(),
+            Format::A2b10g10r10Unorm | Format::A2r10g10b10Unorm => {
+                let reversed = self.format == Format::A2r10g10b10Unorm;
+                words(&self.bytes)
+                    .into_iter()
+                    .flat_map(|texel| {
+                        let component = |shift: u32| unorm8((texel >> shift) & 0x3ff, 0x3ff);
+                        let (red, blue) = if reversed {
+                            (component(20), component(0))
+                        } else {
+                            (component(0), component(20))
+                        };
+                        [red, component(10), blue, unorm8(texel >> 30, 3)]
+                    })
+                    .collect()
+            }
+            Format::Rgba16Unorm => half_words(&self.bytes)
+                .into_iter()
+                .map(|value| unorm8(u32::from(value), u32::from(u16::MAX)))
+                .collect(),
+            Format::Rgba16Sfloat => half_words(&self.bytes)
+                .into_iter()
+                .map(|value| float8(half_to_f32(value)))
+                .collect(),
+            Format::Rgba32Sfloat => words(&self.bytes)
+                .into_iter()
+                .map(|value| float8(f32::from_bits(value)))
+                .collect(),
+            format => {
+                return Err(VortekError::RenderingError(RenderingError::from_string(
+                    format!("Could not convert pixels with format {:?} to RGBA.", format),
+                )))
+            }
+        })
+    }
+}
+
+impl<B: Backend> Drop for OffscreenState<B> {
+    fn drop(&mut self) {
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        unsafe {
+            if let Some(framebuffer) = self.framebuffer.take() {
+                device.destroy_framebuffer(framebuffer);
+            }
+            if let Some(image_view) = self.image_view.take() {
+                device.destroy_image_view(image_view);
+            }
+            if let Some(image) = self.image.take() {
+                device.destroy_image(image);
+            }
+            if let Some(memory) = self.memory.take() {
+                device.free_memory(memory);
+            }
+        }
+    }
+}
+
+/// Converts the bits of a 16-bit floating-point number to a 32-bit float.
+fn half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = i32::from((bits >> 10) & 0x1f);
+    let mantissa = f32::from(bits & 0x3ff);
+    sign * match exponent {
+        0 => mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image_data(format: Format, bytes: Vec<u8>) -> ImageData {
+        ImageData {
+            width: 2,
+            height: 1,
+            format,
+            bytes,
+        }
+    }
+
+    fn packed_bytes(texels: &[u32]) -> Vec<u8> {
+        texels
+            .iter()
+            .flat_map(|texel| texel.to_ne_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn rgba8_is_unchanged() {
+        let bytes = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let image = image_data(Format::Rgba8Srgb, bytes.clone());
+        assert_eq!(image.to_rgba8().unwrap(), bytes);
+    }
+
+    #[test]
+    fn bgra8_is_swizzled() {
+        for format in [Format::Bgra8Unorm, Format::Bgra8Srgb] {
+            let image = image_data(format, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+            assert_eq!(image.to_rgba8().unwrap(), vec![3, 2, 1, 4, 7, 6, 5, 8]);
+        }
+    }
+
+    #[test]
+    fn a2r10g10b10_is_swizzled_and_requantized() {
+        let texels = [3 << 30 | 0x3ff << 20 | 0x200 << 10, 1 << 30 | 0x3ff];
+        let image = image_data(Format::A2r10g10b10Unorm, packed_bytes(&texels));
+        assert_eq!(
+            image.to_rgba8().unwrap(),
+            vec![255, 128, 0, 255, 0, 0, 255, 85]
+        );
+    }
+
+    #[test]
+    fn a2b10g10r10_is_requantized() {
+        let texels = [3 << 30 | 0x3ff << 20 | 0x200 << 10, 1 << 30 | 0x3ff];
+        let image = image_data(Format::A2b10g10r10Unorm, packed_bytes(&texels));
+        assert_eq!(
+            image.to_rgba8().unwrap(),
+            vec![0, 128, 255, 255, 255, 0, 0, 85]
+        );
+    }
+
+    #[test]
+    fn wide_formats_are_requantized() {
+        let unorm16: Vec<u8> = [u16::MAX, 0, 0x8000, u16::MAX]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let image = ImageData {
+            width: 1,
+            height: 1,
+            format: Format::Rgba16Unorm,
+            bytes: unorm16,
+        };
+        assert_eq!(image.to_rgba8().unwrap(), vec![255, 0, 128, 255]);
+
+        // Half-precision 1.0, 0.0, 0.5 and 2.0
+        let float16: Vec<u8> = [0x3c00_u16, 0, 0x3800, 0x4000]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let image = ImageData {
+            format: Format::Rgba16Sfloat,
+            bytes: float16,
+            ..image
+        };
+        assert_eq!(image.to_rgba8().unwrap(), vec![255, 0, 128, 255]);
+
+        let float32: Vec<u8> = [1.0_f32, -1.0, 0.5, 2.0]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        let image = ImageData {
+            format: Format::Rgba32Sfloat,
+            bytes: float32,
+            ..image
+        };
+        assert_eq!(image.to_rgba8().unwrap(), vec![255, 0, 128, 255]);
+    }
+
+    #[test]
+    fn unsupported_format_is_an_error() {
+        let image = image_data(Format::R8Unorm, vec![0, 0]);
+        assert!(image.to_rgba8().is_err());
+    }
+
+    #[cfg(any(
+        feature = "vulkan",
+        all(feature = "metal", target_os = "macos"),
+        all(feature = "dx12", windows)
+    ))]
+    #[test]
+    fn rendered_image_is_upright() {
+        use crate::{
+            camera::Camera,
+            color::Color,
+            graphics::rendering::{dispatch::AnyRendererState, RenderMode},
+            transfer_function::TransferFunction,
+            volume::ScalarVolume,
+        };
+
+        // Skip the test when there is no device to render with
+        let mut renderer_state = match AnyRendererState::new(None, "vortek test", None) {
+            Ok(renderer_state) => renderer_state,
+            Err(_) => return,
+        };
+
+        // Only the upper half of the volume along the world up direction is bright
+        let volume = ScalarVolume::from_fn([8, 8, 8], [1.0; 3], [0.0; 3], |_, _, k| {
+            if k >= 4 {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        renderer_state.upload_volume(&volume).unwrap();
+        renderer_state
+            .set_transfer_function(
+                TransferFunction::linear_ramp(Color::black(), Color::white()),
+                256,
+            )
+            .unwrap();
+        renderer_state.set_render_mode(RenderMode::MaximumIntensity);
+
+        let (lower, upper) = volume.bounding_box();
+        let mut camera = Camera::default();
+        camera.fit_to_bounding_box(lower, upper);
+        camera.set_orientation(0.0, 0.0);
+        renderer_state.set_camera(&camera);
+
+        let (width, height) = (32, 32);
+        let pixels = renderer_state
+            .render_volume_image(&Color::black(), (width, height))
+            .unwrap()
+            .to_rgba8()
+            .unwrap();
+        let row_brightness = |row: u32| -> u32 {
+            let row_length = 4 * width as usize;
+            pixels[row as usize * row_length..(row as usize + 1) * row_length]
+                .chunks(4)
+                .map(|texel| u32::from(texel[0]))
+                .sum()
+        };
+        let upper_brightness: u32 = (0..height / 2).map(row_brightness).sum();
+        let lower_brightness: u32 = (height / 2..height).map(row_brightness).sum();
+        assert!(
+            upper_brightness > lower_brightness,
+            "Upper half brightness {} does not exceed lower half brightness {}.",
+            upper_brightness,
+            lower_brightness
+        );
+    }
+}
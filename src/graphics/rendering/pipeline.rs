@@ -0,0 +1,262 @@
+//! Graphics pipeline management.
+
+use super::{device::DeviceState, render_pass::RenderPassState, RenderingError};
+use crate::error::{VortekError, VortekResult};
+use gfx_hal::{
+    device::Device,
+    pass::Subpass,
+    pso::{
+        self, BlendState, ColorBlendDesc, ColorMask, DescriptorSetLayoutBinding, EntryPoint,
+        GraphicsPipelineDesc, GraphicsShaderSet, Primitive, Rasterizer, ShaderStageFlags,
+        Specialization,
+    },
+    Backend,
+};
+use std::{
+    cell::RefCell,
+    io::Cursor,
+    iter,
+    ops::{Drop, Range},
+    rc::Rc,
+};
+
+/// Compiled SPIR-V code for the shaders in the `shaders` directory.
+pub mod shaders {
+    /// Vertex shader rendering the bounding box of a volume.
+    pub const VOLUME_VERTEX: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/volume.vert.spv"));
+    /// Fragment shader rendering the interior of a volume.
+    pub const VOLUME_FRAGMENT: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/volume.frag.spv"));
+}
+
+const SHADER_ENTRY_POINT: &str = "main";
+
+/// Description of the shaders, resource interface and fixed-function state of a
+/// graphics pipeline.
+#[derive(Clone, Debug)]
+pub struct PipelineDescription {
+    /// SPIR-V code for the vertex shader.
+    pub vertex_shader: &'static [u8],
+    /// SPIR-V code for the fragment shader.
+    pub fragment_shader: &'static [u8],
+    /// Bindings for each descriptor set used by the shaders.
+    pub descriptor_set_layout_bindings: Vec<Vec<DescriptorSetLayoutBinding>>,
+    /// Shader stages and byte ranges of the push constants used by the shaders.
+    pub push_constant_ranges: Vec<(ShaderStageFlags, Range<u32>)>,
+    /// Primitive topology to assemble vertices into.
+    pub primitive: Primitive,
+    /// Rasterization state.
+    pub rasterizer: Rasterizer,
+    /// Blending of fragment colors into the color attachment, or `None` for
+    /// replacing the existing color.
+    pub blend_state: Option<BlendState>,
+}
+
+/// Structure for managing a graphics pipeline along with its layout.
+///
+/// The viewport and scissor rectangle are dynamic state, and must be set when
+/// recording commands using the pipeline.
+pub struct PipelineState<B: Backend> {
+    description: PipelineDescription,
+    descriptor_set_layouts: Option<Vec<B::DescriptorSetLayout>>,
+    pipeline_layout: Option<B::PipelineLayout>,
+    pipeline: Option<B::GraphicsPipeline>,
+    device_state: Rc<RefCell<DeviceState<B>>>,
+}
+
+impl<B: Backend> PipelineState<B> {
+    /// Creates a new pipeline state from the given description, with a pipeline
+    /// compatible with the given render pass.
+    pub fn new(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        render_pass_state: &RenderPassState<B>,
+        description: PipelineDescription,
+    ) -> VortekResult<Self> {
+        // Objects are stored as they are created, so that the ones already
+        // created are destroyed if a later step fails
+        let mut pipeline_state = Self {
+            description,
+            descriptor_set_layouts: Some(Vec::new()),
+            pipeline_layout: None,
+            pipeline: None,
+            device_state,
+        };
+
+        {
+            let borrowed_device_state = pipeline_state.device_state.borrow();
+            let device = borrowed_device_state.device();
+
+            for bindings in &pipeline_state.description.descriptor_set_layout_bindings {
+                let descriptor_set_layout = unsafe {
+                    device
+                        .create_descriptor_set_layout(bindings, iter::empty::<B::Sampler>())
+                        .map_err(|err| {
+                            VortekError::RenderingError(RenderingError::from_error(
+                                "Could not create descriptor set layout: ",
+                                err,
+                            ))
+                        })?
+                };
+                pipeline_state
+                    .descriptor_set_layouts
+                    .as_mut()
+                    .unwrap()
+                    .push(descriptor_set_layout);
+            }
+
+            pipeline_state.pipeline_layout = Some(unsafe {
+                device
+                    .create_pipeline_layout(
+                        pipeline_state.descriptor_set_layouts.as_ref().unwrap(),
+                        &pipeline_state.description.push_constant_ranges,
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create pipeline layout: ",
+                            err,
+                        ))
+                    })?
+            });
+        }
+
+        pipeline_state.rebuild(render_pass_state)?;
+        Ok(pipeline_state)
+    }
+
+    /// Returns a reference to the graphics pipeline held by the pipeline state.
+    pub fn pipeline(&self) -> &B::GraphicsPipeline {
+        self.pipeline
+            .as_ref()
+            .expect("No pipeline in pipeline state.")
+    }
+
+    /// Returns a reference to the pipeline layout held by the pipeline state.
+    pub fn pipeline_layout(&self) -> &B::PipelineLayout {
+        self.pipeline_layout
+            .as_ref()
+            .expect("No pipeline layout in pipeline state.")
+    }
+
+    /// Returns the descriptor set layouts held by the pipeline state.
+    pub fn descriptor_set_layouts(&self) -> &[B::DescriptorSetLayout] {
+        self.descriptor_set_layouts
+            .as_ref()
+            .expect("No descriptor set layouts in pipeline state.")
+    }
+
+    /// Returns the description the pipeline was created from.
+    pub fn description(&self) -> &PipelineDescription {
+        &self.description
+    }
+
+    /// Replaces the graphics pipeline with a new one compatible with the given
+    /// render pass, keeping the existing pipeline layout.
+    ///
+    /// The old pipeline must not be in use by any pending command buffers.
+    pub fn rebuild(&mut self, render_pass_state: &RenderPassState<B>) -> VortekResult<()> {
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+
+        if let Some(pipeline) = self.pipeline.take() {
+            unsafe { device.destroy_graphics_pipeline(pipeline) };
+        }
+
+        let vertex_shader_module =
+            Self::create_shader_module(device, self.description.vertex_shader)?;
+        let fragment_shader_module =
+            match Self::create_shader_module(device, self.description.fragment_shader) {
+                Ok(module) => module,
+                Err(err) => {
+                    unsafe { device.destroy_shader_module(vertex_shader_module) };
+                    return Err(err);
+                }
+            };
+
+        let pipeline = {
+            let shader_set = GraphicsShaderSet {
+                vertex: EntryPoint {
+                    entry: SHADER_ENTRY_POINT,
+                    module: &vertex_shader_module,
+                    specialization: Specialization::default(),
+                },
+                hull: None,
+                domain: None,
+                geometry: None,
+                fragment: Some(EntryPoint {
+                    entry: SHADER_ENTRY_POINT,
+                    module: &fragment_shader_module,
+                    specialization: Specialization::default(),
+                }),
+            };
+
+            let mut pipeline_desc = GraphicsPipelineDesc::new(
+                shader_set,
+                self.description.primitive,
+                self.description.rasterizer,
+                self.pipeline_layout(),
+                Subpass {
+                    index: 0,
+                    main_pass: render_pass_state.render_pass(),
+                },
+            );
+            pipeline_desc.blender.targets.push(ColorBlendDesc {
+                mask: ColorMask::ALL,
+                blend: self.description.blend_state,
+            });
+
+            unsafe { device.create_graphics_pipeline(&pipeline_desc, None) }
+        };
+
+        unsafe {
+            device.destroy_shader_module(vertex_shader_module);
+            device.destroy_shader_module(fragment_shader_module);
+        }
+
+        self.pipeline = Some(pipeline.map_err(|err| {
+            VortekError::RenderingError(RenderingError::from_error(
+                "Could not create graphics pipeline: ",
+                err,
+            ))
+        })?);
+        Ok(())
+    }
+
+    /// Creates a shader module from the given SPIR-V code.
+    fn create_shader_module(device: &B::Device, spirv: &[u8]) -> VortekResult<B::ShaderModule> {
+        let spirv = pso::read_spirv(Cursor::new(spirv)).map_err(|err| {
+            VortekError::RenderingError(RenderingError::from_error(
+                "Could not read SPIR-V code: ",
+                err,
+            ))
+        })?;
+        unsafe {
+            device.create_shader_module(&spirv).map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not create shader module: ",
+                    err,
+                ))
+            })
+        }
+    }
+}
+
+impl<B: Backend> Drop for PipelineState<B> {
+    fn drop(&mut self) {
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        unsafe {
+            if let Some(pipeline) = self.pipeline.take() {
+                device.destroy_graphics_pipeline(pipeline);
+            }
+            if let Some(pipeline_layout) = self.pipeline_layout.take() {
+                device.destroy_pipeline_layout(pipeline_layout);
+            }
+            for descriptor_set_layout in self
+                .descriptor_set_layouts
+                .take()
+                .expect("No descriptor set layouts in pipeline state.")
+            {
+                device.destroy_descriptor_set_layout(descriptor_set_layout);
+            }
+        }
+    }
+}
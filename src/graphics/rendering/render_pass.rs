@@ -27,9 +27,28 @@ impl<B: Backend> RenderPassState<B> {
     pub fn new(
         device_state: Rc<RefCell<DeviceState<B>>>,
         swapchain_state: &SwapchainState<B>,
+    ) -> VortekResult<Self> {
+        Self::with_final_layout(device_state, swapchain_state.format(), Layout::Present)
+    }
+
+    /// Creates a new render pass state for rendering to an offscreen image with
+    /// the given format, which is left ready to be copied from.
+    pub fn new_offscreen(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        format: Format,
+    ) -> VortekResult<Self> {
+        Self::with_final_layout(device_state, format, Layout::TransferSrcOptimal)
+    }
+
+    /// Creates a new render pass state for a color attachement with the given
+    /// format, which is transitioned to the given layout at the end of the pass.
+    fn with_final_layout(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        format: Format,
+        final_layout: Layout,
     ) -> VortekResult<Self> {
         let render_pass = {
-            let attachement = Self::create_attachement(swapchain_state.format());
+            let attachement = Self::create_attachement(format, final_layout);
             let subpass_description = Self::create_subpass_description();
             let subpass_dependency = Self::create_subpass_dependency();
 
@@ -67,7 +86,7 @@ impl<B: Backend> RenderPassState<B> {
     /// Creates a simple image attachement description for the given format,
     /// which clears the attachement at the beginning of the subpass and
     /// preserves the data written to the attachement during the subpass.
-    fn create_attachement(format: Format) -> Attachment {
+    fn create_attachement(format: Format, final_layout: Layout) -> Attachment {
         Attachment {
             format: Some(format),
             samples: 1,
@@ -76,7 +95,7 @@ impl<B: Backend> RenderPassState<B> {
                 store: AttachmentStoreOp::Store,
             },
             stencil_ops: AttachmentOps::DONT_CARE,
-            layouts: Layout::Undefined..Layout::Present,
+            layouts: Layout::Undefined..final_layout,
         }
     }
 
@@ -27,16 +27,18 @@ pub struct SwapchainState<B: Backend> {
 
 impl<B: Backend> SwapchainState<B> {
     /// Creates a new swapchain state from the given backend and device states.
+    ///
+    /// # Panics
+    /// If the backend state is headless.
     pub fn new(
         device_state: Rc<RefCell<DeviceState<B>>>,
         backend_state: &mut BackendState<B>,
     ) -> VortekResult<Self> {
-        let capabilities = backend_state
+        let surface = backend_state
             .surface()
-            .capabilities(device_state.borrow().physical_device());
-        let supported_formats = backend_state
-            .surface()
-            .supported_formats(device_state.borrow().physical_device());
+            .expect("No surface in headless backend state.");
+        let capabilities = surface.capabilities(device_state.borrow().physical_device());
+        let supported_formats = surface.supported_formats(device_state.borrow().physical_device());
 
         debug!("Surface capabilities: {:?}", capabilities);
         debug!("Supported formats: {:?}", supported_formats);
@@ -60,9 +62,7 @@ impl<B: Backend> SwapchainState<B> {
         };
         debug!("{:?}", swapchain_config);
 
-        assert!(backend_state
-            .surface()
-            .supports_queue_family(device_state.borrow().queue_family()));
+        assert!(surface.supports_queue_family(device_state.borrow().queue_family()));
 
         let (swapchain, backbuffer) = unsafe {
             device_state
@@ -167,7 +167,7 @@ impl<B: Backend> SwapchainState<B> {
                 .cloned()
             {
                 Some(srgb_format) => Ok(srgb_format),
-                None => formats.get(0).cloned().ok_or_else(|| {
+                None => formats.first().cloned().ok_or_else(|| {
                     VortekError::RenderingError(RenderingError::from_str(
                         "Supported format list was empty.",
                     ))
@@ -0,0 +1,394 @@
+//! Texture management.
+
+use super::{buffer::BufferState, device::DeviceState, RenderingError};
+use crate::{
+    color::Color,
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VoxelBuffer, VoxelType},
+};
+use gfx_hal::{
+    adapter::PhysicalDevice,
+    command::{BufferImageCopy, CommandBuffer},
+    device::Device,
+    format::{Aspects, Format, ImageFeature, Swizzle},
+    image::{
+        Access, Extent, Filter, Kind, Layout, Offset, SamplerDesc, SubresourceLayers,
+        SubresourceRange, Tiling, Usage, ViewCapabilities, ViewKind, WrapMode,
+    },
+    memory::{Barrier, Dependencies, Properties},
+    pso::PipelineStage,
+    Backend,
+};
+use log::{debug, warn};
+use std::{cell::RefCell, iter, ops::Drop, rc::Rc};
+
+/// Structure for managing a sampled texture in device local memory.
+pub struct TextureState<B: Backend> {
+    image: Option<B::Image>,
+    memory: Option<B::Memory>,
+    image_view: Option<B::ImageView>,
+    sampler: Option<B::Sampler>,
+    format: Format,
+    extent: Extent,
+    sampled_value_range: (f32, f32),
+    device_state: Rc<RefCell<DeviceState<B>>>,
+}
+
+impl<B: Backend> TextureState<B> {
+    /// Creates a new 3D texture holding the voxel values of the given volume.
+    ///
+    /// The texture format matches the voxel type when the device supports
+    /// sampling it, and otherwise falls back to 32-bit floats. Volumes with
+    /// non-uniform grids are resampled to a uniform grid before uploading.
+    pub fn new_3d(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        volume: &ScalarVolume,
+    ) -> VortekResult<Self> {
+        let [nx, ny, nz] = volume.dimensions();
+        let max_size = device_state
+            .borrow()
+            .physical_device()
+            .limits()
+            .max_image_3d_size as usize;
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                format!("Volume dimensions {:?} must all be positive.", [nx, ny, nz]),
+            )));
+        }
+        if nx > max_size || ny > max_size || nz > max_size {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                format!(
+                    "Volume dimensions {:?} exceed the maximum 3D texture size ({}).",
+                    [nx, ny, nz],
+                    max_size
+                ),
+            )));
+        }
+
+        let resampled;
+        let volume = if volume.is_uniform() {
+            volume
+        } else {
+            warn!("Resampling non-uniform volume to uniform grid for texture upload.");
+            resampled = volume.resampled_to_uniform([nx, ny, nz]);
+            &resampled
+        };
+
+        let candidates: &[Format] = match volume.voxel_type() {
+            VoxelType::U8 => &[Format::R8Unorm, Format::R32Sfloat],
+            VoxelType::U16 => &[Format::R16Unorm, Format::R32Sfloat],
+            VoxelType::I16 => &[Format::R16Snorm, Format::R32Sfloat],
+            VoxelType::F32 => &[Format::R32Sfloat],
+        };
+        let (format, filter) =
+            Self::select_format(device_state.borrow().physical_device(), candidates)?;
+        debug!(
+            "Uploading {} volume as 3D texture with format {:?}.",
+            volume.voxel_type(),
+            format
+        );
+
+        let converted;
+        let voxels = if format == Format::R32Sfloat && volume.voxel_type() != VoxelType::F32 {
+            converted = VoxelBuffer::F32(volume.voxels().to_f32());
+            converted.as_bytes()
+        } else {
+            volume.voxels().as_bytes()
+        };
+
+        let (min_value, max_value) = volume.value_range().unwrap_or((0.0, 1.0));
+        let normalization = match format {
+            Format::R8Unorm => f32::from(u8::MAX),
+            Format::R16Unorm => f32::from(u16::MAX),
+            Format::R16Snorm => f32::from(i16::MAX),
+            _ => 1.0,
+        };
+
+        let mut texture_state = Self::new(
+            device_state,
+            Kind::D3(nx as u32, ny as u32, nz as u32),
+            ViewKind::D3,
+            format,
+            SamplerDesc::new(filter, WrapMode::Clamp),
+            voxels,
+        )?;
+        texture_state.sampled_value_range = (min_value / normalization, max_value / normalization);
+        Ok(texture_state)
+    }
+
+    /// Creates a new 1D texture holding the given colors, for use as a lookup
+    /// table in shaders.
+    pub fn new_1d(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        colors: &[Color],
+    ) -> VortekResult<Self> {
+        let max_size = device_state
+            .borrow()
+            .physical_device()
+            .limits()
+            .max_image_1d_size as usize;
+        if colors.is_empty() || colors.len() > max_size {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                format!(
+                    "Number of colors ({}) must be between 1 and the maximum 1D texture size ({}).",
+                    colors.len(),
+                    max_size
+                ),
+            )));
+        }
+
+        let (format, filter) = Self::select_format(
+            device_state.borrow().physical_device(),
+            &[Format::Rgba32Sfloat],
+        )?;
+        let texels: Vec<u8> = colors
+            .iter()
+            .flat_map(Color::to_slice)
+            .flat_map(f32::to_ne_bytes)
+            .collect();
+        Self::new(
+            device_state,
+            Kind::D1(colors.len() as u32, 1),
+            ViewKind::D1,
+            format,
+            SamplerDesc::new(filter, WrapMode::Clamp),
+            &texels,
+        )
+    }
+
+    /// Returns a reference to the image view held by the texture state.
+    pub fn image_view(&self) -> &B::ImageView {
+        self.image_view
+            .as_ref()
+            .expect("No image view in texture state.")
+    }
+
+    /// Returns a reference to the sampler held by the texture state.
+    pub fn sampler(&self) -> &B::Sampler {
+        self.sampler.as_ref().expect("No sampler in texture state.")
+    }
+
+    /// Returns the format of the texture.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Returns the extent of the texture.
+    pub fn extent(&self) -> &Extent {
+        &self.extent
+    }
+
+    /// Returns the values that a shader will sample at the voxels with the
+    /// smallest and largest value.
+    pub fn sampled_value_range(&self) -> (f32, f32) {
+        self.sampled_value_range
+    }
+
+    /// Creates a new texture of the given kind and format, and uploads the given
+    /// tightly packed texel data through a staging buffer.
+    fn new(
+        device_state: Rc<RefCell<DeviceState<B>>>,
+        kind: Kind,
+        view_kind: ViewKind,
+        format: Format,
+        sampler_desc: SamplerDesc,
+        data: &[u8],
+    ) -> VortekResult<Self> {
+        let extent = kind.extent();
+        let color_range = SubresourceRange {
+            aspects: Aspects::COLOR,
+            levels: 0..1,
+            layers: 0..1,
+        };
+
+        // Resources are stored as they are created, so that the ones already
+        // created are destroyed if a later step fails
+        let mut texture_state = Self {
+            image: None,
+            memory: None,
+            image_view: None,
+            sampler: None,
+            format,
+            extent,
+            sampled_value_range: (0.0, 1.0),
+            device_state: Rc::clone(&device_state),
+        };
+
+        unsafe {
+            let borrowed_device_state = device_state.borrow();
+            let device = borrowed_device_state.device();
+
+            texture_state.image = Some(
+                device
+                    .create_image(
+                        kind,
+                        1,
+                        format,
+                        Tiling::Optimal,
+                        Usage::TRANSFER_DST | Usage::SAMPLED,
+                        ViewCapabilities::empty(),
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create image: ",
+                            err,
+                        ))
+                    })?,
+            );
+            let image = texture_state.image.as_mut().unwrap();
+
+            let requirements = device.get_image_requirements(image);
+            let memory_type = borrowed_device_state
+                .find_memory_type(requirements.type_mask, Properties::DEVICE_LOCAL)?;
+            let memory = device
+                .allocate_memory(memory_type, requirements.size)
+                .map_err(|err| {
+                    VortekError::RenderingError(RenderingError::from_error(
+                        "Could not allocate image memory: ",
+                        err,
+                    ))
+                })?;
+            let bind_result = device.bind_image_memory(&memory, 0, image);
+            texture_state.memory = Some(memory);
+            bind_result.map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not bind image memory: ",
+                    err,
+                ))
+            })?;
+
+            texture_state.image_view = Some(
+                device
+                    .create_image_view(
+                        texture_state.image.as_ref().unwrap(),
+                        view_kind,
+                        format,
+                        Swizzle::NO,
+                        color_range.clone(),
+                    )
+                    .map_err(|err| {
+                        VortekError::RenderingError(RenderingError::from_error(
+                            "Could not create image view: ",
+                            err,
+                        ))
+                    })?,
+            );
+
+            texture_state.sampler = Some(device.create_sampler(&sampler_desc).map_err(|err| {
+                VortekError::RenderingError(RenderingError::from_error(
+                    "Could not create sampler: ",
+                    err,
+                ))
+            })?);
+        }
+
+        texture_state.upload(data, color_range)?;
+        Ok(texture_state)
+    }
+
+    /// Copies the given texel data to the image through a staging buffer, and
+    /// transitions the image to a layout suitable for sampling in shaders.
+    fn upload(&self, data: &[u8], color_range: SubresourceRange) -> VortekResult<()> {
+        let staging_buffer_state = BufferState::new_staging(Rc::clone(&self.device_state), data)?;
+        let image = self.image.as_ref().expect("No image in texture state.");
+        let extent = self.extent;
+
+        unsafe {
+            self.device_state
+                .borrow_mut()
+                .execute_one_time_commands(|command_buffer| {
+                    command_buffer.pipeline_barrier(
+                        PipelineStage::TOP_OF_PIPE..PipelineStage::TRANSFER,
+                        Dependencies::empty(),
+                        iter::once(&Barrier::Image {
+                            states: (Access::empty(), Layout::Undefined)
+                                ..(Access::TRANSFER_WRITE, Layout::TransferDstOptimal),
+                            target: image,
+                            families: None,
+                            range: color_range.clone(),
+                        }),
+                    );
+                    command_buffer.copy_buffer_to_image(
+                        staging_buffer_state.buffer(),
+                        image,
+                        Layout::TransferDstOptimal,
+                        iter::once(&BufferImageCopy {
+                            buffer_offset: 0,
+                            buffer_width: extent.width,
+                            buffer_height: extent.height,
+                            image_layers: SubresourceLayers {
+                                aspects: Aspects::COLOR,
+                                level: 0,
+                                layers: 0..1,
+                            },
+                            image_offset: Offset::ZERO,
+                            image_extent: extent,
+                        }),
+                    );
+                    command_buffer.pipeline_barrier(
+                        PipelineStage::TRANSFER..PipelineStage::FRAGMENT_SHADER,
+                        Dependencies::empty(),
+                        iter::once(&Barrier::Image {
+                            states: (Access::TRANSFER_WRITE, Layout::TransferDstOptimal)
+                                ..(Access::SHADER_READ, Layout::ShaderReadOnlyOptimal),
+                            target: image,
+                            families: None,
+                            range: color_range,
+                        }),
+                    );
+                })
+        }
+    }
+
+    /// Selects the first of the given candidate formats that the device can
+    /// sample, along with the filter to sample it with.
+    ///
+    /// Formats that support linear filtering are preferred.
+    fn select_format(
+        physical_device: &B::PhysicalDevice,
+        candidates: &[Format],
+    ) -> VortekResult<(Format, Filter)> {
+        let supports = |format: Format, features: ImageFeature| {
+            physical_device
+                .format_properties(Some(format))
+                .optimal_tiling
+                .contains(features)
+        };
+        candidates
+            .iter()
+            .find(|&&format| supports(format, ImageFeature::SAMPLED | ImageFeature::SAMPLED_LINEAR))
+            .map(|&format| (format, Filter::Linear))
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .find(|&&format| supports(format, ImageFeature::SAMPLED))
+                    .map(|&format| (format, Filter::Nearest))
+            })
+            .ok_or_else(|| {
+                VortekError::RenderingError(RenderingError::from_str(
+                    "Could not find a supported texture format.",
+                ))
+            })
+    }
+}
+
+impl<B: Backend> Drop for TextureState<B> {
+    fn drop(&mut self) {
+        let borrowed_device_state = self.device_state.borrow();
+        let device = borrowed_device_state.device();
+        unsafe {
+            if let Some(sampler) = self.sampler.take() {
+                device.destroy_sampler(sampler);
+            }
+            if let Some(image_view) = self.image_view.take() {
+                device.destroy_image_view(image_view);
+            }
+            if let Some(image) = self.image.take() {
+                device.destroy_image(image);
+            }
+            if let Some(memory) = self.memory.take() {
+                device.free_memory(memory);
+            }
+        }
+    }
+}
@@ -1,31 +1,218 @@
 //! User input.
 
-use winit::event::{Event, WindowEvent};
+pub mod bindings;
+
+pub use winit::event::{ModifiersState, MouseButton, VirtualKeyCode};
+
+use winit::event::{
+    DeviceEvent, ElementState, Event, KeyboardInput, MouseScrollDelta, WindowEvent,
+};
+
+/// Number of logical pixels of scrolling corresponding to one line.
+const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
 
 #[derive(Clone, Debug)]
 pub enum UserInput {
     None,
     TerminationRequested,
     Resized((u32, u32)),
+    /// The ratio of physical to logical pixels changed, for example because the
+    /// window moved to another monitor. Holds the new scale factor and
+    /// physical window size.
+    ScaleFactorChanged {
+        scale_factor: f64,
+        physical_size: (u32, u32),
+    },
+    /// The window gained (`true`) or lost (`false`) keyboard focus.
+    FocusChanged(bool),
     CursorMoved((i32, i32)),
+    CursorLeft,
+    /// A key was pressed, or is repeated while held down.
+    KeyPressed(VirtualKeyCode),
+    KeyReleased(VirtualKeyCode),
+    /// The set of held modifier keys changed.
+    ModifiersChanged(ModifiersState),
+    MouseButtonPressed(MouseButton),
+    MouseButtonReleased(MouseButton),
+    Scrolled(ScrollDelta),
+}
+
+/// Amount of scrolling, positive to the right and upwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ScrollDelta {
+    /// Horizontal and vertical scrolling in lines, as reported by mouse wheels.
+    Lines(f32, f32),
+    /// Horizontal and vertical scrolling in logical pixels, as reported by
+    /// touchpads.
+    Pixels(f64, f64),
 }
 
 impl UserInput {
     pub fn from_event(event: Event<()>) -> Self {
         match event {
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested,
+            Event::WindowEvent { event, .. } => Self::from_window_event(event),
+            Event::DeviceEvent {
+                event: DeviceEvent::ModifiersChanged(modifiers),
                 ..
-            } => Self::TerminationRequested,
-            Event::WindowEvent {
-                event: WindowEvent::Resized(physical_size),
-                ..
-            } => Self::Resized((physical_size.width, physical_size.height)),
-            Event::WindowEvent {
-                event: WindowEvent::CursorMoved { position, .. },
+            } => Self::ModifiersChanged(modifiers),
+            _ => Self::None,
+        }
+    }
+
+    fn from_window_event(event: WindowEvent) -> Self {
+        match event {
+            WindowEvent::CloseRequested => Self::TerminationRequested,
+            WindowEvent::Resized(physical_size) => {
+                Self::Resized((physical_size.width, physical_size.height))
+            }
+            WindowEvent::ScaleFactorChanged {
+                scale_factor,
+                new_inner_size,
+            } => Self::ScaleFactorChanged {
+                scale_factor,
+                physical_size: (new_inner_size.width, new_inner_size.height),
+            },
+            WindowEvent::Focused(focused) => Self::FocusChanged(focused),
+            WindowEvent::CursorMoved { position, .. } => {
+                Self::CursorMoved((position.x, position.y))
+            }
+            WindowEvent::CursorLeft { .. } => Self::CursorLeft,
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
                 ..
-            } => Self::CursorMoved((position.x, position.y)),
+            } => match state {
+                ElementState::Pressed => Self::KeyPressed(key),
+                ElementState::Released => Self::KeyReleased(key),
+            },
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => Self::MouseButtonPressed(button),
+                ElementState::Released => Self::MouseButtonReleased(button),
+            },
+            WindowEvent::MouseWheel { delta, .. } => Self::Scrolled(match delta {
+                MouseScrollDelta::LineDelta(x, y) => ScrollDelta::Lines(x, y),
+                MouseScrollDelta::PixelDelta(position) => {
+                    ScrollDelta::Pixels(position.x, position.y)
+                }
+            }),
             _ => Self::None,
         }
     }
 }
+
+impl ScrollDelta {
+    /// Returns the vertical scrolling in lines, with pixel scrolling converted
+    /// to an equivalent number of lines.
+    pub fn vertical_lines(&self) -> f32 {
+        match *self {
+            Self::Lines(_, lines) => lines,
+            Self::Pixels(_, pixels) => (pixels / PIXELS_PER_SCROLL_LINE) as f32,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use winit::{dpi::PhysicalSize, event::DeviceId};
+
+    #[allow(deprecated)]
+    fn key_event(state: ElementState, key: VirtualKeyCode) -> WindowEvent<'static> {
+        WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty(),
+            },
+            is_synthetic: false,
+        }
+    }
+
+    #[test]
+    fn vertical_lines_converts_pixels_to_lines() {
+        assert_eq!(ScrollDelta::Lines(2.0, -1.5).vertical_lines(), -1.5);
+        assert_eq!(
+            ScrollDelta::Pixels(10.0, 3.0 * PIXELS_PER_SCROLL_LINE).vertical_lines(),
+            3.0
+        );
+        assert_eq!(
+            ScrollDelta::Pixels(0.0, -0.5 * PIXELS_PER_SCROLL_LINE).vertical_lines(),
+            -0.5
+        );
+    }
+
+    #[test]
+    fn maps_key_presses_and_releases() {
+        match UserInput::from_window_event(key_event(ElementState::Pressed, VirtualKeyCode::A)) {
+            UserInput::KeyPressed(VirtualKeyCode::A) => {}
+            input => panic!("Unexpected input {:?}.", input),
+        }
+        match UserInput::from_window_event(key_event(ElementState::Released, VirtualKeyCode::A)) {
+            UserInput::KeyReleased(VirtualKeyCode::A) => {}
+            input => panic!("Unexpected input {:?}.", input),
+        }
+    }
+
+    #[test]
+    fn maps_modifier_changes() {
+        let event = Event::DeviceEvent {
+            device_id: unsafe { DeviceId::dummy() },
+            event: DeviceEvent::ModifiersChanged(ModifiersState::CTRL | ModifiersState::SHIFT),
+        };
+        match UserInput::from_event(event) {
+            UserInput::ModifiersChanged(modifiers) => {
+                assert_eq!(modifiers, ModifiersState::CTRL | ModifiersState::SHIFT)
+            }
+            input => panic!("Unexpected input {:?}.", input),
+        }
+    }
+
+    #[test]
+    fn maps_focus_changes() {
+        for &focused in &[true, false] {
+            match UserInput::from_window_event(WindowEvent::Focused(focused)) {
+                UserInput::FocusChanged(changed_focus) => assert_eq!(changed_focus, focused),
+                input => panic!("Unexpected input {:?}.", input),
+            }
+        }
+    }
+
+    #[test]
+    fn maps_scale_factor_changes() {
+        let mut new_inner_size = PhysicalSize::new(1600, 1200);
+        let event = WindowEvent::ScaleFactorChanged {
+            scale_factor: 2.0,
+            new_inner_size: &mut new_inner_size,
+        };
+        match UserInput::from_window_event(event) {
+            UserInput::ScaleFactorChanged {
+                scale_factor,
+                physical_size,
+            } => {
+                assert_eq!(scale_factor, 2.0);
+                assert_eq!(physical_size, (1600, 1200));
+            }
+            input => panic!("Unexpected input {:?}.", input),
+        }
+    }
+
+    #[test]
+    fn maps_scroll_deltas() {
+        let event = WindowEvent::MouseWheel {
+            device_id: unsafe { DeviceId::dummy() },
+            delta: MouseScrollDelta::PixelDelta((4.0, -8.0).into()),
+            phase: winit::event::TouchPhase::Moved,
+            modifiers: ModifiersState::empty(),
+        };
+        match UserInput::from_window_event(event) {
+            UserInput::Scrolled(delta) => assert_eq!(delta, ScrollDelta::Pixels(4.0, -8.0)),
+            input => panic!("Unexpected input {:?}.", input),
+        }
+    }
+}
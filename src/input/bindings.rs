@@ -0,0 +1,570 @@
+//! Bindings between user input and application actions.
+//!
+//! Bindings can be overridden with a TOML file mapping action names to a
+//! binding or a list of bindings, for example
+//!
+//! ```toml
+//! screenshot = "Ctrl+S"
+//! pan_camera = ["MouseRight", "Shift+MouseLeft"]
+//! ```
+//!
+//! A binding consists of optional `Shift`, `Ctrl`, `Alt` or `Logo` modifiers
+//! followed by a key or mouse button, separated by `+`. Keys are named as the
+//! variants of winit's `VirtualKeyCode` (e.g. `A`, `Key1`, `F12`, `Up`), and
+//! mouse buttons as `MouseLeft`, `MouseRight`, `MouseMiddle` or `Mouse<n>`.
+
+use super::{ModifiersState, MouseButton, UserInput, VirtualKeyCode};
+use crate::error::{VortekError, VortekResult};
+use serde::Deserialize;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::Path,
+};
+
+/// Environment variable holding the path of the bindings file to use.
+pub const BINDINGS_FILE_ENV_VAR: &str = "VORTEK_BINDINGS";
+/// Name of the bindings file used if present in the working directory, unless
+/// a path is given in the environment.
+pub const DEFAULT_BINDINGS_FILE_NAME: &str = "bindings.toml";
+
+/// Application actions that can be bound to user input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    /// Orbit the camera around its target while held and dragged.
+    RotateCamera,
+    /// Move the camera along the view plane while held and dragged.
+    PanCamera,
+    ZoomIn,
+    ZoomOut,
+    /// Point the camera at the volume from the default direction.
+    ResetCamera,
+    /// Switch between perspective and orthographic projection.
+    ToggleProjection,
+    NextRenderMode,
+    NextTimestep,
+    PreviousTimestep,
+    Screenshot,
+    SelectNextIsosurface,
+    IncreaseIsosurfaceValue,
+    DecreaseIsosurfaceValue,
+}
+
+/// Physical input that can trigger an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Trigger {
+    Key(VirtualKeyCode),
+    MouseButton(MouseButton),
+}
+
+/// Trigger together with the modifier keys that must be held.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Binding {
+    pub trigger: Trigger,
+    pub modifiers: ModifiersState,
+}
+
+/// Start or end of an action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionEvent {
+    Started(Action),
+    Ended(Action),
+}
+
+/// Error structure for invalid input bindings.
+#[derive(Clone, Debug)]
+pub struct BindingError {
+    message: Cow<'static, str>,
+}
+
+/// Set of bindings from user input to actions.
+#[derive(Clone, Debug)]
+pub struct InputBindings {
+    bindings: HashMap<Binding, Action>,
+    modifiers: ModifiersState,
+    active_triggers: HashMap<Trigger, Action>,
+}
+
+/// One or more bindings for an action in a bindings file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum BindingNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Names of the actions as used in bindings files.
+const ACTION_NAMES: [(Action, &str); 13] = [
+    (Action::RotateCamera, "rotate_camera"),
+    (Action::PanCamera, "pan_camera"),
+    (Action::ZoomIn, "zoom_in"),
+    (Action::ZoomOut, "zoom_out"),
+    (Action::ResetCamera, "reset_camera"),
+    (Action::ToggleProjection, "toggle_projection"),
+    (Action::NextRenderMode, "next_render_mode"),
+    (Action::NextTimestep, "next_timestep"),
+    (Action::PreviousTimestep, "previous_timestep"),
+    (Action::Screenshot, "screenshot"),
+    (Action::SelectNextIsosurface, "select_next_isosurface"),
+    (Action::IncreaseIsosurfaceValue, "increase_isosurface_value"),
+    (Action::DecreaseIsosurfaceValue, "decrease_isosurface_value"),
+];
+
+/// Default bindings for each action.
+const DEFAULT_BINDINGS: [(Action, &[&str]); 13] = [
+    (Action::RotateCamera, &["MouseLeft"]),
+    (
+        Action::PanCamera,
+        &["MouseRight", "MouseMiddle", "Shift+MouseLeft"],
+    ),
+    (Action::ZoomIn, &["Equals", "Add"]),
+    (Action::ZoomOut, &["Minus", "Subtract"]),
+    (Action::ResetCamera, &["R"]),
+    (Action::ToggleProjection, &["P"]),
+    (Action::NextRenderMode, &["M"]),
+    (Action::NextTimestep, &["Right"]),
+    (Action::PreviousTimestep, &["Left"]),
+    (Action::Screenshot, &["F12"]),
+    (Action::SelectNextIsosurface, &["Tab"]),
+    (Action::IncreaseIsosurfaceValue, &["Up"]),
+    (Action::DecreaseIsosurfaceValue, &["Down"]),
+];
+
+/// Prefix of the names of mouse button triggers.
+const MOUSE_BUTTON_PREFIX: &str = "Mouse";
+
+impl Action {
+    /// Returns the name of the action as used in bindings files.
+    pub fn name(self) -> &'static str {
+        ACTION_NAMES
+            .iter()
+            .find(|(action, _)| *action == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    /// Returns the action with the given name as used in bindings files, or
+    /// `None` if there is no such action.
+    pub fn from_name(name: &str) -> Option<Self> {
+        ACTION_NAMES
+            .iter()
+            .find(|(_, action_name)| *action_name == name)
+            .map(|(action, _)| *action)
+    }
+}
+
+impl Binding {
+    /// Parses a binding of the form `Ctrl+Shift+S`, see the module
+    /// documentation.
+    pub fn parse(text: &str) -> VortekResult<Self> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let trigger_name = parts.pop().unwrap_or_default();
+        let mut modifiers = ModifiersState::default();
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "shift" => ModifiersState::SHIFT,
+                "ctrl" | "control" => ModifiersState::CTRL,
+                "alt" => ModifiersState::ALT,
+                "logo" | "super" | "cmd" => ModifiersState::LOGO,
+                _ => {
+                    return Err(binding_error(format!(
+                        "Unknown modifier \"{}\" in binding \"{}\".",
+                        part, text
+                    )))
+                }
+            };
+        }
+        let trigger = parse_trigger(trigger_name).ok_or_else(|| {
+            binding_error(format!(
+                "Unknown key or mouse button \"{}\" in binding \"{}\".",
+                trigger_name, text
+            ))
+        })?;
+        Ok(Self { trigger, modifiers })
+    }
+}
+
+impl BindingError {
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn from_error<E: fmt::Display>(front_message: &'static str, error: E) -> Self {
+        Self {
+            message: Cow::from(format!("{}{}", front_message, error)),
+        }
+    }
+
+    fn from_string(message: String) -> Self {
+        Self {
+            message: Cow::from(message),
+        }
+    }
+}
+
+impl InputBindings {
+    /// Creates a set of bindings from the given bindings for each action.
+    ///
+    /// Returns an error if the same binding is used for different actions.
+    pub fn new<I: IntoIterator<Item = (Action, Vec<Binding>)>>(
+        action_bindings: I,
+    ) -> VortekResult<Self> {
+        let mut bindings = HashMap::new();
+        for (action, action_bindings) in action_bindings {
+            for binding in action_bindings {
+                match bindings.insert(binding, action) {
+                    Some(other_action) if other_action != action => {
+                        return Err(binding_error(format!(
+                            "Binding {} is used for both {} and {}.",
+                            binding,
+                            other_action.name(),
+                            action.name()
+                        )))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Self {
+            bindings,
+            modifiers: ModifiersState::default(),
+            active_triggers: HashMap::new(),
+        })
+    }
+
+    /// Reads the bindings file at the given path, and returns the default
+    /// bindings with those of the actions in the file replaced.
+    ///
+    /// Returns an error if the file is invalid or the resulting bindings
+    /// conflict.
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> VortekResult<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| binding_error(format!("Could not read {}: {}", path.display(), err)))?;
+        Self::from_toml_str(&text)
+    }
+
+    /// Reads the bindings file at the given path and returns the resulting
+    /// bindings.
+    ///
+    /// Without a path, the file given by the `VORTEK_BINDINGS` environment
+    /// variable is read, or otherwise `bindings.toml` in the working directory
+    /// if it exists. The default bindings are returned if there is no such file.
+    ///
+    /// Returns an error if the file is invalid or the resulting bindings
+    /// conflict.
+    pub fn load(path: Option<&Path>) -> VortekResult<Self> {
+        if let Some(path) = path {
+            return Self::from_toml_file(path);
+        }
+        match std::env::var_os(BINDINGS_FILE_ENV_VAR) {
+            Some(path) => Self::from_toml_file(path),
+            None if Path::new(DEFAULT_BINDINGS_FILE_NAME).is_file() => {
+                Self::from_toml_file(DEFAULT_BINDINGS_FILE_NAME)
+            }
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Parses the given contents of a bindings file, and returns the default
+    /// bindings with those of the actions in the file replaced.
+    ///
+    /// Returns an error if the contents are invalid or the resulting bindings
+    /// conflict.
+    pub fn from_toml_str(text: &str) -> VortekResult<Self> {
+        let overrides: BTreeMap<String, BindingNames> = toml::from_str(text).map_err(|err| {
+            VortekError::BindingError(BindingError::from_error(
+                "Could not parse bindings file: ",
+                err,
+            ))
+        })?;
+        let mut action_bindings = Self::default_action_bindings();
+        for (name, binding_names) in overrides {
+            let action = Action::from_name(&name)
+                .ok_or_else(|| binding_error(format!("Unknown action \"{}\".", name)))?;
+            let binding_names = match binding_names {
+                BindingNames::One(binding_name) => vec![binding_name],
+                BindingNames::Many(binding_names) => binding_names,
+            };
+            let bindings = binding_names
+                .iter()
+                .map(|binding_name| Binding::parse(binding_name))
+                .collect::<VortekResult<_>>()?;
+            action_bindings.insert(action, bindings);
+        }
+        Self::new(action_bindings)
+    }
+
+    /// Returns the actions with the bindings of each action, sorted by action.
+    pub fn action_bindings(&self) -> BTreeMap<Action, Vec<Binding>> {
+        let mut action_bindings: BTreeMap<Action, Vec<Binding>> = BTreeMap::new();
+        for (binding, action) in &self.bindings {
+            action_bindings.entry(*action).or_default().push(*binding);
+        }
+        action_bindings
+    }
+
+    /// Returns the action started or ended by the given input, if any.
+    ///
+    /// An action ends when the key or mouse button that started it is
+    /// released, regardless of the modifiers held at that time. Presses of a
+    /// key that is already held, as generated by auto-repeat, are ignored.
+    pub fn translate(&mut self, input: &UserInput) -> Option<ActionEvent> {
+        let (trigger, pressed) = match *input {
+            UserInput::ModifiersChanged(modifiers) => {
+                self.modifiers = modifiers;
+                return None;
+            }
+            // Releases are missed while the window is unfocused
+            UserInput::FocusChanged(false) => {
+                self.active_triggers.clear();
+                return None;
+            }
+            UserInput::KeyPressed(key) => (Trigger::Key(key), true),
+            UserInput::KeyReleased(key) => (Trigger::Key(key), false),
+            UserInput::MouseButtonPressed(button) => (Trigger::MouseButton(button), true),
+            UserInput::MouseButtonReleased(button) => (Trigger::MouseButton(button), false),
+            _ => return None,
+        };
+        if pressed {
+            if self.active_triggers.contains_key(&trigger) {
+                return None;
+            }
+            let binding = Binding {
+                trigger,
+                modifiers: self.modifiers,
+            };
+            let action = *self.bindings.get(&binding)?;
+            self.active_triggers.insert(trigger, action);
+            Some(ActionEvent::Started(action))
+        } else {
+            self.active_triggers
+                .remove(&trigger)
+                .map(ActionEvent::Ended)
+        }
+    }
+
+    /// Returns the default bindings for each action.
+    fn default_action_bindings() -> BTreeMap<Action, Vec<Binding>> {
+        DEFAULT_BINDINGS
+            .iter()
+            .map(|(action, binding_names)| {
+                let bindings = binding_names
+                    .iter()
+                    .map(|binding_name| Binding::parse(binding_name).unwrap())
+                    .collect();
+                (*action, bindings)
+            })
+            .collect()
+    }
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        Self::new(Self::default_action_bindings()).unwrap()
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (self.modifiers.shift(), "Shift"),
+            (self.modifiers.ctrl(), "Ctrl"),
+            (self.modifiers.alt(), "Alt"),
+            (self.modifiers.logo(), "Logo"),
+        ] {
+            if modifier {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.trigger {
+            Trigger::Key(key) => write!(f, "{:?}", key),
+            Trigger::MouseButton(MouseButton::Other(number)) => {
+                write!(f, "{}{}", MOUSE_BUTTON_PREFIX, number)
+            }
+            Trigger::MouseButton(button) => write!(f, "{}{:?}", MOUSE_BUTTON_PREFIX, button),
+        }
+    }
+}
+
+impl fmt::Display for BindingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Parses the name of a key or mouse button.
+fn parse_trigger(name: &str) -> Option<Trigger> {
+    if let Some(button_name) = name.strip_prefix(MOUSE_BUTTON_PREFIX) {
+        let button = match button_name {
+            "Left" => MouseButton::Left,
+            "Right" => MouseButton::Right,
+            "Middle" => MouseButton::Middle,
+            _ => MouseButton::Other(button_name.parse().ok()?),
+        };
+        return Some(Trigger::MouseButton(button));
+    }
+    toml::Value::String(name.to_string())
+        .try_into()
+        .ok()
+        .map(Trigger::Key)
+}
+
+fn binding_error(message: String) -> VortekError {
+    VortekError::BindingError(BindingError::from_string(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_bindings_with_modifiers() {
+        let binding = Binding::parse("Ctrl + shift+S").unwrap();
+        assert_eq!(binding.trigger, Trigger::Key(VirtualKeyCode::S));
+        assert_eq!(
+            binding.modifiers,
+            ModifiersState::CTRL | ModifiersState::SHIFT
+        );
+        assert_eq!(binding.to_string(), "Shift+Ctrl+S");
+        assert_eq!(Binding::parse(&binding.to_string()).unwrap(), binding);
+
+        let binding = Binding::parse("F12").unwrap();
+        assert_eq!(binding.trigger, Trigger::Key(VirtualKeyCode::F12));
+        assert_eq!(binding.modifiers, ModifiersState::empty());
+    }
+
+    #[test]
+    fn parses_mouse_buttons() {
+        let binding = Binding::parse("Alt+MouseMiddle").unwrap();
+        assert_eq!(binding.trigger, Trigger::MouseButton(MouseButton::Middle));
+        assert_eq!(binding.modifiers, ModifiersState::ALT);
+
+        let binding = Binding::parse("Mouse4").unwrap();
+        assert_eq!(binding.trigger, Trigger::MouseButton(MouseButton::Other(4)));
+        assert_eq!(binding.to_string(), "Mouse4");
+        assert_eq!(
+            Binding::parse("MouseRight").unwrap().to_string(),
+            "MouseRight"
+        );
+    }
+
+    #[test]
+    fn unknown_keys_and_modifiers_are_errors() {
+        for &text in &[
+            "", "Ctrl+", "Foo", "Hyper+A", "Mouse", "MouseTop", "Mouse-1",
+        ] {
+            assert!(Binding::parse(text).is_err(), "{} should be invalid", text);
+        }
+    }
+
+    #[test]
+    fn bindings_file_overrides_defaults() {
+        let bindings = InputBindings::from_toml_str(
+            "screenshot = \"Ctrl+S\"\npan_camera = [\"MouseRight\", \"Mouse4\"]\n",
+        )
+        .unwrap();
+        let action_bindings = bindings.action_bindings();
+        assert_eq!(
+            action_bindings[&Action::Screenshot],
+            vec![Binding::parse("Ctrl+S").unwrap()]
+        );
+        let mut pan_bindings = action_bindings[&Action::PanCamera].clone();
+        pan_bindings.sort_by_key(|binding| binding.to_string());
+        assert_eq!(
+            pan_bindings,
+            vec![
+                Binding::parse("Mouse4").unwrap(),
+                Binding::parse("MouseRight").unwrap()
+            ]
+        );
+        assert_eq!(
+            action_bindings[&Action::ResetCamera],
+            vec![Binding::parse("R").unwrap()]
+        );
+        assert_eq!(action_bindings.len(), ACTION_NAMES.len());
+    }
+
+    #[test]
+    fn conflicting_bindings_are_an_error() {
+        let err = InputBindings::from_toml_str("screenshot = \"R\"").unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("reset_camera") && message.contains("screenshot"));
+
+        // Freeing the binding from its default action resolves the conflict
+        InputBindings::from_toml_str("screenshot = \"R\"\nreset_camera = \"Home\"").unwrap();
+    }
+
+    #[test]
+    fn invalid_bindings_files_are_errors() {
+        assert!(InputBindings::from_toml_str("fly = \"F\"").is_err());
+        assert!(InputBindings::from_toml_str("screenshot = \"Foo\"").is_err());
+        assert!(InputBindings::from_toml_str("screenshot = 12").is_err());
+        assert!(InputBindings::from_toml_str("screenshot").is_err());
+    }
+
+    #[test]
+    fn translate_starts_and_ends_actions() {
+        let mut bindings = InputBindings::default();
+        assert_eq!(
+            bindings.translate(&UserInput::MouseButtonPressed(MouseButton::Left)),
+            Some(ActionEvent::Started(Action::RotateCamera))
+        );
+        assert_eq!(
+            bindings.translate(&UserInput::MouseButtonReleased(MouseButton::Left)),
+            Some(ActionEvent::Ended(Action::RotateCamera))
+        );
+        assert_eq!(
+            bindings.translate(&UserInput::KeyPressed(VirtualKeyCode::Q)),
+            None
+        );
+        assert_eq!(
+            bindings.translate(&UserInput::KeyReleased(VirtualKeyCode::Q)),
+            None
+        );
+    }
+
+    #[test]
+    fn actions_end_regardless_of_modifiers() {
+        let mut bindings = InputBindings::default();
+        bindings.translate(&UserInput::ModifiersChanged(ModifiersState::SHIFT));
+        assert_eq!(
+            bindings.translate(&UserInput::MouseButtonPressed(MouseButton::Left)),
+            Some(ActionEvent::Started(Action::PanCamera))
+        );
+        bindings.translate(&UserInput::ModifiersChanged(ModifiersState::empty()));
+        assert_eq!(
+            bindings.translate(&UserInput::MouseButtonReleased(MouseButton::Left)),
+            Some(ActionEvent::Ended(Action::PanCamera))
+        );
+    }
+
+    #[test]
+    fn repeated_key_presses_are_ignored() {
+        let mut bindings = InputBindings::default();
+        let press = UserInput::KeyPressed(VirtualKeyCode::F12);
+        let release = UserInput::KeyReleased(VirtualKeyCode::F12);
+        assert_eq!(
+            bindings.translate(&press),
+            Some(ActionEvent::Started(Action::Screenshot))
+        );
+        assert_eq!(bindings.translate(&press), None);
+        assert_eq!(
+            bindings.translate(&release),
+            Some(ActionEvent::Ended(Action::Screenshot))
+        );
+        assert_eq!(
+            bindings.translate(&press),
+            Some(ActionEvent::Started(Action::Screenshot))
+        );
+
+        // A release missed while unfocused does not block the next press
+        bindings.translate(&UserInput::FocusChanged(false));
+        assert_eq!(
+            bindings.translate(&press),
+            Some(ActionEvent::Started(Action::Screenshot))
+        );
+    }
+}
@@ -0,0 +1,408 @@
+//! Reading and writing volume data, transfer function presets and images.
+
+pub mod bifrost;
+pub mod fits;
+pub mod metaimage;
+pub mod nrrd;
+pub mod numpy;
+pub mod paraview;
+pub mod png;
+pub mod raw;
+pub mod vtk;
+
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, Volume, VoxelBuffer},
+};
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, fmt, path::Path};
+
+/// Error structure for data I/O operations.
+#[derive(Clone, Debug)]
+pub struct DataIOError {
+    message: Cow<'static, str>,
+}
+
+/// Numeric types that can occur in volume data files.
+///
+/// Types without a matching voxel type are converted to 32-bit floats
+/// when decoded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ElementType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+}
+
+/// Byte order of multi-byte values in a data file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endianness {
+    Little,
+    Big,
+    #[default]
+    Native,
+}
+
+/// File formats that volumes can be read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VolumeFormat {
+    /// Legacy VTK file with structured points.
+    Vtk,
+    /// VTK XML ImageData file.
+    Vti,
+    /// NRRD file with attached or detached header.
+    Nrrd,
+    /// MetaImage file with attached or detached header.
+    MetaImage,
+    /// NumPy array file.
+    Npy,
+    /// NumPy archive of array files.
+    Npz,
+    /// FITS file.
+    Fits,
+    /// Bifrost snapshot described by a parameter file.
+    Bifrost,
+    /// Raw binary file described by a sidecar file.
+    Raw,
+}
+
+/// Names of the volume formats, as used on the command line.
+const VOLUME_FORMAT_NAMES: [(VolumeFormat, &str); 9] = [
+    (VolumeFormat::Vtk, "vtk"),
+    (VolumeFormat::Vti, "vti"),
+    (VolumeFormat::Nrrd, "nrrd"),
+    (VolumeFormat::MetaImage, "metaimage"),
+    (VolumeFormat::Npy, "npy"),
+    (VolumeFormat::Npz, "npz"),
+    (VolumeFormat::Fits, "fits"),
+    (VolumeFormat::Bifrost, "bifrost"),
+    (VolumeFormat::Raw, "raw"),
+];
+
+impl DataIOError {
+    /// Returns the error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn from_error<E: fmt::Display>(front_message: &'static str, error: E) -> Self {
+        Self {
+            message: Cow::from(format!("{}{}", front_message, error)),
+        }
+    }
+
+    fn from_str(message: &'static str) -> Self {
+        Self {
+            message: Cow::from(message),
+        }
+    }
+
+    fn from_string(message: String) -> Self {
+        Self {
+            message: Cow::from(message),
+        }
+    }
+}
+
+impl ElementType {
+    /// Returns the number of bytes occupied by a single value of the type.
+    pub fn size_in_bytes(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 => 8,
+        }
+    }
+}
+
+impl Endianness {
+    /// Whether values with this byte order are stored with the least
+    /// significant byte first.
+    pub fn is_little(self) -> bool {
+        match self {
+            Self::Little => true,
+            Self::Big => false,
+            Self::Native => cfg!(target_endian = "little"),
+        }
+    }
+}
+
+impl VolumeFormat {
+    /// Returns the names of all formats.
+    pub fn names() -> impl Iterator<Item = &'static str> {
+        VOLUME_FORMAT_NAMES.iter().map(|(_, name)| *name)
+    }
+
+    /// Returns the name of the format.
+    pub fn name(self) -> &'static str {
+        VOLUME_FORMAT_NAMES
+            .iter()
+            .find(|(format, _)| *format == self)
+            .map(|(_, name)| *name)
+            .unwrap()
+    }
+
+    /// Returns the format with the given name, or `None` if there is no such
+    /// format.
+    pub fn from_name(name: &str) -> Option<Self> {
+        VOLUME_FORMAT_NAMES
+            .iter()
+            .find(|(_, format_name)| *format_name == name)
+            .map(|(format, _)| *format)
+    }
+
+    /// Determines the format from the extension of the given path, if it is
+    /// recognized.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "vtk" => Some(Self::Vtk),
+            "vti" => Some(Self::Vti),
+            "nrrd" | "nhdr" => Some(Self::Nrrd),
+            "mha" | "mhd" => Some(Self::MetaImage),
+            "npy" => Some(Self::Npy),
+            "npz" => Some(Self::Npz),
+            "fits" | "fit" | "fts" => Some(Self::Fits),
+            "idl" => Some(Self::Bifrost),
+            "raw" | "bin" => Some(Self::Raw),
+            _ => None,
+        }
+    }
+
+    /// Whether files of the format can hold several volumes, so that a
+    /// variable can be selected.
+    pub fn has_variables(self) -> bool {
+        match self {
+            Self::Vtk | Self::Vti | Self::Npz | Self::Fits | Self::Bifrost => true,
+            Self::Nrrd | Self::MetaImage | Self::Npy | Self::Raw => false,
+        }
+    }
+}
+
+/// Decodes the given bytes as a sequence of values with the given type and byte
+/// order, and returns them in a voxel buffer.
+///
+/// Values that cannot be represented by a voxel type are converted to 32-bit floats.
+pub fn decode_values(
+    bytes: &[u8],
+    element_type: ElementType,
+    endianness: Endianness,
+) -> VortekResult<VoxelBuffer> {
+    let size = element_type.size_in_bytes();
+    if bytes.len() % size != 0 {
+        return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+            "Number of bytes ({}) is not a multiple of the element size ({}).",
+            bytes.len(),
+            size
+        ))));
+    }
+
+    macro_rules! decode {
+        ($type:ty) => {
+            bytes.chunks_exact(size).map(|chunk| {
+                let mut array = [0; std::mem::size_of::<$type>()];
+                array.copy_from_slice(chunk);
+                if endianness.is_little() {
+                    <$type>::from_le_bytes(array)
+                } else {
+                    <$type>::from_be_bytes(array)
+                }
+            })
+        };
+    }
+
+    Ok(match element_type {
+        ElementType::U8 => VoxelBuffer::U8(bytes.to_vec()),
+        ElementType::I8 => VoxelBuffer::I16(decode!(i8).map(i16::from).collect()),
+        ElementType::I16 => VoxelBuffer::I16(decode!(i16).collect()),
+        ElementType::U16 => VoxelBuffer::U16(decode!(u16).collect()),
+        ElementType::I32 => VoxelBuffer::F32(decode!(i32).map(|value| value as f32).collect()),
+        ElementType::U32 => VoxelBuffer::F32(decode!(u32).map(|value| value as f32).collect()),
+        ElementType::I64 => VoxelBuffer::F32(decode!(i64).map(|value| value as f32).collect()),
+        ElementType::U64 => VoxelBuffer::F32(decode!(u64).map(|value| value as f32).collect()),
+        ElementType::F32 => VoxelBuffer::F32(decode!(f32).collect()),
+        ElementType::F64 => VoxelBuffer::F32(decode!(f64).map(|value| value as f32).collect()),
+    })
+}
+
+/// Encodes the values in the given voxel buffer as bytes with the given byte order.
+pub fn encode_values(voxels: &VoxelBuffer, endianness: Endianness) -> Vec<u8> {
+    macro_rules! encode {
+        ($values:expr) => {
+            $values
+                .iter()
+                .flat_map(|value| {
+                    if endianness.is_little() {
+                        value.to_le_bytes()
+                    } else {
+                        value.to_be_bytes()
+                    }
+                    .to_vec()
+                })
+                .collect()
+        };
+    }
+
+    match voxels {
+        VoxelBuffer::U8(values) => values.clone(),
+        VoxelBuffer::U16(values) => encode!(values),
+        VoxelBuffer::I16(values) => encode!(values),
+        VoxelBuffer::F32(values) => encode!(values),
+    }
+}
+
+/// Converts the given values to the voxel type best matching the given element
+/// type, and returns them in a voxel buffer.
+pub fn values_from_f64(values: Vec<f64>, element_type: ElementType) -> VoxelBuffer {
+    match element_type {
+        ElementType::U8 => VoxelBuffer::U8(values.into_iter().map(|value| value as u8).collect()),
+        ElementType::I8 | ElementType::I16 => {
+            VoxelBuffer::I16(values.into_iter().map(|value| value as i16).collect())
+        }
+        ElementType::U16 => {
+            VoxelBuffer::U16(values.into_iter().map(|value| value as u16).collect())
+        }
+        _ => VoxelBuffer::F32(values.into_iter().map(|value| value as f32).collect()),
+    }
+}
+
+/// Reads a scalar volume from the file at the given path, which has the given
+/// format or, if `None`, the format indicated by its extension.
+///
+/// For formats holding several volumes, the variable selects the field, array,
+/// HDU index or snapshot variable to read. Without a variable the first volume
+/// is read, except for Bifrost snapshots where a variable is required. Vector
+/// volumes are converted to their magnitude.
+pub fn read_volume<P: AsRef<Path>>(
+    path: P,
+    format: Option<VolumeFormat>,
+    variable: Option<&str>,
+) -> VortekResult<ScalarVolume> {
+    let path = path.as_ref();
+    let format = format
+        .or_else(|| VolumeFormat::from_path(path))
+        .ok_or_else(|| {
+            VortekError::DataIOError(DataIOError::from_string(format!(
+                "Could not determine format of {} from its extension (supported formats are {}).",
+                path.display(),
+                VolumeFormat::names().collect::<Vec<_>>().join(", ")
+            )))
+        })?;
+    if variable.is_some() && !format.has_variables() {
+        return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+            "Variables cannot be selected for the {} format.",
+            format.name()
+        ))));
+    }
+
+    let first_volume = |volumes: Vec<(String, Volume)>| {
+        volumes
+            .into_iter()
+            .next()
+            .map(|(_, volume)| volume)
+            .ok_or_else(|| {
+                VortekError::DataIOError(DataIOError::from_string(format!(
+                    "{} does not contain any volumes.",
+                    path.display()
+                )))
+            })
+    };
+    let select_field = |dataset: vtk::VtkDataset| match variable {
+        Some(name) => dataset.into_field(name),
+        None => first_volume(dataset.into_fields()),
+    };
+
+    Ok(match format {
+        VolumeFormat::Vtk => select_field(vtk::read_vtk_legacy(path)?)?.into_scalar(),
+        VolumeFormat::Vti => select_field(vtk::read_vti(path)?)?.into_scalar(),
+        VolumeFormat::Nrrd => nrrd::read_nrrd(path)?,
+        VolumeFormat::MetaImage => metaimage::read_metaimage(path)?.into_scalar(),
+        VolumeFormat::Npy => numpy::read_npy(path)?.into_scalar(),
+        VolumeFormat::Npz => match variable {
+            Some(name) => numpy::read_npz_array(path, name)?,
+            None => first_volume(numpy::read_npz(path)?)?,
+        }
+        .into_scalar(),
+        VolumeFormat::Fits => match variable {
+            Some(index) => {
+                let index = index.parse().map_err(|_| {
+                    VortekError::DataIOError(DataIOError::from_string(format!(
+                        "Invalid FITS HDU index {}.",
+                        index
+                    )))
+                })?;
+                fits::FitsFile::open(path)?.read_image(index)?
+            }
+            None => fits::read_fits(path)?,
+        },
+        VolumeFormat::Bifrost => {
+            let snapshot = bifrost::BifrostSnapshot::open(path)?;
+            match variable {
+                Some(name) => snapshot.read_variable(name)?,
+                None => {
+                    return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                        "A variable must be selected for Bifrost snapshots (available variables are {}).",
+                        snapshot.variable_names().collect::<Vec<_>>().join(", ")
+                    ))))
+                }
+            }
+        }
+        VolumeFormat::Raw => raw::read_raw_volume_with_sidecar(path)?,
+    })
+}
+
+/// Reads the entire file at the given path into a byte vector.
+fn read_file<P: AsRef<Path>>(path: P) -> VortekResult<Vec<u8>> {
+    std::fs::read(path.as_ref()).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not read {}: {}",
+            path.as_ref().display(),
+            err
+        )))
+    })
+}
+
+/// Writes the given bytes to the file at the given path, replacing any existing file.
+fn write_file<P: AsRef<Path>>(path: P, bytes: &[u8]) -> VortekResult<()> {
+    std::fs::write(path.as_ref(), bytes).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not write {}: {}",
+            path.as_ref().display(),
+            err
+        )))
+    })
+}
+
+/// Reads the entire file at the given path into a string.
+fn read_file_to_string<P: AsRef<Path>>(path: P) -> VortekResult<String> {
+    std::fs::read_to_string(path.as_ref()).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not read {}: {}",
+            path.as_ref().display(),
+            err
+        )))
+    })
+}
+
+/// Returns a path in the temporary directory for a test file with the given
+/// name, unique to the running test process.
+#[cfg(test)]
+pub(crate) fn temporary_path(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("vortek_test_{}_{}", std::process::id(), name))
+}
+
+impl fmt::Display for DataIOError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
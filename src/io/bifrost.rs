@@ -0,0 +1,481 @@
+//! Reading Bifrost simulation snapshots.
+//!
+//! A snapshot consists of an `.idl` parameter file, a `.snap` file with the
+//! primary variables and optionally an `.aux` file with auxiliary variables.
+//! The variables are stored consecutively as Fortran-ordered 32-bit floats.
+//! The grid coordinates are read from the mesh file referred to by the
+//! parameter file, which allows for a non-uniform vertical grid.
+
+use super::{
+    raw::{self, AxisOrder, RawVolumeDescriptor},
+    DataIOError, ElementType, Endianness,
+};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::ScalarVolume,
+};
+use log::{debug, warn};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Names of the primary variables in a snapshot, in the order they are stored.
+pub const PRIMARY_VARIABLE_NAMES: [&str; 8] = ["r", "px", "py", "pz", "e", "bx", "by", "bz"];
+
+/// Parameters read from a Bifrost `.idl` file.
+#[derive(Clone, Debug, Default)]
+pub struct BifrostParameters {
+    values: HashMap<String, String>,
+}
+
+/// Coordinates of the grid points along each axis of a Bifrost simulation.
+#[derive(Clone, Debug)]
+pub struct BifrostMesh {
+    coordinates: [Vec<f32>; 3],
+}
+
+/// A Bifrost snapshot whose variables can be read as scalar volumes.
+#[derive(Clone, Debug)]
+pub struct BifrostSnapshot {
+    parameters: BifrostParameters,
+    mesh: BifrostMesh,
+    snap_path: PathBuf,
+    aux_path: PathBuf,
+    primary_variable_names: Vec<String>,
+    auxiliary_variable_names: Vec<String>,
+    endianness: Endianness,
+}
+
+impl BifrostParameters {
+    /// Reads the parameters in the given `.idl` file.
+    pub fn from_idl_file<P: AsRef<Path>>(path: P) -> VortekResult<Self> {
+        Self::parse(&super::read_file_to_string(path)?)
+    }
+
+    /// Parses the given `.idl` file contents.
+    ///
+    /// Each parameter is specified on a separate line as `name = value`,
+    /// and comments start with `;`.
+    pub fn parse(text: &str) -> VortekResult<Self> {
+        let mut values = HashMap::new();
+        for line in text.lines() {
+            let line = match line.find(';') {
+                Some(comment_start) => &line[..comment_start],
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            let separator = line.find('=').ok_or_else(|| {
+                VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Invalid line in Bifrost parameter file: {}",
+                    line
+                )))
+            })?;
+            let name = line[..separator].trim().to_lowercase();
+            let value = line[separator + 1..]
+                .trim()
+                .trim_matches(|c| c == '"' || c == '\'')
+                .trim();
+            values.insert(name, value.to_string());
+        }
+        Ok(Self { values })
+    }
+
+    /// Returns the value of the given parameter as a string, if present.
+    pub fn get_str(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Returns the value of the given parameter as an integer.
+    pub fn get_int(&self, name: &str) -> VortekResult<i64> {
+        self.get_parsed(name)
+    }
+
+    /// Returns the value of the given parameter as a float.
+    pub fn get_float(&self, name: &str) -> VortekResult<f64> {
+        // Fortran style exponents like 1.0d-3 are allowed
+        self.get_required_str(name).and_then(|value| {
+            value.replace(['d', 'D'], "e").parse().map_err(|_| {
+                VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Invalid value for Bifrost parameter {}: {}",
+                    name, value
+                )))
+            })
+        })
+    }
+
+    fn get_parsed<T: std::str::FromStr>(&self, name: &str) -> VortekResult<T> {
+        self.get_required_str(name).and_then(|value| {
+            value.parse().map_err(|_| {
+                VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Invalid value for Bifrost parameter {}: {}",
+                    name, value
+                )))
+            })
+        })
+    }
+
+    fn get_required_str(&self, name: &str) -> VortekResult<&str> {
+        self.get_str(name).ok_or_else(|| {
+            VortekError::DataIOError(DataIOError::from_string(format!(
+                "Missing Bifrost parameter: {}",
+                name
+            )))
+        })
+    }
+}
+
+impl BifrostMesh {
+    /// Reads the mesh file at the given path, checking that it matches the given
+    /// grid dimensions.
+    ///
+    /// For each axis the file contains the number of grid points followed by four
+    /// arrays, of which only the first (the cell-centered coordinates) is used.
+    pub fn from_mesh_file<P: AsRef<Path>>(path: P, dimensions: [usize; 3]) -> VortekResult<Self> {
+        let path = path.as_ref();
+        let text = super::read_file_to_string(path)?;
+        let invalid_mesh_error = || {
+            VortekError::DataIOError(DataIOError::from_string(format!(
+                "Invalid Bifrost mesh file: {}",
+                path.display()
+            )))
+        };
+
+        let mut tokens = text.split_whitespace();
+        let mut coordinates = [Vec::new(), Vec::new(), Vec::new()];
+        for (axis, axis_coordinates) in coordinates.iter_mut().enumerate() {
+            let number_of_points: usize = tokens
+                .next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(invalid_mesh_error)?;
+            if number_of_points != dimensions[axis] {
+                return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Number of points along axis {} in mesh file ({}) does not match parameter file ({}).",
+                    axis, number_of_points, dimensions[axis]
+                ))));
+            }
+            let arrays = (0..4 * number_of_points)
+                .map(|_| {
+                    tokens
+                        .next()
+                        .and_then(|token| token.replace(['d', 'D'], "e").parse::<f32>().ok())
+                        .ok_or_else(invalid_mesh_error)
+                })
+                .collect::<VortekResult<Vec<_>>>()?;
+            *axis_coordinates = arrays[..number_of_points].to_vec();
+            if !axis_coordinates.windows(2).all(|pair| pair[1] > pair[0]) {
+                return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Coordinates along axis {} in mesh file are not strictly increasing.",
+                    axis
+                ))));
+            }
+        }
+        Ok(Self { coordinates })
+    }
+
+    /// Creates a uniform mesh with the given dimensions and grid spacings,
+    /// starting at zero.
+    pub fn uniform(dimensions: [usize; 3], spacing: [f32; 3]) -> Self {
+        let axis_coordinates = |axis: usize| {
+            (0..dimensions[axis])
+                .map(|index| index as f32 * spacing[axis])
+                .collect()
+        };
+        Self {
+            coordinates: [
+                axis_coordinates(0),
+                axis_coordinates(1),
+                axis_coordinates(2),
+            ],
+        }
+    }
+
+    /// Returns the coordinates of the grid points along the given axis.
+    pub fn coordinates(&self, axis: usize) -> &[f32] {
+        &self.coordinates[axis]
+    }
+
+    /// Whether the grid points along the given axis are uniformly spaced.
+    pub fn is_uniform(&self, axis: usize) -> bool {
+        let coordinates = &self.coordinates[axis];
+        if coordinates.len() < 3 {
+            return true;
+        }
+        let first_spacing = coordinates[1] - coordinates[0];
+        coordinates
+            .windows(2)
+            .all(|pair| ((pair[1] - pair[0]) - first_spacing).abs() <= 1e-4 * first_spacing.abs())
+    }
+}
+
+impl BifrostSnapshot {
+    /// Opens the snapshot described by the given `.idl` file.
+    ///
+    /// The `.snap` and `.aux` files are assumed to have the same name as the
+    /// parameter file, and the mesh file path is taken relative to the
+    /// directory of the parameter file.
+    pub fn open<P: AsRef<Path>>(idl_path: P) -> VortekResult<Self> {
+        let idl_path = idl_path.as_ref();
+        let parameters = BifrostParameters::from_idl_file(idl_path)?;
+
+        let dimensions = [
+            parameters.get_parsed::<usize>("mx")?,
+            parameters.get_parsed::<usize>("my")?,
+            parameters.get_parsed::<usize>("mz")?,
+        ];
+        if dimensions.contains(&0) {
+            return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                "Grid dimensions in Bifrost parameter file must be positive (were {:?}).",
+                dimensions
+            ))));
+        }
+        // Makes sure that the size of each variable can be computed
+        RawVolumeDescriptor::new(dimensions, ElementType::F32).data_size_in_bytes()?;
+        let directory = idl_path.parent().unwrap_or_else(|| Path::new(""));
+
+        let mesh = match parameters.get_str("meshfile") {
+            Some(mesh_file) if directory.join(mesh_file).is_file() => {
+                BifrostMesh::from_mesh_file(directory.join(mesh_file), dimensions)?
+            }
+            mesh_file => {
+                if let Some(mesh_file) = mesh_file {
+                    warn!(
+                        "Could not find Bifrost mesh file {}, assuming uniform grid.",
+                        mesh_file
+                    );
+                }
+                BifrostMesh::uniform(
+                    dimensions,
+                    [
+                        parameters.get_float("dx")? as f32,
+                        parameters.get_float("dy")? as f32,
+                        parameters.get_float("dz")? as f32,
+                    ],
+                )
+            }
+        };
+
+        let include_magnetic_field = parameters.get_str("do_mhd").map(str::trim) != Some("0");
+        let number_of_primary_variables = if include_magnetic_field { 8 } else { 5 };
+        let primary_variable_names = PRIMARY_VARIABLE_NAMES[..number_of_primary_variables]
+            .iter()
+            .map(|&name| name.to_string())
+            .collect();
+
+        let auxiliary_variable_names = parameters.get_str("aux").map_or_else(Vec::new, |aux| {
+            aux.split_whitespace().map(str::to_string).collect()
+        });
+
+        Ok(Self {
+            parameters,
+            mesh,
+            snap_path: idl_path.with_extension("snap"),
+            aux_path: idl_path.with_extension("aux"),
+            primary_variable_names,
+            auxiliary_variable_names,
+            endianness: Endianness::Little,
+        })
+    }
+
+    /// Sets the byte order assumed for the snapshot files (little endian by default).
+    pub fn set_endianness(&mut self, endianness: Endianness) {
+        self.endianness = endianness;
+    }
+
+    /// Returns a reference to the snapshot parameters.
+    pub fn parameters(&self) -> &BifrostParameters {
+        &self.parameters
+    }
+
+    /// Returns a reference to the snapshot mesh.
+    pub fn mesh(&self) -> &BifrostMesh {
+        &self.mesh
+    }
+
+    /// Returns the number of grid points along each axis.
+    pub fn dimensions(&self) -> [usize; 3] {
+        [
+            self.mesh.coordinates[0].len(),
+            self.mesh.coordinates[1].len(),
+            self.mesh.coordinates[2].len(),
+        ]
+    }
+
+    /// Returns the names of all variables that can be read from the snapshot.
+    pub fn variable_names(&self) -> impl Iterator<Item = &str> {
+        self.primary_variable_names
+            .iter()
+            .chain(self.auxiliary_variable_names.iter())
+            .map(String::as_str)
+    }
+
+    /// Reads the variable with the given name into a scalar volume placed on
+    /// the snapshot mesh.
+    pub fn read_variable(&self, name: &str) -> VortekResult<ScalarVolume> {
+        let (path, index) = if let Some(index) = self
+            .primary_variable_names
+            .iter()
+            .position(|variable_name| variable_name == name)
+        {
+            (&self.snap_path, index)
+        } else if let Some(index) = self
+            .auxiliary_variable_names
+            .iter()
+            .position(|variable_name| variable_name == name)
+        {
+            (&self.aux_path, index)
+        } else {
+            return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                "Variable {} not present in Bifrost snapshot (available variables are {}).",
+                name,
+                self.variable_names().collect::<Vec<_>>().join(", ")
+            ))));
+        };
+        debug!("Reading Bifrost variable {} from {}", name, path.display());
+
+        let mut descriptor = RawVolumeDescriptor::new(self.dimensions(), ElementType::F32);
+        descriptor.endianness = self.endianness;
+        descriptor.axis_order = AxisOrder::Fortran;
+        descriptor.byte_offset = (descriptor.data_size_in_bytes()? as u64)
+            .checked_mul(index as u64)
+            .ok_or_else(|| {
+                VortekError::DataIOError(DataIOError::from_string(format!(
+                    "Offset of variable {} in {} is too large.",
+                    name,
+                    path.display()
+                )))
+            })?;
+
+        let mut volume = raw::read_raw_volume(path, &descriptor)?;
+        volume.set_origin([
+            self.mesh.coordinates[0][0],
+            self.mesh.coordinates[1][0],
+            self.mesh.coordinates[2][0],
+        ]);
+        let mut spacing = volume.spacing();
+        for (axis, spacing) in spacing.iter_mut().enumerate() {
+            let coordinates = &self.mesh.coordinates[axis];
+            if coordinates.len() > 1 {
+                *spacing = coordinates[1] - coordinates[0];
+            }
+        }
+        volume.set_spacing(spacing);
+
+        for axis in 0..3 {
+            if !self.mesh.is_uniform(axis) {
+                volume.set_axis_coordinates(axis, self.mesh.coordinates[axis].clone());
+            }
+        }
+        Ok(volume)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::temporary_path;
+    use std::fs;
+
+    fn write_idl_file(name: &str, dimensions: [&str; 3]) -> PathBuf {
+        let path = temporary_path(name);
+        fs::write(
+            &path,
+            format!(
+                "mx = {}\nmy = {}\nmz = {}\ndx = 0.5\ndy = 0.5\ndz = 0.25d0\ndo_mhd = 0\n",
+                dimensions[0], dimensions[1], dimensions[2]
+            ),
+        )
+        .unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_variable_from_uniform_snapshot() {
+        let idl_path = write_idl_file("uniform.idl", ["2", "3", "2"]);
+        let values: Vec<f32> = (0..5 * 12).map(|value| value as f32).collect();
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let snap_path = idl_path.with_extension("snap");
+        fs::write(&snap_path, bytes).unwrap();
+
+        let result =
+            BifrostSnapshot::open(&idl_path).and_then(|snapshot| snapshot.read_variable("py"));
+        fs::remove_file(&idl_path).unwrap();
+        fs::remove_file(&snap_path).unwrap();
+
+        let volume = result.unwrap();
+        assert_eq!(volume.dimensions(), [2, 3, 2]);
+        assert_eq!(volume.spacing(), [0.5, 0.5, 0.25]);
+        assert_eq!(volume.voxels().to_f32(), values[24..36].to_vec());
+    }
+
+    #[test]
+    fn reads_variable_on_non_uniform_mesh() {
+        let idl_path = write_idl_file("mesh.idl", ["2", "1", "3"]);
+        let mesh_path = temporary_path("mesh.mesh");
+        fs::write(
+            &mesh_path,
+            "2\n1.0 1.5\n0 0\n0 0\n0 0\n\
+             1\n-2.0\n0\n0\n0\n\
+             3\n0.0 1.0d-1 4.0E-1\n0 0 0\n0 0 0\n0 0 0\n",
+        )
+        .unwrap();
+        let mut idl_text = fs::read_to_string(&idl_path).unwrap();
+        idl_text.push_str(&format!(
+            "meshfile = \"{}\"\n",
+            mesh_path.file_name().unwrap().to_str().unwrap()
+        ));
+        fs::write(&idl_path, idl_text).unwrap();
+        let values: Vec<f32> = (0..5 * 6).map(|value| value as f32).collect();
+        let bytes: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let snap_path = idl_path.with_extension("snap");
+        fs::write(&snap_path, bytes).unwrap();
+
+        let result =
+            BifrostSnapshot::open(&idl_path).and_then(|snapshot| snapshot.read_variable("r"));
+        fs::remove_file(&idl_path).unwrap();
+        fs::remove_file(&mesh_path).unwrap();
+        fs::remove_file(&snap_path).unwrap();
+
+        let volume = result.unwrap();
+        assert_eq!(volume.dimensions(), [2, 1, 3]);
+        assert_eq!(volume.origin(), [1.0, -2.0, 0.0]);
+        assert_eq!(volume.spacing(), [0.5, 1.0, 0.2]);
+        assert_eq!(volume.axis_coordinates(0), None);
+        assert_eq!(volume.axis_coordinates(1), None);
+        assert_eq!(volume.axis_coordinates(2), Some(&[0.0, 0.1, 0.4][..]));
+        assert_eq!(volume.voxels().to_f32(), values[..6].to_vec());
+    }
+
+    #[test]
+    fn mesh_file_must_match_dimensions() {
+        let mesh_path = temporary_path("mismatched.mesh");
+        fs::write(&mesh_path, "2\n0 1\n0 0\n0 0\n0 0\n").unwrap();
+        let result = BifrostMesh::from_mesh_file(&mesh_path, [3, 1, 1]);
+        fs::remove_file(&mesh_path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn invalid_dimensions_are_an_error() {
+        let max = usize::MAX.to_string();
+        for (name, dimensions) in [
+            ("zero.idl", ["2", "0", "2"]),
+            ("negative.idl", ["2", "-1", "2"]),
+            ("overflowing.idl", [max.as_str(), "2", "1"]),
+        ] {
+            let idl_path = write_idl_file(name, dimensions);
+            let result = BifrostSnapshot::open(&idl_path);
+            fs::remove_file(&idl_path).unwrap();
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,515 @@
+//! Reading FITS image cubes.
+//!
+//! Images in the primary HDU and in `IMAGE` extensions can be read, with
+//! `BSCALE`/`BZERO` scaling applied and voxel spacing and origin derived from
+//! the basic `CRPIXn`/`CRVALn`/`CDELTn` WCS keywords. See the format
+//! specification at <https://fits.gsfc.nasa.gov/fits_standard.html>.
+
+use super::{DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VoxelBuffer, IDENTITY_ORIENTATION},
+};
+use log::debug;
+use std::{convert::TryFrom, path::Path};
+
+/// Value of a FITS header keyword.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FitsValue {
+    Logical(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Undefined,
+}
+
+/// Header of a FITS header and data unit.
+#[derive(Clone, Debug, Default)]
+pub struct FitsHeader {
+    cards: Vec<(String, FitsValue)>,
+}
+
+/// A FITS header and data unit, with the location of its data in the file.
+#[derive(Clone, Debug)]
+pub struct FitsHdu {
+    header: FitsHeader,
+    data_offset: usize,
+    data_size: usize,
+}
+
+/// A FITS file with all its header and data units.
+#[derive(Clone, Debug)]
+pub struct FitsFile {
+    bytes: Vec<u8>,
+    hdus: Vec<FitsHdu>,
+}
+
+const BLOCK_SIZE: usize = 2880;
+const CARD_SIZE: usize = 80;
+
+/// Reads the first non-empty image in the FITS file at the given path into a
+/// scalar volume.
+pub fn read_fits<P: AsRef<Path>>(path: P) -> VortekResult<ScalarVolume> {
+    let file = FitsFile::open(path)?;
+    let index = file.first_image_index().ok_or_else(|| {
+        VortekError::DataIOError(DataIOError::from_str(
+            "FITS file does not contain any images.",
+        ))
+    })?;
+    file.read_image(index)
+}
+
+impl FitsValue {
+    /// Parses the value field of a header card.
+    fn parse(field: &str) -> Self {
+        let field = field.trim_start();
+        if let Some(quoted) = field.strip_prefix('\'') {
+            // Quotes inside strings are escaped by doubling them
+            let mut value = String::new();
+            let mut chars = quoted.chars().peekable();
+            while let Some(c) = chars.next() {
+                if c == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                value.push(c);
+            }
+            return Self::String(value.trim_end().to_string());
+        }
+        let value = match field.find('/') {
+            Some(comment_start) => &field[..comment_start],
+            None => field,
+        }
+        .trim();
+        match value {
+            "" => Self::Undefined,
+            "T" => Self::Logical(true),
+            "F" => Self::Logical(false),
+            _ => {
+                if let Ok(integer) = value.parse() {
+                    Self::Integer(integer)
+                } else if let Ok(float) = value.replace(['D', 'd'], "E").parse() {
+                    Self::Float(float)
+                } else {
+                    Self::String(value.to_string())
+                }
+            }
+        }
+    }
+}
+
+impl FitsHeader {
+    /// Returns the value of the given keyword, if present.
+    pub fn get(&self, keyword: &str) -> Option<&FitsValue> {
+        self.cards
+            .iter()
+            .find(|(card_keyword, _)| card_keyword == keyword)
+            .map(|(_, value)| value)
+    }
+
+    /// Returns the value of the given keyword as an integer, if present and integral.
+    pub fn get_int(&self, keyword: &str) -> Option<i64> {
+        match self.get(keyword) {
+            Some(FitsValue::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given keyword as a float, if present and numeric.
+    pub fn get_float(&self, keyword: &str) -> Option<f64> {
+        match self.get(keyword) {
+            Some(FitsValue::Integer(value)) => Some(*value as f64),
+            Some(FitsValue::Float(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given keyword as a string, if present and textual.
+    pub fn get_str(&self, keyword: &str) -> Option<&str> {
+        match self.get(keyword) {
+            Some(FitsValue::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given keyword as a boolean, if present and logical.
+    pub fn get_bool(&self, keyword: &str) -> Option<bool> {
+        match self.get(keyword) {
+            Some(FitsValue::Logical(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over all keywords and values in the header.
+    pub fn cards(&self) -> impl Iterator<Item = (&str, &FitsValue)> {
+        self.cards
+            .iter()
+            .map(|(keyword, value)| (keyword.as_str(), value))
+    }
+
+    fn get_required_int(&self, keyword: &str) -> VortekResult<i64> {
+        self.get_int(keyword).ok_or_else(|| {
+            fits_error(format!(
+                "Missing or non-integer value for keyword {}.",
+                keyword
+            ))
+        })
+    }
+}
+
+impl FitsHdu {
+    /// Returns a reference to the header of the HDU.
+    pub fn header(&self) -> &FitsHeader {
+        &self.header
+    }
+
+    /// Returns the size of the data in the HDU in bytes, excluding padding.
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    /// Returns the size of each data axis, with the fastest varying axis first.
+    pub fn axis_sizes(&self) -> Vec<usize> {
+        let naxis = self.header.get_int("NAXIS").unwrap_or(0);
+        (1..=naxis)
+            .map(|axis| {
+                self.header
+                    .get_int(&format!("NAXIS{}", axis))
+                    .unwrap_or(0)
+                    .max(0) as usize
+            })
+            .collect()
+    }
+
+    /// Whether the HDU holds image data with at least two dimensions.
+    pub fn is_image(&self) -> bool {
+        let is_image_extension = match self.header.get_str("XTENSION") {
+            Some(extension) => extension.trim() == "IMAGE",
+            None => self.header.get("SIMPLE").is_some(),
+        };
+        let sizes = self.axis_sizes();
+        is_image_extension && sizes.len() >= 2 && sizes.iter().all(|&size| size > 0)
+    }
+}
+
+impl FitsFile {
+    /// Reads and parses the FITS file at the given path.
+    pub fn open<P: AsRef<Path>>(path: P) -> VortekResult<Self> {
+        Self::from_bytes(super::read_file(path)?)
+    }
+
+    /// Parses the given FITS file contents.
+    pub fn from_bytes(bytes: Vec<u8>) -> VortekResult<Self> {
+        if !bytes.starts_with(b"SIMPLE  =") {
+            return Err(fits_error(
+                "File does not start with SIMPLE keyword.".to_string(),
+            ));
+        }
+        let mut hdus = Vec::new();
+        let mut position = 0;
+        while position + BLOCK_SIZE <= bytes.len() {
+            let (header, header_size) = parse_header(&bytes[position..])?;
+            let data_offset = position + header_size;
+            let data_size = data_size(&header)?;
+            if data_size > bytes.len().saturating_sub(data_offset) {
+                return Err(fits_error(format!(
+                    "HDU {} requires {} bytes of data, but only {} are available.",
+                    hdus.len(),
+                    data_size,
+                    bytes.len().saturating_sub(data_offset)
+                )));
+            }
+            debug!(
+                "Found FITS HDU {} with {} data bytes",
+                hdus.len(),
+                data_size
+            );
+            hdus.push(FitsHdu {
+                header,
+                data_offset,
+                data_size,
+            });
+            position = data_offset + round_up_to_block(data_size);
+        }
+        if hdus.is_empty() {
+            return Err(fits_error(format!(
+                "File is shorter than one {} byte header block.",
+                BLOCK_SIZE
+            )));
+        }
+        Ok(Self { bytes, hdus })
+    }
+
+    /// Returns the header and data units in the file.
+    pub fn hdus(&self) -> &[FitsHdu] {
+        &self.hdus
+    }
+
+    /// Returns the index of the first HDU holding an image, if any.
+    pub fn first_image_index(&self) -> Option<usize> {
+        self.hdus.iter().position(FitsHdu::is_image)
+    }
+
+    /// Reads the image in the HDU with the given index into a scalar volume.
+    ///
+    /// Two-dimensional images become volumes with a single voxel along the z-axis.
+    pub fn read_image(&self, index: usize) -> VortekResult<ScalarVolume> {
+        let hdu = self
+            .hdus
+            .get(index)
+            .ok_or_else(|| fits_error(format!("HDU {} does not exist.", index)))?;
+        if !hdu.is_image() {
+            return Err(fits_error(format!("HDU {} does not hold an image.", index)));
+        }
+        let header = &hdu.header;
+
+        let sizes = hdu.axis_sizes();
+        if sizes.len() > 3 && sizes[3..].iter().any(|&size| size > 1) {
+            return Err(fits_error(format!(
+                "Only images with up to 3 non-degenerate axes are supported (sizes were {:?}).",
+                sizes
+            )));
+        }
+        let mut dimensions = [1; 3];
+        for (dimension, &size) in dimensions.iter_mut().zip(&sizes) {
+            *dimension = size;
+        }
+
+        let bitpix = header.get_required_int("BITPIX")?;
+        let element_type = match bitpix {
+            8 => ElementType::U8,
+            16 => ElementType::I16,
+            32 => ElementType::I32,
+            64 => ElementType::I64,
+            -32 => ElementType::F32,
+            -64 => ElementType::F64,
+            _ => return Err(fits_error(format!("Unsupported BITPIX: {}", bitpix))),
+        };
+        let bscale = header.get_float("BSCALE").unwrap_or(1.0);
+        let bzero = header.get_float("BZERO").unwrap_or(0.0);
+        let blank = header.get_int("BLANK");
+
+        let image_size = dimensions
+            .iter()
+            .try_fold(element_type.size_in_bytes(), |product, &size| {
+                product.checked_mul(size)
+            })
+            .filter(|&image_size| image_size <= hdu.data_size)
+            .ok_or_else(|| {
+                fits_error(format!(
+                    "Image in HDU {} is larger than its {} bytes of data.",
+                    index, hdu.data_size
+                ))
+            })?;
+        let data = &self.bytes[hdu.data_offset..hdu.data_offset + image_size];
+
+        let voxels = if bitpix == 16 && bscale == 1.0 && bzero == 32768.0 && blank.is_none() {
+            // Unsigned 16-bit integers are conventionally stored with an offset
+            VoxelBuffer::U16(
+                data.chunks_exact(2)
+                    .map(|chunk| (i16::from_be_bytes([chunk[0], chunk[1]]) as u16) ^ 0x8000)
+                    .collect(),
+            )
+        } else {
+            let raw_values = super::decode_values(data, element_type, Endianness::Big)?;
+            // Blank values only apply to integer data
+            let blank = blank.filter(|_| bitpix > 0);
+            if bscale != 1.0 || bzero != 0.0 || blank.is_some() {
+                VoxelBuffer::F32(
+                    raw_values
+                        .iter_values()
+                        .map(|value| {
+                            if blank.is_some_and(|blank| value == blank as f32) {
+                                f32::NAN
+                            } else {
+                                (bzero + bscale * f64::from(value)) as f32
+                            }
+                        })
+                        .collect(),
+                )
+            } else {
+                raw_values
+            }
+        };
+
+        let mut spacing = [1.0; 3];
+        let mut origin = [0.0; 3];
+        let mut orientation = IDENTITY_ORIENTATION;
+        for axis in 0..3 {
+            let keyword_number = axis + 1;
+            let crpix = header
+                .get_float(&format!("CRPIX{}", keyword_number))
+                .unwrap_or(1.0);
+            let crval = header
+                .get_float(&format!("CRVAL{}", keyword_number))
+                .unwrap_or(0.0);
+            // Unusable increments fall back to unit spacing
+            let cdelt = header
+                .get_float(&format!("CDELT{}", keyword_number))
+                .filter(|cdelt| cdelt.is_finite() && *cdelt != 0.0)
+                .unwrap_or(1.0);
+            // Pixel indices in FITS are one-based
+            origin[axis] = (crval - (crpix - 1.0) * cdelt) as f32;
+            spacing[axis] = cdelt.abs() as f32;
+            orientation[axis][axis] = cdelt.signum() as f32;
+        }
+
+        let mut volume = ScalarVolume::new(dimensions, spacing, origin, voxels);
+        volume.set_orientation(orientation);
+        Ok(volume)
+    }
+}
+
+/// Parses the header starting at the beginning of the given bytes, and returns
+/// the header along with its size in bytes including padding.
+fn parse_header(bytes: &[u8]) -> VortekResult<(FitsHeader, usize)> {
+    let mut cards = Vec::new();
+    for (index, card) in bytes.chunks_exact(CARD_SIZE).enumerate() {
+        let card = std::str::from_utf8(card)
+            .ok()
+            .filter(|card| card.is_ascii())
+            .ok_or_else(|| fits_error("Header contains invalid characters.".to_string()))?;
+        let keyword = card[..8].trim_end();
+        if keyword == "END" {
+            return Ok((
+                FitsHeader { cards },
+                round_up_to_block((index + 1) * CARD_SIZE),
+            ));
+        }
+        if &card[8..10] == "= " {
+            cards.push((keyword.to_string(), FitsValue::parse(&card[10..])));
+        }
+    }
+    Err(fits_error("Header is missing END keyword.".to_string()))
+}
+
+/// Computes the size in bytes of the data described by the given header,
+/// excluding padding.
+fn data_size(header: &FitsHeader) -> VortekResult<usize> {
+    let bitpix = header.get_required_int("BITPIX")?;
+    let naxis = header.get_required_int("NAXIS")?;
+    if naxis == 0 {
+        return Ok(0);
+    }
+    let too_large = || fits_error("Data size is too large.".to_string());
+    let mut number_of_values: usize = 1;
+    for axis in 1..=naxis {
+        let size = header.get_required_int(&format!("NAXIS{}", axis))?.max(0);
+        number_of_values = usize::try_from(size)
+            .ok()
+            .and_then(|size| number_of_values.checked_mul(size))
+            .ok_or_else(too_large)?;
+    }
+    // Random groups and table extensions may have additional parameters
+    let pcount = header.get_int("PCOUNT").unwrap_or(0).max(0);
+    let gcount = header.get_int("GCOUNT").unwrap_or(1).max(0);
+    usize::try_from(pcount)
+        .ok()
+        .and_then(|pcount| pcount.checked_add(number_of_values))
+        .and_then(|size| size.checked_mul(usize::try_from(gcount).ok()?))
+        .and_then(|size| size.checked_mul(bitpix.unsigned_abs() as usize / 8))
+        .ok_or_else(too_large)
+}
+
+fn round_up_to_block(size: usize) -> usize {
+    size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE
+}
+
+fn fits_error(message: String) -> VortekError {
+    VortekError::DataIOError(DataIOError::from_string(format!(
+        "Invalid FITS file: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header_block(cards: &[&str]) -> Vec<u8> {
+        let mut bytes: Vec<u8> = cards
+            .iter()
+            .chain(["END"].iter())
+            .flat_map(|card| format!("{:<80}", card).into_bytes())
+            .collect();
+        bytes.resize(round_up_to_block(bytes.len()), b' ');
+        bytes
+    }
+
+    fn fits_bytes(cards: &[&str], data: &[u8]) -> Vec<u8> {
+        let mut bytes = header_block(cards);
+        bytes.extend_from_slice(data);
+        bytes.resize(round_up_to_block(bytes.len()), 0);
+        bytes
+    }
+
+    const CUBE_CARDS: [&str; 7] = [
+        "SIMPLE  =                    T",
+        "BITPIX  =                  -32",
+        "NAXIS   =                    3",
+        "NAXIS1  =                    3",
+        "NAXIS2  =                    2",
+        "NAXIS3  =                    2",
+        "CDELT3  =                  0.5",
+    ];
+
+    #[test]
+    fn reads_image_cube() {
+        let values: Vec<f32> = (0..12).map(|value| value as f32).collect();
+        let data: Vec<u8> = values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        let file = FitsFile::from_bytes(fits_bytes(&CUBE_CARDS, &data)).unwrap();
+        let volume = file.read_image(file.first_image_index().unwrap()).unwrap();
+        assert_eq!(volume.dimensions(), [3, 2, 2]);
+        assert_eq!(volume.spacing(), [1.0, 1.0, 0.5]);
+        assert_eq!(volume.voxels().to_f32(), values);
+    }
+
+    #[test]
+    fn invalid_increments_give_unit_spacing() {
+        let mut cards = CUBE_CARDS.to_vec();
+        cards[6] = "CDELT3  =                1E999";
+        cards.push("CDELT1  =                  0.0");
+        cards.push("CRPIX2  =                  2.0");
+        cards.push("CDELT2  =                 -0.0");
+        let file = FitsFile::from_bytes(fits_bytes(&cards, &[0; 48])).unwrap();
+        let volume = file.read_image(0).unwrap();
+        assert_eq!(volume.spacing(), [1.0; 3]);
+        assert_eq!(volume.origin(), [0.0, -1.0, 0.0]);
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let bytes = fits_bytes(&CUBE_CARDS, &[0; 48]);
+        assert!(FitsFile::from_bytes(bytes.clone()).is_ok());
+        // Header without END keyword, and header without the data
+        let header_length = header_block(&CUBE_CARDS).len();
+        for length in [0, 400, header_length] {
+            assert!(FitsFile::from_bytes(bytes[..length].to_vec()).is_err());
+        }
+        let mut header = bytes[..header_length].to_vec();
+        header[CUBE_CARDS.len() * CARD_SIZE..][..3].copy_from_slice(b"   ");
+        assert!(FitsFile::from_bytes(header).is_err());
+    }
+
+    #[test]
+    fn overflowing_size_is_an_error() {
+        let size = format!("NAXIS1  = {:>20}", i64::MAX);
+        let mut cards = CUBE_CARDS;
+        cards[3] = &size;
+        assert!(FitsFile::from_bytes(header_block(&cards)).is_err());
+    }
+
+    #[test]
+    fn image_larger_than_data_is_an_error() {
+        let mut cards = CUBE_CARDS.to_vec();
+        cards.push("GCOUNT  =                    0");
+        let file = FitsFile::from_bytes(header_block(&cards)).unwrap();
+        assert!(file.read_image(0).is_err());
+    }
+}
@@ -0,0 +1,688 @@
+//! Reading and writing MetaImage files.
+//!
+//! Both single-file (`.mha`) images and headers (`.mhd`) with separate
+//! uncompressed (`.raw`) or zlib compressed (`.zraw`) data files are supported.
+//! Images with one channel are read as scalar volumes, and images with three
+//! channels as vector volumes. See the format description at
+//! <https://itk.org/Wiki/ITK/MetaIO/Documentation>.
+
+use super::{DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VectorVolume, Volume, VoxelBuffer, VoxelType},
+};
+use flate2::{
+    read::{GzDecoder, ZlibDecoder},
+    write::ZlibEncoder,
+    Compression,
+};
+use log::debug;
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Parsed contents of a MetaImage header.
+#[derive(Clone, Debug)]
+pub struct MetaImageHeader {
+    /// Type of the stored values.
+    pub element_type: ElementType,
+    /// Number of voxels along each axis, with the fastest varying axis first.
+    pub dimensions: Vec<usize>,
+    /// Number of interleaved values stored for each voxel.
+    pub number_of_channels: usize,
+    /// Byte order of the stored values.
+    pub endianness: Endianness,
+    /// Whether the data is zlib compressed.
+    pub compressed: bool,
+    /// Distance between adjacent voxels along each axis.
+    pub element_spacing: Option<Vec<f64>>,
+    /// World space position of the first voxel.
+    pub offset: Option<Vec<f64>>,
+    /// Direction of each axis, stored consecutively.
+    pub transform_matrix: Option<Vec<f64>>,
+    /// Number of bytes to skip at the beginning of the data, or -1 for reading
+    /// the data from the end of the file.
+    pub header_size: i64,
+    /// Path of the separate data file, or `None` if the data follows the header.
+    pub data_file: Option<PathBuf>,
+    /// Fields not used for constructing the volume.
+    pub key_values: Vec<(String, String)>,
+}
+
+/// Reads the MetaImage file at the given path into a scalar or vector volume.
+///
+/// Headers referring to a separate data file have the data read from that file.
+pub fn read_metaimage<P: AsRef<Path>>(path: P) -> VortekResult<Volume> {
+    let path = path.as_ref();
+    let bytes = super::read_file(path)?;
+    let (header, data_start) = parse_header(&bytes)?;
+
+    let data = match header.data_file {
+        Some(ref data_file) => {
+            let data_path = path
+                .parent()
+                .map_or_else(|| data_file.clone(), |parent| parent.join(data_file));
+            debug!("Reading MetaImage data from {}", data_path.display());
+            read_data(&header, &super::read_file(&data_path)?)?
+        }
+        None => read_data(&header, &bytes[data_start..])?,
+    };
+
+    volume_from_header_and_data(&header, data)
+}
+
+/// Parses the MetaImage header at the beginning of the given bytes, and returns
+/// the header along with the index of the first byte after it.
+///
+/// The header ends with the `ElementDataFile` field.
+pub fn parse_header(bytes: &[u8]) -> VortekResult<(MetaImageHeader, usize)> {
+    let mut element_type = None;
+    let mut number_of_dimensions = None;
+    let mut dimensions = None;
+    let mut number_of_channels = 1;
+    let mut endianness = Endianness::Native;
+    let mut compressed = false;
+    let mut element_spacing = None;
+    let mut offset = None;
+    let mut transform_matrix = None;
+    let mut header_size = 0;
+    let mut data_file = None;
+    let mut key_values = Vec::new();
+
+    let mut position = 0;
+    let mut found_data_file = false;
+    while position < bytes.len() && !found_data_file {
+        let line_end = bytes[position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(bytes.len(), |offset| position + offset);
+        let line = std::str::from_utf8(&bytes[position..line_end])
+            .map_err(|_| metaimage_error("Header contains invalid UTF-8.".to_string()))?
+            .trim();
+        position = (line_end + 1).min(bytes.len());
+
+        if line.is_empty() {
+            continue;
+        }
+        let separator = line
+            .find('=')
+            .ok_or_else(|| metaimage_error(format!("Invalid header line: {}", line)))?;
+        let field = line[..separator].trim();
+        let value = line[separator + 1..].trim();
+
+        match field {
+            "ObjectType" => {
+                if value != "Image" {
+                    return Err(metaimage_error(format!(
+                        "Unsupported object type: {}",
+                        value
+                    )));
+                }
+            }
+            "NDims" => number_of_dimensions = Some(parse_number::<usize>(value)?),
+            "DimSize" => dimensions = Some(parse_numbers::<usize>(value)?),
+            "ElementType" => element_type = Some(parse_element_type(value)?),
+            "ElementNumberOfChannels" => number_of_channels = parse_number(value)?,
+            "BinaryDataByteOrderMSB" | "ElementByteOrderMSB" => {
+                endianness = if parse_bool(value)? {
+                    Endianness::Big
+                } else {
+                    Endianness::Little
+                }
+            }
+            "CompressedData" => compressed = parse_bool(value)?,
+            "ElementSpacing" => element_spacing = Some(parse_numbers(value)?),
+            "Offset" | "Position" | "Origin" => offset = Some(parse_numbers(value)?),
+            "TransformMatrix" | "Rotation" | "Orientation" => {
+                transform_matrix = Some(parse_numbers(value)?)
+            }
+            "HeaderSize" => header_size = parse_number(value)?,
+            "ElementDataFile" => {
+                found_data_file = true;
+                if value == "LOCAL" {
+                    continue;
+                }
+                if value.starts_with("LIST") || value.contains('%') {
+                    return Err(metaimage_error(
+                        "Multiple data files are not supported.".to_string(),
+                    ));
+                }
+                data_file = Some(PathBuf::from(value));
+            }
+            _ => key_values.push((field.to_string(), value.to_string())),
+        }
+    }
+
+    if !found_data_file {
+        return Err(metaimage_error("Missing ElementDataFile field.".into()));
+    }
+    let element_type =
+        element_type.ok_or_else(|| metaimage_error("Missing ElementType field.".into()))?;
+    let dimensions: Vec<usize> =
+        dimensions.ok_or_else(|| metaimage_error("Missing DimSize field.".into()))?;
+    let number_of_dimensions =
+        number_of_dimensions.ok_or_else(|| metaimage_error("Missing NDims field.".into()))?;
+    if dimensions.len() != number_of_dimensions {
+        return Err(metaimage_error(format!(
+            "Number of sizes ({}) does not match NDims ({}).",
+            dimensions.len(),
+            number_of_dimensions
+        )));
+    }
+    if header_size < -1 || (header_size == -1 && compressed) {
+        return Err(metaimage_error(format!(
+            "Invalid header size: {}",
+            header_size
+        )));
+    }
+
+    Ok((
+        MetaImageHeader {
+            element_type,
+            dimensions,
+            number_of_channels,
+            endianness,
+            compressed,
+            element_spacing,
+            offset,
+            transform_matrix,
+            header_size,
+            data_file,
+            key_values,
+        },
+        position,
+    ))
+}
+
+/// Decodes the data following the header, or in the separate data file, into a
+/// voxel buffer with interleaved channels.
+fn read_data(header: &MetaImageHeader, bytes: &[u8]) -> VortekResult<VoxelBuffer> {
+    let too_large = || {
+        metaimage_error(format!(
+            "Data with dimensions {:?} and {} channels is too large.",
+            header.dimensions, header.number_of_channels
+        ))
+    };
+    let number_of_values = header
+        .dimensions
+        .iter()
+        .try_fold(header.number_of_channels, |product, &size| {
+            product.checked_mul(size)
+        })
+        .ok_or_else(too_large)?;
+    let data_size = number_of_values
+        .checked_mul(header.element_type.size_in_bytes())
+        .ok_or_else(too_large)?;
+
+    let mut bytes = if header.header_size == -1 {
+        bytes
+            .len()
+            .checked_sub(data_size)
+            .map(|start| &bytes[start..])
+            .ok_or_else(|| insufficient_data_error(bytes.len(), data_size))?
+    } else {
+        let header_size = header.header_size as usize;
+        if header_size > bytes.len() {
+            return Err(insufficient_data_error(bytes.len(), header_size));
+        }
+        &bytes[header_size..]
+    };
+
+    let decompressed;
+    if header.compressed {
+        let mut decoded = Vec::new();
+        // Both zlib and gzip streams are accepted, like in the reference implementation
+        let result = if bytes.starts_with(&[0x1f, 0x8b]) {
+            GzDecoder::new(bytes).read_to_end(&mut decoded)
+        } else {
+            ZlibDecoder::new(bytes).read_to_end(&mut decoded)
+        };
+        result.map_err(|err| {
+            VortekError::DataIOError(DataIOError::from_error(
+                "Could not decompress MetaImage data: ",
+                err,
+            ))
+        })?;
+        decompressed = decoded;
+        bytes = &decompressed;
+    }
+
+    if bytes.len() < data_size {
+        return Err(insufficient_data_error(bytes.len(), data_size));
+    } else if bytes.len() > data_size {
+        debug!(
+            "Ignoring {} trailing MetaImage data bytes.",
+            bytes.len() - data_size
+        );
+    }
+    super::decode_values(&bytes[..data_size], header.element_type, header.endianness)
+}
+
+/// Creates a scalar or vector volume from the given header and decoded data.
+fn volume_from_header_and_data(
+    header: &MetaImageHeader,
+    data: VoxelBuffer,
+) -> VortekResult<Volume> {
+    let number_of_dimensions = header.dimensions.len();
+    if number_of_dimensions > 3 {
+        return Err(metaimage_error(format!(
+            "Only MetaImage files with up to 3 dimensions are supported (NDims was {}).",
+            number_of_dimensions
+        )));
+    }
+    let mut dimensions = [1; 3];
+    dimensions[..number_of_dimensions].copy_from_slice(&header.dimensions);
+
+    let mut spacing = [1.0; 3];
+    if let Some(ref element_spacing) = header.element_spacing {
+        for (spacing, &element_spacing) in spacing.iter_mut().zip(element_spacing) {
+            if element_spacing.is_finite() && element_spacing != 0.0 {
+                *spacing = element_spacing.abs() as f32;
+            }
+        }
+    }
+
+    let mut origin = [0.0; 3];
+    if let Some(ref offset) = header.offset {
+        for (component, &value) in origin.iter_mut().zip(offset) {
+            *component = value as f32;
+        }
+    }
+
+    let mut orientation = crate::volume::IDENTITY_ORIENTATION;
+    if let Some(ref transform_matrix) = header.transform_matrix {
+        if transform_matrix.len() != number_of_dimensions * number_of_dimensions {
+            return Err(metaimage_error(format!(
+                "TransformMatrix has {} elements, but {} are required.",
+                transform_matrix.len(),
+                number_of_dimensions * number_of_dimensions
+            )));
+        }
+        for (axis, direction) in orientation
+            .iter_mut()
+            .enumerate()
+            .take(number_of_dimensions)
+        {
+            let mut vector = [0.0; 3];
+            for (component, &value) in vector.iter_mut().zip(
+                &transform_matrix[axis * number_of_dimensions..(axis + 1) * number_of_dimensions],
+            ) {
+                *component = value as f32;
+            }
+            let norm = vector.iter().map(|c| c * c).sum::<f32>().sqrt();
+            if !(norm.is_finite() && norm > 0.0) {
+                return Err(metaimage_error(format!(
+                    "TransformMatrix row {} is not a valid direction: {:?}",
+                    axis, vector
+                )));
+            }
+            *direction = vector;
+        }
+    }
+    // Negative spacings are represented by flipping the axis direction
+    if let Some(ref element_spacing) = header.element_spacing {
+        for (direction, &element_spacing) in orientation.iter_mut().zip(element_spacing) {
+            if element_spacing < 0.0 {
+                for component in direction.iter_mut() {
+                    *component = -*component;
+                }
+            }
+        }
+    }
+
+    let create_volume = |voxels: VoxelBuffer| {
+        let mut volume = ScalarVolume::new(dimensions, spacing, origin, voxels);
+        volume.set_orientation(orientation);
+        volume
+    };
+
+    match header.number_of_channels {
+        1 => Ok(Volume::Scalar(create_volume(data))),
+        3 => {
+            let values = data.to_f32();
+            let component = |dim: usize| {
+                create_volume(
+                    values
+                        .iter()
+                        .skip(dim)
+                        .step_by(3)
+                        .cloned()
+                        .collect::<VoxelBuffer>(),
+                )
+            };
+            Ok(Volume::Vector(VectorVolume::new(
+                component(0),
+                component(1),
+                component(2),
+            )))
+        }
+        number_of_channels => Err(metaimage_error(format!(
+            "Unsupported number of channels: {} (expected 1 or 3)",
+            number_of_channels
+        ))),
+    }
+}
+
+/// Writes the given volume to a MetaImage file at the given path, optionally
+/// compressing the data with zlib.
+///
+/// If the path has the `.mhd` extension, the data is written to a separate
+/// `.raw` or `.zraw` file in the same directory. Vector volumes are written
+/// as three-channel images.
+pub fn write_metaimage<P: AsRef<Path>>(
+    path: P,
+    volume: &Volume,
+    compressed: bool,
+) -> VortekResult<()> {
+    let path = path.as_ref();
+    let separate = path.extension().and_then(|extension| extension.to_str()) == Some("mhd");
+
+    let data = encode_data(volume, compressed)?;
+
+    let data_path = if separate {
+        Some(path.with_extension(if compressed { "zraw" } else { "raw" }))
+    } else {
+        None
+    };
+
+    let mut contents = create_header(
+        volume,
+        compressed.then_some(data.len()),
+        data_path
+            .as_ref()
+            .and_then(|data_path| data_path.file_name())
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or("LOCAL"),
+    )
+    .into_bytes();
+
+    match data_path {
+        Some(data_path) => super::write_file(&data_path, &data)?,
+        None => contents.extend_from_slice(&data),
+    }
+    super::write_file(path, &contents)
+}
+
+/// Creates the MetaImage header text describing the given volume.
+fn create_header(volume: &Volume, compressed_size: Option<usize>, data_file: &str) -> String {
+    let geometry = match volume {
+        Volume::Scalar(volume) => volume,
+        Volume::Vector(volume) => volume.geometry(),
+    };
+    let element_type = match volume {
+        Volume::Scalar(volume) => match volume.voxel_type() {
+            VoxelType::U8 => "MET_UCHAR",
+            VoxelType::U16 => "MET_USHORT",
+            VoxelType::I16 => "MET_SHORT",
+            VoxelType::F32 => "MET_FLOAT",
+        },
+        Volume::Vector(_) => "MET_FLOAT",
+    };
+    let format_numbers = |numbers: &[f32]| {
+        numbers
+            .iter()
+            .map(f32::to_string)
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    let [nx, ny, nz] = geometry.dimensions();
+
+    let mut header = format!(
+        "ObjectType = Image\n\
+         NDims = 3\n\
+         BinaryData = True\n\
+         BinaryDataByteOrderMSB = {}\n\
+         CompressedData = {}\n",
+        if cfg!(target_endian = "little") {
+            "False"
+        } else {
+            "True"
+        },
+        if compressed_size.is_some() {
+            "True"
+        } else {
+            "False"
+        }
+    );
+    if let Some(compressed_size) = compressed_size {
+        header.push_str(&format!("CompressedDataSize = {}\n", compressed_size));
+    }
+    header.push_str(&format!(
+        "TransformMatrix = {}\n\
+         Offset = {}\n\
+         ElementSpacing = {}\n\
+         DimSize = {} {} {}\n\
+         ElementNumberOfChannels = {}\n\
+         ElementType = {}\n\
+         ElementDataFile = {}\n",
+        format_numbers(&geometry.orientation().concat()),
+        format_numbers(&geometry.origin()),
+        format_numbers(&geometry.spacing()),
+        nx,
+        ny,
+        nz,
+        volume.number_of_components(),
+        element_type,
+        data_file
+    ));
+    header
+}
+
+/// Encodes the voxel values of the given volume, with interleaved vector
+/// components, and optionally compresses them.
+fn encode_data(volume: &Volume, compressed: bool) -> VortekResult<Vec<u8>> {
+    let vector_bytes;
+    let bytes = match volume {
+        Volume::Scalar(volume) => volume.voxels().as_bytes(),
+        Volume::Vector(volume) => {
+            let values: VoxelBuffer = volume.iter_vectors().flatten().collect();
+            vector_bytes = values.as_bytes().to_vec();
+            &vector_bytes
+        }
+    };
+    if compressed {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(bytes)
+            .and_then(|_| encoder.finish())
+            .map_err(|err| {
+                VortekError::DataIOError(DataIOError::from_error(
+                    "Could not compress MetaImage data: ",
+                    err,
+                ))
+            })
+    } else {
+        Ok(bytes.to_vec())
+    }
+}
+
+fn parse_element_type(value: &str) -> VortekResult<ElementType> {
+    Ok(match value {
+        "MET_CHAR" => ElementType::I8,
+        "MET_UCHAR" => ElementType::U8,
+        "MET_SHORT" => ElementType::I16,
+        "MET_USHORT" => ElementType::U16,
+        "MET_INT" | "MET_LONG" => ElementType::I32,
+        "MET_UINT" | "MET_ULONG" => ElementType::U32,
+        "MET_LONG_LONG" => ElementType::I64,
+        "MET_ULONG_LONG" => ElementType::U64,
+        "MET_FLOAT" => ElementType::F32,
+        "MET_DOUBLE" => ElementType::F64,
+        _ => return Err(metaimage_error(format!("Unsupported type: {}", value))),
+    })
+}
+
+fn parse_bool(value: &str) -> VortekResult<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(metaimage_error(format!("Invalid boolean: {}", value))),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> VortekResult<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| metaimage_error(format!("Invalid number: {}", value)))
+}
+
+fn parse_numbers<T: std::str::FromStr>(value: &str) -> VortekResult<Vec<T>> {
+    value.split_whitespace().map(parse_number).collect()
+}
+
+fn insufficient_data_error(available: usize, required: usize) -> VortekError {
+    metaimage_error(format!(
+        "Found {} bytes of data, but {} are required.",
+        available, required
+    ))
+}
+
+fn metaimage_error(message: String) -> VortekError {
+    VortekError::DataIOError(DataIOError::from_string(format!(
+        "Invalid MetaImage file: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::temporary_path;
+    use std::fs;
+
+    fn test_volume() -> ScalarVolume {
+        ScalarVolume::from_fn([3, 2, 4], [0.5, 1.0, 2.0], [1.0, 2.0, 3.0], |i, j, k| {
+            (i + 10 * j + 100 * k) as f32
+        })
+    }
+
+    #[test]
+    fn round_trip_with_local_and_separate_data() {
+        let volume = Volume::Scalar(test_volume());
+        for (compressed, file_name, data_extension) in [
+            (false, "local.mha", None),
+            (true, "compressed.mha", None),
+            (false, "separate.mhd", Some("raw")),
+            (true, "separate_compressed.mhd", Some("zraw")),
+        ] {
+            let path = temporary_path(file_name);
+            write_metaimage(&path, &volume, compressed).unwrap();
+            let result = read_metaimage(&path);
+            fs::remove_file(&path).unwrap();
+            if let Some(extension) = data_extension {
+                fs::remove_file(path.with_extension(extension)).unwrap();
+            }
+            match result.unwrap() {
+                Volume::Scalar(read_volume) => {
+                    assert_eq!(read_volume.dimensions(), [3, 2, 4]);
+                    assert_eq!(read_volume.spacing(), [0.5, 1.0, 2.0]);
+                    assert_eq!(read_volume.origin(), [1.0, 2.0, 3.0]);
+                    assert_eq!(read_volume.voxels(), test_volume().voxels());
+                }
+                Volume::Vector(_) => panic!("Expected scalar volume."),
+            }
+        }
+    }
+
+    #[test]
+    fn vector_round_trip() {
+        let volume = Volume::Vector(VectorVolume::new(
+            test_volume(),
+            test_volume(),
+            test_volume(),
+        ));
+        let path = temporary_path("vector.mha");
+        write_metaimage(&path, &volume, false).unwrap();
+        let result = read_metaimage(&path);
+        fs::remove_file(&path).unwrap();
+        match result.unwrap() {
+            Volume::Vector(read_volume) => {
+                for dim in 0..3 {
+                    assert_eq!(read_volume.component(dim).voxels(), test_volume().voxels());
+                }
+            }
+            Volume::Scalar(_) => panic!("Expected vector volume."),
+        }
+    }
+
+    #[test]
+    fn negative_spacings_flip_axes() {
+        for (transform_matrix, expected_orientation) in [
+            ("", [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]),
+            (
+                "TransformMatrix = 0 1 0 1 0 0 0 0 1\n",
+                [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+            ),
+        ] {
+            let mut bytes = format!(
+                "ObjectType = Image\nNDims = 3\nDimSize = 2 1 1\nElementSpacing = -0.5 2 1\n\
+                 {}ElementType = MET_UCHAR\nElementDataFile = LOCAL\n",
+                transform_matrix
+            )
+            .into_bytes();
+            let (header, data_start) = parse_header(&bytes).unwrap();
+            bytes.extend_from_slice(&[1, 2]);
+            let data = read_data(&header, &bytes[data_start..]).unwrap();
+            match volume_from_header_and_data(&header, data).unwrap() {
+                Volume::Scalar(volume) => {
+                    assert_eq!(volume.spacing(), [0.5, 2.0, 1.0]);
+                    assert_eq!(volume.orientation(), expected_orientation);
+                }
+                Volume::Vector(_) => panic!("Expected scalar volume."),
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_transform_matrix_is_an_error() {
+        for transform_matrix in [
+            "1 0 0 0 0 0 0 0 1",
+            "1 0 0 0 nan 0 0 0 1",
+            "1e300 0 0 0 1 0 0 0 1",
+        ] {
+            let mut bytes = format!(
+                "ObjectType = Image\nNDims = 3\nDimSize = 2 1 1\nTransformMatrix = {}\n\
+                 ElementType = MET_UCHAR\nElementDataFile = LOCAL\n",
+                transform_matrix
+            )
+            .into_bytes();
+            let (header, data_start) = parse_header(&bytes).unwrap();
+            bytes.extend_from_slice(&[1, 2]);
+            let data = read_data(&header, &bytes[data_start..]).unwrap();
+            assert!(volume_from_header_and_data(&header, data).is_err());
+        }
+    }
+
+    #[test]
+    fn truncated_header_is_an_error() {
+        let header = "ObjectType = Image\nNDims = 3\nDimSize = 2 2 2\n\
+                      ElementType = MET_UCHAR\nElementDataFile = LOCAL\n";
+        for length in [0, 20, 40, 70] {
+            assert!(parse_header(&header.as_bytes()[..length]).is_err());
+        }
+        assert!(parse_header(header.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        let mut bytes = b"ObjectType = Image\nNDims = 3\nDimSize = 2 2 2\n\
+                          ElementType = MET_UCHAR\nElementDataFile = LOCAL\n"
+            .to_vec();
+        let (header, data_start) = parse_header(&bytes).unwrap();
+        bytes.extend_from_slice(&[0; 7]);
+        assert!(read_data(&header, &bytes[data_start..]).is_err());
+    }
+
+    #[test]
+    fn overflowing_size_is_an_error() {
+        let header = format!(
+            "ObjectType = Image\nNDims = 3\nDimSize = {} 2 1\n\
+             ElementType = MET_FLOAT\nElementDataFile = LOCAL\n",
+            usize::MAX / 2
+        );
+        let (header, _) = parse_header(header.as_bytes()).unwrap();
+        assert!(read_data(&header, &[]).is_err());
+    }
+}
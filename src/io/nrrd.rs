@@ -0,0 +1,693 @@
+//! Reading and writing NRRD files.
+//!
+//! Both attached (`.nrrd`) and detached (`.nhdr`) headers are supported, with
+//! raw, gzip and ASCII encoded data. See the format specification at
+//! <http://teem.sourceforge.net/nrrd/format.html>.
+
+use super::{DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VoxelBuffer, VoxelType},
+};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use log::{debug, warn};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+/// Encoding of the data in a NRRD file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NrrdEncoding {
+    Raw,
+    Gzip,
+    Ascii,
+}
+
+/// Parsed contents of a NRRD header.
+#[derive(Clone, Debug)]
+pub struct NrrdHeader {
+    /// Type of the stored values.
+    pub element_type: ElementType,
+    /// Number of values along each axis, with the fastest varying axis first.
+    pub sizes: Vec<usize>,
+    /// Byte order of the stored values.
+    pub endianness: Endianness,
+    /// Encoding of the stored values.
+    pub encoding: NrrdEncoding,
+    /// Vector between adjacent samples for each axis, or `None` for non-spatial axes.
+    pub space_directions: Option<Vec<Option<Vec<f64>>>>,
+    /// World space position of the first sample.
+    pub space_origin: Option<Vec<f64>>,
+    /// Distance between adjacent samples along each axis.
+    pub spacings: Option<Vec<f64>>,
+    /// Path of the separate data file, if the header is detached.
+    pub data_file: Option<PathBuf>,
+    /// Number of lines to skip at the beginning of the data file.
+    pub line_skip: usize,
+    /// Number of bytes to skip at the beginning of the data, or -1 for reading
+    /// the data from the end of the file.
+    pub byte_skip: i64,
+    /// Additional key-value pairs.
+    pub key_values: Vec<(String, String)>,
+}
+
+const NRRD_MAGIC: &[u8] = b"NRRD000";
+
+/// Reads the NRRD file at the given path into a scalar volume.
+///
+/// Detached headers have their data read from the data file they refer to.
+pub fn read_nrrd<P: AsRef<Path>>(path: P) -> VortekResult<ScalarVolume> {
+    let path = path.as_ref();
+    let bytes = super::read_file(path)?;
+    let (header, data_start) = parse_header(&bytes)?;
+
+    let data = match header.data_file {
+        Some(ref data_file) => {
+            let data_path = path
+                .parent()
+                .map_or_else(|| data_file.clone(), |parent| parent.join(data_file));
+            debug!("Reading NRRD data from {}", data_path.display());
+            read_data(&header, &super::read_file(&data_path)?)?
+        }
+        None => read_data(&header, &bytes[data_start..])?,
+    };
+
+    volume_from_header_and_data(&header, data)
+}
+
+/// Parses the NRRD header at the beginning of the given bytes, and returns the
+/// header along with the index of the first byte after it.
+pub fn parse_header(bytes: &[u8]) -> VortekResult<(NrrdHeader, usize)> {
+    if !bytes.starts_with(NRRD_MAGIC) {
+        return Err(nrrd_error("Missing NRRD magic line.".to_string()));
+    }
+
+    let mut element_type = None;
+    let mut dimension = None;
+    let mut sizes = None;
+    let mut endianness = None;
+    let mut encoding = None;
+    let mut space_directions = None;
+    let mut space_origin = None;
+    let mut spacings = None;
+    let mut data_file = None;
+    let mut line_skip = 0;
+    let mut byte_skip = 0;
+    let mut key_values = Vec::new();
+
+    let mut position = 0;
+    let mut is_first_line = true;
+    while position < bytes.len() {
+        let line_end = bytes[position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(bytes.len(), |offset| position + offset);
+        let line = std::str::from_utf8(&bytes[position..line_end])
+            .map_err(|_| nrrd_error("Header contains invalid UTF-8.".to_string()))?
+            .trim_end_matches('\r');
+        position = (line_end + 1).min(bytes.len());
+
+        if is_first_line {
+            is_first_line = false;
+            continue;
+        }
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(separator) = line.find(":=") {
+            key_values.push((
+                line[..separator].to_string(),
+                line[separator + 2..].to_string(),
+            ));
+            continue;
+        }
+        let separator = line
+            .find(": ")
+            .ok_or_else(|| nrrd_error(format!("Invalid header line: {}", line)))?;
+        let field = line[..separator].trim().to_lowercase();
+        let value = line[separator + 2..].trim();
+
+        match field.as_str() {
+            "type" => element_type = Some(parse_element_type(value)?),
+            "dimension" => dimension = Some(parse_number::<usize>(value)?),
+            "sizes" => sizes = Some(parse_numbers::<usize>(value)?),
+            "endian" => {
+                endianness = Some(match value {
+                    "little" => Endianness::Little,
+                    "big" => Endianness::Big,
+                    _ => return Err(nrrd_error(format!("Invalid endianness: {}", value))),
+                })
+            }
+            "encoding" => {
+                encoding = Some(match value {
+                    "raw" => NrrdEncoding::Raw,
+                    "gzip" | "gz" => NrrdEncoding::Gzip,
+                    "ascii" | "text" | "txt" => NrrdEncoding::Ascii,
+                    _ => return Err(nrrd_error(format!("Unsupported encoding: {}", value))),
+                })
+            }
+            "space directions" => space_directions = Some(parse_space_directions(value)?),
+            "space origin" => space_origin = Some(parse_vector(value)?),
+            "spacings" => {
+                spacings = Some(
+                    value
+                        .split_whitespace()
+                        .map(|spacing| {
+                            if spacing.eq_ignore_ascii_case("nan") {
+                                Ok(f64::NAN)
+                            } else {
+                                parse_number(spacing)
+                            }
+                        })
+                        .collect::<VortekResult<Vec<_>>>()?,
+                )
+            }
+            "data file" | "datafile" => {
+                if value.starts_with("LIST") || value.contains('%') {
+                    return Err(nrrd_error(
+                        "Multiple detached data files are not supported.".to_string(),
+                    ));
+                }
+                data_file = Some(PathBuf::from(value))
+            }
+            "line skip" | "lineskip" => line_skip = parse_number(value)?,
+            "byte skip" | "byteskip" => byte_skip = parse_number(value)?,
+            _ => debug!("Ignoring NRRD field {}", field),
+        }
+    }
+
+    let element_type = element_type.ok_or_else(|| nrrd_error("Missing type field.".into()))?;
+    let sizes: Vec<usize> = sizes.ok_or_else(|| nrrd_error("Missing sizes field.".into()))?;
+    let encoding = encoding.ok_or_else(|| nrrd_error("Missing encoding field.".into()))?;
+
+    let dimension = dimension.ok_or_else(|| nrrd_error("Missing dimension field.".into()))?;
+    if sizes.len() != dimension {
+        return Err(nrrd_error(format!(
+            "Number of sizes ({}) does not match dimension ({}).",
+            sizes.len(),
+            dimension
+        )));
+    }
+    let endianness = match endianness {
+        Some(endianness) => endianness,
+        None if element_type.size_in_bytes() > 1 && encoding != NrrdEncoding::Ascii => {
+            return Err(nrrd_error("Missing endian field.".to_string()))
+        }
+        None => Endianness::Native,
+    };
+    if byte_skip < -1 || (byte_skip == -1 && encoding != NrrdEncoding::Raw) {
+        return Err(nrrd_error(format!("Invalid byte skip: {}", byte_skip)));
+    }
+
+    Ok((
+        NrrdHeader {
+            element_type,
+            sizes,
+            endianness,
+            encoding,
+            space_directions,
+            space_origin,
+            spacings,
+            data_file,
+            line_skip,
+            byte_skip,
+            key_values,
+        },
+        position,
+    ))
+}
+
+/// Decodes the data following the header, or in the detached data file,
+/// into a voxel buffer.
+fn read_data(header: &NrrdHeader, bytes: &[u8]) -> VortekResult<VoxelBuffer> {
+    let too_large = || nrrd_error(format!("Data with sizes {:?} is too large.", header.sizes));
+    let number_of_values = header
+        .sizes
+        .iter()
+        .try_fold(1_usize, |product, &size| product.checked_mul(size))
+        .ok_or_else(too_large)?;
+    let data_size = number_of_values
+        .checked_mul(header.element_type.size_in_bytes())
+        .ok_or_else(too_large)?;
+
+    let mut bytes = skip_lines(bytes, header.line_skip)?;
+
+    let decompressed;
+    if header.encoding == NrrdEncoding::Gzip {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut decoded)
+            .map_err(|err| {
+                VortekError::DataIOError(DataIOError::from_error(
+                    "Could not decompress NRRD data: ",
+                    err,
+                ))
+            })?;
+        decompressed = decoded;
+        bytes = &decompressed;
+    }
+
+    if header.encoding == NrrdEncoding::Raw && header.byte_skip == -1 {
+        bytes = bytes
+            .len()
+            .checked_sub(data_size)
+            .map(|start| &bytes[start..])
+            .ok_or_else(|| insufficient_data_error(bytes.len(), data_size))?;
+    } else {
+        let byte_skip = header.byte_skip as usize;
+        if byte_skip > bytes.len() {
+            return Err(insufficient_data_error(bytes.len(), byte_skip));
+        }
+        bytes = &bytes[byte_skip..];
+    }
+
+    if header.encoding == NrrdEncoding::Ascii {
+        let text = std::str::from_utf8(bytes)
+            .map_err(|_| nrrd_error("ASCII data contains invalid UTF-8.".to_string()))?;
+        let values = text
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .take(number_of_values)
+            .map(parse_number::<f64>)
+            .collect::<VortekResult<Vec<_>>>()?;
+        if values.len() != number_of_values {
+            return Err(nrrd_error(format!(
+                "Found {} ASCII values, but {} are required.",
+                values.len(),
+                number_of_values
+            )));
+        }
+        Ok(super::values_from_f64(values, header.element_type))
+    } else {
+        if bytes.len() < data_size {
+            return Err(insufficient_data_error(bytes.len(), data_size));
+        } else if bytes.len() > data_size {
+            debug!(
+                "Ignoring {} trailing NRRD data bytes.",
+                bytes.len() - data_size
+            );
+        }
+        super::decode_values(&bytes[..data_size], header.element_type, header.endianness)
+    }
+}
+
+/// Creates a scalar volume from the given header and decoded data.
+fn volume_from_header_and_data(
+    header: &NrrdHeader,
+    data: VoxelBuffer,
+) -> VortekResult<ScalarVolume> {
+    if header.sizes.len() > 3 {
+        return Err(nrrd_error(format!(
+            "Only NRRD files with up to 3 dimensions can be read as scalar volumes (dimension was {}).",
+            header.sizes.len()
+        )));
+    }
+    let mut dimensions = [1; 3];
+    dimensions[..header.sizes.len()].copy_from_slice(&header.sizes);
+
+    let mut spacing = [1.0; 3];
+    let mut orientation = crate::volume::IDENTITY_ORIENTATION;
+
+    // Negative spacings are represented by flipping the axis direction
+    if let Some(ref spacings) = header.spacings {
+        for (axis, &header_spacing) in spacings.iter().enumerate().take(3) {
+            if header_spacing.is_finite() && header_spacing != 0.0 {
+                spacing[axis] = header_spacing.abs() as f32;
+                if header_spacing < 0.0 {
+                    for component in orientation[axis].iter_mut() {
+                        *component = -*component;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(ref space_directions) = header.space_directions {
+        if space_directions.len() != header.sizes.len() {
+            return Err(nrrd_error(format!(
+                "Number of space directions ({}) does not match dimension ({}).",
+                space_directions.len(),
+                header.sizes.len()
+            )));
+        }
+        for (axis, direction) in space_directions.iter().enumerate() {
+            if let Some(direction) = direction {
+                if direction.len() > 3 {
+                    return Err(nrrd_error(format!(
+                        "Unsupported space dimension: {}",
+                        direction.len()
+                    )));
+                }
+                let mut vector = [0.0; 3];
+                for (component, &value) in vector.iter_mut().zip(direction) {
+                    *component = value as f32;
+                }
+                let norm = vector.iter().map(|c| c * c).sum::<f32>().sqrt();
+                if norm > 0.0 {
+                    spacing[axis] = norm;
+                    orientation[axis] = vector;
+                } else {
+                    warn!("Ignoring zero space direction for NRRD axis {}.", axis);
+                }
+            }
+        }
+    }
+
+    let mut origin = [0.0; 3];
+    if let Some(ref space_origin) = header.space_origin {
+        if space_origin.len() > 3 {
+            return Err(nrrd_error(format!(
+                "Unsupported space dimension: {}",
+                space_origin.len()
+            )));
+        }
+        for (component, &value) in origin.iter_mut().zip(space_origin) {
+            *component = value as f32;
+        }
+    }
+
+    let mut volume = ScalarVolume::new(dimensions, spacing, origin, data);
+    volume.set_orientation(orientation);
+    Ok(volume)
+}
+
+/// Writes the given scalar volume to a NRRD file at the given path, using the
+/// given encoding.
+///
+/// If the path has the `.nhdr` extension, a detached header is written and the
+/// data is written to a separate file in the same directory.
+pub fn write_nrrd<P: AsRef<Path>>(
+    path: P,
+    volume: &ScalarVolume,
+    encoding: NrrdEncoding,
+) -> VortekResult<()> {
+    let path = path.as_ref();
+    let detached = path.extension().and_then(|extension| extension.to_str()) == Some("nhdr");
+
+    let data = encode_data(volume, encoding)?;
+
+    let data_path = if detached {
+        let extension = match encoding {
+            NrrdEncoding::Raw => "raw",
+            NrrdEncoding::Gzip => "raw.gz",
+            NrrdEncoding::Ascii => "txt",
+        };
+        Some(path.with_extension(extension))
+    } else {
+        None
+    };
+
+    let mut contents = create_header(
+        volume,
+        encoding,
+        data_path
+            .as_ref()
+            .and_then(|data_path| data_path.file_name())
+            .and_then(|file_name| file_name.to_str()),
+    )
+    .into_bytes();
+
+    match data_path {
+        Some(data_path) => super::write_file(&data_path, &data)?,
+        None => contents.extend_from_slice(&data),
+    }
+    super::write_file(path, &contents)
+}
+
+/// Creates the NRRD header text describing the given volume.
+fn create_header(volume: &ScalarVolume, encoding: NrrdEncoding, data_file: Option<&str>) -> String {
+    let type_name = match volume.voxel_type() {
+        VoxelType::U8 => "uint8",
+        VoxelType::U16 => "uint16",
+        VoxelType::I16 => "int16",
+        VoxelType::F32 => "float",
+    };
+    let [nx, ny, nz] = volume.dimensions();
+    let spacing = volume.spacing();
+    let space_directions: Vec<String> = volume
+        .orientation()
+        .iter()
+        .zip(&spacing)
+        .map(|(direction, &spacing)| {
+            format_vector(&[
+                direction[0] * spacing,
+                direction[1] * spacing,
+                direction[2] * spacing,
+            ])
+        })
+        .collect();
+
+    let mut header = format!(
+        "NRRD0004\n\
+         # Complete NRRD file format specification at:\n\
+         # http://teem.sourceforge.net/nrrd/format.html\n\
+         type: {}\n\
+         dimension: 3\n\
+         space dimension: 3\n\
+         sizes: {} {} {}\n\
+         space directions: {}\n\
+         space origin: {}\n\
+         endian: {}\n\
+         encoding: {}\n",
+        type_name,
+        nx,
+        ny,
+        nz,
+        space_directions.join(" "),
+        format_vector(&volume.origin()),
+        if cfg!(target_endian = "little") {
+            "little"
+        } else {
+            "big"
+        },
+        match encoding {
+            NrrdEncoding::Raw => "raw",
+            NrrdEncoding::Gzip => "gzip",
+            NrrdEncoding::Ascii => "ascii",
+        }
+    );
+    if let Some(data_file) = data_file {
+        header.push_str(&format!("data file: {}\n", data_file));
+    } else {
+        header.push('\n');
+    }
+    header
+}
+
+/// Encodes the voxel values of the given volume with the given encoding.
+fn encode_data(volume: &ScalarVolume, encoding: NrrdEncoding) -> VortekResult<Vec<u8>> {
+    match encoding {
+        NrrdEncoding::Raw => Ok(volume.voxels().as_bytes().to_vec()),
+        NrrdEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(volume.voxels().as_bytes())
+                .and_then(|_| encoder.finish())
+                .map_err(|err| {
+                    VortekError::DataIOError(DataIOError::from_error(
+                        "Could not compress NRRD data: ",
+                        err,
+                    ))
+                })
+        }
+        NrrdEncoding::Ascii => {
+            let [nx, _, _] = volume.dimensions();
+            let mut text = String::new();
+            for (index, value) in volume.iter_values().enumerate() {
+                text.push_str(&value.to_string());
+                text.push(if (index + 1) % nx == 0 { '\n' } else { ' ' });
+            }
+            Ok(text.into_bytes())
+        }
+    }
+}
+
+fn parse_element_type(value: &str) -> VortekResult<ElementType> {
+    Ok(match value {
+        "signed char" | "int8" | "int8_t" => ElementType::I8,
+        "uchar" | "unsigned char" | "uint8" | "uint8_t" => ElementType::U8,
+        "short" | "short int" | "signed short" | "signed short int" | "int16" | "int16_t" => {
+            ElementType::I16
+        }
+        "ushort" | "unsigned short" | "unsigned short int" | "uint16" | "uint16_t" => {
+            ElementType::U16
+        }
+        "int" | "signed int" | "int32" | "int32_t" => ElementType::I32,
+        "uint" | "unsigned int" | "uint32" | "uint32_t" => ElementType::U32,
+        "longlong"
+        | "long long"
+        | "long long int"
+        | "signed long long"
+        | "signed long long int"
+        | "int64"
+        | "int64_t" => ElementType::I64,
+        "ulonglong" | "unsigned long long" | "unsigned long long int" | "uint64" | "uint64_t" => {
+            ElementType::U64
+        }
+        "float" => ElementType::F32,
+        "double" => ElementType::F64,
+        _ => return Err(nrrd_error(format!("Unsupported type: {}", value))),
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> VortekResult<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| nrrd_error(format!("Invalid number: {}", value)))
+}
+
+fn parse_numbers<T: std::str::FromStr>(value: &str) -> VortekResult<Vec<T>> {
+    value.split_whitespace().map(parse_number).collect()
+}
+
+/// Parses a vector on the form `(x,y,z)`.
+fn parse_vector(value: &str) -> VortekResult<Vec<f64>> {
+    let value = value.trim();
+    if !(value.starts_with('(') && value.ends_with(')')) {
+        return Err(nrrd_error(format!("Invalid vector: {}", value)));
+    }
+    value[1..value.len() - 1]
+        .split(',')
+        .map(parse_number)
+        .collect()
+}
+
+/// Parses a list of space direction vectors, where non-spatial axes are
+/// indicated by `none`.
+fn parse_space_directions(value: &str) -> VortekResult<Vec<Option<Vec<f64>>>> {
+    let mut directions = Vec::new();
+    let mut remaining = value.trim();
+    while !remaining.is_empty() {
+        if remaining.starts_with("none") {
+            directions.push(None);
+            remaining = remaining["none".len()..].trim_start();
+        } else {
+            let end = remaining
+                .find(')')
+                .ok_or_else(|| nrrd_error(format!("Invalid space directions: {}", value)))?;
+            directions.push(Some(parse_vector(&remaining[..=end])?));
+            remaining = remaining[end + 1..].trim_start();
+        }
+    }
+    Ok(directions)
+}
+
+/// Formats the given vector on the form `(x,y,z)`.
+fn format_vector(vector: &[f32; 3]) -> String {
+    format!("({},{},{})", vector[0], vector[1], vector[2])
+}
+
+/// Returns the given bytes without the given number of leading lines.
+fn skip_lines(bytes: &[u8], line_skip: usize) -> VortekResult<&[u8]> {
+    let mut remaining = bytes;
+    for _ in 0..line_skip {
+        let line_end = remaining
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .ok_or_else(|| nrrd_error("Data has fewer lines than the line skip.".to_string()))?;
+        remaining = &remaining[line_end + 1..];
+    }
+    Ok(remaining)
+}
+
+fn insufficient_data_error(available: usize, required: usize) -> VortekError {
+    nrrd_error(format!(
+        "Found {} bytes of data, but {} are required.",
+        available, required
+    ))
+}
+
+fn nrrd_error(message: String) -> VortekError {
+    VortekError::DataIOError(DataIOError::from_string(format!(
+        "Invalid NRRD file: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::temporary_path;
+    use std::fs;
+
+    fn test_volume() -> ScalarVolume {
+        ScalarVolume::from_fn([3, 2, 4], [0.5, 1.0, 2.0], [1.0, 2.0, 3.0], |i, j, k| {
+            (i + 10 * j + 100 * k) as f32
+        })
+    }
+
+    #[test]
+    fn round_trip_with_all_encodings() {
+        let volume = test_volume();
+        for (encoding, file_name) in [
+            (NrrdEncoding::Raw, "raw.nrrd"),
+            (NrrdEncoding::Gzip, "gzip.nrrd"),
+            (NrrdEncoding::Ascii, "ascii.nrrd"),
+            (NrrdEncoding::Raw, "detached.nhdr"),
+        ] {
+            let path = temporary_path(file_name);
+            write_nrrd(&path, &volume, encoding).unwrap();
+            let result = read_nrrd(&path);
+            fs::remove_file(&path).unwrap();
+            if file_name.ends_with(".nhdr") {
+                fs::remove_file(path.with_extension("raw")).unwrap();
+            }
+            let read_volume = result.unwrap();
+            assert_eq!(read_volume.dimensions(), volume.dimensions());
+            assert_eq!(read_volume.spacing(), volume.spacing());
+            assert_eq!(read_volume.origin(), volume.origin());
+            assert_eq!(read_volume.voxels(), volume.voxels());
+        }
+    }
+
+    #[test]
+    fn negative_spacings_flip_axes() {
+        let mut bytes =
+            b"NRRD0004\ntype: uint8\ndimension: 3\nsizes: 2 1 1\nspacings: -0.5 2 1\nencoding: raw\n\n"
+                .to_vec();
+        let (header, data_start) = parse_header(&bytes).unwrap();
+        bytes.extend_from_slice(&[1, 2]);
+        let data = read_data(&header, &bytes[data_start..]).unwrap();
+        let volume = volume_from_header_and_data(&header, data).unwrap();
+        assert_eq!(volume.spacing(), [0.5, 2.0, 1.0]);
+        assert_eq!(
+            volume.orientation(),
+            [[-1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]]
+        );
+    }
+
+    #[test]
+    fn truncated_header_is_an_error() {
+        let header =
+            "NRRD0004\ntype: float\ndimension: 3\nsizes: 3 2 4\nendian: little\nencoding: raw\n\n";
+        for length in [0, 4, 10, 30] {
+            assert!(parse_header(&header.as_bytes()[..length]).is_err());
+        }
+        assert!(parse_header(header.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn truncated_data_is_an_error() {
+        let mut bytes =
+            b"NRRD0004\ntype: uint8\ndimension: 3\nsizes: 2 2 2\nencoding: raw\n\n".to_vec();
+        let (header, data_start) = parse_header(&bytes).unwrap();
+        bytes.extend_from_slice(&[0; 7]);
+        assert!(read_data(&header, &bytes[data_start..]).is_err());
+    }
+
+    #[test]
+    fn overflowing_size_is_an_error() {
+        let header = format!(
+            "NRRD0004\ntype: float\ndimension: 3\nsizes: {} 2 1\nendian: little\nencoding: raw\n\n",
+            usize::MAX / 2
+        );
+        let (header, _) = parse_header(header.as_bytes()).unwrap();
+        assert!(read_data(&header, &[]).is_err());
+    }
+}
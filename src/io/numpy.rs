@@ -0,0 +1,611 @@
+//! Reading and writing NumPy `.npy` and `.npz` files.
+//!
+//! The first three array axes are taken to be the x-, y- and z-axis of the
+//! volume. Arrays of rank 3 map to scalar volumes, and arrays of rank 4 with
+//! a trailing dimension of 3 map to vector volumes. See the format
+//! specification at <https://numpy.org/doc/stable/reference/generated/numpy.lib.format.html>.
+
+use super::{raw, DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VectorVolume, Volume, VoxelBuffer, VoxelType},
+};
+use log::debug;
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, Write},
+    path::Path,
+};
+use zip::{
+    read::ZipFile, result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter,
+};
+
+/// Parsed contents of a `.npy` header.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpyHeader {
+    /// Type of the stored values.
+    pub element_type: ElementType,
+    /// Byte order of the stored values.
+    pub endianness: Endianness,
+    /// Whether the array is stored in Fortran (column-major) order.
+    pub fortran_order: bool,
+    /// Number of values along each array axis.
+    pub shape: Vec<usize>,
+}
+
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+const NPY_ALIGNMENT: usize = 64;
+
+/// Reads the `.npy` file at the given path into a volume.
+pub fn read_npy<P: AsRef<Path>>(path: P) -> VortekResult<Volume> {
+    read_npy_from_bytes(&super::read_file(path)?)
+}
+
+/// Decodes the given `.npy` file contents into a volume.
+pub fn read_npy_from_bytes(bytes: &[u8]) -> VortekResult<Volume> {
+    let (header, data_start) = parse_header(bytes)?;
+    let too_large = || npy_error(format!("Array with shape {:?} is too large.", header.shape));
+    let number_of_values = header
+        .shape
+        .iter()
+        .try_fold(1_usize, |product, &size| product.checked_mul(size))
+        .ok_or_else(too_large)?;
+    let data_size = number_of_values
+        .checked_mul(header.element_type.size_in_bytes())
+        .ok_or_else(too_large)?;
+    let data = data_start
+        .checked_add(data_size)
+        .and_then(|data_end| bytes.get(data_start..data_end))
+        .ok_or_else(|| {
+            npy_error(format!(
+                "Found {} bytes of data, but {} are required.",
+                bytes.len().saturating_sub(data_start),
+                data_size
+            ))
+        })?;
+    let voxels = super::decode_values(data, header.element_type, header.endianness)?;
+    volume_from_array(&header.shape, header.fortran_order, voxels)
+}
+
+/// Reads all arrays in the `.npz` archive at the given path that represent
+/// volumes, and returns them along with their names.
+pub fn read_npz<P: AsRef<Path>>(path: P) -> VortekResult<Vec<(String, Volume)>> {
+    read_npz_from_reader(open_npz_file(path.as_ref())?)
+}
+
+/// Reads all arrays in the given `.npz` archive that represent volumes, and
+/// returns them along with their names.
+///
+/// Other arrays, like metadata with fewer dimensions or an unsupported data
+/// type, are skipped.
+pub fn read_npz_from_reader<R: Read + Seek>(reader: R) -> VortekResult<Vec<(String, Volume)>> {
+    let mut archive = open_npz_archive(reader)?;
+    let mut volumes = Vec::with_capacity(archive.len());
+    for index in 0..archive.len() {
+        let entry = archive.by_index(index).map_err(|err| {
+            VortekError::DataIOError(DataIOError::from_error(
+                "Could not read NPZ archive entry: ",
+                err,
+            ))
+        })?;
+        let name = entry.name().trim_end_matches(".npy").to_string();
+        let bytes = decompress_npz_entry(entry, &name)?;
+        match parse_header(&bytes) {
+            Ok((header, _)) if is_volume_shape(&header.shape) => {}
+            _ => {
+                debug!("Skipping NPZ array {}, which is not a volume.", name);
+                continue;
+            }
+        }
+        let volume = decode_npz_array(&bytes, &name)?;
+        volumes.push((name, volume));
+    }
+    Ok(volumes)
+}
+
+/// Reads the array with the given name from the `.npz` archive at the given path.
+///
+/// Only the archive entry holding the array is decompressed.
+pub fn read_npz_array<P: AsRef<Path>>(path: P, name: &str) -> VortekResult<Volume> {
+    let mut archive = open_npz_archive(open_npz_file(path.as_ref())?)?;
+    let entry = archive
+        .by_name(&format!("{}.npy", name))
+        .map_err(|err| match err {
+            ZipError::FileNotFound => VortekError::DataIOError(DataIOError::from_string(format!(
+                "Array {} not present in NPZ archive.",
+                name
+            ))),
+            err => VortekError::DataIOError(DataIOError::from_error(
+                "Could not read NPZ archive entry: ",
+                err,
+            )),
+        })?;
+    decode_npz_array(&decompress_npz_entry(entry, name)?, name)
+}
+
+fn open_npz_file(path: &Path) -> VortekResult<BufReader<File>> {
+    let file = File::open(path).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not read {}: {}",
+            path.display(),
+            err
+        )))
+    })?;
+    Ok(BufReader::new(file))
+}
+
+fn open_npz_archive<R: Read + Seek>(reader: R) -> VortekResult<ZipArchive<R>> {
+    ZipArchive::new(reader).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_error("Could not open NPZ archive: ", err))
+    })
+}
+
+/// Decompresses the given archive entry holding the array with the given name.
+fn decompress_npz_entry(mut entry: ZipFile, name: &str) -> VortekResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    entry.read_to_end(&mut bytes).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not decompress NPZ array {}: {}",
+            name, err
+        )))
+    })?;
+    Ok(bytes)
+}
+
+/// Decodes the `.npy` contents of the array with the given name into a volume.
+fn decode_npz_array(bytes: &[u8], name: &str) -> VortekResult<Volume> {
+    read_npy_from_bytes(bytes).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not read NPZ array {}: {}",
+            name, err
+        )))
+    })
+}
+
+/// Writes the given volume to a `.npy` file at the given path.
+pub fn write_npy<P: AsRef<Path>>(path: P, volume: &Volume) -> VortekResult<()> {
+    super::write_file(path, &encode_npy(volume))
+}
+
+/// Writes the given named volumes as arrays in a `.npz` archive at the given path,
+/// optionally compressing them with deflate.
+pub fn write_npz<P: AsRef<Path>>(
+    path: P,
+    volumes: &[(&str, &Volume)],
+    compressed: bool,
+) -> VortekResult<()> {
+    let path = path.as_ref();
+    let map_write_err = |err: &dyn std::fmt::Display| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not write {}: {}",
+            path.display(),
+            err
+        )))
+    };
+    let file = File::create(path).map_err(|err| map_write_err(&err))?;
+    let mut writer = ZipWriter::new(BufWriter::new(file));
+    let options = FileOptions::default().compression_method(if compressed {
+        CompressionMethod::Deflated
+    } else {
+        CompressionMethod::Stored
+    });
+    for (name, volume) in volumes {
+        writer
+            .start_file(format!("{}.npy", name), options)
+            .map_err(|err| map_write_err(&err))?;
+        writer
+            .write_all(&encode_npy(volume))
+            .map_err(|err| map_write_err(&err))?;
+    }
+    writer.finish().map_err(|err| map_write_err(&err))?;
+    Ok(())
+}
+
+/// Parses the `.npy` header at the beginning of the given bytes, and returns
+/// the header along with the index of the first data byte.
+pub fn parse_header(bytes: &[u8]) -> VortekResult<(NpyHeader, usize)> {
+    if !bytes.starts_with(NPY_MAGIC) || bytes.len() < NPY_MAGIC.len() + 4 {
+        return Err(npy_error("Missing NPY magic string.".to_string()));
+    }
+    let major_version = bytes[NPY_MAGIC.len()];
+    let (header_length, header_start) = match major_version {
+        1 => (
+            u16::from_le_bytes([bytes[8], bytes[9]]) as usize,
+            NPY_MAGIC.len() + 4,
+        ),
+        2 | 3 if bytes.len() >= 12 => (
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+            NPY_MAGIC.len() + 6,
+        ),
+        _ => {
+            return Err(npy_error(format!(
+                "Unsupported format version: {}",
+                major_version
+            )))
+        }
+    };
+    let header_end = header_start + header_length;
+    let header_text = bytes
+        .get(header_start..header_end)
+        .ok_or_else(|| npy_error("Truncated header.".to_string()))?;
+    let header_text = if major_version == 3 {
+        std::str::from_utf8(header_text).ok()
+    } else {
+        // Versions 1 and 2 use Latin-1, but valid headers only contain ASCII
+        std::str::from_utf8(header_text)
+            .ok()
+            .filter(|text| text.is_ascii())
+    }
+    .ok_or_else(|| npy_error("Header contains invalid characters.".to_string()))?;
+
+    Ok((parse_header_dict(header_text)?, header_end))
+}
+
+/// Parses the Python dictionary literal describing the array.
+fn parse_header_dict(text: &str) -> VortekResult<NpyHeader> {
+    let descr = dict_value(text, "descr")?;
+    let descr = descr
+        .strip_prefix('\'')
+        .or_else(|| descr.strip_prefix('"'))
+        .and_then(|descr| descr.get(..descr.len().saturating_sub(1)))
+        .ok_or_else(|| npy_error(format!("Unsupported descr: {}", descr)))?;
+    let (element_type, endianness) = parse_descr(descr)?;
+
+    let fortran_order = match dict_value(text, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        value => return Err(npy_error(format!("Invalid fortran_order: {}", value))),
+    };
+
+    let shape = dict_value(text, "shape")?;
+    let shape = shape
+        .strip_prefix('(')
+        .and_then(|shape| shape.strip_suffix(')'))
+        .ok_or_else(|| npy_error(format!("Invalid shape: {}", shape)))?
+        .split(',')
+        .map(str::trim)
+        .filter(|size| !size.is_empty())
+        .map(|size| {
+            size.trim_end_matches('L')
+                .parse()
+                .map_err(|_| npy_error(format!("Invalid shape size: {}", size)))
+        })
+        .collect::<VortekResult<Vec<usize>>>()?;
+
+    Ok(NpyHeader {
+        element_type,
+        endianness,
+        fortran_order,
+        shape,
+    })
+}
+
+/// Finds the textual value of the given key in the header dictionary.
+fn dict_value<'a>(text: &'a str, key: &str) -> VortekResult<&'a str> {
+    let missing_key_error = || npy_error(format!("Header is missing key {}.", key));
+    let key_start = text
+        .find(&format!("'{}'", key))
+        .or_else(|| text.find(&format!("\"{}\"", key)))
+        .ok_or_else(missing_key_error)?;
+    let after_key = &text[key_start + key.len() + 2..];
+    let value = after_key
+        .trim_start()
+        .strip_prefix(':')
+        .ok_or_else(missing_key_error)?
+        .trim_start();
+    let value_end = if value.starts_with('(') {
+        value.find(')').map(|end| end + 1)
+    } else if value.starts_with('\'') || value.starts_with('"') {
+        value[1..].find(&value[..1]).map(|end| end + 2)
+    } else {
+        value.find([',', '}'])
+    }
+    .ok_or_else(missing_key_error)?;
+    Ok(value[..value_end].trim())
+}
+
+/// Parses a NumPy type description string like `<f4`.
+fn parse_descr(descr: &str) -> VortekResult<(ElementType, Endianness)> {
+    let unsupported_error = || npy_error(format!("Unsupported data type: {}", descr));
+    let mut chars = descr.chars();
+    let endianness = match chars.next().ok_or_else(unsupported_error)? {
+        '<' => Endianness::Little,
+        '>' => Endianness::Big,
+        '|' | '=' => Endianness::Native,
+        _ => return Err(unsupported_error()),
+    };
+    let element_type = match chars.as_str() {
+        "u1" | "b1" => ElementType::U8,
+        "i1" => ElementType::I8,
+        "u2" => ElementType::U16,
+        "i2" => ElementType::I16,
+        "u4" => ElementType::U32,
+        "i4" => ElementType::I32,
+        "u8" => ElementType::U64,
+        "i8" => ElementType::I64,
+        "f4" => ElementType::F32,
+        "f8" => ElementType::F64,
+        _ => return Err(unsupported_error()),
+    };
+    Ok((element_type, endianness))
+}
+
+/// Whether arrays with the given shape can be read as volumes.
+fn is_volume_shape(shape: &[usize]) -> bool {
+    matches!(*shape, [_, _, _] | [_, _, _, 3])
+}
+
+/// Creates a volume from the given array values with the given shape and order.
+fn volume_from_array(
+    shape: &[usize],
+    fortran_order: bool,
+    voxels: VoxelBuffer,
+) -> VortekResult<Volume> {
+    match *shape {
+        [nx, ny, nz] => {
+            let voxels = if fortran_order {
+                voxels
+            } else {
+                raw::c_to_fortran_order(voxels, [nx, ny, nz])
+            };
+            Ok(Volume::Scalar(ScalarVolume::with_unit_spacing(
+                [nx, ny, nz],
+                voxels,
+            )))
+        }
+        [nx, ny, nz, 3] => {
+            let number_of_voxels = nx * ny * nz;
+            let values = voxels.to_f32();
+            let component = |dim: usize| {
+                let component_values: Vec<f32> = if fortran_order {
+                    values[dim * number_of_voxels..(dim + 1) * number_of_voxels].to_vec()
+                } else {
+                    values.iter().skip(dim).step_by(3).cloned().collect()
+                };
+                let voxels = VoxelBuffer::F32(component_values);
+                ScalarVolume::with_unit_spacing(
+                    [nx, ny, nz],
+                    if fortran_order {
+                        voxels
+                    } else {
+                        raw::c_to_fortran_order(voxels, [nx, ny, nz])
+                    },
+                )
+            };
+            Ok(Volume::Vector(VectorVolume::new(
+                component(0),
+                component(1),
+                component(2),
+            )))
+        }
+        _ => Err(npy_error(format!(
+            "Only arrays with shape (nx, ny, nz) or (nx, ny, nz, 3) are supported (shape was {:?}).",
+            shape
+        ))),
+    }
+}
+
+/// Encodes the given volume as `.npy` file contents, using Fortran order.
+fn encode_npy(volume: &Volume) -> Vec<u8> {
+    let [nx, ny, nz] = volume.dimensions();
+    let (voxel_type, shape, data) = match volume {
+        Volume::Scalar(volume) => (
+            volume.voxel_type(),
+            format!("({}, {}, {})", nx, ny, nz),
+            super::encode_values(volume.voxels(), Endianness::Little),
+        ),
+        Volume::Vector(volume) => (
+            VoxelType::F32,
+            format!("({}, {}, {}, 3)", nx, ny, nz),
+            (0..3)
+                .flat_map(|dim| {
+                    super::encode_values(
+                        &VoxelBuffer::F32(volume.component(dim).voxels().to_f32()),
+                        Endianness::Little,
+                    )
+                })
+                .collect(),
+        ),
+    };
+    let descr = match voxel_type {
+        VoxelType::U8 => "|u1",
+        VoxelType::U16 => "<u2",
+        VoxelType::I16 => "<i2",
+        VoxelType::F32 => "<f4",
+    };
+
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': True, 'shape': {}, }}",
+        descr, shape
+    );
+    // The header is padded with spaces and terminated with a newline so that
+    // the data starts at an aligned offset
+    let unpadded_length = NPY_MAGIC.len() + 4 + header.len() + 1;
+    let padding = (NPY_ALIGNMENT - unpadded_length % NPY_ALIGNMENT) % NPY_ALIGNMENT;
+    header.extend(std::iter::repeat_n(' ', padding));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(NPY_MAGIC.len() + 4 + header.len() + data.len());
+    bytes.extend_from_slice(NPY_MAGIC);
+    bytes.extend_from_slice(&[1, 0]);
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(&data);
+    bytes
+}
+
+fn npy_error(message: String) -> VortekError {
+    VortekError::DataIOError(DataIOError::from_string(format!(
+        "Invalid NPY file: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{read_volume, temporary_path};
+    use std::{fs, io};
+
+    fn test_volume() -> ScalarVolume {
+        ScalarVolume::from_fn([3, 2, 4], [1.0; 3], [0.0; 3], |i, j, k| {
+            (i + 10 * j + 100 * k) as f32
+        })
+    }
+
+    fn npy_bytes(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    fn npz_archive(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(io::Cursor::new(Vec::new()));
+        for (name, bytes) in entries {
+            writer.start_file(*name, FileOptions::default()).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn scalar(volume: Volume) -> ScalarVolume {
+        match volume {
+            Volume::Scalar(volume) => volume,
+            Volume::Vector(_) => panic!("Expected scalar volume."),
+        }
+    }
+
+    #[test]
+    fn npy_round_trip() {
+        let volume = Volume::Scalar(test_volume());
+        let path = temporary_path("round_trip.npy");
+        write_npy(&path, &volume).unwrap();
+        let result = read_npy(&path);
+        fs::remove_file(&path).unwrap();
+        let read_volume = scalar(result.unwrap());
+        assert_eq!(read_volume.dimensions(), [3, 2, 4]);
+        assert_eq!(read_volume.voxels(), test_volume().voxels());
+    }
+
+    #[test]
+    fn npz_round_trip() {
+        let scalar_volume = Volume::Scalar(test_volume());
+        let vector_volume = Volume::Vector(VectorVolume::new(
+            test_volume(),
+            test_volume(),
+            test_volume(),
+        ));
+        let path = temporary_path("round_trip.npz");
+        write_npz(
+            &path,
+            &[("scalar", &scalar_volume), ("vector", &vector_volume)],
+            true,
+        )
+        .unwrap();
+        let volumes = read_npz(&path).unwrap();
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(volumes[0].0, "scalar");
+        assert_eq!(
+            scalar(volumes[0].1.clone()).voxels(),
+            test_volume().voxels()
+        );
+        assert_eq!(volumes[1].0, "vector");
+        let array = read_npz_array(&path, "scalar");
+        let missing_array = read_npz_array(&path, "missing");
+        fs::remove_file(&path).unwrap();
+        assert_eq!(scalar(array.unwrap()).voxels(), test_volume().voxels());
+        assert!(missing_array.is_err());
+        match &volumes[1].1 {
+            Volume::Vector(volume) => {
+                for dim in 0..3 {
+                    assert_eq!(volume.component(dim).voxels(), test_volume().voxels());
+                }
+            }
+            Volume::Scalar(_) => panic!("Expected vector volume."),
+        }
+    }
+
+    #[test]
+    fn npz_arrays_that_are_not_volumes_are_skipped() {
+        let entries = [
+            (
+                "time.npy",
+                npy_bytes(
+                    "{'descr': '<f8', 'fortran_order': False, 'shape': (1,), }\n",
+                    &1.5_f64.to_le_bytes(),
+                ),
+            ),
+            (
+                "units.npy",
+                npy_bytes(
+                    "{'descr': '<U1', 'fortran_order': False, 'shape': (), }\n",
+                    &u32::from(b'K').to_le_bytes(),
+                ),
+            ),
+            ("density.npy", encode_npy(&Volume::Scalar(test_volume()))),
+        ];
+        let archive = npz_archive(&entries);
+
+        let volumes = read_npz_from_reader(io::Cursor::new(&archive)).unwrap();
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].0, "density");
+        assert_eq!(
+            scalar(volumes[0].1.clone()).voxels(),
+            test_volume().voxels()
+        );
+
+        let path = temporary_path("metadata.npz");
+        fs::write(&path, &archive).unwrap();
+        let metadata_array = read_npz_array(&path, "time");
+        fs::write(&path, npz_archive(&entries[..2])).unwrap();
+        let first_volume = read_volume(&path, None, None);
+        fs::remove_file(&path).unwrap();
+        assert!(metadata_array.is_err());
+        assert!(first_volume.is_err());
+    }
+
+    #[test]
+    fn c_and_fortran_order_give_same_volume() {
+        let expected = test_volume();
+        let fortran_data: Vec<u8> = expected
+            .voxels()
+            .to_f32()
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect();
+        let c_data: Vec<u8> = (0..3)
+            .flat_map(|i| (0..2).flat_map(move |j| (0..4).map(move |k| (i, j, k))))
+            .flat_map(|(i, j, k)| ((i + 10 * j + 100 * k) as f32).to_le_bytes())
+            .collect();
+        for (fortran_order, data) in [("True", fortran_data), ("False", c_data)] {
+            let header = format!(
+                "{{'descr': '<f4', 'fortran_order': {}, 'shape': (3, 2, 4), }}\n",
+                fortran_order
+            );
+            let volume = scalar(read_npy_from_bytes(&npy_bytes(&header, &data)).unwrap());
+            assert_eq!(volume.voxels(), expected.voxels());
+        }
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let header = "{'descr': '|u1', 'fortran_order': True, 'shape': (2, 2, 2), }\n";
+        let bytes = npy_bytes(header, &[0; 8]);
+        assert!(read_npy_from_bytes(&bytes).is_ok());
+        for length in [0, 6, 9, 20, bytes.len() - 1] {
+            assert!(read_npy_from_bytes(&bytes[..length]).is_err());
+        }
+    }
+
+    #[test]
+    fn overflowing_shape_is_an_error() {
+        let header = format!(
+            "{{'descr': '<f4', 'fortran_order': True, 'shape': ({}, 2, 1), }}\n",
+            usize::MAX / 2
+        );
+        assert!(read_npy_from_bytes(&npy_bytes(&header, &[])).is_err());
+    }
+}
@@ -0,0 +1,647 @@
+//! Reading and writing ParaView JSON colormap and transfer function presets.
+//!
+//! A preset file holds a list of presets, each with a `Name`, an `RGBPoints`
+//! array of `x, r, g, b` quadruples, an optional `Points` array of
+//! `x, opacity, midpoint, sharpness` quadruples and a `ColorSpace` for color
+//! interpolation. The `x` coordinates are data values, which are mapped onto
+//! the normalized domain of the transfer function. Categorical presets with
+//! only `IndexedColors` are not supported.
+
+use super::DataIOError;
+use crate::{
+    color::{Color, ColorSpace},
+    error::{VortekError, VortekResult},
+    transfer_function::{ColorPoint, DomainScale, OpacityPoint, TransferFunction},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+
+/// Number of opacity points used to approximate a segment of a ParaView
+/// opacity curve that is not linear.
+const CURVED_SEGMENT_SAMPLES: usize = 16;
+/// Default midpoint of a ParaView opacity curve segment.
+const DEFAULT_MIDPOINT: f32 = 0.5;
+/// Default sharpness of a ParaView opacity curve segment.
+const DEFAULT_SHARPNESS: f32 = 0.0;
+
+/// Transfer function preset as stored in ParaView JSON files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParaViewPreset {
+    /// Name of the preset.
+    pub name: String,
+    /// Transfer function with the colors and opacities of the preset over a
+    /// normalized domain, which uses the value range of the volume.
+    pub transfer_function: TransferFunction,
+    /// Data values of the first and last control points of the preset. Set
+    /// this as the domain of the transfer function to map the preset to the
+    /// original values instead of rescaling it to the volume.
+    pub value_range: (f32, f32),
+}
+
+/// Raw contents of a preset in a ParaView JSON file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct RawPreset {
+    #[serde(default)]
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color_space: Option<String>,
+    #[serde(rename = "RGBPoints", skip_serializing_if = "Option::is_none")]
+    rgb_points: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_log_scale: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    below_range_color: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_below_range_color: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    above_range_color: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    use_above_range_color: Option<Value>,
+}
+
+impl ParaViewPreset {
+    /// Creates a preset with the given name and transfer function.
+    ///
+    /// The value range is the domain of the transfer function if it is set,
+    /// and the normalized range [0, 1] otherwise.
+    pub fn new<S: Into<String>>(name: S, transfer_function: TransferFunction) -> Self {
+        let value_range = transfer_function.domain().unwrap_or((0.0, 1.0));
+        Self {
+            name: name.into(),
+            transfer_function,
+            value_range,
+        }
+    }
+
+    /// Returns the transfer function with its domain set to the original value
+    /// range of the preset, unless the range is empty.
+    pub fn transfer_function_with_value_range(&self) -> TransferFunction {
+        let mut transfer_function = self.transfer_function.clone();
+        let (min_value, max_value) = self.value_range;
+        if min_value < max_value {
+            transfer_function.set_domain(Some(self.value_range));
+        }
+        transfer_function
+    }
+}
+
+/// Reads the presets in the ParaView JSON preset file at the given path.
+pub fn read_paraview_presets<P: AsRef<Path>>(path: P) -> VortekResult<Vec<ParaViewPreset>> {
+    parse_paraview_presets(&super::read_file_to_string(path)?)
+}
+
+/// Parses the given contents of a ParaView JSON preset file, which may hold a
+/// list of presets or a single preset.
+pub fn parse_paraview_presets(text: &str) -> VortekResult<Vec<ParaViewPreset>> {
+    let value: Value = serde_json::from_str(text).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_error(
+            "Could not parse ParaView preset file: ",
+            err,
+        ))
+    })?;
+    let raw_presets: Vec<RawPreset> = match value {
+        Value::Array(_) => serde_json::from_value(value),
+        _ => serde_json::from_value(value).map(|raw_preset| vec![raw_preset]),
+    }
+    .map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_error(
+            "Invalid ParaView preset file: ",
+            err,
+        ))
+    })?;
+    raw_presets.into_iter().map(convert_raw_preset).collect()
+}
+
+/// Writes the given presets to a ParaView JSON preset file at the given path.
+pub fn write_paraview_presets<P: AsRef<Path>>(
+    path: P,
+    presets: &[ParaViewPreset],
+) -> VortekResult<()> {
+    super::write_file(path, format_paraview_presets(presets)?.as_bytes())
+}
+
+/// Formats the given presets as the contents of a ParaView JSON preset file.
+///
+/// Color spaces without a ParaView counterpart are written as the closest
+/// one: linear RGB as `RGB`, HSL as `HSV` and LCh and Oklab as `Lab`.
+pub fn format_paraview_presets(presets: &[ParaViewPreset]) -> VortekResult<String> {
+    let raw_presets: Vec<RawPreset> = presets.iter().map(create_raw_preset).collect();
+    serde_json::to_string_pretty(&raw_presets).map_err(|err| {
+        VortekError::DataIOError(DataIOError::from_error(
+            "Could not format ParaView presets: ",
+            err,
+        ))
+    })
+}
+
+fn convert_raw_preset(raw_preset: RawPreset) -> VortekResult<ParaViewPreset> {
+    let name = raw_preset.name;
+    let invalid = |message: &str| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Invalid ParaView preset \"{}\": {}",
+            name, message
+        )))
+    };
+
+    let rgb_points = raw_preset
+        .rgb_points
+        .ok_or_else(|| invalid("No RGBPoints array."))?;
+    if rgb_points.is_empty() || rgb_points.len() % 4 != 0 {
+        return Err(invalid(
+            "Length of RGBPoints is not a positive multiple of 4.",
+        ));
+    }
+    if let Some(points) = &raw_preset.points {
+        if points.is_empty() || points.len() % 4 != 0 {
+            return Err(invalid("Length of Points is not a positive multiple of 4."));
+        }
+    }
+    if !rgb_points.iter().step_by(4).all(|value| value.is_finite()) {
+        return Err(invalid("Non-finite coordinate in RGBPoints."));
+    }
+    if !raw_preset
+        .points
+        .iter()
+        .flatten()
+        .step_by(4)
+        .all(|value| value.is_finite())
+    {
+        return Err(invalid("Non-finite coordinate in Points."));
+    }
+
+    let scale = if raw_preset.use_log_scale.as_ref().is_some_and(is_true) {
+        DomainScale::Logarithmic
+    } else {
+        DomainScale::Linear
+    };
+
+    let (min_value, max_value) = rgb_points
+        .iter()
+        .step_by(4)
+        .chain(raw_preset.points.iter().flatten().step_by(4))
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        });
+    if scale == DomainScale::Logarithmic && min_value <= 0.0 {
+        return Err(invalid("Non-positive values in logarithmic preset."));
+    }
+    let normalize = |value: f32| {
+        let (value, min_value, max_value) = match scale {
+            DomainScale::Linear => (value, min_value, max_value),
+            DomainScale::Logarithmic => (value.ln(), min_value.ln(), max_value.ln()),
+        };
+        if max_value > min_value {
+            ((value - min_value) / (max_value - min_value)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    };
+
+    let mut color_points: Vec<ColorPoint> = rgb_points
+        .chunks_exact(4)
+        .map(|point| ColorPoint {
+            position: normalize(point[0]),
+            color: Color::from_components(point[1], point[2], point[3], 1.0),
+        })
+        .collect();
+
+    let color_space = raw_preset.color_space.as_deref().unwrap_or("RGB");
+    let interpolation_space = match color_space.to_lowercase().as_str() {
+        "rgb" | "step" => ColorSpace::Srgb,
+        "hsv" => ColorSpace::Hsv,
+        "lab" | "lab/ciede2000" | "ciede2000" => ColorSpace::Lab,
+        "diverging" => ColorSpace::Diverging,
+        _ => {
+            return Err(invalid(&format!(
+                "Unsupported ColorSpace \"{}\".",
+                color_space
+            )))
+        }
+    };
+    if color_space.eq_ignore_ascii_case("step") {
+        color_points = make_piecewise_constant(color_points);
+    }
+
+    let opacity_points = match &raw_preset.points {
+        Some(points) => create_opacity_points(points, normalize),
+        None => vec![
+            OpacityPoint {
+                position: 0.0,
+                opacity: 0.0,
+            },
+            OpacityPoint {
+                position: 1.0,
+                opacity: 1.0,
+            },
+        ],
+    };
+
+    let mut transfer_function = TransferFunction::new(color_points, opacity_points);
+    transfer_function.set_interpolation_space(interpolation_space);
+    transfer_function.set_scale(scale);
+
+    let out_of_range_color = |color: &Option<Vec<f32>>, enabled: &Option<Value>, position| match (
+        color,
+        enabled.as_ref().is_some_and(is_true),
+    ) {
+        (Some(color), true) if color.len() >= 3 => Ok(Some(Color::from_components(
+            color[0],
+            color[1],
+            color[2],
+            transfer_function.evaluate(position).alpha(),
+        ))),
+        (_, true) => Err(invalid(
+            "Out-of-range color enabled without three components.",
+        )),
+        (_, false) => Ok(None),
+    };
+    let below_range_color = out_of_range_color(
+        &raw_preset.below_range_color,
+        &raw_preset.use_below_range_color,
+        0.0,
+    )?;
+    let above_range_color = out_of_range_color(
+        &raw_preset.above_range_color,
+        &raw_preset.use_above_range_color,
+        1.0,
+    )?;
+    transfer_function.set_out_of_range_colors(below_range_color, above_range_color);
+
+    Ok(ParaViewPreset {
+        name,
+        transfer_function,
+        value_range: (min_value, max_value),
+    })
+}
+
+fn create_raw_preset(preset: &ParaViewPreset) -> RawPreset {
+    let transfer_function = &preset.transfer_function;
+    let (min_value, max_value) = preset.value_range;
+    let scale = transfer_function.scale();
+    let denormalize = |position: f32| match scale {
+        DomainScale::Linear => min_value + position * (max_value - min_value),
+        DomainScale::Logarithmic => {
+            (min_value.ln() + position * (max_value.ln() - min_value.ln())).exp()
+        }
+    };
+
+    let rgb_points = transfer_function
+        .color_points()
+        .iter()
+        .flat_map(|point| {
+            let [red, green, blue, _] = point.color.to_slice();
+            [denormalize(point.position), red, green, blue]
+        })
+        .collect();
+    let points = transfer_function
+        .opacity_points()
+        .iter()
+        .flat_map(|point| {
+            [
+                denormalize(point.position),
+                point.opacity,
+                DEFAULT_MIDPOINT,
+                DEFAULT_SHARPNESS,
+            ]
+        })
+        .collect();
+    let color_space = match transfer_function.interpolation_space() {
+        ColorSpace::Srgb | ColorSpace::LinearRgb => "RGB",
+        ColorSpace::Hsv | ColorSpace::Hsl => "HSV",
+        ColorSpace::Lab | ColorSpace::Lch | ColorSpace::Oklab => "Lab",
+        ColorSpace::Diverging => "Diverging",
+    };
+
+    let (below_range_color, above_range_color) = transfer_function.out_of_range_colors();
+    let rgb = |color: Option<&Color>| {
+        color.map(|color| {
+            let [red, green, blue, _] = color.to_slice();
+            vec![red, green, blue]
+        })
+    };
+
+    RawPreset {
+        name: preset.name.clone(),
+        color_space: Some(color_space.to_string()),
+        rgb_points: Some(rgb_points),
+        points: Some(points),
+        use_log_scale: Some(Value::Bool(scale == DomainScale::Logarithmic)),
+        use_below_range_color: Some(Value::Bool(below_range_color.is_some())),
+        below_range_color: rgb(below_range_color),
+        use_above_range_color: Some(Value::Bool(above_range_color.is_some())),
+        above_range_color: rgb(above_range_color),
+    }
+}
+
+/// Converts the given sorted color points into points giving each color until
+/// the next point, as in ParaView's `Step` color space.
+fn make_piecewise_constant(color_points: Vec<ColorPoint>) -> Vec<ColorPoint> {
+    let mut step_points = Vec::with_capacity(2 * color_points.len());
+    for point in color_points {
+        let previous_color = step_points
+            .last()
+            .map(|previous: &ColorPoint| previous.color.clone());
+        if let Some(color) = previous_color {
+            step_points.push(ColorPoint {
+                position: point.position,
+                color,
+            });
+        }
+        step_points.push(point);
+    }
+    step_points
+}
+
+/// Creates opacity points from the given ParaView opacity curve, where
+/// segments with non-default midpoint or sharpness are approximated by linear
+/// pieces.
+fn create_opacity_points<F: Fn(f32) -> f32>(points: &[f32], normalize: F) -> Vec<OpacityPoint> {
+    let mut sorted_points: Vec<&[f32]> = points.chunks_exact(4).collect();
+    sorted_points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+
+    let mut opacity_points = Vec::with_capacity(sorted_points.len());
+    for (index, point) in sorted_points.iter().enumerate() {
+        let (position, opacity) = (normalize(point[0]), point[1]);
+        opacity_points.push(OpacityPoint { position, opacity });
+
+        let (midpoint, sharpness) = (point[2], point[3]);
+        let next = match sorted_points.get(index + 1) {
+            Some(next) => next,
+            None => break,
+        };
+        let is_linear =
+            (midpoint - DEFAULT_MIDPOINT).abs() <= f32::EPSILON && sharpness.abs() <= f32::EPSILON;
+        if is_linear {
+            continue;
+        }
+        let next_position = normalize(next[0]);
+        for sample in 1..CURVED_SEGMENT_SAMPLES {
+            let fraction = sample as f32 / CURVED_SEGMENT_SAMPLES as f32;
+            opacity_points.push(OpacityPoint {
+                position: position + fraction * (next_position - position),
+                opacity: evaluate_segment(opacity, next[1], midpoint, sharpness, fraction),
+            });
+        }
+    }
+    opacity_points
+}
+
+/// Evaluates a segment of a ParaView opacity curve between the given
+/// opacities at the given fraction of the way along the segment, following
+/// `vtkPiecewiseFunction`.
+fn evaluate_segment(
+    start_opacity: f32,
+    end_opacity: f32,
+    midpoint: f32,
+    sharpness: f32,
+    fraction: f32,
+) -> f32 {
+    let midpoint = midpoint.clamp(0.00001, 0.99999);
+    let mut t = if fraction < midpoint {
+        0.5 * fraction / midpoint
+    } else {
+        0.5 + 0.5 * (fraction - midpoint) / (1.0 - midpoint)
+    };
+
+    if sharpness > 0.99 {
+        return if t < 0.5 { start_opacity } else { end_opacity };
+    }
+    if sharpness < 0.01 {
+        return start_opacity + t * (end_opacity - start_opacity);
+    }
+
+    // Make the curve flatter towards the ends with increasing sharpness
+    let exponent = 1.0 + 10.0 * sharpness;
+    if t < 0.5 {
+        t = 0.5 * (2.0 * t).powf(exponent);
+    } else if t > 0.5 {
+        t = 1.0 - 0.5 * (2.0 * (1.0 - t)).powf(exponent);
+    }
+
+    // Hermite interpolation with tangents reduced by the sharpness
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let tangent = (end_opacity - start_opacity) * (1.0 - sharpness);
+    let value = (2.0 * t3 - 3.0 * t2 + 1.0) * start_opacity
+        + (-2.0 * t3 + 3.0 * t2) * end_opacity
+        + (t3 - 2.0 * t2 + t) * tangent
+        + (t3 - t2) * tangent;
+    value.clamp(
+        start_opacity.min(end_opacity),
+        start_opacity.max(end_opacity),
+    )
+}
+
+/// Whether the given JSON value is `true` or a non-zero number, as ParaView
+/// writes flags either way.
+fn is_true(value: &Value) -> bool {
+    value
+        .as_bool()
+        .or_else(|| value.as_f64().map(|number| number != 0.0))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_positions(transfer_function: &TransferFunction) -> Vec<f32> {
+        transfer_function
+            .color_points()
+            .iter()
+            .map(|point| point.position)
+            .collect()
+    }
+
+    fn parse_single_preset(text: &str) -> ParaViewPreset {
+        let mut presets = parse_paraview_presets(text).unwrap();
+        assert_eq!(presets.len(), 1);
+        presets.remove(0)
+    }
+
+    #[test]
+    fn parses_preset() {
+        let text = r#"[{"Name": "Gray", "ColorSpace": "RGB",
+            "RGBPoints": [-1, 0, 0, 0, 1, 1, 1, 1], "Points": [-1, 0, 0.5, 0, 1, 1, 0.5, 0]}]"#;
+        let presets = parse_paraview_presets(text).unwrap();
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].name, "Gray");
+    }
+
+    #[test]
+    fn normalizes_positions_to_value_range() {
+        let preset = parse_single_preset(
+            r#"{"Name": "Ramp", "RGBPoints": [-1, 0, 0, 0, 0, 0.5, 0.25, 0, 3, 1, 1, 1],
+                "Points": [-1, 0, 0.5, 0, 3, 0.75, 0.5, 0]}"#,
+        );
+        assert_eq!(preset.value_range, (-1.0, 3.0));
+        let transfer_function = &preset.transfer_function;
+        assert_eq!(color_positions(transfer_function), vec![0.0, 0.25, 1.0]);
+        assert_eq!(
+            transfer_function.color_points()[1].color,
+            Color::from_components(0.5, 0.25, 0.0, 1.0)
+        );
+        assert_eq!(
+            transfer_function.opacity_points(),
+            &[
+                OpacityPoint {
+                    position: 0.0,
+                    opacity: 0.0
+                },
+                OpacityPoint {
+                    position: 1.0,
+                    opacity: 0.75
+                }
+            ]
+        );
+        assert_eq!(
+            preset.transfer_function_with_value_range().domain(),
+            Some((-1.0, 3.0))
+        );
+    }
+
+    #[test]
+    fn maps_color_spaces() {
+        for (name, space) in [
+            ("RGB", ColorSpace::Srgb),
+            ("HSV", ColorSpace::Hsv),
+            ("Lab", ColorSpace::Lab),
+            ("Lab/CIEDE2000", ColorSpace::Lab),
+            ("Diverging", ColorSpace::Diverging),
+        ] {
+            let preset = parse_single_preset(&format!(
+                r#"{{"Name": "A", "ColorSpace": "{}", "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1]}}"#,
+                name
+            ));
+            assert_eq!(preset.transfer_function.interpolation_space(), space);
+        }
+        assert!(parse_paraview_presets(
+            r#"{"Name": "A", "ColorSpace": "XYZ", "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn step_color_space_gives_piecewise_constant_colors() {
+        let preset = parse_single_preset(
+            r#"{"Name": "Steps", "ColorSpace": "Step",
+                "RGBPoints": [0, 1, 0, 0, 1, 0, 1, 0, 2, 0, 0, 1]}"#,
+        );
+        let transfer_function = &preset.transfer_function;
+        assert_eq!(transfer_function.interpolation_space(), ColorSpace::Srgb);
+        assert_eq!(
+            color_positions(transfer_function),
+            vec![0.0, 0.5, 0.5, 1.0, 1.0]
+        );
+        let red = Color::from_components(1.0, 0.0, 0.0, 1.0);
+        let green = Color::from_components(0.0, 1.0, 0.0, 1.0);
+        assert_eq!(
+            transfer_function.evaluate(0.25).to_slice()[..3],
+            red.to_slice()[..3]
+        );
+        assert_eq!(
+            transfer_function.evaluate(0.75).to_slice()[..3],
+            green.to_slice()[..3]
+        );
+    }
+
+    #[test]
+    fn log_scale_presets_are_normalized_logarithmically() {
+        let preset = parse_single_preset(
+            r#"{"Name": "Log", "UseLogScale": 1,
+                "RGBPoints": [1, 0, 0, 0, 10, 0.5, 0.5, 0.5, 100, 1, 1, 1]}"#,
+        );
+        let transfer_function = &preset.transfer_function;
+        assert_eq!(transfer_function.scale(), DomainScale::Logarithmic);
+        let positions = color_positions(transfer_function);
+        assert_eq!(positions[0], 0.0);
+        assert!((positions[1] - 0.5).abs() < 1e-6);
+        assert_eq!(positions[2], 1.0);
+
+        assert!(parse_paraview_presets(
+            r#"{"Name": "Log", "UseLogScale": true, "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn curved_opacity_segments_are_sampled() {
+        let preset = parse_single_preset(
+            r#"{"Name": "Curve", "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1],
+                "Points": [0, 0, 0.25, 0, 1, 1, 0.5, 0]}"#,
+        );
+        let opacity_points = preset.transfer_function.opacity_points();
+        assert_eq!(opacity_points.len(), CURVED_SEGMENT_SAMPLES + 1);
+        // Half the opacity is reached at the midpoint of the segment
+        let midpoint = opacity_points[CURVED_SEGMENT_SAMPLES / 4];
+        assert_eq!(midpoint.position, 0.25);
+        assert_eq!(midpoint.opacity, 0.5);
+    }
+
+    #[test]
+    fn evaluates_segments_with_sharpness() {
+        assert_eq!(evaluate_segment(0.0, 1.0, 0.5, 1.0, 0.4), 0.0);
+        assert_eq!(evaluate_segment(0.0, 1.0, 0.5, 1.0, 0.6), 1.0);
+        assert_eq!(evaluate_segment(0.2, 0.6, 0.5, 0.5, 0.5), 0.4);
+        let flattened = evaluate_segment(0.0, 1.0, 0.5, 0.5, 0.1);
+        assert!(flattened > 0.0 && flattened < 0.1);
+    }
+
+    #[test]
+    fn exported_preset_round_trips() {
+        // ParaView's "Cool to Warm" preset rescaled to [-2, 5] with a linear
+        // opacity ramp, as written by its preset export
+        let text = "[
+\t{
+\t\t\"ColorSpace\" : \"Diverging\",
+\t\t\"Name\" : \"Cool to Warm\",
+\t\t\"NanColor\" : [ 1, 1, 0 ],
+\t\t\"Points\" : [ -2, 0, 0.5, 0, 5, 1, 0.5, 0 ],
+\t\t\"RGBPoints\" : [ -2, 0.23137254902, 0.298039215686, 0.752941176471, \
+                         1.5, 0.865, 0.865, 0.865, \
+                         5, 0.705882352941, 0.0156862745098, 0.149019607843 ]
+\t}
+]";
+        let preset = parse_single_preset(text);
+        assert_eq!(preset.value_range, (-2.0, 5.0));
+        let transfer_function = preset.transfer_function_with_value_range();
+        for (value, expected) in [
+            (-2.0, [0.231_372_55, 0.298_039_2, 0.752_941_2, 0.0]),
+            (1.5, [0.865, 0.865, 0.865, 0.5]),
+            (5.0, [0.705_882_4, 0.015_686_275, 0.149_019_6, 1.0]),
+        ] {
+            let color = transfer_function
+                .evaluate_value(value, (0.0, 1.0))
+                .to_slice();
+            for (component, expected_component) in color.iter().zip(&expected) {
+                assert!((component - expected_component).abs() < 1e-6);
+            }
+        }
+
+        let formatted = format_paraview_presets(std::slice::from_ref(&preset)).unwrap();
+        let round_tripped = parse_single_preset(&formatted);
+        assert_eq!(round_tripped, preset);
+        let round_tripped_transfer_function = round_tripped.transfer_function_with_value_range();
+        for step in 0..=70 {
+            let value = -2.0 + 0.1 * step as f32;
+            assert_eq!(
+                round_tripped_transfer_function.evaluate_value(value, (0.0, 1.0)),
+                transfer_function.evaluate_value(value, (0.0, 1.0))
+            );
+        }
+    }
+
+    #[test]
+    fn non_finite_coordinates_are_an_error() {
+        for text in [
+            r#"{"Name": "A", "RGBPoints": [0, 0, 0, 0, 1e300, 1, 1, 1]}"#,
+            r#"{"Name": "B", "RGBPoints": [0, 0, 0, 0, 1, 1, 1, 1], "Points": [-1e300, 0, 0.5, 0]}"#,
+        ] {
+            assert!(parse_paraview_presets(text).is_err());
+        }
+    }
+}
@@ -0,0 +1,42 @@
+//! Writing rendered images as PNG files.
+
+use super::DataIOError;
+use crate::{
+    error::{VortekError, VortekResult},
+    graphics::rendering::offscreen::ImageData,
+};
+use png::{BitDepth, ColorType, Encoder};
+use std::path::Path;
+
+/// Writes the given image to a PNG file at the given path, replacing any
+/// existing file.
+pub fn write_png<P: AsRef<Path>>(path: P, image: &ImageData) -> VortekResult<()> {
+    let bytes = encode_png(image.width, image.height, &image.to_rgba8()?)?;
+    super::write_file(path, &bytes)
+}
+
+/// Encodes the given 8-bit RGBA pixels, stored row by row from the top, as the
+/// contents of a PNG file.
+///
+/// # Panics
+/// If the number of pixels does not match the width and height.
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> VortekResult<Vec<u8>> {
+    assert_eq!(
+        rgba.len() as u64,
+        4 * u64::from(width) * u64::from(height),
+        "Number of pixels does not match image size."
+    );
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = Encoder::new(&mut bytes, width, height);
+        encoder.set_color(ColorType::RGBA);
+        encoder.set_depth(BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(rgba))
+            .map_err(|err| {
+                VortekError::DataIOError(DataIOError::from_error("Could not encode PNG: ", err))
+            })?;
+    }
+    Ok(bytes)
+}
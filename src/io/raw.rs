@@ -0,0 +1,346 @@
+//! Reading headerless raw binary volume files.
+
+use super::{DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VoxelBuffer},
+};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// Order in which the axes of a 3D array vary in a flat buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AxisOrder {
+    /// Row-major order, where the z-index varies fastest.
+    C,
+    /// Column-major order, where the x-index varies fastest.
+    Fortran,
+}
+
+/// Description of the layout of a raw binary volume file.
+///
+/// Can be deserialized from a TOML or JSON sidecar file, where all fields
+/// except `dimensions` and `element_type` are optional.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RawVolumeDescriptor {
+    /// Number of voxels along the x-, y- and z-axis.
+    pub dimensions: [usize; 3],
+    /// Type of the stored values.
+    pub element_type: ElementType,
+    /// Byte order of the stored values.
+    #[serde(default)]
+    pub endianness: Endianness,
+    /// Number of bytes to skip at the beginning of the file.
+    #[serde(default)]
+    pub byte_offset: u64,
+    /// Order in which the axes vary in the file.
+    #[serde(default = "default_axis_order")]
+    pub axis_order: AxisOrder,
+    /// Distance between adjacent voxel centers along each axis.
+    #[serde(default = "default_spacing")]
+    pub spacing: [f32; 3],
+    /// Position of the center of the first voxel.
+    #[serde(default)]
+    pub origin: [f32; 3],
+}
+
+/// File extensions that are recognized for sidecar descriptor files.
+pub const SIDECAR_EXTENSIONS: [&str; 2] = ["toml", "json"];
+
+impl RawVolumeDescriptor {
+    /// Creates a new descriptor for a file with the given dimensions and element
+    /// type, stored in native byte order and Fortran axis order without any offset.
+    pub fn new(dimensions: [usize; 3], element_type: ElementType) -> Self {
+        Self {
+            dimensions,
+            element_type,
+            endianness: Endianness::default(),
+            byte_offset: 0,
+            axis_order: default_axis_order(),
+            spacing: default_spacing(),
+            origin: [0.0; 3],
+        }
+    }
+
+    /// Reads a descriptor from the given TOML or JSON sidecar file, with the
+    /// format determined by the file extension.
+    pub fn from_sidecar_file<P: AsRef<Path>>(path: P) -> VortekResult<Self> {
+        let path = path.as_ref();
+        let text = super::read_file_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml_str(&text),
+            Some("json") => Self::from_json_str(&text),
+            _ => Err(VortekError::DataIOError(DataIOError::from_string(format!(
+                "Unsupported sidecar file extension for {} (expected .toml or .json).",
+                path.display()
+            )))),
+        }
+    }
+
+    /// Parses a descriptor from the given TOML string.
+    pub fn from_toml_str(text: &str) -> VortekResult<Self> {
+        toml::from_str(text).map_err(|err| {
+            VortekError::DataIOError(DataIOError::from_error(
+                "Could not parse TOML sidecar: ",
+                err,
+            ))
+        })
+    }
+
+    /// Parses a descriptor from the given JSON string.
+    pub fn from_json_str(text: &str) -> VortekResult<Self> {
+        serde_json::from_str(text).map_err(|err| {
+            VortekError::DataIOError(DataIOError::from_error(
+                "Could not parse JSON sidecar: ",
+                err,
+            ))
+        })
+    }
+
+    /// Returns the total number of voxels described, or an error if it does
+    /// not fit in a `usize`.
+    pub fn number_of_voxels(&self) -> VortekResult<usize> {
+        let [nx, ny, nz] = self.dimensions;
+        nx.checked_mul(ny)
+            .and_then(|n| n.checked_mul(nz))
+            .ok_or_else(|| self.size_overflow_error())
+    }
+
+    /// Returns the number of bytes of voxel data described, excluding the
+    /// offset, or an error if it does not fit in a `usize`.
+    pub fn data_size_in_bytes(&self) -> VortekResult<usize> {
+        self.number_of_voxels()?
+            .checked_mul(self.element_type.size_in_bytes())
+            .ok_or_else(|| self.size_overflow_error())
+    }
+
+    fn size_overflow_error(&self) -> VortekError {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Size of volume with dimensions {:?} is too large.",
+            self.dimensions
+        )))
+    }
+}
+
+/// Reads the raw binary volume file at the given path, laid out as described
+/// by the given descriptor.
+pub fn read_raw_volume<P: AsRef<Path>>(
+    path: P,
+    descriptor: &RawVolumeDescriptor,
+) -> VortekResult<ScalarVolume> {
+    let path = path.as_ref();
+    let map_io_err = |err| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not read {}: {}",
+            path.display(),
+            err
+        )))
+    };
+
+    let mut file = File::open(path).map_err(map_io_err)?;
+    let file_size = file.metadata().map_err(map_io_err)?.len();
+
+    let data_size = descriptor.data_size_in_bytes()?;
+    let required_size = descriptor
+        .byte_offset
+        .checked_add(data_size as u64)
+        .ok_or_else(|| {
+            VortekError::DataIOError(DataIOError::from_string(format!(
+                "Byte offset {} is too large.",
+                descriptor.byte_offset
+            )))
+        })?;
+    if file_size < required_size {
+        return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+            "Size of {} ({} bytes) is smaller than the {} bytes required by its description.",
+            path.display(),
+            file_size,
+            required_size
+        ))));
+    } else if file_size > required_size {
+        debug!(
+            "Ignoring {} trailing bytes in {}.",
+            file_size - required_size,
+            path.display()
+        );
+    }
+
+    let mut bytes = vec![0; data_size];
+    file.seek(SeekFrom::Start(descriptor.byte_offset))
+        .map_err(map_io_err)?;
+    file.read_exact(&mut bytes).map_err(map_io_err)?;
+
+    volume_from_bytes(&bytes, descriptor)
+}
+
+/// Reads the raw binary volume file at the given path, laid out as described
+/// by the sidecar descriptor file next to it.
+///
+/// The sidecar is looked for by appending `.toml` or `.json` to the full file
+/// name, and then by replacing the extension of the file with `.toml` or `.json`.
+pub fn read_raw_volume_with_sidecar<P: AsRef<Path>>(path: P) -> VortekResult<ScalarVolume> {
+    let path = path.as_ref();
+    let sidecar_path = find_sidecar_file(path).ok_or_else(|| {
+        VortekError::DataIOError(DataIOError::from_string(format!(
+            "Could not find sidecar descriptor file for {}.",
+            path.display()
+        )))
+    })?;
+    debug!("Using sidecar file {}", sidecar_path.display());
+    let descriptor = RawVolumeDescriptor::from_sidecar_file(sidecar_path)?;
+    read_raw_volume(path, &descriptor)
+}
+
+/// Looks for a sidecar descriptor file belonging to the given data file.
+pub fn find_sidecar_file<P: AsRef<Path>>(path: P) -> Option<PathBuf> {
+    let path = path.as_ref();
+    let appended = SIDECAR_EXTENSIONS.iter().map(|extension| {
+        let mut file_name = path.as_os_str().to_os_string();
+        file_name.push(".");
+        file_name.push(extension);
+        PathBuf::from(file_name)
+    });
+    let replaced = SIDECAR_EXTENSIONS
+        .iter()
+        .map(|extension| path.with_extension(extension));
+    appended
+        .chain(replaced)
+        .find(|candidate| candidate != path && candidate.is_file())
+}
+
+/// Decodes a scalar volume from the given bytes, laid out as described by the given
+/// descriptor but without any byte offset.
+pub fn volume_from_bytes(
+    bytes: &[u8],
+    descriptor: &RawVolumeDescriptor,
+) -> VortekResult<ScalarVolume> {
+    let required_size = descriptor.data_size_in_bytes()?;
+    if bytes.len() != required_size {
+        return Err(VortekError::DataIOError(DataIOError::from_string(format!(
+            "Number of data bytes ({}) does not match the {} bytes required by the description.",
+            bytes.len(),
+            required_size
+        ))));
+    }
+
+    let voxels = super::decode_values(bytes, descriptor.element_type, descriptor.endianness)?;
+    let voxels = match descriptor.axis_order {
+        AxisOrder::Fortran => voxels,
+        AxisOrder::C => c_to_fortran_order(voxels, descriptor.dimensions),
+    };
+
+    Ok(ScalarVolume::new(
+        descriptor.dimensions,
+        descriptor.spacing,
+        descriptor.origin,
+        voxels,
+    ))
+}
+
+/// Reorders the values of a buffer holding a 3D array with the given dimensions
+/// from C order to Fortran order.
+pub fn c_to_fortran_order(voxels: VoxelBuffer, dimensions: [usize; 3]) -> VoxelBuffer {
+    fn transpose<T: Copy>(values: &[T], [nx, ny, nz]: [usize; 3]) -> Vec<T> {
+        let mut transposed = Vec::with_capacity(values.len());
+        for k in 0..nz {
+            for j in 0..ny {
+                for i in 0..nx {
+                    transposed.push(values[k + nz * (j + ny * i)]);
+                }
+            }
+        }
+        transposed
+    }
+    match voxels {
+        VoxelBuffer::U8(values) => VoxelBuffer::U8(transpose(&values, dimensions)),
+        VoxelBuffer::U16(values) => VoxelBuffer::U16(transpose(&values, dimensions)),
+        VoxelBuffer::I16(values) => VoxelBuffer::I16(transpose(&values, dimensions)),
+        VoxelBuffer::F32(values) => VoxelBuffer::F32(transpose(&values, dimensions)),
+    }
+}
+
+fn default_axis_order() -> AxisOrder {
+    AxisOrder::Fortran
+}
+
+fn default_spacing() -> [f32; 3] {
+    [1.0; 3]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::{encode_values, temporary_path};
+    use std::fs;
+
+    #[test]
+    fn round_trip_through_file() {
+        let path = temporary_path("round_trip.raw");
+        let values: Vec<u16> = (0..24).collect();
+        let voxels = VoxelBuffer::from(values);
+        let mut descriptor = RawVolumeDescriptor::new([2, 3, 4], ElementType::U16);
+        descriptor.endianness = Endianness::Big;
+        descriptor.byte_offset = 5;
+        let mut bytes = vec![0; 5];
+        bytes.extend(encode_values(&voxels, Endianness::Big));
+        fs::write(&path, &bytes).unwrap();
+        let result = read_raw_volume(&path, &descriptor);
+        fs::remove_file(&path).unwrap();
+        let volume = result.unwrap();
+        assert_eq!(volume.dimensions(), [2, 3, 4]);
+        assert_eq!(volume.voxels(), &voxels);
+    }
+
+    #[test]
+    fn c_order_is_converted_to_fortran_order() {
+        let dimensions = [2, 3, 4];
+        let fortran = ScalarVolume::from_fn(dimensions, [1.0; 3], [0.0; 3], |i, j, k| {
+            (i + 10 * j + 100 * k) as f32
+        });
+        let c_values: Vec<f32> = (0..2)
+            .flat_map(|i| {
+                (0..3).flat_map(move |j| (0..4).map(move |k| (i + 10 * j + 100 * k) as f32))
+            })
+            .collect();
+        let mut descriptor = RawVolumeDescriptor::new(dimensions, ElementType::F32);
+        descriptor.axis_order = AxisOrder::C;
+        let bytes = encode_values(&VoxelBuffer::from(c_values), Endianness::Native);
+        let volume = volume_from_bytes(&bytes, &descriptor).unwrap();
+        assert_eq!(volume.voxels(), fortran.voxels());
+    }
+
+    #[test]
+    fn truncated_file_is_an_error() {
+        let path = temporary_path("truncated.raw");
+        fs::write(&path, [0; 10]).unwrap();
+        let mut descriptor = RawVolumeDescriptor::new([2, 2, 2], ElementType::U8);
+        descriptor.byte_offset = 4;
+        let result = read_raw_volume(&path, &descriptor);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn overflowing_size_is_an_error() {
+        let descriptor = RawVolumeDescriptor::new([usize::MAX, 2, 1], ElementType::U8);
+        assert!(descriptor.number_of_voxels().is_err());
+        let descriptor = RawVolumeDescriptor::new([usize::MAX / 2, 1, 1], ElementType::F32);
+        assert!(descriptor.data_size_in_bytes().is_err());
+        assert!(volume_from_bytes(&[], &descriptor).is_err());
+
+        let path = temporary_path("offset.raw");
+        fs::write(&path, [0; 8]).unwrap();
+        let mut descriptor = RawVolumeDescriptor::new([2, 2, 2], ElementType::U8);
+        descriptor.byte_offset = u64::MAX;
+        let result = read_raw_volume(&path, &descriptor);
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,1370 @@
+//! Reading VTK image data files.
+//!
+//! Legacy `.vtk` files with `STRUCTURED_POINTS` datasets in ASCII or binary
+//! form, and XML ImageData `.vti` files with ASCII, inline base64 or appended
+//! raw/base64 data, optionally zlib-compressed, are supported. Every point data
+//! array with one or three components is exposed as a scalar or vector field.
+//! See the format specification at <https://vtk.org/wp-content/uploads/2015/04/file-formats.pdf>.
+
+use super::{DataIOError, ElementType, Endianness};
+use crate::{
+    error::{VortekError, VortekResult},
+    volume::{ScalarVolume, VectorVolume, Volume, VoxelBuffer, IDENTITY_ORIENTATION},
+};
+use flate2::read::ZlibDecoder;
+use log::{debug, warn};
+use std::{convert::TryFrom, io::Read, path::Path};
+
+/// Point data arrays of a VTK image dataset, as scalar or vector volumes.
+#[derive(Clone, Debug, Default)]
+pub struct VtkDataset {
+    fields: Vec<(String, Volume)>,
+}
+
+/// Geometry of the image grid shared by all fields of a dataset.
+#[derive(Clone, Debug)]
+struct Geometry {
+    dimensions: [usize; 3],
+    spacing: [f32; 3],
+    origin: [f32; 3],
+    orientation: [[f32; 3]; 3],
+}
+
+/// Reads the VTK file at the given path, with the format determined from the
+/// file extension (`.vtk` for legacy and `.vti` for XML ImageData).
+pub fn read_vtk<P: AsRef<Path>>(path: P) -> VortekResult<VtkDataset> {
+    let path = path.as_ref();
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("vtk") => read_vtk_legacy(path),
+        Some("vti") => read_vti(path),
+        _ => Err(VortekError::DataIOError(DataIOError::from_string(format!(
+            "Unsupported VTK file extension for {} (expected .vtk or .vti).",
+            path.display()
+        )))),
+    }
+}
+
+/// Reads the legacy VTK file with a `STRUCTURED_POINTS` dataset at the given path.
+pub fn read_vtk_legacy<P: AsRef<Path>>(path: P) -> VortekResult<VtkDataset> {
+    LegacyReader::new(&super::read_file(path)?).read()
+}
+
+/// Reads the XML ImageData file at the given path.
+pub fn read_vti<P: AsRef<Path>>(path: P) -> VortekResult<VtkDataset> {
+    read_vti_from_bytes(&super::read_file(path)?)
+}
+
+impl VtkDataset {
+    /// Returns the names of all fields in the dataset.
+    pub fn field_names(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns a reference to the field with the given name, if present.
+    pub fn field(&self, name: &str) -> Option<&Volume> {
+        self.fields
+            .iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, volume)| volume)
+    }
+
+    /// Consumes the dataset and returns the field with the given name.
+    pub fn into_field(self, name: &str) -> VortekResult<Volume> {
+        let available_names = self.field_names().collect::<Vec<_>>().join(", ");
+        self.fields
+            .into_iter()
+            .find(|(field_name, _)| field_name == name)
+            .map(|(_, volume)| volume)
+            .ok_or_else(|| {
+                vtk_error(format!(
+                    "Field {} not present (available fields are {}).",
+                    name, available_names
+                ))
+            })
+    }
+
+    /// Consumes the dataset and returns all fields along with their names.
+    pub fn into_fields(self) -> Vec<(String, Volume)> {
+        self.fields
+    }
+
+    /// Adds a field from the given interleaved array values, or skips it with a
+    /// warning if the number of components is not supported.
+    fn add_field(
+        &mut self,
+        name: String,
+        geometry: &Geometry,
+        number_of_components: usize,
+        values: VoxelBuffer,
+    ) -> VortekResult<()> {
+        let number_of_values = value_count(geometry.number_of_voxels()?, number_of_components)?;
+        if values.len() != number_of_values {
+            return Err(vtk_error(format!(
+                "Array {} has {} values, but {} are required.",
+                name,
+                values.len(),
+                number_of_values
+            )));
+        }
+        let volume = match number_of_components {
+            1 => Volume::Scalar(geometry.create_volume(values)),
+            3 => {
+                let values = values.to_f32();
+                let component = |dim: usize| {
+                    geometry.create_volume(
+                        values
+                            .iter()
+                            .skip(dim)
+                            .step_by(3)
+                            .cloned()
+                            .collect::<Vec<_>>(),
+                    )
+                };
+                Volume::Vector(VectorVolume::new(component(0), component(1), component(2)))
+            }
+            _ => {
+                warn!(
+                    "Skipping VTK array {} with unsupported number of components {}.",
+                    name, number_of_components
+                );
+                return Ok(());
+            }
+        };
+        debug!("Read VTK field {}", name);
+        self.fields.push((name, volume));
+        Ok(())
+    }
+}
+
+impl Geometry {
+    fn number_of_voxels(&self) -> VortekResult<usize> {
+        let [nx, ny, nz] = self.dimensions;
+        value_count(nx, ny).and_then(|n| value_count(n, nz))
+    }
+
+    fn create_volume<V: Into<VoxelBuffer>>(&self, values: V) -> ScalarVolume {
+        let mut volume = ScalarVolume::new(self.dimensions, self.spacing, self.origin, values);
+        volume.set_orientation(self.orientation);
+        volume
+    }
+}
+
+/// Sequential reader for the mix of text and binary data in legacy VTK files.
+struct LegacyReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    is_binary: bool,
+}
+
+impl<'a> LegacyReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            is_binary: false,
+        }
+    }
+
+    fn read(mut self) -> VortekResult<VtkDataset> {
+        if !self.next_line()?.starts_with("# vtk DataFile") {
+            return Err(vtk_error("Missing legacy VTK header line.".to_string()));
+        }
+        let _title = self.next_line()?;
+        self.is_binary = match self.next_line()?.trim() {
+            "ASCII" => false,
+            "BINARY" => true,
+            format => return Err(vtk_error(format!("Invalid file format: {}", format))),
+        };
+
+        self.expect_token("DATASET")?;
+        let dataset_type = self.required_token()?;
+        if dataset_type != "STRUCTURED_POINTS" {
+            return Err(vtk_error(format!(
+                "Unsupported dataset type: {} (only STRUCTURED_POINTS is supported)",
+                dataset_type
+            )));
+        }
+
+        let mut dimensions = None;
+        let mut spacing = [1.0; 3];
+        let mut origin = [0.0; 3];
+        let mut dataset = VtkDataset::default();
+        let mut geometry = None;
+        let mut number_of_points = 0;
+        let mut is_point_data = false;
+
+        while let Some(keyword) = self.next_token() {
+            match keyword.to_uppercase().as_str() {
+                "DIMENSIONS" => {
+                    dimensions = Some([
+                        self.parse_token()?,
+                        self.parse_token()?,
+                        self.parse_token()?,
+                    ])
+                }
+                "SPACING" | "ASPECT_RATIO" => {
+                    spacing = [
+                        self.parse_token()?,
+                        self.parse_token()?,
+                        self.parse_token()?,
+                    ]
+                }
+                "ORIGIN" => {
+                    origin = [
+                        self.parse_token()?,
+                        self.parse_token()?,
+                        self.parse_token()?,
+                    ]
+                }
+                "POINT_DATA" => {
+                    number_of_points = self.parse_token()?;
+                    is_point_data = true;
+                    let dimensions: [usize; 3] = dimensions
+                        .ok_or_else(|| vtk_error("Missing DIMENSIONS keyword.".to_string()))?;
+                    let point_geometry = Geometry {
+                        dimensions,
+                        spacing,
+                        origin,
+                        orientation: IDENTITY_ORIENTATION,
+                    };
+                    if number_of_points != point_geometry.number_of_voxels()? {
+                        return Err(vtk_error(format!(
+                            "Number of points ({}) does not match dimensions {:?}.",
+                            number_of_points, dimensions
+                        )));
+                    }
+                    geometry = Some(point_geometry);
+                }
+                "CELL_DATA" => {
+                    number_of_points = self.parse_token()?;
+                    is_point_data = false;
+                }
+                "SCALARS" => {
+                    let name = self.required_token()?.to_string();
+                    let element_type = parse_legacy_type(self.required_token()?)?;
+                    let mut number_of_components = 1;
+                    let mut token = self.required_token()?;
+                    if token != "LOOKUP_TABLE" {
+                        number_of_components = token.parse().map_err(|_| {
+                            vtk_error(format!("Invalid number of components: {}", token))
+                        })?;
+                        token = self.required_token()?;
+                    }
+                    if token != "LOOKUP_TABLE" {
+                        return Err(vtk_error(format!("Expected LOOKUP_TABLE, found {}", token)));
+                    }
+                    let _table_name = self.required_token()?;
+                    let values = self.read_values(
+                        element_type,
+                        value_count(number_of_points, number_of_components)?,
+                    )?;
+                    if let (true, Some(ref geometry)) = (is_point_data, &geometry) {
+                        dataset.add_field(name, geometry, number_of_components, values)?;
+                    }
+                }
+                "VECTORS" | "NORMALS" => {
+                    let name = self.required_token()?.to_string();
+                    let element_type = parse_legacy_type(self.required_token()?)?;
+                    let values =
+                        self.read_values(element_type, value_count(number_of_points, 3)?)?;
+                    if let (true, Some(ref geometry)) = (is_point_data, &geometry) {
+                        dataset.add_field(name, geometry, 3, values)?;
+                    }
+                }
+                "TENSORS" => {
+                    let _name = self.required_token()?;
+                    let element_type = parse_legacy_type(self.required_token()?)?;
+                    self.read_values(element_type, value_count(number_of_points, 9)?)?;
+                }
+                "TEXTURE_COORDINATES" => {
+                    let _name = self.required_token()?;
+                    let dimension: usize = self.parse_token()?;
+                    let element_type = parse_legacy_type(self.required_token()?)?;
+                    self.read_values(element_type, value_count(number_of_points, dimension)?)?;
+                }
+                "LOOKUP_TABLE" => {
+                    let _name = self.required_token()?;
+                    let size: usize = self.parse_token()?;
+                    let element_type = if self.is_binary {
+                        ElementType::U8
+                    } else {
+                        ElementType::F32
+                    };
+                    self.read_values(element_type, value_count(size, 4)?)?;
+                }
+                "COLOR_SCALARS" => {
+                    let _name = self.required_token()?;
+                    let number_of_values: usize = self.parse_token()?;
+                    let element_type = if self.is_binary {
+                        ElementType::U8
+                    } else {
+                        ElementType::F32
+                    };
+                    self.read_values(
+                        element_type,
+                        value_count(number_of_points, number_of_values)?,
+                    )?;
+                }
+                "FIELD" => {
+                    let _field_name = self.required_token()?;
+                    let number_of_arrays: usize = self.parse_token()?;
+                    for _ in 0..number_of_arrays {
+                        let mut name = self.required_token()?;
+                        if name == "METADATA" {
+                            self.skip_metadata()?;
+                            name = self.required_token()?;
+                        }
+                        let name = name.to_string();
+                        let number_of_components: usize = self.parse_token()?;
+                        let number_of_tuples: usize = self.parse_token()?;
+                        let element_type = parse_legacy_type(self.required_token()?)?;
+                        let values = self.read_values(
+                            element_type,
+                            value_count(number_of_tuples, number_of_components)?,
+                        )?;
+                        if let (true, Some(ref geometry)) = (is_point_data, &geometry) {
+                            if number_of_tuples == number_of_points {
+                                dataset.add_field(name, geometry, number_of_components, values)?;
+                            }
+                        }
+                    }
+                }
+                "METADATA" => self.skip_metadata()?,
+                _ => return Err(vtk_error(format!("Unsupported keyword: {}", keyword))),
+            }
+        }
+        Ok(dataset)
+    }
+
+    /// Reads the given number of values of the given type, either as whitespace
+    /// separated text or as big-endian binary data starting on the next line.
+    fn read_values(
+        &mut self,
+        element_type: ElementType,
+        number_of_values: usize,
+    ) -> VortekResult<VoxelBuffer> {
+        if self.is_binary {
+            self.skip_line();
+            let size = number_of_values
+                .checked_mul(element_type.size_in_bytes())
+                .ok_or_else(|| {
+                    vtk_error(format!("Invalid number of values {}.", number_of_values))
+                })?;
+            let bytes = self
+                .position
+                .checked_add(size)
+                .and_then(|end| self.bytes.get(self.position..end))
+                .ok_or_else(|| vtk_error("Unexpected end of binary data.".to_string()))?;
+            self.position += size;
+            super::decode_values(bytes, element_type, Endianness::Big)
+        } else {
+            let values = (0..number_of_values)
+                .map(|_| self.parse_token::<f64>())
+                .collect::<VortekResult<Vec<_>>>()?;
+            Ok(super::values_from_f64(values, element_type))
+        }
+    }
+
+    /// Skips a metadata block, which is terminated by an empty line.
+    fn skip_metadata(&mut self) -> VortekResult<()> {
+        self.skip_line();
+        while self.position < self.bytes.len() {
+            if self.next_line()?.trim().is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_line(&mut self) -> VortekResult<&'a str> {
+        let start = self.position;
+        self.skip_line();
+        std::str::from_utf8(&self.bytes[start..self.position])
+            .map(|line| line.trim_end_matches(['\n', '\r']))
+            .map_err(|_| vtk_error("Header contains invalid UTF-8.".to_string()))
+    }
+
+    fn skip_line(&mut self) {
+        self.position = self.bytes[self.position..]
+            .iter()
+            .position(|&byte| byte == b'\n')
+            .map_or(self.bytes.len(), |offset| self.position + offset + 1);
+    }
+
+    fn next_token(&mut self) -> Option<&'a str> {
+        while self.position < self.bytes.len() && self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        let start = self.position;
+        while self.position < self.bytes.len() && !self.bytes[self.position].is_ascii_whitespace() {
+            self.position += 1;
+        }
+        if start == self.position {
+            None
+        } else {
+            std::str::from_utf8(&self.bytes[start..self.position]).ok()
+        }
+    }
+
+    fn required_token(&mut self) -> VortekResult<&'a str> {
+        self.next_token()
+            .ok_or_else(|| vtk_error("Unexpected end of file.".to_string()))
+    }
+
+    fn parse_token<T: std::str::FromStr>(&mut self) -> VortekResult<T> {
+        let token = self.required_token()?;
+        token
+            .parse()
+            .map_err(|_| vtk_error(format!("Invalid number: {}", token)))
+    }
+
+    fn expect_token(&mut self, expected: &str) -> VortekResult<()> {
+        let token = self.required_token()?;
+        if token.eq_ignore_ascii_case(expected) {
+            Ok(())
+        } else {
+            Err(vtk_error(format!("Expected {}, found {}", expected, token)))
+        }
+    }
+}
+
+fn parse_legacy_type(name: &str) -> VortekResult<ElementType> {
+    Ok(match name {
+        "unsigned_char" => ElementType::U8,
+        "char" => ElementType::I8,
+        "unsigned_short" => ElementType::U16,
+        "short" => ElementType::I16,
+        "unsigned_int" => ElementType::U32,
+        "int" => ElementType::I32,
+        "unsigned_long" | "vtktypeuint64" => ElementType::U64,
+        "long" | "vtktypeint64" | "vtkIdType" => ElementType::I64,
+        "float" => ElementType::F32,
+        "double" => ElementType::F64,
+        _ => return Err(vtk_error(format!("Unsupported data type: {}", name))),
+    })
+}
+
+/// An XML start or empty-element tag.
+struct XmlTag<'a> {
+    name: &'a str,
+    attributes: Vec<(&'a str, &'a str)>,
+    is_empty: bool,
+    is_end: bool,
+    /// Index of the first byte after the tag.
+    end: usize,
+}
+
+impl<'a> XmlTag<'a> {
+    fn attribute(&self, name: &str) -> Option<&'a str> {
+        self.attributes
+            .iter()
+            .find(|(attribute_name, _)| *attribute_name == name)
+            .map(|(_, value)| *value)
+    }
+
+    fn required_attribute(&self, name: &str) -> VortekResult<&'a str> {
+        self.attribute(name).ok_or_else(|| {
+            vtk_error(format!(
+                "Missing attribute {} on {} element.",
+                name, self.name
+            ))
+        })
+    }
+
+    /// Finds the next tag in the given text at or after the given index,
+    /// skipping declarations and comments.
+    fn next(text: &'a str, from: usize) -> VortekResult<Option<Self>> {
+        let mut start = from;
+        loop {
+            let tag_start = match text[start..].find('<') {
+                Some(offset) => start + offset,
+                None => return Ok(None),
+            };
+            let rest = &text[tag_start..];
+            if rest.starts_with("<?") || rest.starts_with("<!") {
+                let terminator = if rest.starts_with("<!--") { "-->" } else { ">" };
+                start = tag_start
+                    + rest
+                        .find(terminator)
+                        .ok_or_else(|| vtk_error("Unterminated XML tag.".to_string()))?
+                    + terminator.len();
+                continue;
+            }
+            let tag_end = tag_start
+                + rest
+                    .find('>')
+                    .ok_or_else(|| vtk_error("Unterminated XML tag.".to_string()))?;
+            let mut content = &text[tag_start + 1..tag_end];
+            let is_end = content.starts_with('/');
+            let is_empty = content.ends_with('/');
+            content = content.trim_start_matches('/').trim_end_matches('/');
+
+            let name_end = content
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(content.len());
+            let name = &content[..name_end];
+            let mut attributes = Vec::new();
+            let mut remaining = content[name_end..].trim_start();
+            while !remaining.is_empty() {
+                let equals = remaining
+                    .find('=')
+                    .ok_or_else(|| vtk_error(format!("Invalid XML attributes in {}", name)))?;
+                let attribute_name = remaining[..equals].trim();
+                let value_part = remaining[equals + 1..].trim_start();
+                let quote = value_part
+                    .chars()
+                    .next()
+                    .filter(|&c| c == '"' || c == '\'')
+                    .ok_or_else(|| vtk_error(format!("Invalid XML attributes in {}", name)))?;
+                let value_end = value_part[1..]
+                    .find(quote)
+                    .ok_or_else(|| vtk_error(format!("Invalid XML attributes in {}", name)))?;
+                attributes.push((attribute_name, &value_part[1..=value_end]));
+                remaining = value_part[value_end + 2..].trim_start();
+            }
+
+            return Ok(Some(Self {
+                name,
+                attributes,
+                is_empty,
+                is_end,
+                end: tag_end + 1,
+            }));
+        }
+    }
+}
+
+/// Settings from the root element that determine how binary data is decoded.
+#[derive(Clone, Copy, Debug)]
+struct BinarySettings {
+    endianness: Endianness,
+    header_type: ElementType,
+    is_compressed: bool,
+}
+
+/// Description of a data array in an XML file.
+struct DataArray<'a> {
+    name: String,
+    element_type: ElementType,
+    number_of_components: usize,
+    format: &'a str,
+    offset: usize,
+    content: &'a str,
+}
+
+/// Decodes the given XML ImageData file contents.
+pub fn read_vti_from_bytes(bytes: &[u8]) -> VortekResult<VtkDataset> {
+    // Appended raw data is not valid text, so only the part before it is parsed as XML
+    let appended_start = find_bytes(bytes, b"<AppendedData");
+    let xml = std::str::from_utf8(&bytes[..appended_start.unwrap_or(bytes.len())])
+        .map_err(|_| vtk_error("XML contains invalid UTF-8.".to_string()))?;
+
+    let mut settings = None;
+    let mut geometry = None;
+    let mut arrays = Vec::new();
+    let mut is_point_data = false;
+    let mut number_of_pieces = 0;
+
+    let mut position: usize = 0;
+    while let Some(tag) = XmlTag::next(xml, position)? {
+        position = tag.end;
+        match (tag.name, tag.is_end) {
+            ("VTKFile", false) => {
+                if tag.attribute("type") != Some("ImageData") {
+                    return Err(vtk_error(
+                        "Only XML files of type ImageData are supported.".to_string(),
+                    ));
+                }
+                settings = Some(parse_binary_settings(&tag)?);
+            }
+            ("ImageData", false) => geometry = Some(parse_image_geometry(&tag)?),
+            ("Piece", false) => {
+                number_of_pieces += 1;
+                if number_of_pieces > 1 {
+                    return Err(vtk_error(
+                        "ImageData with multiple pieces is not supported.".to_string(),
+                    ));
+                }
+            }
+            ("PointData", false) => is_point_data = !tag.is_empty,
+            ("PointData", true) => is_point_data = false,
+            ("DataArray", false) if is_point_data => {
+                let content = if tag.is_empty {
+                    ""
+                } else {
+                    let content_end = xml[tag.end..]
+                        .find("</DataArray")
+                        .ok_or_else(|| vtk_error("Unterminated DataArray.".to_string()))?;
+                    position = tag.end + content_end;
+                    &xml[tag.end..position]
+                };
+                arrays.push(DataArray {
+                    name: tag.required_attribute("Name")?.to_string(),
+                    element_type: parse_xml_type(tag.required_attribute("type")?)?,
+                    number_of_components: parse_attribute(
+                        tag.attribute("NumberOfComponents").unwrap_or("1"),
+                    )?,
+                    format: tag.attribute("format").unwrap_or("ascii"),
+                    offset: parse_attribute(tag.attribute("offset").unwrap_or("0"))?,
+                    content,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let settings = settings.ok_or_else(|| vtk_error("Missing VTKFile element.".to_string()))?;
+    let geometry = geometry.ok_or_else(|| vtk_error("Missing ImageData element.".to_string()))?;
+
+    let appended = match appended_start {
+        Some(start) => Some(parse_appended_data(bytes, start)?),
+        None => None,
+    };
+
+    let mut dataset = VtkDataset::default();
+    for array in arrays {
+        let values = match array.format {
+            "ascii" => {
+                let values = array
+                    .content
+                    .split_whitespace()
+                    .map(|token| {
+                        token
+                            .parse()
+                            .map_err(|_| vtk_error(format!("Invalid number: {}", token)))
+                    })
+                    .collect::<VortekResult<Vec<f64>>>()?;
+                super::values_from_f64(values, array.element_type)
+            }
+            "binary" => {
+                let bytes = decode_base64_array(array.content, settings)?;
+                super::decode_values(&bytes, array.element_type, settings.endianness)?
+            }
+            "appended" => {
+                let (is_base64, data) = appended.ok_or_else(|| {
+                    vtk_error(format!(
+                        "Array {} refers to missing appended data.",
+                        array.name
+                    ))
+                })?;
+                let data = data
+                    .get(array.offset..)
+                    .ok_or_else(|| vtk_error(format!("Invalid offset for {}.", array.name)))?;
+                let bytes = if is_base64 {
+                    let text = std::str::from_utf8(data).map_err(|_| {
+                        vtk_error("Appended base64 data is not valid text.".to_string())
+                    })?;
+                    decode_base64_array(text, settings)?
+                } else {
+                    decode_raw_array(data, settings)?
+                };
+                super::decode_values(&bytes, array.element_type, settings.endianness)?
+            }
+            format => return Err(vtk_error(format!("Unsupported array format: {}", format))),
+        };
+        dataset.add_field(array.name, &geometry, array.number_of_components, values)?;
+    }
+    Ok(dataset)
+}
+
+fn parse_binary_settings(tag: &XmlTag<'_>) -> VortekResult<BinarySettings> {
+    let endianness = match tag.attribute("byte_order").unwrap_or("LittleEndian") {
+        "LittleEndian" => Endianness::Little,
+        "BigEndian" => Endianness::Big,
+        byte_order => return Err(vtk_error(format!("Invalid byte order: {}", byte_order))),
+    };
+    let header_type = match tag.attribute("header_type").unwrap_or("UInt32") {
+        "UInt32" => ElementType::U32,
+        "UInt64" => ElementType::U64,
+        header_type => {
+            return Err(vtk_error(format!(
+                "Unsupported header type: {}",
+                header_type
+            )))
+        }
+    };
+    let is_compressed = match tag.attribute("compressor") {
+        None | Some("") => false,
+        Some("vtkZLibDataCompressor") => true,
+        Some(compressor) => {
+            return Err(vtk_error(format!("Unsupported compressor: {}", compressor)))
+        }
+    };
+    Ok(BinarySettings {
+        endianness,
+        header_type,
+        is_compressed,
+    })
+}
+
+fn parse_image_geometry(tag: &XmlTag<'_>) -> VortekResult<Geometry> {
+    let extent: Vec<i64> = parse_attribute_list(tag.required_attribute("WholeExtent")?)?;
+    if extent.len() != 6 {
+        return Err(vtk_error(format!("Invalid WholeExtent: {:?}", extent)));
+    }
+    let spacing: Vec<f32> = parse_attribute_list(tag.attribute("Spacing").unwrap_or("1 1 1"))?;
+    let origin: Vec<f32> = parse_attribute_list(tag.attribute("Origin").unwrap_or("0 0 0"))?;
+    let direction: Vec<f32> =
+        parse_attribute_list(tag.attribute("Direction").unwrap_or("1 0 0 0 1 0 0 0 1"))?;
+    if spacing.len() != 3 || origin.len() != 3 || direction.len() != 9 {
+        return Err(vtk_error("Invalid ImageData geometry.".to_string()));
+    }
+
+    let mut dimensions = [0; 3];
+    let mut orientation = [[0.0; 3]; 3];
+    let mut volume_origin = [origin[0], origin[1], origin[2]];
+    for axis in 0..3 {
+        dimensions[axis] = extent[2 * axis + 1]
+            .checked_sub(extent[2 * axis])
+            .and_then(|difference| difference.checked_add(1))
+            .and_then(|size| usize::try_from(size.max(0)).ok())
+            .ok_or_else(|| vtk_error(format!("Invalid WholeExtent: {:?}", extent)))?;
+        // The direction matrix is stored in row-major order with the axis
+        // directions as columns
+        for (dim, component) in orientation[axis].iter_mut().enumerate() {
+            *component = direction[3 * dim + axis];
+        }
+        let norm = orientation[axis].iter().map(|c| c * c).sum::<f32>().sqrt();
+        if !(norm.is_finite() && norm > 0.0) {
+            return Err(vtk_error(format!("Invalid Direction: {:?}", direction)));
+        }
+        // The origin refers to index zero, which may lie outside the extent
+        let start_offset = extent[2 * axis] as f32 * spacing[axis];
+        for (dim, origin_component) in volume_origin.iter_mut().enumerate() {
+            *origin_component += start_offset * orientation[axis][dim];
+        }
+    }
+
+    // Negative spacings are represented by flipping the axis direction
+    let mut volume_spacing = [1.0; 3];
+    for axis in 0..3 {
+        volume_spacing[axis] = spacing[axis].abs();
+        if spacing[axis] < 0.0 {
+            for component in orientation[axis].iter_mut() {
+                *component = -*component;
+            }
+        }
+    }
+
+    Ok(Geometry {
+        dimensions,
+        spacing: volume_spacing,
+        origin: volume_origin,
+        orientation,
+    })
+}
+
+/// Locates the appended data section, and returns whether it is base64 encoded
+/// along with the bytes following the leading underscore.
+fn parse_appended_data(bytes: &[u8], start: usize) -> VortekResult<(bool, &[u8])> {
+    let tag_end = start
+        + bytes[start..]
+            .iter()
+            .position(|&byte| byte == b'>')
+            .ok_or_else(|| vtk_error("Unterminated AppendedData tag.".to_string()))?;
+    let tag_text = std::str::from_utf8(&bytes[start..=tag_end])
+        .map_err(|_| vtk_error("Invalid AppendedData tag.".to_string()))?;
+    let tag = XmlTag::next(tag_text, 0)?
+        .ok_or_else(|| vtk_error("Invalid AppendedData tag.".to_string()))?;
+    let is_base64 = match tag.attribute("encoding").unwrap_or("raw") {
+        "raw" => false,
+        "base64" => true,
+        encoding => {
+            return Err(vtk_error(format!(
+                "Unsupported appended data encoding: {}",
+                encoding
+            )))
+        }
+    };
+    let data_start = tag_end
+        + 1
+        + bytes[tag_end + 1..]
+            .iter()
+            .position(|&byte| byte == b'_')
+            .ok_or_else(|| vtk_error("Missing appended data marker.".to_string()))?
+        + 1;
+    Ok((is_base64, &bytes[data_start..]))
+}
+
+/// Decodes a raw binary array, consisting of a header followed by the
+/// possibly compressed data.
+fn decode_raw_array(data: &[u8], settings: BinarySettings) -> VortekResult<Vec<u8>> {
+    let truncated = || vtk_error("Unexpected end of appended data.".to_string());
+    let header_size = settings.header_type.size_in_bytes();
+    let read_header_value = |index: usize| -> VortekResult<usize> {
+        data.get(index * header_size..(index + 1) * header_size)
+            .map(|bytes| read_unsigned(bytes, settings.endianness))
+            .ok_or_else(truncated)
+    };
+    if settings.is_compressed {
+        let number_of_blocks = read_header_value(0)?;
+        let blocks_start = compressed_header_size(number_of_blocks, header_size)?;
+        if blocks_start > data.len() {
+            return Err(truncated());
+        }
+        let compressed_sizes = (0..number_of_blocks)
+            .map(|block| read_header_value(3 + block))
+            .collect::<VortekResult<Vec<_>>>()?;
+        decompress_blocks(&data[blocks_start..], &compressed_sizes)
+    } else {
+        let size = read_header_value(0)?;
+        header_size
+            .checked_add(size)
+            .and_then(|end| data.get(header_size..end))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(truncated)
+    }
+}
+
+/// Decodes a base64 encoded binary array, consisting of a header followed by
+/// the possibly compressed data.
+///
+/// For compressed data the header and data are encoded separately. For
+/// uncompressed data they may be encoded either together or separately.
+fn decode_base64_array(text: &str, settings: BinarySettings) -> VortekResult<Vec<u8>> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let truncated = || vtk_error("Unexpected end of base64 data.".to_string());
+    let header_size = settings.header_type.size_in_bytes();
+    let encoded_length = |size: usize| -> VortekResult<usize> {
+        size.div_ceil(3)
+            .checked_mul(4)
+            .ok_or_else(|| vtk_error(format!("Invalid base64 data size {}.", size)))
+    };
+    let decode = |start: usize, length: usize| -> VortekResult<Vec<u8>> {
+        let end = start.saturating_add(length).min(text.len());
+        let encoded = text.get(start..end).ok_or_else(truncated)?;
+        base64::decode(encoded).map_err(|err| {
+            VortekError::DataIOError(DataIOError::from_error("Invalid base64 data: ", err))
+        })
+    };
+    let read_header_value = |header: &[u8], index: usize| -> VortekResult<usize> {
+        header
+            .get(index * header_size..(index + 1) * header_size)
+            .map(|bytes| read_unsigned(bytes, settings.endianness))
+            .ok_or_else(truncated)
+    };
+
+    if settings.is_compressed {
+        let first_value = decode(0, encoded_length(header_size)?)?;
+        let number_of_blocks = read_header_value(&first_value, 0)?;
+        let header_length = compressed_header_size(number_of_blocks, header_size)?;
+        let header = decode(0, encoded_length(header_length)?)?;
+        if header.len() < header_length {
+            return Err(truncated());
+        }
+        let compressed_sizes = (0..number_of_blocks)
+            .map(|block| read_header_value(&header, 3 + block))
+            .collect::<VortekResult<Vec<_>>>()?;
+        let total_compressed_size = compressed_sizes
+            .iter()
+            .try_fold(0usize, |total, &size| total.checked_add(size))
+            .ok_or_else(truncated)?;
+        let data_start = encoded_length(header_length)?;
+        let data = decode(data_start, encoded_length(total_compressed_size)?)?;
+        decompress_blocks(&data, &compressed_sizes)
+    } else {
+        let header_length = encoded_length(header_size)?;
+        let header_is_separate = text
+            .get(..header_length.min(text.len()))
+            .is_some_and(|header| header.ends_with('='));
+        let first_value = decode(0, header_length)?;
+        let size = read_header_value(&first_value, 0)?;
+        if header_is_separate {
+            let data = decode(header_length, encoded_length(size)?)?;
+            Ok(data[..size.min(data.len())].to_vec())
+        } else {
+            let data = decode(0, encoded_length(header_size.saturating_add(size))?)?;
+            header_size
+                .checked_add(size)
+                .and_then(|end| data.get(header_size..end))
+                .map(<[u8]>::to_vec)
+                .ok_or_else(truncated)
+        }
+    }
+}
+
+/// Computes the size in bytes of the header of a compressed array with the
+/// given number of blocks, which holds three values in addition to the
+/// compressed size of each block.
+fn compressed_header_size(number_of_blocks: usize, header_size: usize) -> VortekResult<usize> {
+    number_of_blocks
+        .checked_add(3)
+        .and_then(|number_of_values| number_of_values.checked_mul(header_size))
+        .ok_or_else(|| vtk_error(format!("Invalid number of blocks {}.", number_of_blocks)))
+}
+
+/// Decompresses and concatenates consecutive zlib compressed blocks with the
+/// given compressed sizes.
+fn decompress_blocks(data: &[u8], compressed_sizes: &[usize]) -> VortekResult<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    let mut position: usize = 0;
+    for &compressed_size in compressed_sizes {
+        let block = position
+            .checked_add(compressed_size)
+            .and_then(|end| data.get(position..end))
+            .ok_or_else(|| vtk_error("Unexpected end of compressed data.".to_string()))?;
+        ZlibDecoder::new(block)
+            .read_to_end(&mut decompressed)
+            .map_err(|err| {
+                VortekError::DataIOError(DataIOError::from_error(
+                    "Could not decompress VTK data: ",
+                    err,
+                ))
+            })?;
+        position += compressed_size;
+    }
+    Ok(decompressed)
+}
+
+fn read_unsigned(bytes: &[u8], endianness: Endianness) -> usize {
+    let mut value: u64 = 0;
+    for index in 0..bytes.len() {
+        let byte = if endianness.is_little() {
+            bytes[bytes.len() - 1 - index]
+        } else {
+            bytes[index]
+        };
+        value = (value << 8) | u64::from(byte);
+    }
+    value as usize
+}
+
+fn parse_xml_type(name: &str) -> VortekResult<ElementType> {
+    Ok(match name {
+        "Int8" | "Char" => ElementType::I8,
+        "UInt8" | "UChar" => ElementType::U8,
+        "Int16" | "Short" => ElementType::I16,
+        "UInt16" | "UShort" => ElementType::U16,
+        "Int32" | "Int" => ElementType::I32,
+        "UInt32" | "UInt" => ElementType::U32,
+        "Int64" | "Long" => ElementType::I64,
+        "UInt64" | "ULong" => ElementType::U64,
+        "Float32" | "Float" => ElementType::F32,
+        "Float64" | "Double" => ElementType::F64,
+        _ => return Err(vtk_error(format!("Unsupported data type: {}", name))),
+    })
+}
+
+fn parse_attribute<T: std::str::FromStr>(value: &str) -> VortekResult<T> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| vtk_error(format!("Invalid attribute value: {}", value)))
+}
+
+fn parse_attribute_list<T: std::str::FromStr>(value: &str) -> VortekResult<Vec<T>> {
+    value.split_whitespace().map(parse_attribute).collect()
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Computes the number of values in an array with the given number of tuples
+/// and components, or returns an error if it does not fit in a `usize`.
+fn value_count(number_of_tuples: usize, number_of_components: usize) -> VortekResult<usize> {
+    number_of_tuples
+        .checked_mul(number_of_components)
+        .ok_or_else(|| {
+            vtk_error(format!(
+                "Array with {} tuples of {} components is too large.",
+                number_of_tuples, number_of_components
+            ))
+        })
+}
+
+fn vtk_error(message: String) -> VortekError {
+    VortekError::DataIOError(DataIOError::from_string(format!(
+        "Invalid VTK file: {}",
+        message
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::ZlibEncoder, Compression};
+    use std::io::Write;
+
+    fn encode_inline_floats(values: &[f32]) -> String {
+        let mut bytes = ((values.len() * 4) as u32).to_le_bytes().to_vec();
+        bytes.extend(values.iter().flat_map(|value| value.to_le_bytes()));
+        base64::encode(&bytes)
+    }
+
+    fn legacy_binary_file(dimensions: &str, number_of_points: usize, values: &[f32]) -> Vec<u8> {
+        let mut bytes = format!(
+            "# vtk DataFile Version 3.0\ntest\nBINARY\nDATASET STRUCTURED_POINTS\n\
+             DIMENSIONS {}\nSPACING 0.5 1 2\nORIGIN 1 2 3\nPOINT_DATA {}\n\
+             SCALARS density float 1\nLOOKUP_TABLE default\n",
+            dimensions, number_of_points
+        )
+        .into_bytes();
+        bytes.extend(values.iter().flat_map(|value| value.to_be_bytes()));
+        bytes.push(b'\n');
+        bytes
+    }
+
+    /// Attributes of the ImageData element for a 2x2x1 grid.
+    const IMAGE_ATTRIBUTES: &str =
+        "WholeExtent=\"0 1 0 1 0 0\" Origin=\"1 2 3\" Spacing=\"0.5 1 2\"";
+
+    /// Creates a little-endian XML ImageData file with the given extra
+    /// attributes on the root element, attributes on the ImageData element,
+    /// data array elements and appended data section.
+    fn vti_file(
+        root_attributes: &str,
+        image_attributes: &str,
+        data_arrays: &str,
+        appended: Option<(&str, &[u8])>,
+    ) -> Vec<u8> {
+        let mut bytes = format!(
+            "<?xml version=\"1.0\"?>
+<VTKFile type=\"ImageData\" byte_order=\"LittleEndian\"{}>
+  <ImageData {}>
+    <Piece Extent=\"0 1 0 1 0 0\">
+      <PointData>
+        {}
+      </PointData>
+    </Piece>
+  </ImageData>
+",
+            root_attributes, image_attributes, data_arrays
+        )
+        .into_bytes();
+        if let Some((encoding, data)) = appended {
+            bytes.extend(format!("  <AppendedData encoding=\"{}\">\n   _", encoding).bytes());
+            bytes.extend(data);
+            bytes.extend(b"\n  </AppendedData>\n");
+        }
+        bytes.extend(b"</VTKFile>\n");
+        bytes
+    }
+
+    /// Creates an XML ImageData file for a 2x2x1 grid with a single binary
+    /// float array with the given inline content.
+    fn vti_with_inline_array(root_attributes: &str, content: &str) -> Vec<u8> {
+        let data_array = format!(
+            "<DataArray type=\"Float32\" Name=\"density\" format=\"binary\">{}</DataArray>",
+            content
+        );
+        vti_file(root_attributes, IMAGE_ATTRIBUTES, &data_array, None)
+    }
+
+    fn float_bytes(values: &[f32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    fn double_bytes(values: &[f64]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .collect()
+    }
+
+    /// Prepends the 32-bit size header of an uncompressed binary array.
+    fn with_size_header(data: &[u8]) -> Vec<u8> {
+        let mut bytes = (data.len() as u32).to_le_bytes().to_vec();
+        bytes.extend(data);
+        bytes
+    }
+
+    /// Splits the given data into zlib compressed blocks of the given size, and
+    /// returns the 32-bit block header along with the concatenated blocks.
+    fn compress_blocks(data: &[u8], block_size: usize) -> (Vec<u8>, Vec<u8>) {
+        let blocks: Vec<Vec<u8>> = data
+            .chunks(block_size)
+            .map(|chunk| {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(chunk).unwrap();
+                encoder.finish().unwrap()
+            })
+            .collect();
+        let last_block_size = match data.len() % block_size {
+            0 => block_size,
+            size => size,
+        };
+        let mut header_values = vec![blocks.len(), block_size, last_block_size];
+        header_values.extend(blocks.iter().map(Vec::len));
+        let header = header_values
+            .iter()
+            .flat_map(|&value| (value as u32).to_le_bytes())
+            .collect();
+        (header, blocks.concat())
+    }
+
+    const SCALARS: [f32; 4] = [1.0, -2.5, 3.0, 4.25];
+    const VECTORS: [f64; 12] = [
+        1.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 3.0, -1.0, -2.0, -3.0,
+    ];
+
+    const SCALAR_AND_VECTOR_ARRAYS: &str = "<DataArray type=\"Float32\" Name=\"density\" \
+        format=\"appended\" offset=\"0\"/>
+        <DataArray type=\"Float64\" Name=\"velocity\" NumberOfComponents=\"3\" \
+        format=\"appended\" offset=\"OFFSET\"/>";
+
+    /// Checks that the given dataset holds the scalar and vector test fields
+    /// on the 2x2x1 grid.
+    fn assert_has_test_fields(dataset: &VtkDataset) {
+        assert_eq!(
+            dataset.field_names().collect::<Vec<_>>(),
+            vec!["density", "velocity"]
+        );
+        match dataset.field("density").unwrap() {
+            Volume::Scalar(volume) => {
+                assert_eq!(volume.dimensions(), [2, 2, 1]);
+                assert_eq!(volume.spacing(), [0.5, 1.0, 2.0]);
+                assert_eq!(volume.origin(), [1.0, 2.0, 3.0]);
+                assert_eq!(volume.voxels().to_f32(), SCALARS.to_vec());
+            }
+            Volume::Vector(_) => panic!("Expected scalar field."),
+        }
+        match dataset.field("velocity").unwrap() {
+            Volume::Vector(volume) => {
+                assert_eq!(volume.dimensions(), [2, 2, 1]);
+                let expected: Vec<[f32; 3]> = VECTORS
+                    .chunks(3)
+                    .map(|vector| [vector[0] as f32, vector[1] as f32, vector[2] as f32])
+                    .collect();
+                assert_eq!(volume.iter_vectors().collect::<Vec<_>>(), expected);
+            }
+            Volume::Scalar(_) => panic!("Expected vector field."),
+        }
+    }
+
+    #[test]
+    fn reads_appended_raw_arrays() {
+        let mut appended = with_size_header(&float_bytes(&SCALARS));
+        let offset = appended.len();
+        appended.extend(with_size_header(&double_bytes(&VECTORS)));
+        let arrays = SCALAR_AND_VECTOR_ARRAYS.replace("OFFSET", &offset.to_string());
+        let bytes = vti_file(
+            " header_type=\"UInt32\"",
+            IMAGE_ATTRIBUTES,
+            &arrays,
+            Some(("raw", &appended)),
+        );
+        assert_has_test_fields(&read_vti_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_appended_base64_arrays() {
+        let mut appended = base64::encode(with_size_header(&float_bytes(&SCALARS)));
+        let offset = appended.len();
+        // The header may also be encoded separately from the data
+        appended.push_str(&base64::encode((VECTORS.len() as u32 * 8).to_le_bytes()));
+        appended.push_str(&base64::encode(double_bytes(&VECTORS)));
+        let arrays = SCALAR_AND_VECTOR_ARRAYS.replace("OFFSET", &offset.to_string());
+        let bytes = vti_file(
+            "",
+            IMAGE_ATTRIBUTES,
+            &arrays,
+            Some(("base64", appended.as_bytes())),
+        );
+        assert_has_test_fields(&read_vti_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_compressed_appended_raw_arrays() {
+        // Blocks that do not evenly divide the data exercise the partial last block
+        let (header, blocks) = compress_blocks(&float_bytes(&SCALARS), 12);
+        let mut appended = [header, blocks].concat();
+        let offset = appended.len();
+        let (header, blocks) = compress_blocks(&double_bytes(&VECTORS), 32);
+        appended.extend(header);
+        appended.extend(blocks);
+        let arrays = SCALAR_AND_VECTOR_ARRAYS.replace("OFFSET", &offset.to_string());
+        let bytes = vti_file(
+            " compressor=\"vtkZLibDataCompressor\"",
+            IMAGE_ATTRIBUTES,
+            &arrays,
+            Some(("raw", &appended)),
+        );
+        assert_has_test_fields(&read_vti_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_compressed_inline_base64_arrays() {
+        let encode = |data: &[u8], block_size: usize| {
+            let (header, blocks) = compress_blocks(data, block_size);
+            base64::encode(header) + &base64::encode(blocks)
+        };
+        let arrays = format!(
+            "<DataArray type=\"Float32\" Name=\"density\" format=\"binary\">
+          {}
+        </DataArray>
+        <DataArray type=\"Float64\" Name=\"velocity\" NumberOfComponents=\"3\" format=\"binary\">{}</DataArray>",
+            encode(&float_bytes(&SCALARS), 16),
+            encode(&double_bytes(&VECTORS), 40)
+        );
+        let bytes = vti_file(
+            " compressor=\"vtkZLibDataCompressor\"",
+            IMAGE_ATTRIBUTES,
+            &arrays,
+            None,
+        );
+        assert_has_test_fields(&read_vti_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_ascii_arrays() {
+        let arrays = "<DataArray type=\"Float32\" Name=\"density\" format=\"ascii\">
+          1 -2.5 3 4.25
+        </DataArray>
+        <DataArray type=\"Float64\" Name=\"velocity\" NumberOfComponents=\"3\">
+          1 0 0  0 2 0  0 0 3  -1 -2 -3
+        </DataArray>";
+        let bytes = vti_file("", IMAGE_ATTRIBUTES, arrays, None);
+        assert_has_test_fields(&read_vti_from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn reads_legacy_ascii_file() {
+        let text = "# vtk DataFile Version 2.0
+test
+ASCII
+DATASET STRUCTURED_POINTS
+DIMENSIONS 2 2 1
+ORIGIN 1 2 3
+SPACING 0.5 1 2
+POINT_DATA 4
+SCALARS density float
+LOOKUP_TABLE default
+1 -2.5
+3 4.25
+VECTORS velocity double
+1 0 0 0 2 0
+0 0 3 -1 -2 -3
+FIELD extra 1
+temperature 1 4 int
+10 20 30 40
+";
+        let dataset = LegacyReader::new(text.as_bytes()).read().unwrap();
+        let temperature = match dataset.field("temperature").unwrap() {
+            Volume::Scalar(volume) => volume.voxels().to_f32(),
+            Volume::Vector(_) => panic!("Expected scalar field."),
+        };
+        assert_eq!(temperature, vec![10.0, 20.0, 30.0, 40.0]);
+
+        let mut fields = dataset.into_fields();
+        fields.retain(|(name, _)| name != "temperature");
+        assert_has_test_fields(&VtkDataset { fields });
+    }
+
+    #[test]
+    fn reads_legacy_binary_file() {
+        let values: Vec<f32> = (0..12).map(|value| value as f32).collect();
+        let bytes = legacy_binary_file("3 2 2", 12, &values);
+        let dataset = LegacyReader::new(&bytes).read().unwrap();
+        match dataset.field("density").unwrap() {
+            Volume::Scalar(volume) => {
+                assert_eq!(volume.dimensions(), [3, 2, 2]);
+                assert_eq!(volume.spacing(), [0.5, 1.0, 2.0]);
+                assert_eq!(volume.origin(), [1.0, 2.0, 3.0]);
+                assert_eq!(volume.voxels().to_f32(), values);
+            }
+            Volume::Vector(_) => panic!("Expected scalar field."),
+        }
+    }
+
+    #[test]
+    fn truncated_legacy_file_is_an_error() {
+        let values: Vec<f32> = (0..12).map(|value| value as f32).collect();
+        let bytes = legacy_binary_file("3 2 2", 12, &values);
+        let data_start = bytes.len() - 4 * values.len() - 1;
+        for length in [0, 10, 30, 60, data_start, bytes.len() - 5] {
+            assert!(LegacyReader::new(&bytes[..length]).read().is_err());
+        }
+    }
+
+    #[test]
+    fn legacy_file_with_overflowing_size_is_an_error() {
+        let dimensions = format!("{} 2 1", usize::MAX);
+        let bytes = legacy_binary_file(&dimensions, 0, &[]);
+        assert!(LegacyReader::new(&bytes).read().is_err());
+    }
+
+    #[test]
+    fn overflowing_extent_is_an_error() {
+        let image_attributes = IMAGE_ATTRIBUTES.replace(
+            "WholeExtent=\"0 1 0 1 0 0\"",
+            &format!("WholeExtent=\"{} {} 0 1 0 0\"", i64::MIN, i64::MAX),
+        );
+        let bytes = vti_file("", &image_attributes, "", None);
+        assert!(read_vti_from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn degenerate_direction_is_an_error() {
+        let read_with_direction = |direction: &str| {
+            let image_attributes = format!("{} Direction=\"{}\"", IMAGE_ATTRIBUTES, direction);
+            read_vti_from_bytes(&vti_file("", &image_attributes, "", None))
+        };
+        assert!(read_with_direction("0 1 0 1 0 0 0 0 1").is_ok());
+        for direction in [
+            "1 0 0 0 0 0 0 0 1",
+            "1 0 0 0 NaN 0 0 0 1",
+            "inf 0 0 0 1 0 0 0 1",
+        ] {
+            assert!(read_with_direction(direction).is_err(), "{}", direction);
+        }
+    }
+
+    #[test]
+    fn reads_inline_base64_array() {
+        let values = [1.0, 2.0, 3.0, 4.0];
+        let bytes = vti_with_inline_array("", &encode_inline_floats(&values));
+        let dataset = read_vti_from_bytes(&bytes).unwrap();
+        match dataset.field("density").unwrap() {
+            Volume::Scalar(volume) => {
+                assert_eq!(volume.dimensions(), [2, 2, 1]);
+                assert_eq!(volume.voxels().to_f32(), values.to_vec());
+            }
+            Volume::Vector(_) => panic!("Expected scalar field."),
+        }
+    }
+
+    #[test]
+    fn truncated_base64_array_is_an_error() {
+        let encoded = encode_inline_floats(&[1.0, 2.0, 3.0, 4.0]);
+        for length in [0, 2, 4, 8, encoded.len() - 4] {
+            let bytes = vti_with_inline_array("", &encoded[..length]);
+            assert!(read_vti_from_bytes(&bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn base64_array_with_multibyte_characters_is_an_error() {
+        let bytes = vti_with_inline_array("", "AAAA\u{e9}\u{e9}\u{e9}AAAA");
+        assert!(read_vti_from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn compressed_array_with_huge_block_count_is_an_error() {
+        let settings = " compressor=\"vtkZLibDataCompressor\"";
+        for number_of_blocks in [u32::MAX, 1000] {
+            let header = base64::encode(number_of_blocks.to_le_bytes());
+            let bytes = vti_with_inline_array(settings, &header);
+            assert!(read_vti_from_bytes(&bytes).is_err());
+        }
+        let bytes = vti_with_inline_array(settings, "");
+        assert!(read_vti_from_bytes(&bytes).is_err());
+    }
+
+    #[test]
+    fn truncated_raw_compressed_array_is_an_error() {
+        let settings = BinarySettings {
+            endianness: Endianness::Little,
+            header_type: ElementType::U64,
+            is_compressed: true,
+        };
+        let header = u64::MAX.to_le_bytes();
+        assert!(decode_raw_array(&header, settings).is_err());
+        assert!(decode_raw_array(&header[..3], settings).is_err());
+    }
+}
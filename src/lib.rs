@@ -1,8 +1,15 @@
 //! GPU-based volume rendering.
 
 pub mod application;
+pub mod camera;
+pub mod cli;
 pub mod color;
 pub mod error;
 pub mod graphics;
 pub mod input;
+pub mod io;
 pub mod running;
+pub mod transfer_function;
+pub mod transform;
+pub mod viewer;
+pub mod volume;
@@ -1,70 +1,77 @@
 //! Running the application.
 
 use crate::{
-    application::ApplicationState,
-    color::Color,
     error::VortekResult,
-    graphics::{
-        rendering::{backend, RendererState, RendererStateType},
-        window,
-    },
-    input::UserInput,
+    input::bindings::InputBindings,
+    io::{self, png, VolumeFormat},
+    viewer::{Viewer, ViewerConfig},
 };
-use log::error;
-use simple_logger;
-use std::process;
-use winit::event_loop::ControlFlow;
+use log::{info, Level};
+use std::path::PathBuf;
 
-pub fn run() {
-    simple_logger::init().unwrap_or_else(|err| {
-        eprintln!("Logger initialization failed: {}", err);
-        process::exit(1);
-    });
-
-    let (window_state, event_loop) = window::create_window_and_event_loop(
-        window::DEFAULT_WINDOW_NAME,
-        window::DEFAULT_WINDOW_SIZE,
-    )
-    .unwrap_or_else(|err| {
-        error!("{}", err);
-        process::exit(1);
-    });
-
-    let mut app_state =
-        ApplicationState::new(window_state.inner_physical_size().into(), Color::black());
-
-    let (backend_state, _instance) =
-        backend::create_backend_state(window_state).unwrap_or_else(|err| {
-            error!("Could not initialize backend: {}", err);
-            process::exit(1);
-        });
-    let mut renderer_state = RendererState::new(backend_state).unwrap_or_else(|err| {
-        error!("Could not initialize renderer: {}", err);
-        process::exit(1);
-    });
-
-    event_loop.run(move |event, _, control_flow| {
-        // Pause event loop if no events are available to process
-        *control_flow = ControlFlow::Wait;
-
-        let input = UserInput::from_event(event);
+/// Options for running the application.
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    /// File to read the rendered volume from, if any.
+    pub input: Option<InputOptions>,
+    /// Most verbose level of log messages to output.
+    pub log_level: Level,
+    /// Path of a PNG file to render a single image to without opening a
+    /// window, if any.
+    pub headless_output: Option<PathBuf>,
+    /// Path of the input bindings file, or `None` to look for one as described
+    /// for `InputBindings::load`.
+    pub bindings_file: Option<PathBuf>,
+    /// Configuration of the viewer.
+    pub viewer: ViewerConfig,
+}
 
-        if let UserInput::TerminationRequested = input {
-            *control_flow = ControlFlow::Exit;
-        } else {
-            app_state.update_from_input(&input);
+/// Options for reading the rendered volume.
+#[derive(Clone, Debug)]
+pub struct InputOptions {
+    /// Path of the file holding the volume.
+    pub path: PathBuf,
+    /// Format of the file, or `None` to determine it from the extension.
+    pub format: Option<VolumeFormat>,
+    /// Variable to read, for formats holding several volumes.
+    pub variable: Option<String>,
+}
 
-            if let Err(err) = render_frame(&mut renderer_state, &app_state) {
-                error!("Rendering error: {}", err);
-                process::exit(1);
-            }
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            input: None,
+            log_level: Level::Info,
+            headless_output: None,
+            bindings_file: None,
+            viewer: ViewerConfig::default(),
         }
-    });
+    }
 }
 
-fn render_frame(
-    renderer_state: &mut RendererStateType,
-    app_state: &ApplicationState,
-) -> VortekResult<()> {
-    renderer_state.draw_clear_frame(app_state.background_color())
+/// Reads the volume specified by the options and either opens a viewer for
+/// it or renders it to an image file.
+///
+/// The logger is not initialized here, so that should be done beforehand.
+pub fn run(options: RunOptions) -> VortekResult<()> {
+    let mut viewer = Viewer::new(options.viewer);
+
+    if let Some(input) = &options.input {
+        info!("Reading volume from {}.", input.path.display());
+        viewer = viewer.with_volume(io::read_volume(
+            &input.path,
+            input.format,
+            input.variable.as_deref(),
+        )?);
+    }
+
+    if let Some(output_path) = &options.headless_output {
+        let image = viewer.render_image()?;
+        png::write_png(output_path, &image)?;
+        info!("Saved image to {}.", output_path.display());
+        Ok(())
+    } else {
+        let input_bindings = InputBindings::load(options.bindings_file.as_deref())?;
+        viewer.with_input_bindings(input_bindings).run()
+    }
 }
@@ -0,0 +1,490 @@
+//! Transfer functions mapping volume values to colors and opacities.
+
+use crate::color::{Color, ColorSpace};
+
+/// Number of entries in a lookup table with standard resolution.
+pub const STANDARD_TABLE_SIZE: usize = 256;
+/// Number of entries in a lookup table with high resolution, for transfer
+/// functions with sharp features.
+pub const HIGH_RESOLUTION_TABLE_SIZE: usize = 4096;
+
+/// Smallest lower end of a logarithmic domain, relative to the upper end.
+const MIN_RELATIVE_LOGARITHMIC_LOWER_BOUND: f32 = 1e-6;
+
+/// Control point of the color curve of a transfer function.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorPoint {
+    /// Normalized position of the point in the domain, between zero and one.
+    pub position: f32,
+    /// Color at the point. The alpha component is ignored.
+    pub color: Color,
+}
+
+/// Control point of the opacity curve of a transfer function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OpacityPoint {
+    /// Normalized position of the point in the domain, between zero and one.
+    pub position: f32,
+    /// Opacity at the point, between zero and one.
+    pub opacity: f32,
+}
+
+/// How volume values are mapped to normalized positions in the domain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DomainScale {
+    /// Positions are proportional to the value.
+    #[default]
+    Linear,
+    /// Positions are proportional to the logarithm of the value.
+    Logarithmic,
+}
+
+/// Transfer function with separate piecewise-linear color and opacity curves
+/// over a normalized domain.
+///
+/// Normalized positions zero and one correspond to the ends of the value
+/// domain, which is the value range of the volume unless set explicitly.
+/// Values outside the domain get the color at the nearest end unless an
+/// out-of-range color is set.
+///
+/// Colors between control points are interpolated in sRGB by default, matching
+/// most colormap tables, but a perceptual color space can be selected instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferFunction {
+    color_points: Vec<ColorPoint>,
+    opacity_points: Vec<OpacityPoint>,
+    interpolation_space: ColorSpace,
+    scale: DomainScale,
+    domain: Option<(f32, f32)>,
+    below_range_color: Option<Color>,
+    above_range_color: Option<Color>,
+}
+
+impl TransferFunction {
+    /// Creates a new transfer function with the given color and opacity
+    /// control points, which need not be sorted.
+    ///
+    /// # Panics
+    /// If either set of points is empty, or if any position is outside the
+    /// range [0, 1].
+    pub fn new(color_points: Vec<ColorPoint>, opacity_points: Vec<OpacityPoint>) -> Self {
+        let mut transfer_function = Self {
+            color_points: Vec::new(),
+            opacity_points: Vec::new(),
+            interpolation_space: ColorSpace::default(),
+            scale: DomainScale::default(),
+            domain: None,
+            below_range_color: None,
+            above_range_color: None,
+        };
+        transfer_function.set_color_points(color_points);
+        transfer_function.set_opacity_points(opacity_points);
+        transfer_function
+    }
+
+    /// Creates a new transfer function going linearly from the first to the
+    /// second given color, with opacity increasing linearly from zero to one.
+    pub fn linear_ramp(start_color: Color, end_color: Color) -> Self {
+        Self::new(
+            vec![
+                ColorPoint {
+                    position: 0.0,
+                    color: start_color,
+                },
+                ColorPoint {
+                    position: 1.0,
+                    color: end_color,
+                },
+            ],
+            vec![
+                OpacityPoint {
+                    position: 0.0,
+                    opacity: 0.0,
+                },
+                OpacityPoint {
+                    position: 1.0,
+                    opacity: 1.0,
+                },
+            ],
+        )
+    }
+
+    /// Returns the control points of the color curve, sorted by position.
+    pub fn color_points(&self) -> &[ColorPoint] {
+        &self.color_points
+    }
+
+    /// Replaces the control points of the color curve.
+    ///
+    /// # Panics
+    /// If there are no points, or if any position is outside the range [0, 1].
+    pub fn set_color_points(&mut self, mut color_points: Vec<ColorPoint>) {
+        assert!(!color_points.is_empty(), "No color control points.");
+        for point in &color_points {
+            check_position(point.position);
+        }
+        color_points.sort_by(|a, b| a.position.total_cmp(&b.position));
+        self.color_points = color_points;
+    }
+
+    /// Returns the control points of the opacity curve, sorted by position.
+    pub fn opacity_points(&self) -> &[OpacityPoint] {
+        &self.opacity_points
+    }
+
+    /// Replaces the control points of the opacity curve.
+    ///
+    /// Opacities are clamped to the range [0, 1].
+    ///
+    /// # Panics
+    /// If there are no points, or if any position is outside the range [0, 1].
+    pub fn set_opacity_points(&mut self, mut opacity_points: Vec<OpacityPoint>) {
+        assert!(!opacity_points.is_empty(), "No opacity control points.");
+        for point in opacity_points.iter_mut() {
+            check_position(point.position);
+            point.opacity = point.opacity.clamp(0.0, 1.0);
+        }
+        opacity_points.sort_by(|a, b| a.position.total_cmp(&b.position));
+        self.opacity_points = opacity_points;
+    }
+
+    /// Returns the color space in which colors between control points are
+    /// interpolated.
+    pub fn interpolation_space(&self) -> ColorSpace {
+        self.interpolation_space
+    }
+
+    /// Sets the color space in which colors between control points are
+    /// interpolated.
+    pub fn set_interpolation_space(&mut self, interpolation_space: ColorSpace) {
+        self.interpolation_space = interpolation_space;
+    }
+
+    /// Returns how values are mapped to normalized positions.
+    pub fn scale(&self) -> DomainScale {
+        self.scale
+    }
+
+    /// Sets how values are mapped to normalized positions.
+    pub fn set_scale(&mut self, scale: DomainScale) {
+        self.scale = scale;
+    }
+
+    /// Returns the values corresponding to normalized positions zero and one,
+    /// or `None` if the value range of the volume is used.
+    pub fn domain(&self) -> Option<(f32, f32)> {
+        self.domain
+    }
+
+    /// Sets the values corresponding to normalized positions zero and one, or
+    /// uses the value range of the volume if `None`.
+    ///
+    /// # Panics
+    /// If the lower value is not smaller than the upper value.
+    pub fn set_domain(&mut self, domain: Option<(f32, f32)>) {
+        if let Some((lower, upper)) = domain {
+            assert!(lower < upper, "Empty transfer function domain.");
+        }
+        self.domain = domain;
+    }
+
+    /// Returns the colors for values below and above the domain, where `None`
+    /// means that the color at the nearest end of the domain is used.
+    pub fn out_of_range_colors(&self) -> (Option<&Color>, Option<&Color>) {
+        (
+            self.below_range_color.as_ref(),
+            self.above_range_color.as_ref(),
+        )
+    }
+
+    /// Sets the colors, including opacity, for values below and above the
+    /// domain, where `None` means that the color at the nearest end of the
+    /// domain is used.
+    pub fn set_out_of_range_colors(
+        &mut self,
+        below_range_color: Option<Color>,
+        above_range_color: Option<Color>,
+    ) {
+        self.below_range_color = below_range_color;
+        self.above_range_color = above_range_color;
+    }
+
+    /// Evaluates the color and opacity curves at the given normalized position,
+    /// which is clamped to the range [0, 1].
+    pub fn evaluate(&self, position: f32) -> Color {
+        let position = position.clamp(0.0, 1.0);
+        let [red, green, blue, _] = interpolate(
+            &self.color_points,
+            position,
+            |point| point.position,
+            |lower, upper, fraction| {
+                lower
+                    .color
+                    .interpolate(&upper.color, fraction, self.interpolation_space)
+            },
+            |point| point.color.clone(),
+        )
+        .into_slice();
+        let opacity = interpolate(
+            &self.opacity_points,
+            position,
+            |point| point.position,
+            |lower, upper, fraction| lower.opacity + fraction * (upper.opacity - lower.opacity),
+            |point| point.opacity,
+        );
+        Color::from_components(red, green, blue, opacity)
+    }
+
+    /// Evaluates the transfer function for the given volume value, where the
+    /// given value range of the volume is used if no domain is set.
+    pub fn evaluate_value(&self, value: f32, value_range: (f32, f32)) -> Color {
+        let (lower, upper) = self.effective_domain(value_range);
+        match (&self.below_range_color, &self.above_range_color) {
+            (Some(color), _) if value < lower => color.clone(),
+            (_, Some(color)) if value > upper => color.clone(),
+            _ => self.evaluate(self.normalized_position(value, (lower, upper))),
+        }
+    }
+
+    /// Computes a lookup table with the given number of entries, evenly
+    /// covering the given value range of the volume.
+    ///
+    /// # Panics
+    /// If the size is smaller than two.
+    pub fn bake(&self, size: usize, value_range: (f32, f32)) -> Vec<Color> {
+        assert!(size >= 2, "Lookup table must have at least two entries.");
+        let (min_value, max_value) = value_range;
+        (0..size)
+            .map(|index| {
+                let fraction = index as f32 / (size - 1) as f32;
+                let value = min_value + fraction * (max_value - min_value);
+                self.evaluate_value(value, value_range)
+            })
+            .collect()
+    }
+
+    /// Returns the domain to use for a volume with the given value range,
+    /// adjusted to be valid for the scale.
+    fn effective_domain(&self, value_range: (f32, f32)) -> (f32, f32) {
+        let (lower, upper) = self.domain.unwrap_or(value_range);
+        match self.scale {
+            DomainScale::Linear => (lower, upper),
+            DomainScale::Logarithmic => {
+                let upper = upper.max(f32::MIN_POSITIVE);
+                (
+                    lower.max(upper * MIN_RELATIVE_LOGARITHMIC_LOWER_BOUND),
+                    upper,
+                )
+            }
+        }
+    }
+
+    /// Maps the given value to a normalized position in the given domain.
+    fn normalized_position(&self, value: f32, (lower, upper): (f32, f32)) -> f32 {
+        let (value, lower, upper) = match self.scale {
+            DomainScale::Linear => (value, lower, upper),
+            DomainScale::Logarithmic => (value.max(lower).ln(), lower.ln(), upper.ln()),
+        };
+        if upper > lower {
+            (value - lower) / (upper - lower)
+        } else {
+            0.0
+        }
+    }
+}
+
+impl Default for TransferFunction {
+    /// Creates a transfer function mapping values to increasingly bright and
+    /// opaque gray.
+    fn default() -> Self {
+        Self::linear_ramp(Color::black(), Color::white())
+    }
+}
+
+fn check_position(position: f32) {
+    assert!(
+        (0.0..=1.0).contains(&position),
+        "Control point position {} is outside the range [0, 1].",
+        position
+    );
+}
+
+/// Interpolates between the sorted control points enclosing the given
+/// position, using the value of the nearest point outside the range of the
+/// points.
+pub(crate) fn interpolate<P, T, FP, FI, FV>(
+    points: &[P],
+    position: f32,
+    point_position: FP,
+    interpolate_between: FI,
+    value: FV,
+) -> T
+where
+    FP: Fn(&P) -> f32,
+    FI: Fn(&P, &P, f32) -> T,
+    FV: Fn(&P) -> T,
+{
+    let upper_index = points
+        .iter()
+        .position(|point| point_position(point) > position)
+        .unwrap_or(points.len());
+    if upper_index == 0 {
+        return value(&points[0]);
+    }
+    let lower = &points[upper_index - 1];
+    let upper = match points.get(upper_index) {
+        Some(upper) => upper,
+        None => return value(lower),
+    };
+    let fraction =
+        (position - point_position(lower)) / (point_position(upper) - point_position(lower));
+    interpolate_between(lower, upper, fraction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_color_close(color: &Color, expected: [f32; 4]) {
+        for (&component, &expected) in color.to_slice().iter().zip(&expected) {
+            assert!(
+                (component - expected).abs() < 1e-5,
+                "{:?} is not close to {:?}",
+                color,
+                expected
+            );
+        }
+    }
+
+    fn three_point_function() -> TransferFunction {
+        TransferFunction::new(
+            vec![
+                ColorPoint {
+                    position: 1.0,
+                    color: Color::white(),
+                },
+                ColorPoint {
+                    position: 0.0,
+                    color: Color::black(),
+                },
+                ColorPoint {
+                    position: 0.5,
+                    color: Color::from_components(1.0, 0.0, 0.0, 1.0),
+                },
+            ],
+            vec![
+                OpacityPoint {
+                    position: 0.25,
+                    opacity: 0.0,
+                },
+                OpacityPoint {
+                    position: 0.75,
+                    opacity: 2.0,
+                },
+            ],
+        )
+    }
+
+    #[test]
+    fn evaluate_interpolates_between_control_points() {
+        let transfer_function = three_point_function();
+        assert_eq!(transfer_function.color_points()[1].position, 0.5);
+        assert_eq!(transfer_function.opacity_points()[1].opacity, 1.0);
+
+        assert_color_close(&transfer_function.evaluate(0.25), [0.5, 0.0, 0.0, 0.0]);
+        assert_color_close(&transfer_function.evaluate(0.5), [1.0, 0.0, 0.0, 0.5]);
+        assert_color_close(&transfer_function.evaluate(0.75), [1.0, 0.5, 0.5, 1.0]);
+    }
+
+    #[test]
+    fn evaluate_clamps_at_the_ends() {
+        let transfer_function = three_point_function();
+        assert_color_close(&transfer_function.evaluate(0.0), [0.0, 0.0, 0.0, 0.0]);
+        assert_color_close(&transfer_function.evaluate(-1.0), [0.0, 0.0, 0.0, 0.0]);
+        assert_color_close(&transfer_function.evaluate(1.0), [1.0, 1.0, 1.0, 1.0]);
+        assert_color_close(&transfer_function.evaluate(2.0), [1.0, 1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn evaluate_value_uses_out_of_range_colors() {
+        let mut transfer_function = three_point_function();
+        transfer_function.set_domain(Some((0.0, 10.0)));
+        let below = Color::from_components(0.0, 0.0, 1.0, 1.0);
+        transfer_function.set_out_of_range_colors(Some(below.clone()), None);
+
+        // The domain takes precedence over the value range of the volume
+        let value_range = (-100.0, 100.0);
+        assert_eq!(transfer_function.evaluate_value(-1.0, value_range), below);
+        assert_color_close(
+            &transfer_function.evaluate_value(0.0, value_range),
+            [0.0, 0.0, 0.0, 0.0],
+        );
+        assert_color_close(
+            &transfer_function.evaluate_value(5.0, value_range),
+            [1.0, 0.0, 0.0, 0.5],
+        );
+        assert_color_close(
+            &transfer_function.evaluate_value(11.0, value_range),
+            [1.0, 1.0, 1.0, 1.0],
+        );
+
+        let above = Color::from_components(0.0, 1.0, 0.0, 0.5);
+        transfer_function.set_out_of_range_colors(None, Some(above.clone()));
+        assert_eq!(transfer_function.evaluate_value(11.0, value_range), above);
+        assert_color_close(
+            &transfer_function.evaluate_value(-1.0, value_range),
+            [0.0, 0.0, 0.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn logarithmic_scale_maps_logarithm_of_value() {
+        let mut transfer_function = TransferFunction::default();
+        transfer_function.set_scale(DomainScale::Logarithmic);
+
+        let domain = transfer_function.effective_domain((1.0, 100.0));
+        assert_eq!(domain, (1.0, 100.0));
+        let position = transfer_function.normalized_position(10.0, domain);
+        assert!((position - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn logarithmic_scale_raises_non_positive_lower_bound() {
+        let mut transfer_function = TransferFunction::default();
+        transfer_function.set_scale(DomainScale::Logarithmic);
+
+        let domain = transfer_function.effective_domain((-5.0, 100.0));
+        assert_eq!(
+            domain,
+            (100.0 * MIN_RELATIVE_LOGARITHMIC_LOWER_BOUND, 100.0)
+        );
+        // The domain spans six decades, of which 10 is five above the bottom
+        let position = transfer_function.normalized_position(10.0, domain);
+        assert!((position - 5.0 / 6.0).abs() < 1e-5);
+        assert_eq!(transfer_function.normalized_position(-3.0, domain), 0.0);
+        assert_eq!(transfer_function.normalized_position(0.0, domain), 0.0);
+
+        let (lower, upper) = transfer_function.effective_domain((-2.0, -1.0));
+        assert!(lower > 0.0 && upper > lower);
+    }
+
+    #[test]
+    fn bake_covers_value_range() {
+        let table = TransferFunction::default().bake(5, (10.0, 14.0));
+        assert_eq!(table.len(), 5);
+        assert_color_close(&table[0], [0.0, 0.0, 0.0, 0.0]);
+        assert_color_close(&table[2], [0.5, 0.5, 0.5, 0.5]);
+        assert_color_close(&table[4], [1.0, 1.0, 1.0, 1.0]);
+
+        let table = TransferFunction::default().bake(HIGH_RESOLUTION_TABLE_SIZE, (0.0, 1.0));
+        assert_eq!(table.len(), HIGH_RESOLUTION_TABLE_SIZE);
+        assert_eq!(table[0], Color::from_components(0.0, 0.0, 0.0, 0.0));
+        assert_eq!(table[HIGH_RESOLUTION_TABLE_SIZE - 1], Color::white());
+    }
+
+    #[test]
+    #[should_panic(expected = "at least two entries")]
+    fn bake_with_single_entry_panics() {
+        TransferFunction::default().bake(1, (0.0, 1.0));
+    }
+}
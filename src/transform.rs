@@ -0,0 +1,323 @@
+//! Vector and matrix operations for spatial transformations.
+//!
+//! Matrices are stored in column-major order, so that `matrix[column][row]`
+//! is the element in the given row and column, matching the layout expected
+//! by shaders.
+
+/// 4x4 matrix stored as an array of columns.
+pub type Matrix4 = [[f32; 4]; 4];
+
+/// Returns the 4x4 identity matrix.
+pub fn identity() -> Matrix4 {
+    [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ]
+}
+
+/// Computes the matrix product `a * b`, which applies `b` before `a`.
+pub fn multiply(a: &Matrix4, b: &Matrix4) -> Matrix4 {
+    let mut product = [[0.0; 4]; 4];
+    for (column, product_column) in product.iter_mut().enumerate() {
+        for (row, element) in product_column.iter_mut().enumerate() {
+            *element = (0..4).map(|k| a[k][row] * b[column][k]).sum();
+        }
+    }
+    product
+}
+
+/// Returns the transpose of the given matrix.
+pub fn transpose(matrix: &Matrix4) -> Matrix4 {
+    let mut transposed = [[0.0; 4]; 4];
+    for (column, transposed_column) in transposed.iter_mut().enumerate() {
+        for (row, element) in transposed_column.iter_mut().enumerate() {
+            *element = matrix[row][column];
+        }
+    }
+    transposed
+}
+
+/// Transforms the given point with the given matrix, including the
+/// perspective division.
+pub fn transform_point(matrix: &Matrix4, point: [f32; 3]) -> [f32; 3] {
+    let mut transformed = [0.0; 4];
+    for (row, element) in transformed.iter_mut().enumerate() {
+        *element = matrix[0][row] * point[0]
+            + matrix[1][row] * point[1]
+            + matrix[2][row] * point[2]
+            + matrix[3][row];
+    }
+    [
+        transformed[0] / transformed[3],
+        transformed[1] / transformed[3],
+        transformed[2] / transformed[3],
+    ]
+}
+
+/// Transforms the given direction with the given matrix, ignoring translation.
+pub fn transform_direction(matrix: &Matrix4, direction: [f32; 3]) -> [f32; 3] {
+    let mut transformed = [0.0; 3];
+    for (row, element) in transformed.iter_mut().enumerate() {
+        *element = matrix[0][row] * direction[0]
+            + matrix[1][row] * direction[1]
+            + matrix[2][row] * direction[2];
+    }
+    transformed
+}
+
+/// Computes the inverse of the given matrix, or returns `None` if it is singular.
+pub fn invert(matrix: &Matrix4) -> Option<Matrix4> {
+    // Gauss-Jordan elimination with partial pivoting on the row-major transpose
+    let mut rows = [[0.0f64; 8]; 4];
+    for (row, augmented_row) in rows.iter_mut().enumerate() {
+        for column in 0..4 {
+            augmented_row[column] = f64::from(matrix[column][row]);
+        }
+        augmented_row[4 + row] = 1.0;
+    }
+    for pivot in 0..4 {
+        let pivot_row = (pivot..4)
+            .max_by(|&a, &b| rows[a][pivot].abs().total_cmp(&rows[b][pivot].abs()))
+            .unwrap();
+        if rows[pivot_row][pivot].abs() < 1e-12 {
+            return None;
+        }
+        rows.swap(pivot, pivot_row);
+        let scale = 1.0 / rows[pivot][pivot];
+        for element in rows[pivot].iter_mut() {
+            *element *= scale;
+        }
+        for row in 0..4 {
+            if row != pivot {
+                let factor = rows[row][pivot];
+                let pivot_values = rows[pivot];
+                for (element, &pivot_value) in rows[row].iter_mut().zip(&pivot_values) {
+                    *element -= factor * pivot_value;
+                }
+            }
+        }
+    }
+    let mut inverse = [[0.0; 4]; 4];
+    for (column, inverse_column) in inverse.iter_mut().enumerate() {
+        for (row, element) in inverse_column.iter_mut().enumerate() {
+            *element = rows[row][4 + column] as f32;
+        }
+    }
+    Some(inverse)
+}
+
+/// Creates a view matrix for a camera at the given eye position looking at the
+/// given target, with the given up direction.
+///
+/// The camera looks along the negative z-axis in view space.
+pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> Matrix4 {
+    let forward = normalize(subtract(target, eye));
+    let right = normalize(cross(forward, up));
+    let true_up = cross(right, forward);
+    [
+        [right[0], true_up[0], -forward[0], 0.0],
+        [right[1], true_up[1], -forward[1], 0.0],
+        [right[2], true_up[2], -forward[2], 0.0],
+        [-dot(right, eye), -dot(true_up, eye), dot(forward, eye), 1.0],
+    ]
+}
+
+/// Creates a perspective projection matrix with the given vertical field of
+/// view (in radians), aspect ratio (width over height) and clip distances.
+///
+/// The matrix maps to clip space with the y-axis pointing down and depths in
+/// the range [0, 1].
+pub fn perspective(vertical_field_of_view: f32, aspect_ratio: f32, near: f32, far: f32) -> Matrix4 {
+    let focal_length = 1.0 / (0.5 * vertical_field_of_view).tan();
+    [
+        [focal_length / aspect_ratio, 0.0, 0.0, 0.0],
+        [0.0, -focal_length, 0.0, 0.0],
+        [0.0, 0.0, far / (near - far), -1.0],
+        [0.0, 0.0, near * far / (near - far), 0.0],
+    ]
+}
+
+/// Creates an orthographic projection matrix for a view volume of the given
+/// width and height centered on the view axis, with the given clip distances.
+///
+/// The matrix maps to clip space with the y-axis pointing down and depths in
+/// the range [0, 1].
+pub fn orthographic(width: f32, height: f32, near: f32, far: f32) -> Matrix4 {
+    [
+        [2.0 / width, 0.0, 0.0, 0.0],
+        [0.0, -2.0 / height, 0.0, 0.0],
+        [0.0, 0.0, 1.0 / (near - far), 0.0],
+        [0.0, 0.0, near / (near - far), 1.0],
+    ]
+}
+
+/// Computes `a - b`.
+pub fn subtract(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+/// Computes `a + b * scale`.
+pub fn add_scaled(a: [f32; 3], b: [f32; 3], scale: f32) -> [f32; 3] {
+    [
+        a[0] + b[0] * scale,
+        a[1] + b[1] * scale,
+        a[2] + b[2] * scale,
+    ]
+}
+
+/// Computes the dot product of the given vectors.
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Computes the cross product of the given vectors.
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+/// Computes the Euclidean length of the given vector.
+pub fn length(vector: [f32; 3]) -> f32 {
+    dot(vector, vector).sqrt()
+}
+
+/// Returns the given vector scaled to unit length, or the vector itself if its
+/// length is zero.
+pub fn normalize(vector: [f32; 3]) -> [f32; 3] {
+    let length = length(vector);
+    if length > 0.0 {
+        [vector[0] / length, vector[1] / length, vector[2] / length]
+    } else {
+        vector
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: &[f32], b: &[f32]) {
+        for (&a_value, &b_value) in a.iter().zip(b) {
+            assert!(
+                (a_value - b_value).abs() < 1e-4,
+                "{:?} is not close to {:?}",
+                a,
+                b
+            );
+        }
+    }
+
+    fn assert_matrices_close(a: &Matrix4, b: &Matrix4) {
+        for (a_column, b_column) in a.iter().zip(b) {
+            assert_close(a_column, b_column);
+        }
+    }
+
+    #[test]
+    fn inverse_multiplies_to_identity() {
+        let view = look_at([3.0, -2.0, 5.0], [0.5, 1.0, -1.0], [0.0, 0.0, 1.0]);
+        let matrices = [
+            identity(),
+            view,
+            multiply(&perspective(1.0, 1.5, 0.1, 50.0), &view),
+            multiply(&orthographic(4.0, 3.0, 1.0, 10.0), &view),
+            [
+                [2.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 3.0, 0.0],
+                [0.0, -1.0, 0.0, 0.0],
+                [7.0, 8.0, 9.0, 1.0],
+            ],
+        ];
+        for matrix in &matrices {
+            let inverse = invert(matrix).unwrap();
+            assert_matrices_close(&multiply(matrix, &inverse), &identity());
+            assert_matrices_close(&multiply(&inverse, matrix), &identity());
+        }
+    }
+
+    #[test]
+    fn singular_matrix_has_no_inverse() {
+        assert_eq!(invert(&[[0.0; 4]; 4]), None);
+        let matrix = [
+            [1.0, 2.0, 3.0, 0.0],
+            [2.0, 4.0, 6.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        assert_eq!(invert(&matrix), None);
+    }
+
+    #[test]
+    fn transpose_swaps_rows_and_columns() {
+        let matrix = [
+            [1.0, 2.0, 3.0, 4.0],
+            [5.0, 6.0, 7.0, 8.0],
+            [9.0, 10.0, 11.0, 12.0],
+            [13.0, 14.0, 15.0, 16.0],
+        ];
+        let transposed = transpose(&matrix);
+        assert_eq!(transposed[0], [1.0, 5.0, 9.0, 13.0]);
+        assert_eq!(transpose(&transposed), matrix);
+    }
+
+    #[test]
+    fn look_at_puts_target_on_negative_z_axis() {
+        let eye = [1.0, 2.0, 3.0];
+        let view = look_at(eye, [1.0, 2.0, 0.0], [0.0, 1.0, 0.0]);
+        assert_close(&transform_point(&view, eye), &[0.0, 0.0, 0.0]);
+        assert_close(&transform_point(&view, [1.0, 2.0, 0.0]), &[0.0, 0.0, -3.0]);
+        assert_close(
+            &transform_direction(&view, [0.0, 1.0, 0.0]),
+            &[0.0, 1.0, 0.0],
+        );
+        assert_close(
+            &transform_direction(&view, [1.0, 0.0, 0.0]),
+            &[1.0, 0.0, 0.0],
+        );
+    }
+
+    #[test]
+    fn perspective_maps_clip_distances_to_unit_depth_range() {
+        let (near, far) = (0.5, 20.0);
+        let vertical_field_of_view = 1.2;
+        let projection = perspective(vertical_field_of_view, 2.0, near, far);
+        assert_close(
+            &transform_point(&projection, [0.0, 0.0, -near]),
+            &[0.0, 0.0, 0.0],
+        );
+        assert_close(
+            &transform_point(&projection, [0.0, 0.0, -far]),
+            &[0.0, 0.0, 1.0],
+        );
+
+        // The top and right edges of the view at a given distance
+        let distance = 4.0;
+        let half_height = distance * (0.5 * vertical_field_of_view).tan();
+        let corner = transform_point(&projection, [2.0 * half_height, half_height, -distance]);
+        assert_close(&corner[..2], &[1.0, -1.0]);
+        assert!(corner[2] > 0.0 && corner[2] < 1.0);
+    }
+
+    #[test]
+    fn orthographic_maps_clip_distances_to_unit_depth_range() {
+        let (near, far) = (1.0, 9.0);
+        let projection = orthographic(4.0, 2.0, near, far);
+        assert_close(
+            &transform_point(&projection, [2.0, 1.0, -near]),
+            &[1.0, -1.0, 0.0],
+        );
+        assert_close(
+            &transform_point(&projection, [-2.0, -1.0, -far]),
+            &[-1.0, 1.0, 1.0],
+        );
+        assert_close(
+            &transform_point(&projection, [0.0, 0.0, -5.0]),
+            &[0.0, 0.0, 0.5],
+        );
+    }
+}
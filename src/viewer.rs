@@ -0,0 +1,428 @@
+//! Embeddable volume viewer.
+//!
+//! A `Viewer` owns the window, renderer and event loop, and reports every
+//! failure to the caller instead of exiting the process. It never installs a
+//! logger, so the host application is free to use any `log` implementation.
+
+use crate::{
+    application::ApplicationState,
+    camera::Projection,
+    color::Color,
+    error::{VortekError, VortekResult},
+    graphics::{
+        rendering::{
+            backend::BackendKind, dispatch::AnyRendererState, offscreen::ImageData, RenderMode,
+            RenderingError,
+        },
+        window,
+    },
+    input::{bindings::InputBindings, UserInput},
+    io::png,
+    transfer_function::{self, TransferFunction},
+    volume::ScalarVolume,
+};
+use log::{error, info};
+use std::{mem, path::PathBuf};
+use winit::{dpi::LogicalSize, event_loop::ControlFlow, platform::desktop::EventLoopExtDesktop};
+
+/// Prefix of the names of screenshot files, which are followed by a number.
+const SCREENSHOT_FILE_PREFIX: &str = "vortek_screenshot_";
+
+/// Callback invoked before each frame is rendered.
+type UpdateCallback =
+    Box<dyn FnMut(&mut ApplicationState, &mut AnyRendererState) -> VortekResult<()>>;
+
+/// Callback invoked with each user input, returning whether the input was
+/// handled.
+type InputCallback = Box<dyn FnMut(&UserInput, &mut ApplicationState) -> bool>;
+
+/// Configuration of a viewer.
+#[derive(Clone, Debug)]
+pub struct ViewerConfig {
+    /// Title of the window.
+    pub window_title: String,
+    /// Logical size of the window, or size in pixels of rendered images.
+    pub window_size: (u32, u32),
+    /// Color behind the volume.
+    pub background_color: Color,
+    /// Transfer function mapping volume values to colors.
+    pub transfer_function: TransferFunction,
+    /// How samples along each ray through the volume are combined.
+    pub render_mode: RenderMode,
+    /// Initial yaw and pitch angles of the camera in radians, or `None` for
+    /// the default direction.
+    pub camera_orientation: Option<(f32, f32)>,
+    /// Factor by which the camera initially zooms in from the distance where
+    /// the whole volume is visible.
+    pub camera_zoom: f32,
+    /// Initial projection of the camera.
+    pub projection: Projection,
+    /// Backend to try first, or `None` to use the one given by the
+    /// `VORTEK_BACKEND` environment variable or otherwise the available one
+    /// with the highest priority. Running fails if the backend is unavailable
+    /// or no backend can be used. The empty backend is only used if given here
+    /// or by the environment variable.
+    pub backend: Option<BackendKind>,
+    /// Whether frames are rendered continuously rather than only in response
+    /// to events, for example to animate the view from the update callback.
+    pub redraw_continuously: bool,
+    /// Factor by which the resolution of screenshots exceeds that of the window.
+    pub screenshot_scale: u32,
+    /// Directory to save screenshots in.
+    pub screenshot_directory: PathBuf,
+}
+
+/// Builder and runner for an interactive view of a volume.
+pub struct Viewer {
+    config: ViewerConfig,
+    volume: Option<ScalarVolume>,
+    input_bindings: InputBindings,
+    update_callback: Option<UpdateCallback>,
+    input_callback: Option<InputCallback>,
+}
+
+impl Default for ViewerConfig {
+    fn default() -> Self {
+        Self {
+            window_title: String::from(window::DEFAULT_WINDOW_NAME),
+            window_size: (
+                window::DEFAULT_WINDOW_SIZE.width as u32,
+                window::DEFAULT_WINDOW_SIZE.height as u32,
+            ),
+            background_color: Color::black(),
+            transfer_function: TransferFunction::default(),
+            render_mode: RenderMode::default(),
+            camera_orientation: None,
+            camera_zoom: 1.0,
+            projection: Projection::default(),
+            backend: None,
+            redraw_continuously: false,
+            screenshot_scale: 1,
+            screenshot_directory: PathBuf::from("."),
+        }
+    }
+}
+
+impl Viewer {
+    /// Creates a new viewer with the given configuration, without any volume
+    /// or callbacks and with the default input bindings.
+    pub fn new(config: ViewerConfig) -> Self {
+        Self {
+            config,
+            volume: None,
+            input_bindings: InputBindings::default(),
+            update_callback: None,
+            input_callback: None,
+        }
+    }
+
+    /// Returns the configuration of the viewer.
+    pub fn config(&self) -> &ViewerConfig {
+        &self.config
+    }
+
+    /// Sets the volume to render.
+    pub fn with_volume(mut self, volume: ScalarVolume) -> Self {
+        self.volume = Some(volume);
+        self
+    }
+
+    /// Sets the bindings between user input and application actions.
+    pub fn with_input_bindings(mut self, input_bindings: InputBindings) -> Self {
+        self.input_bindings = input_bindings;
+        self
+    }
+
+    /// Sets a callback that is invoked before each frame is rendered, and can
+    /// modify the application state and renderer, for example to change the
+    /// camera or upload a new volume.
+    ///
+    /// An error returned from the callback stops the viewer and is returned
+    /// from `run`.
+    pub fn on_update<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&mut ApplicationState, &mut AnyRendererState) -> VortekResult<()> + 'static,
+    {
+        self.update_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Sets a callback that is invoked with each user input before the input
+    /// bindings are applied. If the callback returns `true`, the input is
+    /// considered handled and the bindings are not applied.
+    pub fn on_input<F>(mut self, callback: F) -> Self
+    where
+        F: FnMut(&UserInput, &mut ApplicationState) -> bool + 'static,
+    {
+        self.input_callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Opens a window and renders the volume in it, handling user input until
+    /// the window is closed or an error occurs.
+    pub fn run(mut self) -> VortekResult<()> {
+        self.check_config()?;
+        let (width, height) = self.config.window_size;
+        let (window_state, mut event_loop) = window::create_window_and_event_loop(
+            self.config.window_title.clone(),
+            LogicalSize::new(f64::from(width), f64::from(height)),
+        )?;
+
+        let mut app_state =
+            self.create_application_state(window_state.inner_physical_size().into());
+        app_state.set_input_bindings(mem::take(&mut self.input_bindings));
+
+        let mut renderer_state = AnyRendererState::new(
+            Some(window_state),
+            &self.config.window_title,
+            self.config.backend,
+        )?;
+        self.prepare_renderer(&mut renderer_state)?;
+
+        let mut result = Ok(());
+        event_loop.run_return(|event, _, control_flow| {
+            // Pause event loop if no events are available to process, unless
+            // frames should be rendered continuously
+            *control_flow = if self.config.redraw_continuously {
+                ControlFlow::Poll
+            } else {
+                ControlFlow::Wait
+            };
+
+            let input = UserInput::from_event(event);
+
+            if let UserInput::TerminationRequested = input {
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            let handled = self
+                .input_callback
+                .as_mut()
+                .is_some_and(|callback| callback(&input, &mut app_state));
+            if !handled {
+                app_state.update_from_input(&input);
+            }
+
+            if let Err(err) = self.render_frame(&mut renderer_state, &mut app_state) {
+                result = Err(err);
+                *control_flow = ControlFlow::Exit;
+                return;
+            }
+
+            if app_state.take_screenshot_request() {
+                match save_screenshot(&renderer_state, &app_state) {
+                    Ok(path) => info!("Saved screenshot to {}.", path.display()),
+                    Err(err) => error!("Could not save screenshot: {}", err),
+                }
+            }
+        });
+        result
+    }
+
+    /// Renders a single image of the volume with the configured window size in
+    /// pixels, without opening a window.
+    ///
+    /// The update callback is invoked once before rendering.
+    pub fn render_image(mut self) -> VortekResult<ImageData> {
+        self.check_config()?;
+        let mut renderer_state =
+            AnyRendererState::new(None, &self.config.window_title, self.config.backend)?;
+        self.prepare_renderer(&mut renderer_state)?;
+
+        let mut app_state = self.create_application_state(self.config.window_size);
+        if let Some(callback) = self.update_callback.as_mut() {
+            callback(&mut app_state, &mut renderer_state)?;
+        }
+        update_renderer(&mut renderer_state, &app_state)?;
+        renderer_state.render_volume_image(app_state.background_color(), self.config.window_size)
+    }
+
+    /// Returns an error if the window size or screenshot scale is zero.
+    fn check_config(&self) -> VortekResult<()> {
+        let (width, height) = self.config.window_size;
+        if width == 0 || height == 0 {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                format!("Window size {}x{} is empty.", width, height),
+            )));
+        }
+        if self.config.screenshot_scale == 0 {
+            return Err(VortekError::RenderingError(RenderingError::from_string(
+                "Screenshot scale must be positive.".to_string(),
+            )));
+        }
+        Ok(())
+    }
+
+    /// Creates the application state for a window with the given physical
+    /// size, with the camera initially viewing the volume as configured.
+    fn create_application_state(&self, physical_window_size: (u32, u32)) -> ApplicationState {
+        let config = &self.config;
+        let mut app_state =
+            ApplicationState::new(physical_window_size, config.background_color.clone());
+        app_state.set_render_mode(config.render_mode);
+        app_state.set_screenshot_scale(config.screenshot_scale);
+        app_state.set_screenshot_directory(config.screenshot_directory.clone());
+        if let Some(volume) = &self.volume {
+            let (lower, upper) = volume.bounding_box();
+            app_state.set_volume_bounding_box(lower, upper);
+            if let Some(value_range) = volume.value_range() {
+                app_state.set_value_range(value_range);
+            }
+        }
+
+        let camera = app_state.camera_mut();
+        if let Some((yaw, pitch)) = config.camera_orientation {
+            camera.set_orientation(yaw, pitch);
+        }
+        camera.set_projection(config.projection);
+        camera.zoom(config.camera_zoom);
+        app_state
+    }
+
+    /// Uploads the volume and the transfer function to the renderer.
+    fn prepare_renderer(&self, renderer_state: &mut AnyRendererState) -> VortekResult<()> {
+        renderer_state.set_transfer_function(
+            self.config.transfer_function.clone(),
+            transfer_function::STANDARD_TABLE_SIZE,
+        )?;
+        if let Some(volume) = &self.volume {
+            renderer_state.upload_volume(volume)?;
+        }
+        Ok(())
+    }
+
+    fn render_frame(
+        &mut self,
+        renderer_state: &mut AnyRendererState,
+        app_state: &mut ApplicationState,
+    ) -> VortekResult<()> {
+        if let Some(callback) = self.update_callback.as_mut() {
+            callback(app_state, renderer_state)?;
+        }
+        update_renderer(renderer_state, app_state)?;
+        renderer_state.draw_volume_frame(app_state.background_color())
+    }
+}
+
+/// Passes the parts of the application state that affect rendering on to the
+/// renderer.
+fn update_renderer(
+    renderer_state: &mut AnyRendererState,
+    app_state: &ApplicationState,
+) -> VortekResult<()> {
+    renderer_state.set_camera(app_state.camera());
+    renderer_state.set_render_mode(app_state.render_mode());
+    renderer_state.set_isosurfaces(app_state.isosurfaces())
+}
+
+/// Renders the current frame offscreen at the screenshot resolution and writes
+/// it to a new PNG file in the screenshot directory, whose path is returned.
+fn save_screenshot(
+    renderer_state: &AnyRendererState,
+    app_state: &ApplicationState,
+) -> VortekResult<PathBuf> {
+    let (width, height) = app_state.physical_window_size();
+    let scale = app_state.screenshot_scale();
+    // Sizes within range are checked against the framebuffer limit by the renderer
+    let size = width
+        .max(1)
+        .checked_mul(scale)
+        .zip(height.max(1).checked_mul(scale))
+        .ok_or_else(|| {
+            VortekError::RenderingError(RenderingError::from_string(format!(
+                "Screenshot size {}x{} scaled by {} is too large.",
+                width, height, scale
+            )))
+        })?;
+    let image = renderer_state.render_volume_image(app_state.background_color(), size)?;
+    let path = (1..)
+        .map(|number| {
+            app_state
+                .screenshot_directory()
+                .join(format!("{}{:04}.png", SCREENSHOT_FILE_PREFIX, number))
+        })
+        .find(|path| !path.exists())
+        .unwrap();
+    png::write_png(&path, &image)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::rendering::Isosurface;
+
+    fn test_volume() -> ScalarVolume {
+        ScalarVolume::from_fn([2, 3, 4], [1.0; 3], [0.0; 3], |i, j, k| (i + j + k) as f32)
+    }
+
+    fn empty_backend_config() -> ViewerConfig {
+        ViewerConfig {
+            window_size: (4, 3),
+            backend: Some(BackendKind::Empty),
+            ..ViewerConfig::default()
+        }
+    }
+
+    #[test]
+    fn renders_image_with_window_size() {
+        let image = Viewer::new(empty_backend_config()).render_image().unwrap();
+        assert_eq!((image.width, image.height), (4, 3));
+    }
+
+    #[test]
+    fn renders_image_in_every_mode() {
+        for render_mode in RenderMode::names().map(|name| RenderMode::from_name(name).unwrap()) {
+            let config = ViewerConfig {
+                render_mode,
+                ..empty_backend_config()
+            };
+            let image = Viewer::new(config)
+                .with_volume(test_volume())
+                .render_image()
+                .unwrap();
+            assert_eq!(image.to_rgba8().unwrap(), [0, 0, 0, 255].repeat(12));
+        }
+    }
+
+    #[test]
+    fn renders_image_with_isosurfaces() {
+        let config = ViewerConfig {
+            render_mode: RenderMode::Isosurface,
+            ..empty_backend_config()
+        };
+        let image = Viewer::new(config)
+            .with_volume(test_volume())
+            .on_update(|app_state, _| {
+                app_state.add_isosurface(Isosurface {
+                    value: 3.0,
+                    color: Color::white(),
+                });
+                Ok(())
+            })
+            .render_image()
+            .unwrap();
+        assert_eq!((image.width, image.height), (4, 3));
+    }
+
+    #[test]
+    fn empty_window_size_is_an_error() {
+        for window_size in [(0, 3), (4, 0)] {
+            let config = ViewerConfig {
+                window_size,
+                ..empty_backend_config()
+            };
+            assert!(Viewer::new(config).render_image().is_err());
+        }
+    }
+
+    #[test]
+    fn zero_screenshot_scale_is_an_error() {
+        let config = ViewerConfig {
+            screenshot_scale: 0,
+            ..empty_backend_config()
+        };
+        assert!(Viewer::new(config).render_image().is_err());
+    }
+}
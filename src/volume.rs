@@ -0,0 +1,1092 @@
+//! Representation of volumetric data.
+
+use std::{fmt, iter};
+
+/// Scalar types that can be stored in a voxel buffer.
+pub trait VoxelValue: Copy + fmt::Debug + PartialOrd + 'static {
+    /// The voxel type tag corresponding to the scalar type.
+    const VOXEL_TYPE: VoxelType;
+
+    /// Converts the value to a 32-bit float.
+    fn to_f32(self) -> f32;
+
+    /// Converts the given 32-bit float to the scalar type, saturating at
+    /// the bounds of the type.
+    fn from_f32(value: f32) -> Self;
+
+    /// Wraps the given vector of values in the matching voxel buffer variant.
+    fn into_buffer(values: Vec<Self>) -> VoxelBuffer;
+}
+
+/// Tag identifying the scalar type of the values in a voxel buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VoxelType {
+    U8,
+    U16,
+    I16,
+    F32,
+}
+
+/// Buffer of voxel values with a type determined at runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VoxelBuffer {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    I16(Vec<i16>),
+    F32(Vec<f32>),
+}
+
+/// Regular 3D grid of scalar values.
+///
+/// Voxels are stored with the x-index varying fastest and the z-index
+/// varying slowest, which is the layout expected for 3D textures.
+///
+/// The world space position of a voxel is given by the origin plus the sum
+/// over axes of the voxel index times the spacing times the unit direction
+/// vector of the axis. By default the axis directions are aligned with the
+/// coordinate axes.
+///
+/// Axes can alternatively be given explicit, non-uniformly spaced coordinates,
+/// in which case the distance of a voxel from the origin along the axis is
+/// determined by its coordinate relative to the first coordinate.
+#[derive(Clone, Debug)]
+pub struct ScalarVolume {
+    dimensions: [usize; 3],
+    spacing: [f32; 3],
+    origin: [f32; 3],
+    orientation: [[f32; 3]; 3],
+    coordinates: [Option<Vec<f32>>; 3],
+    voxels: VoxelBuffer,
+}
+
+/// Regular 3D grid of 3-component vectors.
+///
+/// Each vector component is stored as a separate scalar volume, and all
+/// components share the same grid geometry.
+#[derive(Clone, Debug)]
+pub struct VectorVolume {
+    components: [ScalarVolume; 3],
+}
+
+/// Either a scalar or a vector volume.
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum Volume {
+    Scalar(ScalarVolume),
+    Vector(VectorVolume),
+}
+
+/// Orientation where the volume axes are aligned with the coordinate axes.
+pub const IDENTITY_ORIENTATION: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+impl VoxelValue for u8 {
+    const VOXEL_TYPE: VoxelType = VoxelType::U8;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value as Self
+    }
+
+    fn into_buffer(values: Vec<Self>) -> VoxelBuffer {
+        VoxelBuffer::U8(values)
+    }
+}
+
+impl VoxelValue for u16 {
+    const VOXEL_TYPE: VoxelType = VoxelType::U16;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value as Self
+    }
+
+    fn into_buffer(values: Vec<Self>) -> VoxelBuffer {
+        VoxelBuffer::U16(values)
+    }
+}
+
+impl VoxelValue for i16 {
+    const VOXEL_TYPE: VoxelType = VoxelType::I16;
+
+    fn to_f32(self) -> f32 {
+        f32::from(self)
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value as Self
+    }
+
+    fn into_buffer(values: Vec<Self>) -> VoxelBuffer {
+        VoxelBuffer::I16(values)
+    }
+}
+
+impl VoxelValue for f32 {
+    const VOXEL_TYPE: VoxelType = VoxelType::F32;
+
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    fn from_f32(value: f32) -> Self {
+        value
+    }
+
+    fn into_buffer(values: Vec<Self>) -> VoxelBuffer {
+        VoxelBuffer::F32(values)
+    }
+}
+
+impl VoxelType {
+    /// Returns the number of bytes occupied by a single value of the type.
+    pub fn size_in_bytes(self) -> usize {
+        match self {
+            Self::U8 => 1,
+            Self::U16 | Self::I16 => 2,
+            Self::F32 => 4,
+        }
+    }
+
+    /// Returns the name of the type.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U16 => "u16",
+            Self::I16 => "i16",
+            Self::F32 => "f32",
+        }
+    }
+}
+
+impl fmt::Display for VoxelType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl VoxelBuffer {
+    /// Creates a new buffer of the given type with all values set to zero.
+    pub fn zeros(voxel_type: VoxelType, length: usize) -> Self {
+        match voxel_type {
+            VoxelType::U8 => Self::U8(vec![0; length]),
+            VoxelType::U16 => Self::U16(vec![0; length]),
+            VoxelType::I16 => Self::I16(vec![0; length]),
+            VoxelType::F32 => Self::F32(vec![0.0; length]),
+        }
+    }
+
+    /// Returns the type of the values in the buffer.
+    pub fn voxel_type(&self) -> VoxelType {
+        match self {
+            Self::U8(_) => VoxelType::U8,
+            Self::U16(_) => VoxelType::U16,
+            Self::I16(_) => VoxelType::I16,
+            Self::F32(_) => VoxelType::F32,
+        }
+    }
+
+    /// Returns the number of values in the buffer.
+    pub fn len(&self) -> usize {
+        match self {
+            Self::U8(values) => values.len(),
+            Self::U16(values) => values.len(),
+            Self::I16(values) => values.len(),
+            Self::F32(values) => values.len(),
+        }
+    }
+
+    /// Whether the buffer contains no values.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the value at the given linear index converted to a 32-bit float.
+    ///
+    /// # Panics
+    /// If the index is out of bounds.
+    pub fn value(&self, index: usize) -> f32 {
+        match self {
+            Self::U8(values) => values[index].to_f32(),
+            Self::U16(values) => values[index].to_f32(),
+            Self::I16(values) => values[index].to_f32(),
+            Self::F32(values) => values[index],
+        }
+    }
+
+    /// Sets the value at the given linear index, converting from a 32-bit float.
+    ///
+    /// # Panics
+    /// If the index is out of bounds.
+    pub fn set_value(&mut self, index: usize, value: f32) {
+        match self {
+            Self::U8(values) => values[index] = u8::from_f32(value),
+            Self::U16(values) => values[index] = u16::from_f32(value),
+            Self::I16(values) => values[index] = i16::from_f32(value),
+            Self::F32(values) => values[index] = value,
+        }
+    }
+
+    /// Returns an iterator over all values in the buffer converted to 32-bit floats.
+    pub fn iter_values(&self) -> Box<dyn Iterator<Item = f32> + '_> {
+        match self {
+            Self::U8(values) => Box::new(values.iter().map(|&value| value.to_f32())),
+            Self::U16(values) => Box::new(values.iter().map(|&value| value.to_f32())),
+            Self::I16(values) => Box::new(values.iter().map(|&value| value.to_f32())),
+            Self::F32(values) => Box::new(values.iter().cloned()),
+        }
+    }
+
+    /// Returns the minimum and maximum value in the buffer, ignoring NaNs,
+    /// or `None` if there are no non-NaN values.
+    pub fn value_range(&self) -> Option<(f32, f32)> {
+        self.iter_values()
+            .filter(|value| !value.is_nan())
+            .fold(None, |range, value| match range {
+                None => Some((value, value)),
+                Some((min, max)) => Some((min.min(value), max.max(value))),
+            })
+    }
+
+    /// Returns a copy of the buffer with all values converted to 32-bit floats.
+    pub fn to_f32(&self) -> Vec<f32> {
+        match self {
+            Self::F32(values) => values.clone(),
+            _ => self.iter_values().collect(),
+        }
+    }
+
+    /// Returns the raw bytes of the buffer in native endianness.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            match self {
+                Self::U8(values) => values.as_slice(),
+                Self::U16(values) => Self::slice_as_bytes(values),
+                Self::I16(values) => Self::slice_as_bytes(values),
+                Self::F32(values) => Self::slice_as_bytes(values),
+            }
+        }
+    }
+
+    /// Reinterprets the given slice of plain numbers as a slice of bytes.
+    ///
+    /// # Safety
+    /// The element type must not contain padding or pointers.
+    unsafe fn slice_as_bytes<T: VoxelValue>(values: &[T]) -> &[u8] {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, std::mem::size_of_val(values))
+    }
+}
+
+impl<T: VoxelValue> From<Vec<T>> for VoxelBuffer {
+    fn from(values: Vec<T>) -> Self {
+        T::into_buffer(values)
+    }
+}
+
+impl ScalarVolume {
+    /// Creates a new scalar volume with the given grid dimensions, voxel spacing,
+    /// origin and voxel values.
+    ///
+    /// # Panics
+    /// If the number of voxel values does not match the grid dimensions.
+    pub fn new<V: Into<VoxelBuffer>>(
+        dimensions: [usize; 3],
+        spacing: [f32; 3],
+        origin: [f32; 3],
+        voxels: V,
+    ) -> Self {
+        let voxels = voxels.into();
+        assert_eq!(
+            Some(voxels.len()),
+            number_of_grid_points(dimensions),
+            "Number of voxel values does not match volume dimensions."
+        );
+        Self {
+            dimensions,
+            spacing,
+            origin,
+            orientation: IDENTITY_ORIENTATION,
+            coordinates: [None, None, None],
+            voxels,
+        }
+    }
+
+    /// Creates a new scalar volume with unit spacing and origin at zero.
+    ///
+    /// # Panics
+    /// If the number of voxel values does not match the grid dimensions.
+    pub fn with_unit_spacing<V: Into<VoxelBuffer>>(dimensions: [usize; 3], voxels: V) -> Self {
+        Self::new(dimensions, [1.0; 3], [0.0; 3], voxels)
+    }
+
+    /// Creates a new 32-bit float volume by evaluating the given function
+    /// for the indices of every voxel.
+    ///
+    /// # Panics
+    /// If the total number of voxels overflows.
+    pub fn from_fn<F>(dimensions: [usize; 3], spacing: [f32; 3], origin: [f32; 3], f: F) -> Self
+    where
+        F: Fn(usize, usize, usize) -> f32,
+    {
+        let mut values = Vec::with_capacity(
+            number_of_grid_points(dimensions).expect("Volume dimensions are too large."),
+        );
+        for k in 0..dimensions[2] {
+            for j in 0..dimensions[1] {
+                for i in 0..dimensions[0] {
+                    values.push(f(i, j, k));
+                }
+            }
+        }
+        Self::new(dimensions, spacing, origin, values)
+    }
+
+    /// Returns the number of voxels along each axis.
+    pub fn dimensions(&self) -> [usize; 3] {
+        self.dimensions
+    }
+
+    /// Returns the total number of voxels.
+    pub fn number_of_voxels(&self) -> usize {
+        self.voxels.len()
+    }
+
+    /// Returns the distance between adjacent voxel centers along each axis.
+    pub fn spacing(&self) -> [f32; 3] {
+        self.spacing
+    }
+
+    /// Returns the position of the center of the first voxel.
+    pub fn origin(&self) -> [f32; 3] {
+        self.origin
+    }
+
+    /// Returns the unit direction vectors of the x-, y- and z-axis of the volume.
+    pub fn orientation(&self) -> [[f32; 3]; 3] {
+        self.orientation
+    }
+
+    /// Sets the direction vectors of the x-, y- and z-axis of the volume.
+    ///
+    /// The vectors are normalized before they are stored.
+    ///
+    /// # Panics
+    /// If any of the vectors has zero length.
+    pub fn set_orientation(&mut self, orientation: [[f32; 3]; 3]) {
+        for (stored, direction) in self.orientation.iter_mut().zip(&orientation) {
+            let norm = vector_norm(direction);
+            assert!(norm > 0.0, "Axis direction vector has zero length.");
+            *stored = [
+                direction[0] / norm,
+                direction[1] / norm,
+                direction[2] / norm,
+            ];
+        }
+    }
+
+    /// Whether the axes of the volume are aligned with the coordinate axes.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.orientation == IDENTITY_ORIENTATION
+    }
+
+    /// Sets the distance between adjacent voxel centers along each axis.
+    ///
+    /// Any non-uniform coordinates are discarded.
+    pub fn set_spacing(&mut self, spacing: [f32; 3]) {
+        self.spacing = spacing;
+        self.coordinates = [None, None, None];
+    }
+
+    /// Returns the coordinates of the voxel centers along the given axis,
+    /// or `None` if the axis is uniformly spaced.
+    pub fn axis_coordinates(&self, axis: usize) -> Option<&[f32]> {
+        self.coordinates[axis].as_deref()
+    }
+
+    /// Gives the given axis explicit, possibly non-uniformly spaced coordinates.
+    ///
+    /// The spacing of the axis is set to the average distance between the
+    /// coordinates. The origin is not modified.
+    ///
+    /// # Panics
+    /// If the number of coordinates does not match the dimension of the axis,
+    /// or if the coordinates are not strictly increasing.
+    pub fn set_axis_coordinates(&mut self, axis: usize, coordinates: Vec<f32>) {
+        assert_eq!(
+            coordinates.len(),
+            self.dimensions[axis],
+            "Number of coordinates does not match volume dimension."
+        );
+        assert!(
+            coordinates.windows(2).all(|pair| pair[1] > pair[0]),
+            "Coordinates are not strictly increasing."
+        );
+        if coordinates.len() > 1 {
+            self.spacing[axis] = (coordinates[coordinates.len() - 1] - coordinates[0])
+                / (coordinates.len() - 1) as f32;
+        }
+        self.coordinates[axis] = Some(coordinates);
+    }
+
+    /// Whether all axes are uniformly spaced.
+    pub fn is_uniform(&self) -> bool {
+        self.coordinates.iter().all(Option::is_none)
+    }
+
+    /// Sets the position of the center of the first voxel.
+    pub fn set_origin(&mut self, origin: [f32; 3]) {
+        self.origin = origin;
+    }
+
+    /// Returns the type of the voxel values.
+    pub fn voxel_type(&self) -> VoxelType {
+        self.voxels.voxel_type()
+    }
+
+    /// Returns a reference to the voxel buffer.
+    pub fn voxels(&self) -> &VoxelBuffer {
+        &self.voxels
+    }
+
+    /// Returns a mutable reference to the voxel buffer.
+    pub fn voxels_mut(&mut self) -> &mut VoxelBuffer {
+        &mut self.voxels
+    }
+
+    /// Consumes the volume and returns the voxel buffer.
+    pub fn into_voxels(self) -> VoxelBuffer {
+        self.voxels
+    }
+
+    /// Returns the physical size of the region spanned by the voxel centers,
+    /// measured along each volume axis.
+    pub fn extent(&self) -> [f32; 3] {
+        let mut extent = [0.0; 3];
+        for ((extent, &dimension), &spacing) in
+            extent.iter_mut().zip(&self.dimensions).zip(&self.spacing)
+        {
+            *extent = dimension.saturating_sub(1) as f32 * spacing;
+        }
+        extent
+    }
+
+    /// Returns the lower and upper corners of the axis-aligned world space box
+    /// enclosing the region spanned by the voxel centers.
+    pub fn bounding_box(&self) -> ([f32; 3], [f32; 3]) {
+        let max_coords = [
+            self.dimensions[0].saturating_sub(1) as f32,
+            self.dimensions[1].saturating_sub(1) as f32,
+            self.dimensions[2].saturating_sub(1) as f32,
+        ];
+        let mut lower = [f32::INFINITY; 3];
+        let mut upper = [f32::NEG_INFINITY; 3];
+        for corner in 0..8 {
+            let coords = [
+                if corner & 1 == 0 { 0.0 } else { max_coords[0] },
+                if corner & 2 == 0 { 0.0 } else { max_coords[1] },
+                if corner & 4 == 0 { 0.0 } else { max_coords[2] },
+            ];
+            let position = self.index_coords_to_position(coords);
+            for dim in 0..3 {
+                lower[dim] = lower[dim].min(position[dim]);
+                upper[dim] = upper[dim].max(position[dim]);
+            }
+        }
+        (lower, upper)
+    }
+
+    /// Computes the linear buffer index of the voxel with the given indices.
+    pub fn linear_index(&self, i: usize, j: usize, k: usize) -> usize {
+        i + self.dimensions[0] * (j + self.dimensions[1] * k)
+    }
+
+    /// Computes the voxel indices corresponding to the given linear buffer index.
+    pub fn voxel_indices(&self, linear_index: usize) -> [usize; 3] {
+        let i = linear_index % self.dimensions[0];
+        let jk = linear_index / self.dimensions[0];
+        [i, jk % self.dimensions[1], jk / self.dimensions[1]]
+    }
+
+    /// Returns the value of the voxel with the given indices.
+    ///
+    /// # Panics
+    /// If any of the indices are out of bounds.
+    pub fn value(&self, i: usize, j: usize, k: usize) -> f32 {
+        assert!(
+            i < self.dimensions[0] && j < self.dimensions[1] && k < self.dimensions[2],
+            "Voxel indices out of bounds."
+        );
+        self.voxels.value(self.linear_index(i, j, k))
+    }
+
+    /// Returns the value of the voxel with the given indices, or `None` if any
+    /// of the indices are out of bounds.
+    pub fn get(&self, i: usize, j: usize, k: usize) -> Option<f32> {
+        if i < self.dimensions[0] && j < self.dimensions[1] && k < self.dimensions[2] {
+            Some(self.voxels.value(self.linear_index(i, j, k)))
+        } else {
+            None
+        }
+    }
+
+    /// Sets the value of the voxel with the given indices.
+    ///
+    /// # Panics
+    /// If any of the indices are out of bounds.
+    pub fn set_value(&mut self, i: usize, j: usize, k: usize, value: f32) {
+        assert!(
+            i < self.dimensions[0] && j < self.dimensions[1] && k < self.dimensions[2],
+            "Voxel indices out of bounds."
+        );
+        let index = self.linear_index(i, j, k);
+        self.voxels.set_value(index, value);
+    }
+
+    /// Converts the given continuous voxel index coordinates to a world space position.
+    pub fn index_coords_to_position(&self, coords: [f32; 3]) -> [f32; 3] {
+        let mut position = self.origin;
+        for (axis, axis_direction) in self.orientation.iter().enumerate() {
+            let offset = self.axis_offset(axis, coords[axis]);
+            for (component, &direction) in position.iter_mut().zip(axis_direction) {
+                *component += offset * direction;
+            }
+        }
+        position
+    }
+
+    /// Converts the given world space position to continuous voxel index coordinates.
+    pub fn position_to_index_coords(&self, position: [f32; 3]) -> [f32; 3] {
+        let relative = [
+            position[0] - self.origin[0],
+            position[1] - self.origin[1],
+            position[2] - self.origin[2],
+        ];
+        let mut offsets = relative;
+        if !self.is_axis_aligned() {
+            // Solve the linear system with the axis direction vectors as columns
+            // using Cramer's rule
+            let columns = self.orientation;
+            let determinant = triple_product(&columns[0], &columns[1], &columns[2]);
+            for (axis, offset) in offsets.iter_mut().enumerate() {
+                let mut replaced = columns;
+                replaced[axis] = relative;
+                *offset = triple_product(&replaced[0], &replaced[1], &replaced[2]) / determinant;
+            }
+        }
+        [
+            self.offset_to_index_coord(0, offsets[0]),
+            self.offset_to_index_coord(1, offsets[1]),
+            self.offset_to_index_coord(2, offsets[2]),
+        ]
+    }
+
+    /// Computes the distance from the origin along the given axis for the
+    /// given continuous index coordinate.
+    fn axis_offset(&self, axis: usize, coord: f32) -> f32 {
+        match self.coordinates[axis] {
+            Some(ref coordinates) if coordinates.len() > 1 => {
+                let max_index = coordinates.len() - 1;
+                let lower = (coord.max(0.0).floor() as usize).min(max_index - 1);
+                let lower_coordinate = coordinates[lower];
+                let slope = coordinates[lower + 1] - lower_coordinate;
+                lower_coordinate + (coord - lower as f32) * slope - coordinates[0]
+            }
+            _ => coord * self.spacing[axis],
+        }
+    }
+
+    /// Computes the continuous index coordinate along the given axis for the
+    /// given distance from the origin.
+    fn offset_to_index_coord(&self, axis: usize, offset: f32) -> f32 {
+        match self.coordinates[axis] {
+            Some(ref coordinates) if coordinates.len() > 1 => {
+                let coordinate = coordinates[0] + offset;
+                let max_index = coordinates.len() - 1;
+                let upper = coordinates
+                    .binary_search_by(|probe| probe.total_cmp(&coordinate))
+                    .unwrap_or_else(|insertion_index| insertion_index)
+                    .clamp(1, max_index);
+                let lower = upper - 1;
+                lower as f32
+                    + (coordinate - coordinates[lower]) / (coordinates[upper] - coordinates[lower])
+            }
+            _ => offset / self.spacing[axis],
+        }
+    }
+
+    /// Trilinearly interpolates the voxel values at the given world space position,
+    /// or returns `None` if the position lies outside the volume.
+    pub fn sample_trilinear(&self, position: [f32; 3]) -> Option<f32> {
+        self.sample_trilinear_at_index_coords(self.position_to_index_coords(position))
+    }
+
+    /// Trilinearly interpolates the voxel values at the given continuous index
+    /// coordinates, or returns `None` if the coordinates lie outside the volume.
+    pub fn sample_trilinear_at_index_coords(&self, coords: [f32; 3]) -> Option<f32> {
+        let mut lower = [0; 3];
+        let mut upper = [0; 3];
+        let mut weights = [0.0; 3];
+
+        for dim in 0..3 {
+            let coord = coords[dim];
+            let max_index = self.dimensions[dim].checked_sub(1)?;
+            if !(coord >= 0.0 && coord <= max_index as f32) {
+                return None;
+            }
+            lower[dim] = (coord.floor() as usize).min(max_index);
+            upper[dim] = (lower[dim] + 1).min(max_index);
+            weights[dim] = coord - lower[dim] as f32;
+        }
+
+        let value = |i: usize, j: usize, k: usize| self.voxels.value(self.linear_index(i, j, k));
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+        let c00 = lerp(
+            value(lower[0], lower[1], lower[2]),
+            value(upper[0], lower[1], lower[2]),
+            weights[0],
+        );
+        let c10 = lerp(
+            value(lower[0], upper[1], lower[2]),
+            value(upper[0], upper[1], lower[2]),
+            weights[0],
+        );
+        let c01 = lerp(
+            value(lower[0], lower[1], upper[2]),
+            value(upper[0], lower[1], upper[2]),
+            weights[0],
+        );
+        let c11 = lerp(
+            value(lower[0], upper[1], upper[2]),
+            value(upper[0], upper[1], upper[2]),
+            weights[0],
+        );
+
+        Some(lerp(
+            lerp(c00, c10, weights[1]),
+            lerp(c01, c11, weights[1]),
+            weights[2],
+        ))
+    }
+
+    /// Creates a new volume with the same grid geometry as this volume, but
+    /// holding the given voxel values.
+    ///
+    /// # Panics
+    /// If the number of voxel values does not match the grid dimensions.
+    pub fn with_voxels<V: Into<VoxelBuffer>>(&self, voxels: V) -> Self {
+        let voxels = voxels.into();
+        assert_eq!(
+            voxels.len(),
+            self.voxels.len(),
+            "Number of voxel values does not match volume dimensions."
+        );
+        Self {
+            dimensions: self.dimensions,
+            spacing: self.spacing,
+            origin: self.origin,
+            orientation: self.orientation,
+            coordinates: self.coordinates.clone(),
+            voxels,
+        }
+    }
+
+    /// Whether this volume has the same grid geometry as the given volume.
+    pub fn has_same_geometry(&self, other: &Self) -> bool {
+        self.dimensions == other.dimensions
+            && self.spacing == other.spacing
+            && self.origin == other.origin
+            && self.orientation == other.orientation
+            && self.coordinates == other.coordinates
+    }
+
+    /// Returns a 32-bit float volume with the given dimensions and uniform spacing
+    /// covering the same region as this volume, with values trilinearly interpolated
+    /// from this volume.
+    ///
+    /// This is useful for mapping volumes with non-uniform coordinates onto a grid
+    /// that can be uploaded as a texture.
+    pub fn resampled_to_uniform(&self, dimensions: [usize; 3]) -> Self {
+        let extent = self.extent();
+        let mut spacing = [1.0; 3];
+        for (axis, spacing) in spacing.iter_mut().enumerate() {
+            if dimensions[axis] > 1 {
+                *spacing = extent[axis] / (dimensions[axis] - 1) as f32;
+            }
+        }
+        let mut resampled = Self::from_fn(dimensions, spacing, self.origin, |i, j, k| {
+            let coords = [
+                self.offset_to_index_coord(0, i as f32 * spacing[0]),
+                self.offset_to_index_coord(1, j as f32 * spacing[1]),
+                self.offset_to_index_coord(2, k as f32 * spacing[2]),
+            ];
+            let mut clamped_coords = [0.0; 3];
+            for axis in 0..3 {
+                clamped_coords[axis] = coords[axis]
+                    .max(0.0)
+                    .min(self.dimensions[axis].saturating_sub(1) as f32);
+            }
+            self.sample_trilinear_at_index_coords(clamped_coords)
+                .unwrap_or(0.0)
+        });
+        resampled.orientation = self.orientation;
+        resampled
+    }
+
+    /// Returns the minimum and maximum voxel value, ignoring NaNs, or `None`
+    /// if there are no non-NaN values.
+    pub fn value_range(&self) -> Option<(f32, f32)> {
+        self.voxels.value_range()
+    }
+
+    /// Returns an iterator over all voxel values, in buffer order.
+    pub fn iter_values(&self) -> Box<dyn Iterator<Item = f32> + '_> {
+        self.voxels.iter_values()
+    }
+
+    /// Returns an iterator over the indices and values of all voxels, in buffer order.
+    pub fn iter_indexed(&self) -> impl Iterator<Item = ([usize; 3], f32)> + '_ {
+        let [nx, ny, _] = self.dimensions;
+        self.voxels
+            .iter_values()
+            .zip(0..)
+            .map(move |(value, index)| ([index % nx, (index / nx) % ny, index / (nx * ny)], value))
+    }
+
+    /// Returns an iterator over the indices of all voxels, in buffer order.
+    pub fn iter_indices(&self) -> impl Iterator<Item = [usize; 3]> {
+        let [nx, ny, nz] = self.dimensions;
+        (0..nz).flat_map(move |k| (0..ny).flat_map(move |j| (0..nx).map(move |i| [i, j, k])))
+    }
+
+    /// Returns a copy of the volume with all voxel values converted to 32-bit floats.
+    pub fn to_f32(&self) -> Self {
+        Self {
+            dimensions: self.dimensions,
+            spacing: self.spacing,
+            origin: self.origin,
+            orientation: self.orientation,
+            coordinates: self.coordinates.clone(),
+            voxels: VoxelBuffer::F32(self.voxels.to_f32()),
+        }
+    }
+
+    /// Returns a copy of the volume with all voxel values linearly mapped from
+    /// their value range to the unit interval and stored as 32-bit floats.
+    pub fn normalized(&self) -> Self {
+        let (min, max) = self.value_range().unwrap_or((0.0, 1.0));
+        let scale = if max > min { 1.0 / (max - min) } else { 0.0 };
+        Self {
+            dimensions: self.dimensions,
+            spacing: self.spacing,
+            origin: self.origin,
+            orientation: self.orientation,
+            coordinates: self.coordinates.clone(),
+            voxels: VoxelBuffer::F32(
+                self.iter_values()
+                    .map(|value| (value - min) * scale)
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl VectorVolume {
+    /// Creates a new vector volume from the given component volumes.
+    ///
+    /// # Panics
+    /// If the component volumes do not have the same grid geometry.
+    pub fn new(x: ScalarVolume, y: ScalarVolume, z: ScalarVolume) -> Self {
+        assert!(
+            x.has_same_geometry(&y) && x.has_same_geometry(&z),
+            "Vector component volumes have different geometries."
+        );
+        Self {
+            components: [x, y, z],
+        }
+    }
+
+    /// Creates a new vector volume from the given interleaved vector components,
+    /// with the geometry of the given scalar volume.
+    ///
+    /// # Panics
+    /// If the number of vectors does not match the grid dimensions.
+    pub fn from_interleaved(geometry: &ScalarVolume, vectors: &[[f32; 3]]) -> Self {
+        let component = |dim: usize| {
+            geometry.with_voxels(vectors.iter().map(|vector| vector[dim]).collect::<Vec<_>>())
+        };
+        Self {
+            components: [component(0), component(1), component(2)],
+        }
+    }
+
+    /// Returns the number of voxels along each axis.
+    pub fn dimensions(&self) -> [usize; 3] {
+        self.components[0].dimensions()
+    }
+
+    /// Returns a reference to the scalar volume holding the given vector component.
+    pub fn component(&self, dim: usize) -> &ScalarVolume {
+        &self.components[dim]
+    }
+
+    /// Returns a mutable reference to the scalar volume holding the given vector component.
+    ///
+    /// The geometry of the component volume should not be modified.
+    pub fn component_mut(&mut self, dim: usize) -> &mut ScalarVolume {
+        &mut self.components[dim]
+    }
+
+    /// Returns a reference to the volume holding the x-component, which also
+    /// describes the geometry of the vector volume.
+    pub fn geometry(&self) -> &ScalarVolume {
+        &self.components[0]
+    }
+
+    /// Consumes the vector volume and returns the component volumes.
+    pub fn into_components(self) -> [ScalarVolume; 3] {
+        self.components
+    }
+
+    /// Returns the vector at the voxel with the given indices.
+    ///
+    /// # Panics
+    /// If any of the indices are out of bounds.
+    pub fn value(&self, i: usize, j: usize, k: usize) -> [f32; 3] {
+        [
+            self.components[0].value(i, j, k),
+            self.components[1].value(i, j, k),
+            self.components[2].value(i, j, k),
+        ]
+    }
+
+    /// Trilinearly interpolates the vectors at the given world space position,
+    /// or returns `None` if the position lies outside the volume.
+    pub fn sample_trilinear(&self, position: [f32; 3]) -> Option<[f32; 3]> {
+        let coords = self.components[0].position_to_index_coords(position);
+        Some([
+            self.components[0].sample_trilinear_at_index_coords(coords)?,
+            self.components[1].sample_trilinear_at_index_coords(coords)?,
+            self.components[2].sample_trilinear_at_index_coords(coords)?,
+        ])
+    }
+
+    /// Returns an iterator over all vectors, in buffer order.
+    pub fn iter_vectors(&self) -> impl Iterator<Item = [f32; 3]> + '_ {
+        self.components[0]
+            .iter_values()
+            .zip(self.components[1].iter_values())
+            .zip(self.components[2].iter_values())
+            .map(|((x, y), z)| [x, y, z])
+    }
+
+    /// Computes a scalar volume with the length of each vector.
+    pub fn magnitude(&self) -> ScalarVolume {
+        self.components[0].with_voxels(
+            self.iter_vectors()
+                .map(|vector| vector_norm(&vector))
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+impl Volume {
+    /// Returns the number of voxels along each axis.
+    pub fn dimensions(&self) -> [usize; 3] {
+        match self {
+            Self::Scalar(volume) => volume.dimensions(),
+            Self::Vector(volume) => volume.dimensions(),
+        }
+    }
+
+    /// Returns the number of values stored for each voxel.
+    pub fn number_of_components(&self) -> usize {
+        match self {
+            Self::Scalar(_) => 1,
+            Self::Vector(_) => 3,
+        }
+    }
+
+    /// Returns a scalar volume representing the volume, which is the vector
+    /// magnitude in the case of a vector volume.
+    pub fn to_scalar(&self) -> ScalarVolume {
+        match self {
+            Self::Scalar(volume) => volume.clone(),
+            Self::Vector(volume) => volume.magnitude(),
+        }
+    }
+
+    /// Consumes the volume and returns a scalar volume representing it, which
+    /// is the vector magnitude in the case of a vector volume.
+    pub fn into_scalar(self) -> ScalarVolume {
+        match self {
+            Self::Scalar(volume) => volume,
+            Self::Vector(volume) => volume.magnitude(),
+        }
+    }
+}
+
+impl From<ScalarVolume> for Volume {
+    fn from(volume: ScalarVolume) -> Self {
+        Self::Scalar(volume)
+    }
+}
+
+impl From<VectorVolume> for Volume {
+    fn from(volume: VectorVolume) -> Self {
+        Self::Vector(volume)
+    }
+}
+
+impl iter::FromIterator<u8> for VoxelBuffer {
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        Self::U8(iter.into_iter().collect())
+    }
+}
+
+impl iter::FromIterator<u16> for VoxelBuffer {
+    fn from_iter<I: IntoIterator<Item = u16>>(iter: I) -> Self {
+        Self::U16(iter.into_iter().collect())
+    }
+}
+
+impl iter::FromIterator<i16> for VoxelBuffer {
+    fn from_iter<I: IntoIterator<Item = i16>>(iter: I) -> Self {
+        Self::I16(iter.into_iter().collect())
+    }
+}
+
+impl iter::FromIterator<f32> for VoxelBuffer {
+    fn from_iter<I: IntoIterator<Item = f32>>(iter: I) -> Self {
+        Self::F32(iter.into_iter().collect())
+    }
+}
+
+/// Computes the total number of points in a grid with the given dimensions,
+/// or returns `None` if it overflows.
+fn number_of_grid_points(dimensions: [usize; 3]) -> Option<usize> {
+    dimensions[0]
+        .checked_mul(dimensions[1])
+        .and_then(|n| n.checked_mul(dimensions[2]))
+}
+
+/// Computes the Euclidean norm of the given vector.
+fn vector_norm(vector: &[f32; 3]) -> f32 {
+    (vector[0] * vector[0] + vector[1] * vector[1] + vector[2] * vector[2]).sqrt()
+}
+
+/// Computes the scalar triple product `a · (b × c)` of the given vectors.
+fn triple_product(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> f32 {
+    a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+        + a[2] * (b[0] * c[1] - b[1] * c[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} is not close to {}", a, b);
+    }
+
+    fn assert_all_close(a: [f32; 3], b: [f32; 3]) {
+        for (&a, &b) in a.iter().zip(&b) {
+            assert_close(a, b);
+        }
+    }
+
+    /// Creates a volume whose values vary linearly with the voxel indices,
+    /// so that trilinear interpolation reproduces the function exactly.
+    fn linear_volume() -> ScalarVolume {
+        ScalarVolume::from_fn([3, 4, 5], [2.0, 1.0, 0.5], [1.0, -1.0, 0.0], |i, j, k| {
+            (i + 2 * j + 3 * k) as f32
+        })
+    }
+
+    #[test]
+    fn sample_trilinear_interpolates_between_voxels() {
+        let volume = linear_volume();
+        assert_close(volume.sample_trilinear([1.0, -1.0, 0.0]).unwrap(), 0.0);
+        assert_close(volume.sample_trilinear([5.0, 2.0, 2.0]).unwrap(), 20.0);
+
+        // Index coordinates (0.5, 1.25, 2.5)
+        let value = volume.sample_trilinear([2.0, 0.25, 1.25]).unwrap();
+        assert_close(value, 0.5 + 2.0 * 1.25 + 3.0 * 2.5);
+    }
+
+    #[test]
+    fn sample_trilinear_outside_volume_is_none() {
+        let volume = linear_volume();
+        assert_eq!(volume.sample_trilinear([0.9, 0.0, 0.0]), None);
+        assert_eq!(volume.sample_trilinear([5.1, 0.0, 0.0]), None);
+        assert_eq!(volume.sample_trilinear([1.0, -1.0, 2.1]), None);
+        assert_eq!(volume.sample_trilinear([f32::NAN, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn position_to_index_coords_inverts_rotated_orientation() {
+        let mut volume = linear_volume();
+        // Rotation by 90 degrees about the z-axis, given with unnormalized vectors
+        volume.set_orientation([[0.0, 2.0, 0.0], [-3.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        assert!(!volume.is_axis_aligned());
+
+        let coords = [1.5, 2.0, 3.0];
+        let position = volume.index_coords_to_position(coords);
+        assert_all_close(position, [1.0 - 2.0, -1.0 + 3.0, 1.5]);
+        assert_all_close(volume.position_to_index_coords(position), coords);
+        assert_close(volume.sample_trilinear(position).unwrap(), 1.5 + 4.0 + 9.0);
+
+        let (lower, upper) = volume.bounding_box();
+        assert_all_close(lower, [1.0 - 3.0, -1.0, 0.0]);
+        assert_all_close(upper, [1.0, -1.0 + 4.0, 2.0]);
+    }
+
+    #[test]
+    fn non_uniform_offsets_are_found_by_binary_search() {
+        let mut volume = ScalarVolume::with_unit_spacing([4, 1, 1], vec![0.0_f32; 4]);
+        volume.set_axis_coordinates(0, vec![10.0, 11.0, 13.0, 17.0]);
+        assert!(!volume.is_uniform());
+        assert_close(volume.spacing()[0], 7.0 / 3.0);
+
+        for &(offset, coord) in &[(0.0, 0.0), (2.0, 1.5), (3.0, 2.0), (5.0, 2.5), (7.0, 3.0)] {
+            assert_close(volume.offset_to_index_coord(0, offset), coord);
+            assert_close(volume.axis_offset(0, coord), offset);
+        }
+        // Offsets beyond the coordinates extrapolate from the outermost cells
+        assert_close(volume.offset_to_index_coord(0, -1.0), -1.0);
+        assert_close(volume.offset_to_index_coord(0, 11.0), 4.0);
+    }
+
+    #[test]
+    fn resampled_to_uniform_covers_non_uniform_grid() {
+        let coordinates = vec![0.0, 1.0, 3.0, 7.0];
+        let mut values = coordinates.clone();
+        values.extend(coordinates.iter().map(|coordinate| coordinate + 10.0));
+        let mut volume = ScalarVolume::new([4, 2, 1], [1.0; 3], [5.0, 0.0, 0.0], values);
+        volume.set_axis_coordinates(0, coordinates);
+
+        let resampled = volume.resampled_to_uniform([8, 3, 1]);
+        assert!(resampled.is_uniform());
+        assert_eq!(resampled.dimensions(), [8, 3, 1]);
+        assert_eq!(resampled.voxel_type(), VoxelType::F32);
+        assert_eq!(resampled.origin(), volume.origin());
+        assert_all_close(resampled.spacing(), [1.0, 0.5, 1.0]);
+        assert_eq!(resampled.extent(), volume.extent());
+        for i in 0..8 {
+            assert_close(resampled.value(i, 0, 0), i as f32);
+            assert_close(resampled.value(i, 1, 0), i as f32 + 5.0);
+            assert_close(resampled.value(i, 2, 0), i as f32 + 10.0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match volume dimensions")]
+    fn overflowing_dimensions_panic() {
+        ScalarVolume::with_unit_spacing([usize::MAX, 2, 1], vec![0_u8; 0]);
+    }
+}